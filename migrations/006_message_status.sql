@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS message_status (
+    sid TEXT PRIMARY KEY,
+    status TEXT NOT NULL,
+    error_code TEXT,
+    to_phone TEXT,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
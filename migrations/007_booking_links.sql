@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS booking_links (
+    token TEXT PRIMARY KEY,
+    booking_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_booking_links_booking ON booking_links(booking_id);
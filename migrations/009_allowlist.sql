@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS allowlist (
+    phone TEXT PRIMARY KEY,
+    note TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
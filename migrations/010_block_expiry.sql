@@ -0,0 +1 @@
+ALTER TABLE blocked_numbers ADD COLUMN blocked_until TEXT;
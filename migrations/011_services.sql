@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN services TEXT;
+ALTER TABLE bookings ADD COLUMN service TEXT;
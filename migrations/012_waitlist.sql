@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS waitlist (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    phone TEXT NOT NULL,
+    date_time TEXT NOT NULL,
+    duration_minutes INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE(phone, date_time)
+);
+
+CREATE INDEX IF NOT EXISTS idx_waitlist_date_time ON waitlist(date_time);
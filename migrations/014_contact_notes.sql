@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS contact_notes (
+    phone TEXT PRIMARY KEY,
+    notes TEXT NOT NULL DEFAULT '',
+    tags TEXT NOT NULL DEFAULT '',
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,4 @@
+ALTER TABLE users ADD COLUMN notify_on_booking INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN notify_on_cancel INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN notify_on_block INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN notify_on_pause INTEGER NOT NULL DEFAULT 1;
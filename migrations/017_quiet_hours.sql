@@ -0,0 +1,8 @@
+ALTER TABLE users ADD COLUMN quiet_hours TEXT;
+
+CREATE TABLE IF NOT EXISTS queued_replies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    phone TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS needs_human (
+    phone TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
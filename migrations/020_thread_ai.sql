@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS thread_ai (
+    phone TEXT PRIMARY KEY,
+    ai_enabled INTEGER NOT NULL DEFAULT 1,
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
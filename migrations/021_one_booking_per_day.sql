@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN one_booking_per_customer_per_day INTEGER NOT NULL DEFAULT 0;
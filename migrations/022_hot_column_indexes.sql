@@ -0,0 +1,7 @@
+-- Composites for range scans (date_time + status) and thread history (phone, id order);
+-- they supersede the single-column indexes from 001 and 002.
+CREATE INDEX IF NOT EXISTS idx_bookings_date_time_status ON bookings(date_time, status);
+DROP INDEX IF EXISTS idx_bookings_date_time;
+
+CREATE INDEX IF NOT EXISTS idx_inbox_events_phone_id ON inbox_events(phone, id);
+DROP INDEX IF EXISTS idx_inbox_events_phone;
@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN reminder_hours_before INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN reminder_template TEXT;
+ALTER TABLE bookings ADD COLUMN reminder_sent_at TEXT;
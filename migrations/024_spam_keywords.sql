@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN spam_keywords TEXT;
+ALTER TABLE users ADD COLUMN block_spam INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN use_24h_time INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+use axum::body::Body;
+use axum::extract::{FromRequest, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Form;
+
+use crate::logging::mask_phone;
+
+/// Webhook bodies are buffered to read the sender; this matches axum's
+/// default body limit, so nothing the handler would accept is cut off.
+const MAX_WEBHOOK_BODY: usize = 2 * 1024 * 1024;
+
+/// Middleware logging one `access` event per request: method, path, status,
+/// latency, and for inbound SMS the masked sender. Formatted as JSON when
+/// `LOG_FORMAT=json`.
+pub async fn log_requests(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let (req, from) = match path.as_str() {
+        "/webhook/sms" | "/webhook/sms/json" => sender(req).await,
+        _ => (req, None),
+    };
+
+    let started = Instant::now();
+    let response = next.run(req).await;
+    tracing::info!(
+        target: "access",
+        method = %method,
+        path = %path,
+        status = response.status().as_u16(),
+        latency_ms = started.elapsed().as_millis() as u64,
+        from = from.as_deref(),
+        "request"
+    );
+    response
+}
+
+/// Read the masked `From`/`from` of an inbound SMS, handing back an
+/// equivalent request for the handler.
+async fn sender(req: Request) -> (Request, Option<String>) {
+    let (parts, body) = req.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_WEBHOOK_BODY).await {
+        Ok(bytes) => bytes,
+        Err(_) => return (Request::from_parts(parts, Body::empty()), None),
+    };
+
+    let from = if parts.uri.path() == "/webhook/sms/json" {
+        serde_json::from_slice::<serde_json::Value>(&bytes)
+            .ok()
+            .and_then(|v| v.get("from")?.as_str().map(str::to_string))
+    } else {
+        let probe = Request::from_parts(parts.clone(), Body::from(bytes.clone()));
+        Form::<Vec<(String, String)>>::from_request(probe, &())
+            .await
+            .ok()
+            .and_then(|Form(fields)| fields.into_iter().find(|(k, _)| k == "From"))
+            .map(|(_, v)| v)
+    };
+    (Request::from_parts(parts, Body::from(bytes)), from.map(|f| mask_phone(&f)))
+}
@@ -0,0 +1,160 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use dashmap::DashMap;
+
+use crate::state::AppState;
+
+/// Routes that check a token (bearer header or `?token=`) and answer 401.
+const TOKEN_ROUTES: &[&str] = &[
+    "/api/admin/",
+    "/api/dev/",
+    "/api/inbox/",
+    "/calendar/feed.ics",
+    "/metrics",
+    "/webhook/sms/json",
+];
+
+/// Failed token checks per client address. After `max_failures` within
+/// `window` of the first one, the address is refused until the window ends.
+pub struct AuthLimiter {
+    max_failures: u32,
+    window: Duration,
+    failures: DashMap<IpAddr, (u32, Instant)>,
+    /// Peers allowed to name the client in `X-Forwarded-For` (`TRUSTED_PROXIES`).
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl AuthLimiter {
+    /// `max_failures` of 0 turns the limiter off.
+    pub fn new(max_failures: u32, window: Duration, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            max_failures,
+            window,
+            failures: DashMap::new(),
+            trusted_proxies,
+        }
+    }
+
+    /// Seconds until `ip` may try again, if it's locked out.
+    pub fn locked_for(&self, ip: IpAddr) -> Option<u64> {
+        if self.max_failures == 0 {
+            return None;
+        }
+        let (count, since) = *self.failures.get(&ip)?;
+        let remaining = self.window.checked_sub(since.elapsed())?;
+        (count >= self.max_failures).then(|| remaining.as_secs().max(1))
+    }
+
+    pub fn record_failure(&self, ip: IpAddr) {
+        if self.max_failures == 0 {
+            return;
+        }
+        let mut entry = self.failures.entry(ip).or_insert((0, Instant::now()));
+        if entry.1.elapsed() >= self.window {
+            *entry = (0, Instant::now());
+        }
+        entry.0 += 1;
+    }
+
+    pub fn reset(&self, ip: IpAddr) {
+        self.failures.remove(&ip);
+    }
+
+    /// Forget clients whose window has passed.
+    pub fn prune(&self) {
+        self.failures
+            .retain(|_, (_, since)| since.elapsed() < self.window);
+    }
+}
+
+/// The client's address: the peer, or the last `X-Forwarded-For` hop when the
+/// peer is one of the configured `trusted` proxies. Clients can't pick their
+/// own key by sending the header directly, even from a shared network.
+fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted: &[IpAddr]) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse().ok())
+        .or(Some(peer))
+}
+
+/// Middleware refusing token-protected routes with 429 for a client that
+/// keeps failing auth (`AUTH_MAX_FAILURES` within `AUTH_LOCKOUT_SECS`). A
+/// successful request clears its count.
+pub async fn limit_failed_auth(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    if !TOKEN_ROUTES.iter().any(|r| path.starts_with(r)) {
+        return next.run(req).await;
+    }
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let Some(ip) = client_ip(req.headers(), peer, &state.auth_limiter.trusted_proxies) else {
+        return next.run(req).await;
+    };
+
+    if let Some(retry_after) = state.auth_limiter.locked_for(ip) {
+        tracing::warn!(%ip, "too many failed auth attempts, refusing");
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("retry-after", retry_after.to_string())],
+            Json(serde_json::json!({"error": "too many failed attempts"})),
+        )
+            .into_response();
+    }
+
+    let response = next.run(req).await;
+    match response.status() {
+        StatusCode::UNAUTHORIZED => state.auth_limiter.record_failure(ip),
+        status if status.is_success() => state.auth_limiter.reset(ip),
+        _ => {}
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_ip_trusts_forwarded_for_only_from_proxies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "198.51.100.7, 203.0.113.9".parse().unwrap(),
+        );
+
+        let trusted = ["127.0.0.1".parse().unwrap()];
+        let proxy = "127.0.0.1".parse().ok();
+        assert_eq!(
+            client_ip(&headers, proxy, &trusted),
+            "203.0.113.9".parse().ok()
+        );
+        let direct = "192.0.2.1".parse().ok();
+        assert_eq!(client_ip(&headers, direct, &trusted), direct);
+        assert_eq!(client_ip(&HeaderMap::new(), proxy, &trusted), proxy);
+        assert_eq!(client_ip(&headers, None, &trusted), None);
+
+        // A private-network neighbour isn't a proxy unless configured as one
+        let neighbour = "10.0.0.5".parse().ok();
+        assert_eq!(client_ip(&headers, neighbour, &trusted), neighbour);
+        assert_eq!(client_ip(&headers, proxy, &[]), proxy);
+    }
+}
@@ -1,18 +1,66 @@
 use std::env;
+use std::net::IpAddr;
+
+use crate::services::dates::YearlessDatePolicy;
 
 #[derive(Clone, Debug)]
 pub struct AppConfig {
     pub port: u16,
     pub database_url: String,
+    /// How long a write waits on another connection's lock before failing.
+    pub db_busy_timeout_ms: u64,
     pub admin_token: String,
+    pub json_webhook_token: String,
     pub ollama_url: String,
+    pub ollama_model: String,
     pub twilio_account_sid: String,
     pub twilio_auth_token: String,
     pub twilio_phone_number: String,
     pub owner_phone: String,
+    pub backup_owner_phone: String,
+    pub public_url: String,
     pub llm_provider: String,
     pub groq_api_key: String,
     pub groq_model: String,
+    pub llm_temperature: f32,
+    pub yearless_date_policy: YearlessDatePolicy,
+    pub llm_max_concurrency: usize,
+    pub max_history_messages: usize,
+    pub metrics_token: String,
+    pub shutdown_timeout_secs: u64,
+    pub auto_block_minutes: i64,
+    pub debounce_ms: u64,
+    pub broadcast_batch_size: usize,
+    pub broadcast_batch_delay_ms: u64,
+    pub sse_keepalive_secs: u64,
+    pub ics_alarm_minutes: u32,
+    pub llm_debug_log: bool,
+    pub http_timeout_secs: u64,
+    pub llm_breaker_threshold: u32,
+    pub llm_breaker_cooldown_secs: u64,
+    pub dev_notification_buffer: usize,
+    pub max_inbound_body: usize,
+    pub summarize_after: usize,
+    pub confirm_reprompt_minutes: i64,
+    /// How long after expiring a conversation's pending booking can still be confirmed.
+    pub conversation_grace_minutes: i64,
+    /// How often the cleanup task deletes expired rows.
+    pub cleanup_interval_minutes: u64,
+    /// Inbox events older than this are pruned (keeping each thread's latest); 0 = keep all.
+    pub inbox_retention_days: i64,
+    /// "json" for one JSON object per log line; anything else is human-readable.
+    pub log_format: String,
+    /// Include customer message text in logs.
+    pub log_message_bodies: bool,
+    /// Country code such as `+1` for numbers typed without one; empty = none.
+    pub default_country_code: String,
+    /// When set, owner SMS commands must start with it: `#<secret> pause`.
+    pub admin_command_secret: String,
+    /// Failed token checks from one address before it's locked out; 0 = no limit.
+    pub auth_max_failures: u32,
+    pub auth_lockout_secs: u64,
+    /// Reverse proxies whose `X-Forwarded-For` is believed; empty = none.
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl AppConfig {
@@ -23,17 +71,148 @@ impl AppConfig {
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(3000),
             database_url: env::var("DATABASE_URL").unwrap_or_else(|_| "phonebook.db".to_string()),
+            db_busy_timeout_ms: env::var("DB_BUSY_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5000),
             admin_token: env::var("ADMIN_TOKEN").unwrap_or_else(|_| "changeme".to_string()),
+            json_webhook_token: env::var("JSON_WEBHOOK_TOKEN").unwrap_or_default(),
             ollama_url: env::var("OLLAMA_URL")
                 .unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            ollama_model: env::var("OLLAMA_MODEL").unwrap_or_else(|_| "llama3.2".to_string()),
             twilio_account_sid: env::var("TWILIO_ACCOUNT_SID").unwrap_or_default(),
             twilio_auth_token: env::var("TWILIO_AUTH_TOKEN").unwrap_or_default(),
             twilio_phone_number: env::var("TWILIO_PHONE_NUMBER").unwrap_or_default(),
             owner_phone: env::var("OWNER_PHONE").unwrap_or_default(),
+            backup_owner_phone: env::var("BACKUP_OWNER_PHONE").unwrap_or_default(),
+            public_url: env::var("PUBLIC_URL")
+                .or_else(|_| env::var("PUBLIC_BASE_URL"))
+                .unwrap_or_default(),
             llm_provider: env::var("LLM_PROVIDER").unwrap_or_else(|_| "ollama".to_string()),
             groq_api_key: env::var("GROQ_API_KEY").unwrap_or_default(),
             groq_model: env::var("GROQ_MODEL")
                 .unwrap_or_else(|_| "llama-3.3-70b-versatile".to_string()),
+            llm_temperature: env::var("LLM_TEMPERATURE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|t: &f32| (0.0..=2.0).contains(t))
+                .unwrap_or(0.2),
+            yearless_date_policy: YearlessDatePolicy::parse(
+                &env::var("YEARLESS_DATE_POLICY").unwrap_or_default(),
+            ),
+            llm_max_concurrency: env::var("LLM_MAX_CONCURRENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(8),
+            max_history_messages: env::var("MAX_HISTORY_MESSAGES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
+            metrics_token: env::var("METRICS_TOKEN").unwrap_or_default(),
+            shutdown_timeout_secs: env::var("SHUTDOWN_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            auto_block_minutes: env::var("AUTO_BLOCK_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(1440),
+            debounce_ms: env::var("DEBOUNCE_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            broadcast_batch_size: env::var("BROADCAST_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(10),
+            broadcast_batch_delay_ms: env::var("BROADCAST_BATCH_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            sse_keepalive_secs: env::var("SSE_KEEPALIVE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(30),
+            ics_alarm_minutes: env::var("ICS_ALARM_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            llm_debug_log: env::var("LLM_DEBUG_LOG")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false),
+            http_timeout_secs: env::var("HTTP_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(15),
+            llm_breaker_threshold: env::var("LLM_BREAKER_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            llm_breaker_cooldown_secs: env::var("LLM_BREAKER_COOLDOWN_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            dev_notification_buffer: env::var("DEV_NOTIFICATION_BUFFER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100),
+            max_inbound_body: env::var("MAX_INBOUND_BODY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1600),
+            summarize_after: env::var("SUMMARIZE_AFTER")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            confirm_reprompt_minutes: env::var("CONFIRM_REPROMPT_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            conversation_grace_minutes: env::var("CONVERSATION_GRACE_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n >= 0)
+                .unwrap_or(5),
+            cleanup_interval_minutes: env::var("CLEANUP_INTERVAL_MINUTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(10),
+            inbox_retention_days: env::var("INBOX_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n >= 0)
+                .unwrap_or(365),
+            log_format: env::var("LOG_FORMAT").unwrap_or_else(|_| "pretty".to_string()),
+            log_message_bodies: env::var("LOG_MESSAGE_BODIES")
+                .map(|v| !(v == "0" || v.eq_ignore_ascii_case("false")))
+                .unwrap_or(true),
+            default_country_code: env::var("DEFAULT_COUNTRY_CODE")
+                .ok()
+                .map(|v| v.trim().trim_start_matches('+').to_string())
+                .filter(|v| (1..=3).contains(&v.len()) && v.chars().all(|c| c.is_ascii_digit()))
+                .map(|v| format!("+{v}"))
+                .unwrap_or_default(),
+            admin_command_secret: env::var("ADMIN_COMMAND_SECRET").unwrap_or_default(),
+            auth_max_failures: env::var("AUTH_MAX_FAILURES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            auth_lockout_secs: env::var("AUTH_LOCKOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(900),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|ip| ip.trim().parse().ok())
+                .collect(),
         }
     }
 }
@@ -1,43 +1,76 @@
 use anyhow::Context;
-use rusqlite::Connection;
-use std::fs;
-use std::path::Path;
+use rusqlite::{Connection, OptionalExtension};
 
+/// `(file name, SQL)` for a file in `migrations/`, embedded at compile time.
+macro_rules! migration {
+    ($name:literal) => {
+        ($name, include_str!(concat!("../../migrations/", $name)))
+    };
+}
+
+/// Every migration, in the order they're applied. Embedded in the binary so
+/// `init_db` works wherever it runs from; add new files here as well.
+const MIGRATIONS: &[(&str, &str)] = &[
+    migration!("001_initial.sql"),
+    migration!("002_inbox_events.sql"),
+    migration!("003_ai_preferences.sql"),
+    migration!("004_monthly_activity.sql"),
+    migration!("005_booking_events.sql"),
+    migration!("006_message_status.sql"),
+    migration!("007_booking_links.sql"),
+    migration!("008_user_language.sql"),
+    migration!("009_allowlist.sql"),
+    migration!("010_block_expiry.sql"),
+    migration!("011_services.sql"),
+    migration!("012_waitlist.sql"),
+    migration!("013_opt_out.sql"),
+    migration!("014_contact_notes.sql"),
+    migration!("015_user_location.sql"),
+    migration!("016_owner_notifications.sql"),
+    migration!("017_quiet_hours.sql"),
+    migration!("018_conversation_summary.sql"),
+    migration!("019_needs_human.sql"),
+    migration!("020_thread_ai.sql"),
+    migration!("021_one_booking_per_day.sql"),
+    migration!("022_hot_column_indexes.sql"),
+    migration!("023_booking_reminders.sql"),
+    migration!("024_spam_keywords.sql"),
+    migration!("025_time_format.sql"),
+];
+
+/// The schema this binary migrates to. Migrations are numbered from 001
+/// without gaps, so this is also the number of the latest one.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+/// Apply any migrations the database hasn't seen and tag it with
+/// `SCHEMA_VERSION`. Refuses a database already migrated past that by a newer
+/// binary, rather than running against tables it doesn't know.
 pub fn run_migrations(conn: &Connection) -> anyhow::Result<()> {
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS _migrations (
             name TEXT PRIMARY KEY,
             applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE TABLE IF NOT EXISTS _migrations_meta (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
         );",
     )
     .context("failed to create migrations table")?;
 
-    let migrations_dir = Path::new("migrations");
-    if !migrations_dir.exists() {
-        tracing::warn!("migrations directory not found, skipping");
-        return Ok(());
+    let found = schema_version(conn)?;
+    if let Some(newer) = found.filter(|v| *v > SCHEMA_VERSION) {
+        anyhow::bail!(
+            "database schema version {newer} is newer than this binary supports ({SCHEMA_VERSION}); \
+             run a newer phonebook or restore a backup taken before the upgrade"
+        );
     }
 
-    let mut entries: Vec<_> = fs::read_dir(migrations_dir)
-        .context("failed to read migrations directory")?
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            e.path()
-                .extension()
-                .map(|ext| ext == "sql")
-                .unwrap_or(false)
-        })
-        .collect();
-
-    entries.sort_by_key(|e| e.file_name());
-
-    for entry in entries {
-        let name = entry.file_name().to_string_lossy().to_string();
-
+    for &(name, sql) in MIGRATIONS {
         let already_applied: bool = conn
             .query_row(
                 "SELECT COUNT(*) > 0 FROM _migrations WHERE name = ?1",
-                [&name],
+                [name],
                 |row| row.get(0),
             )
             .context("failed to check migration status")?;
@@ -46,17 +79,127 @@ pub fn run_migrations(conn: &Connection) -> anyhow::Result<()> {
             continue;
         }
 
-        let sql = fs::read_to_string(entry.path())
-            .with_context(|| format!("failed to read migration file: {name}"))?;
-
-        conn.execute_batch(&sql)
+        conn.execute_batch(sql)
             .with_context(|| format!("failed to apply migration: {name}"))?;
 
-        conn.execute("INSERT INTO _migrations (name) VALUES (?1)", [&name])
+        conn.execute("INSERT INTO _migrations (name) VALUES (?1)", [name])
             .with_context(|| format!("failed to record migration: {name}"))?;
 
         tracing::info!("applied migration: {name}");
     }
 
+    if found != Some(SCHEMA_VERSION) {
+        conn.execute(
+            "INSERT INTO _migrations_meta (key, value) VALUES ('schema_version', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [SCHEMA_VERSION],
+        )
+        .context("failed to record schema version")?;
+    }
+
     Ok(())
 }
+
+/// The version the database was last migrated to, if it's been tagged.
+pub fn schema_version(conn: &Connection) -> anyhow::Result<Option<i64>> {
+    conn.query_row(
+        "SELECT value FROM _migrations_meta WHERE key = 'schema_version'",
+        [],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("failed to read schema version")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_migration_file_is_embedded_in_order() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("migrations");
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".sql"))
+            .collect();
+        files.sort();
+
+        let embedded: Vec<&str> = MIGRATIONS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(embedded, files);
+        let latest = files.last().unwrap();
+        assert_eq!(latest[..3].parse::<i64>().unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_fresh_in_memory_db_has_every_table() {
+        let conn = crate::db::init_db(":memory:").unwrap();
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .unwrap();
+        let tables: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        for table in [
+            "_migrations",
+            "users",
+            "bookings",
+            "conversations",
+            "blocked_numbers",
+            "rate_limits",
+            "inbox_events",
+            "monthly_activity",
+            "booking_events",
+            "message_status",
+            "booking_links",
+            "allowlist",
+            "waitlist",
+            "opted_out",
+            "contact_notes",
+            "queued_replies",
+            "needs_human",
+            "thread_ai",
+        ] {
+            assert!(tables.iter().any(|t| t == table), "missing table {table}");
+        }
+        let applied: usize = conn
+            .query_row("SELECT COUNT(*) FROM _migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(applied, MIGRATIONS.len());
+        assert_eq!(schema_version(&conn).unwrap(), Some(SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_db_from_newer_binary_is_refused() {
+        let path =
+            std::env::temp_dir().join(format!("phonebook-schema-{}.db", uuid::Uuid::new_v4()));
+        let path_str = path.to_str().unwrap();
+
+        let conn = crate::db::init_db(path_str).unwrap();
+        conn.execute(
+            "UPDATE _migrations_meta SET value = ?1 WHERE key = 'schema_version'",
+            [SCHEMA_VERSION + 1],
+        )
+        .unwrap();
+        drop(conn);
+
+        let err = crate::db::init_db(path_str).unwrap_err();
+        for suffix in ["", "-wal", "-shm"] {
+            let mut leftover = path.clone().into_os_string();
+            leftover.push(suffix);
+            let _ = std::fs::remove_file(leftover);
+        }
+        let message = format!("{err:#}");
+        assert!(
+            message.contains(&format!(
+                "schema version {} is newer than this binary supports ({SCHEMA_VERSION})",
+                SCHEMA_VERSION + 1
+            )),
+            "{message}"
+        );
+    }
+}
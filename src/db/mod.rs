@@ -1,12 +1,32 @@
 pub mod migrations;
+pub mod pool;
 pub mod queries;
 
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
 use anyhow::Context;
-use rusqlite::Connection;
+use rusqlite::{Connection, ErrorCode};
+
+/// How long SQLite waits on another connection's lock unless configured.
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// Attempts made by [`retry_on_busy`] before giving up.
+const BUSY_RETRY_ATTEMPTS: u32 = 5;
 
 pub fn init_db(path: &str) -> anyhow::Result<Connection> {
+    init_db_with_busy_timeout(path, DEFAULT_BUSY_TIMEOUT)
+}
+
+/// Open and migrate the database, waiting up to `busy_timeout` whenever
+/// another connection (a backup, the sqlite3 shell) holds the write lock.
+pub fn init_db_with_busy_timeout(path: &str, busy_timeout: Duration) -> anyhow::Result<Connection> {
     let conn = Connection::open(path).context("failed to open database")?;
 
+    conn.busy_timeout(busy_timeout)
+        .context("failed to set database busy timeout")?;
     conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")
         .context("failed to set database pragmas")?;
 
@@ -14,3 +34,204 @@ pub fn init_db(path: &str) -> anyhow::Result<Connection> {
 
     Ok(conn)
 }
+
+/// Whether `err` is SQLite reporting the database busy or locked.
+pub fn is_busy(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _))
+                if matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+        )
+    })
+}
+
+/// Run a write, retrying with a short backoff if it still hits a busy
+/// database after the busy timeout. `f` must be safe to run again: a busy
+/// statement writes nothing, but earlier statements in `f` may have.
+pub fn retry_on_busy<T>(mut f: impl FnMut() -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(e) if attempt < BUSY_RETRY_ATTEMPTS && is_busy(&e) => {
+                tracing::warn!(attempt, "database busy, retrying write");
+                std::thread::sleep(Duration::from_millis(25 << attempt));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Take a consistent snapshot of the whole database at `path` with SQLite's
+/// online backup API, into a scratch file (WAL pages included) that only
+/// this user can read. The file is unlinked before it's returned, so it's
+/// gone once the handle is dropped.
+///
+/// A database on disk is copied through a connection of its own, so `shared`
+/// stays free for other requests however long the copy takes. An in-memory
+/// database only exists on `shared`, so it's copied from there.
+pub fn backup_to_file(path: &str, shared: &Mutex<Connection>) -> anyhow::Result<File> {
+    if path == ":memory:" {
+        let conn = shared.lock().unwrap();
+        return snapshot(&conn);
+    }
+    let conn = Connection::open(path).context("failed to open database for backup")?;
+    conn.busy_timeout(DEFAULT_BUSY_TIMEOUT)
+        .context("failed to set database busy timeout")?;
+    snapshot(&conn)
+}
+
+fn snapshot(conn: &Connection) -> anyhow::Result<File> {
+    let path = std::env::temp_dir().join(format!("phonebook-backup-{}.db", uuid::Uuid::new_v4()));
+
+    let result = create_private(&path)
+        .and_then(|()| {
+            conn.backup(rusqlite::DatabaseName::Main, &path, None)
+                .context("database backup failed")
+        })
+        .and_then(|()| File::open(&path).context("failed to open database backup"));
+
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let mut leftover = path.clone().into_os_string();
+        leftover.push(suffix);
+        let _ = std::fs::remove_file(leftover);
+    }
+
+    result
+}
+
+/// Create an empty `path` readable only by this user. SQLite keeps the mode
+/// of an existing file, and gives its journal the same one.
+fn create_private(path: &Path) -> anyhow::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path).context("failed to create backup file")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_plan(conn: &Connection, sql: &str) -> String {
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {sql}")).unwrap();
+        let details: Vec<String> = stmt
+            .query_map([], |row| row.get(3))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        details.join("; ")
+    }
+
+    #[test]
+    fn test_hot_queries_use_indexes() {
+        let conn = init_db(":memory:").unwrap();
+
+        let plan = query_plan(
+            &conn,
+            "SELECT * FROM bookings WHERE date_time >= '2025-01-01' AND date_time <= '2025-02-01'
+             AND status NOT IN ('cancelled', 'no_show') ORDER BY date_time ASC",
+        );
+        assert!(plan.contains("idx_bookings_date_time_status"), "{plan}");
+
+        let plan = query_plan(
+            &conn,
+            "SELECT * FROM inbox_events WHERE phone = '+15551110000' ORDER BY id ASC LIMIT 50",
+        );
+        assert!(plan.contains("idx_inbox_events_phone_id"), "{plan}");
+        assert!(!plan.contains("TEMP B-TREE"), "{plan}");
+
+        let plan = query_plan(
+            &conn,
+            "SELECT 1 FROM blocked_numbers WHERE phone = '+15551110000'",
+        );
+        assert!(plan.contains("INDEX"), "{plan}");
+    }
+
+    #[test]
+    fn test_backup_includes_uncheckpointed_wal_writes() {
+        let path = std::env::temp_dir().join(format!("phonebook-test-{}.db", uuid::Uuid::new_v4()));
+        let conn = init_db(path.to_str().unwrap()).unwrap();
+        conn.execute_batch("PRAGMA wal_autocheckpoint=0;").unwrap();
+        queries::block_number(&conn, "+15551110000", None, false).unwrap();
+
+        let shared = Mutex::new(conn);
+        let mut file = backup_to_file(path.to_str().unwrap(), &shared).unwrap();
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut file, &mut bytes).unwrap();
+        assert!(bytes.starts_with(b"SQLite format 3\0"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(file.metadata().unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let copy_path = path.with_extension("copy.db");
+        std::fs::write(&copy_path, &bytes).unwrap();
+        let copy = Connection::open(&copy_path).unwrap();
+        assert!(queries::is_blocked(&copy, "+15551110000").unwrap());
+
+        drop(copy);
+        drop(shared);
+        for p in [&path, &copy_path] {
+            remove_db_files(p);
+        }
+    }
+
+    fn remove_db_files(path: &std::path::Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut f = path.to_path_buf().into_os_string();
+            f.push(suffix);
+            let _ = std::fs::remove_file(f);
+        }
+    }
+
+    /// Hold the write lock on `path` from a second connection for `hold`.
+    fn hold_write_lock(path: &std::path::Path, hold: Duration) -> std::thread::JoinHandle<()> {
+        let other = Connection::open(path).unwrap();
+        other.execute_batch("BEGIN IMMEDIATE").unwrap();
+        std::thread::spawn(move || {
+            std::thread::sleep(hold);
+            other.execute_batch("COMMIT").unwrap();
+        })
+    }
+
+    #[test]
+    fn test_writes_wait_out_a_concurrent_writer() {
+        let path = std::env::temp_dir().join(format!("phonebook-test-{}.db", uuid::Uuid::new_v4()));
+        let conn = init_db(path.to_str().unwrap()).unwrap();
+
+        let writer = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let conn = init_db(path.to_str().unwrap()).unwrap();
+                for i in 0..50 {
+                    queries::block_number(&conn, &format!("+1555111{i:04}"), None, false).unwrap();
+                }
+            }
+        });
+        for _ in 0..50 {
+            queries::increment_message_count(&conn, "+15552220000").unwrap();
+        }
+        writer.join().unwrap();
+        assert_eq!(
+            queries::increment_message_count(&conn, "+15552220000").unwrap(),
+            51
+        );
+
+        // Past the busy timeout, the retry loop still gets the write through
+        conn.busy_timeout(Duration::ZERO).unwrap();
+        let holder = hold_write_lock(&path, Duration::from_millis(100));
+        let err = queries::block_number(&conn, "+15553330000", None, false).unwrap_err();
+        assert!(is_busy(&err), "{err:#}");
+        retry_on_busy(|| queries::block_number(&conn, "+15553330000", None, false)).unwrap();
+        holder.join().unwrap();
+        assert!(queries::is_blocked(&conn, "+15553330000").unwrap());
+
+        drop(conn);
+        remove_db_files(&path);
+    }
+}
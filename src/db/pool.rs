@@ -0,0 +1,95 @@
+//! Async access to the shared SQLite connection. Queries run on tokio's
+//! blocking thread pool, so waiting on the lock or on disk I/O never stalls
+//! the async workers that serve other requests.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use rusqlite::Connection;
+
+use crate::db::{self, queries};
+use crate::models::{Booking, BookingActor};
+
+pub type Db = Arc<Mutex<Connection>>;
+
+/// Run `f` against the connection on the blocking pool.
+pub async fn with_db<F, T>(db: &Db, f: F) -> anyhow::Result<T>
+where
+    F: FnOnce(&Connection) -> anyhow::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let db = Arc::clone(db);
+    tokio::task::spawn_blocking(move || {
+        let conn = db.lock().unwrap();
+        f(&conn)
+    })
+    .await
+    .context("database task panicked")?
+}
+
+/// Like [`with_db`] for writes: retried via [`db::retry_on_busy`] if the
+/// database stays busy past its timeout.
+pub async fn write<F, T>(db: &Db, mut f: F) -> anyhow::Result<T>
+where
+    F: FnMut(&Connection) -> anyhow::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    with_db(db, move |conn| db::retry_on_busy(|| f(conn))).await
+}
+
+pub async fn is_blocked(db: &Db, phone: &str) -> anyhow::Result<bool> {
+    let phone = phone.to_string();
+    with_db(db, move |conn| queries::is_blocked(conn, &phone)).await
+}
+
+pub async fn is_opted_out(db: &Db, phone: &str) -> anyhow::Result<bool> {
+    let phone = phone.to_string();
+    with_db(db, move |conn| queries::is_opted_out(conn, &phone)).await
+}
+
+pub async fn is_ai_enabled(db: &Db, phone: &str) -> anyhow::Result<bool> {
+    let phone = phone.to_string();
+    with_db(db, move |conn| queries::is_ai_enabled(conn, &phone)).await
+}
+
+pub async fn needs_human(db: &Db, phone: &str) -> anyhow::Result<bool> {
+    let phone = phone.to_string();
+    with_db(db, move |conn| queries::needs_human(conn, &phone)).await
+}
+
+pub async fn increment_message_count(db: &Db, phone: &str) -> anyhow::Result<i64> {
+    let phone = phone.to_string();
+    write(db, move |conn| {
+        queries::increment_message_count(conn, &phone)
+    })
+    .await
+}
+
+pub async fn create_booking(db: &Db, booking: Booking, actor: BookingActor) -> anyhow::Result<()> {
+    write(db, move |conn| {
+        queries::create_booking(conn, &booking, actor)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_queries_round_trip() {
+        let db: Db = Arc::new(Mutex::new(db::init_db(":memory:").unwrap()));
+
+        assert!(!is_blocked(&db, "+15551110000").await.unwrap());
+        assert_eq!(increment_message_count(&db, "+15551110000").await.unwrap(), 1);
+        assert_eq!(increment_message_count(&db, "+15551110000").await.unwrap(), 2);
+
+        let blocked = with_db(&db, |conn| {
+            queries::block_number(conn, "+15551110000", None, false)?;
+            queries::is_blocked(conn, "+15551110000")
+        })
+        .await
+        .unwrap();
+        assert!(blocked);
+    }
+}
@@ -1,20 +1,59 @@
-use chrono::{NaiveDateTime, Utc};
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use rusqlite::{params, Connection};
+use serde::Serialize;
 
 use crate::models::{
-    Booking, BookingStatus, Conversation, ConversationMessage, ConversationState, InboxEvent,
+    Booking, BookingActor, BookingEvent, BookingLink, BookingStatus, Conversation, ConversationMessage, ConversationState, InboxEvent,
     InboxThread, PendingBooking, User,
 };
 
 // ── Conversations ──
 
-pub fn get_conversation(conn: &Connection, phone: &str) -> anyhow::Result<Option<Conversation>> {
-    let now = Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
+/// The customer's live conversation. With `include_expired`, whatever is
+/// stored is returned even past its expiry, for debugging.
+pub fn get_conversation(
+    conn: &Connection,
+    phone: &str,
+    include_expired: bool,
+) -> anyhow::Result<Option<Conversation>> {
+    if include_expired {
+        load_conversation(conn, phone, None)
+    } else {
+        get_conversation_with_grace(conn, phone, 0)
+    }
+}
+
+/// Like `get_conversation`, but a conversation that expired less than
+/// `grace_minutes` ago is still returned when it holds a pending booking, so
+/// a slightly late "yes" confirms instead of starting over.
+pub fn get_conversation_with_grace(
+    conn: &Connection,
+    phone: &str,
+    grace_minutes: i64,
+) -> anyhow::Result<Option<Conversation>> {
+    load_conversation(conn, phone, Some(grace_minutes))
+}
+
+/// `grace_minutes` of `None` skips the expiry checks entirely.
+fn load_conversation(
+    conn: &Connection,
+    phone: &str,
+    grace_minutes: Option<i64>,
+) -> anyhow::Result<Option<Conversation>> {
+    let now = Utc::now().naive_utc();
+    // An empty cutoff sorts before every stored timestamp
+    let cutoff = grace_minutes.map_or_else(String::new, |grace| {
+        (now - chrono::Duration::minutes(grace.max(0)))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    });
     let mut stmt = conn.prepare(
-        "SELECT phone, messages, state, last_activity, expires_at FROM conversations WHERE phone = ?1 AND expires_at > ?2",
+        "SELECT phone, messages, state, last_activity, expires_at, summary FROM conversations WHERE phone = ?1 AND expires_at > ?2",
     )?;
 
-    let result = stmt.query_row(params![phone, now], |row| {
+    let result = stmt.query_row(params![phone, cutoff], |row| {
         let messages_json: String = row.get(1)?;
         let state_str: String = row.get(2)?;
         let last_activity_str: String = row.get(3)?;
@@ -26,14 +65,23 @@ pub fn get_conversation(conn: &Connection, phone: &str) -> anyhow::Result<Option
             state_str,
             last_activity_str,
             expires_at_str,
+            row.get::<_, Option<String>>(5)?,
         ))
     });
 
     match result {
-        Ok((phone, messages_json, state_str, last_activity_str, expires_at_str)) => {
+        Ok((phone, messages_json, state_str, last_activity_str, expires_at_str, summary)) => {
             let data: serde_json::Value =
                 serde_json::from_str(&messages_json).unwrap_or(serde_json::json!({}));
 
+            let paused_autoreply_sent = data
+                .get("paused_autoreply_sent")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let reprompt_sent = data
+                .get("reprompt_sent")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
             let (messages, pending_booking): (Vec<ConversationMessage>, Option<PendingBooking>) =
                 if data.is_array() {
                     // Legacy format: just an array of messages
@@ -56,12 +104,18 @@ pub fn get_conversation(conn: &Connection, phone: &str) -> anyhow::Result<Option
             let expires_at =
                 NaiveDateTime::parse_from_str(&expires_at_str, "%Y-%m-%d %H:%M:%S")
                     .unwrap_or_else(|_| Utc::now().naive_utc());
+            if grace_minutes.is_some() && expires_at <= now && pending_booking.is_none() {
+                return Ok(None);
+            }
 
             Ok(Some(Conversation {
                 phone,
                 messages,
                 state: ConversationState::parse(&state_str),
                 pending_booking,
+                paused_autoreply_sent,
+                reprompt_sent,
+                summary,
                 last_activity,
                 expires_at,
             }))
@@ -75,6 +129,8 @@ pub fn save_conversation(conn: &Connection, conv: &Conversation) -> anyhow::Resu
     let data = serde_json::json!({
         "messages": conv.messages,
         "pending_booking": conv.pending_booking,
+        "paused_autoreply_sent": conv.paused_autoreply_sent,
+        "reprompt_sent": conv.reprompt_sent,
     });
     let messages_json = serde_json::to_string(&data)?;
     let state_str = conv.state.as_str();
@@ -82,34 +138,73 @@ pub fn save_conversation(conn: &Connection, conv: &Conversation) -> anyhow::Resu
     let expires_at = conv.expires_at.format("%Y-%m-%d %H:%M:%S").to_string();
 
     conn.execute(
-        "INSERT INTO conversations (phone, messages, state, last_activity, expires_at)
-         VALUES (?1, ?2, ?3, ?4, ?5)
+        "INSERT INTO conversations (phone, messages, state, last_activity, expires_at, summary)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
          ON CONFLICT(phone) DO UPDATE SET
            messages = excluded.messages,
            state = excluded.state,
            last_activity = excluded.last_activity,
-           expires_at = excluded.expires_at",
-        params![conv.phone, messages_json, state_str, last_activity, expires_at],
+           expires_at = excluded.expires_at,
+           summary = excluded.summary",
+        params![conv.phone, messages_json, state_str, last_activity, expires_at, conv.summary],
     )?;
     Ok(())
 }
 
-pub fn expire_old_conversations(conn: &Connection) -> anyhow::Result<usize> {
+/// Live conversations waiting on a booking confirmation with no activity
+/// since `idle_since`.
+pub fn get_stale_confirming(
+    conn: &Connection,
+    idle_since: &NaiveDateTime,
+) -> anyhow::Result<Vec<String>> {
     let now = Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
-    let count = conn.execute("DELETE FROM conversations WHERE expires_at <= ?1", params![now])?;
+    let idle_since = idle_since.format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut stmt = conn.prepare(
+        "SELECT phone FROM conversations
+         WHERE state = 'confirming' AND last_activity <= ?1 AND expires_at > ?2",
+    )?;
+    let rows = stmt.query_map(params![idle_since, now], |row| row.get(0))?;
+
+    let mut phones = vec![];
+    for row in rows {
+        phones.push(row?);
+    }
+    Ok(phones)
+}
+
+/// Delete conversations that expired more than `grace_minutes` ago.
+pub fn expire_old_conversations(conn: &Connection, grace_minutes: i64) -> anyhow::Result<usize> {
+    let cutoff = (Utc::now().naive_utc() - chrono::Duration::minutes(grace_minutes.max(0)))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    let count = conn.execute(
+        "DELETE FROM conversations WHERE expires_at <= ?1",
+        params![cutoff],
+    )?;
     Ok(count)
 }
 
+/// Forget `phone`'s conversation so its next text starts fresh. Returns
+/// whether one was stored.
+pub fn delete_conversation(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let deleted = conn.execute("DELETE FROM conversations WHERE phone = ?1", params![phone])?;
+    Ok(deleted > 0)
+}
+
 // ── Bookings ──
 
-pub fn create_booking(conn: &Connection, booking: &Booking) -> anyhow::Result<()> {
+pub fn create_booking(
+    conn: &Connection,
+    booking: &Booking,
+    actor: BookingActor,
+) -> anyhow::Result<()> {
     let date_time = booking.date_time.format("%Y-%m-%d %H:%M:%S").to_string();
     let created_at = booking.created_at.format("%Y-%m-%d %H:%M:%S").to_string();
     let updated_at = booking.updated_at.format("%Y-%m-%d %H:%M:%S").to_string();
 
     conn.execute(
-        "INSERT INTO bookings (id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        "INSERT INTO bookings (id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
         params![
             booking.id,
             booking.customer_phone,
@@ -120,15 +215,24 @@ pub fn create_booking(conn: &Connection, booking: &Booking) -> anyhow::Result<()
             booking.notes,
             created_at,
             updated_at,
+            booking.service,
         ],
     )?;
+    record_booking_event(
+        conn,
+        &booking.id,
+        "create",
+        actor,
+        None,
+        &booking.status,
+    )?;
     Ok(())
 }
 
 pub fn get_bookings_for_phone(conn: &Connection, phone: &str) -> anyhow::Result<Vec<Booking>> {
     let mut stmt = conn.prepare(
-        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at
-         FROM bookings WHERE customer_phone = ?1 AND status != 'cancelled' ORDER BY date_time ASC",
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings WHERE customer_phone = ?1 AND status NOT IN ('cancelled', 'no_show') ORDER BY date_time ASC",
     )?;
 
     let rows = stmt.query_map(params![phone], |row| {
@@ -142,6 +246,29 @@ pub fn get_bookings_for_phone(conn: &Connection, phone: &str) -> anyhow::Result<
     Ok(bookings)
 }
 
+/// A customer's non-cancelled bookings starting on `date`.
+pub fn get_bookings_for_phone_on_date(
+    conn: &Connection,
+    phone: &str,
+    date: &NaiveDate,
+) -> anyhow::Result<Vec<Booking>> {
+    let day = date.format("%Y-%m-%d").to_string();
+    let mut stmt = conn.prepare(
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings WHERE customer_phone = ?1 AND date(date_time) = ?2 AND status NOT IN ('cancelled', 'no_show') ORDER BY date_time ASC",
+    )?;
+
+    let rows = stmt.query_map(params![phone, day], |row| {
+        Ok(parse_booking_row(row))
+    })?;
+
+    let mut bookings = vec![];
+    for row in rows {
+        bookings.push(row??);
+    }
+    Ok(bookings)
+}
+
 pub fn get_bookings_in_range(
     conn: &Connection,
     start: &NaiveDateTime,
@@ -151,8 +278,8 @@ pub fn get_bookings_in_range(
     let end_str = end.format("%Y-%m-%d %H:%M:%S").to_string();
 
     let mut stmt = conn.prepare(
-        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at
-         FROM bookings WHERE date_time >= ?1 AND date_time <= ?2 AND status != 'cancelled' ORDER BY date_time ASC",
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings WHERE date_time >= ?1 AND date_time <= ?2 AND status NOT IN ('cancelled', 'no_show') ORDER BY date_time ASC",
     )?;
 
     let rows = stmt.query_map(params![start_str, end_str], |row| {
@@ -166,11 +293,139 @@ pub fn get_bookings_in_range(
     Ok(bookings)
 }
 
+/// Bookings for the calendar feed: one `status` if given, otherwise all of
+/// them. Cancelled ones stay in so subscribers see `STATUS:CANCELLED`.
+pub fn get_feed_bookings(
+    conn: &Connection,
+    start: &NaiveDateTime,
+    end: &NaiveDateTime,
+    status: Option<&BookingStatus>,
+) -> anyhow::Result<Vec<Booking>> {
+    let start_str = start.format("%Y-%m-%d %H:%M:%S").to_string();
+    let end_str = end.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let mut stmt = conn.prepare(
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings WHERE date_time >= ?1 AND date_time <= ?2
+           AND (?3 IS NULL OR status = ?3)
+         ORDER BY date_time ASC",
+    )?;
+
+    let rows = stmt.query_map(params![start_str, end_str, status.map(|s| s.as_str())], |row| {
+        Ok(parse_booking_row(row))
+    })?;
+
+    let mut bookings = vec![];
+    for row in rows {
+        bookings.push(row??);
+    }
+    Ok(bookings)
+}
+
+/// Non-cancelled bookings starting after `now` and by `until` whose reminder
+/// hasn't gone out yet.
+pub fn get_bookings_due_for_reminder(
+    conn: &Connection,
+    now: &NaiveDateTime,
+    until: &NaiveDateTime,
+) -> anyhow::Result<Vec<Booking>> {
+    let now_str = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    let until_str = until.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let mut stmt = conn.prepare(
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings WHERE date_time > ?1 AND date_time <= ?2 AND status NOT IN ('cancelled', 'no_show')
+           AND reminder_sent_at IS NULL
+         ORDER BY date_time ASC",
+    )?;
+
+    let rows = stmt.query_map(params![now_str, until_str], |row| {
+        Ok(parse_booking_row(row))
+    })?;
+
+    let mut bookings = vec![];
+    for row in rows {
+        bookings.push(row??);
+    }
+    Ok(bookings)
+}
+
+pub fn mark_reminder_sent(conn: &Connection, id: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE bookings SET reminder_sent_at = datetime('now') WHERE id = ?1",
+        params![id],
+    )?;
+    Ok(())
+}
+
+/// The customer's upcoming, non-cancelled booking most recently reminded at
+/// or after `since`.
+pub fn get_reminded_booking(
+    conn: &Connection,
+    phone: &str,
+    since: &NaiveDateTime,
+) -> anyhow::Result<Option<Booking>> {
+    let since_str = since.format("%Y-%m-%d %H:%M:%S").to_string();
+    let now = Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
+    let result = conn.query_row(
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings
+         WHERE customer_phone = ?1 AND reminder_sent_at >= ?2 AND date_time > ?3
+           AND status NOT IN ('cancelled', 'no_show')
+         ORDER BY reminder_sent_at DESC, date_time ASC
+         LIMIT 1",
+        params![phone, since_str, now],
+        |row| Ok(parse_booking_row(row)),
+    );
+
+    match result {
+        Ok(booking) => Ok(Some(booking?)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub fn update_booking_status(
     conn: &Connection,
     id: &str,
     status: &BookingStatus,
+    actor: BookingActor,
 ) -> anyhow::Result<bool> {
+    let event = match status {
+        BookingStatus::Pending => "pending",
+        BookingStatus::Confirmed => "confirm",
+        BookingStatus::Cancelled => "cancel",
+        BookingStatus::NoShow => "no_show",
+    };
+    set_booking_status(conn, id, status, event, actor)
+}
+
+/// Cancel a booking that is being replaced by a new one, logged as a reschedule.
+pub fn mark_booking_rescheduled(
+    conn: &Connection,
+    id: &str,
+    actor: BookingActor,
+) -> anyhow::Result<bool> {
+    set_booking_status(conn, id, &BookingStatus::Cancelled, "reschedule", actor)
+}
+
+fn set_booking_status(
+    conn: &Connection,
+    id: &str,
+    status: &BookingStatus,
+    event: &str,
+    actor: BookingActor,
+) -> anyhow::Result<bool> {
+    let old_status: Option<String> = match conn.query_row(
+        "SELECT status FROM bookings WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    ) {
+        Ok(s) => Some(s),
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+
     let now = Utc::now()
         .naive_utc()
         .format("%Y-%m-%d %H:%M:%S")
@@ -179,9 +434,83 @@ pub fn update_booking_status(
         "UPDATE bookings SET status = ?1, updated_at = ?2 WHERE id = ?3",
         params![status.as_str(), now, id],
     )?;
+    if count > 0 {
+        record_booking_event(conn, id, event, actor, old_status.as_deref(), status)?;
+    }
     Ok(count > 0)
 }
 
+// ── Booking Events ──
+
+fn record_booking_event(
+    conn: &Connection,
+    booking_id: &str,
+    event: &str,
+    actor: BookingActor,
+    old_status: Option<&str>,
+    new_status: &BookingStatus,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO booking_events (booking_id, event, actor, old_status, new_status)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![booking_id, event, actor.as_str(), old_status, new_status.as_str()],
+    )?;
+    Ok(())
+}
+
+/// How many times each booking changed after it was created, keyed by booking
+/// id. Used as the .ics SEQUENCE so calendar clients replace stale copies.
+pub fn get_booking_sequences(conn: &Connection) -> anyhow::Result<HashMap<String, u32>> {
+    let mut stmt = conn.prepare(
+        "SELECT booking_id, COUNT(*) FROM booking_events
+         WHERE event != 'create' GROUP BY booking_id",
+    )?;
+
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let mut sequences = HashMap::new();
+    for row in rows {
+        let (id, count) = row?;
+        sequences.insert(id, count);
+    }
+    Ok(sequences)
+}
+
+pub fn get_booking_sequence(conn: &Connection, booking_id: &str) -> anyhow::Result<u32> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM booking_events WHERE booking_id = ?1 AND event != 'create'",
+        params![booking_id],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+pub fn get_booking_events(conn: &Connection, booking_id: &str) -> anyhow::Result<Vec<BookingEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, booking_id, event, actor, old_status, new_status, created_at
+         FROM booking_events WHERE booking_id = ?1
+         ORDER BY id ASC",
+    )?;
+
+    let rows = stmt.query_map(params![booking_id], |row| {
+        Ok(BookingEvent {
+            id: row.get(0)?,
+            booking_id: row.get(1)?,
+            event: row.get(2)?,
+            actor: row.get(3)?,
+            old_status: row.get(4)?,
+            new_status: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    })?;
+
+    let mut events = vec![];
+    for row in rows {
+        events.push(row?);
+    }
+    Ok(events)
+}
+
 pub fn get_all_bookings(
     conn: &Connection,
     status_filter: Option<&str>,
@@ -189,7 +518,7 @@ pub fn get_all_bookings(
 ) -> anyhow::Result<Vec<Booking>> {
     let (sql, params_vec): (String, Vec<Box<dyn rusqlite::types::ToSql>>) = match status_filter {
         Some(status) => (
-            "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at \
+            "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service \
              FROM bookings WHERE status = ?1 ORDER BY date_time DESC LIMIT ?2"
                 .to_string(),
             vec![
@@ -198,7 +527,7 @@ pub fn get_all_bookings(
             ],
         ),
         None => (
-            "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at \
+            "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service \
              FROM bookings ORDER BY date_time DESC LIMIT ?1"
                 .to_string(),
             vec![Box::new(limit) as Box<dyn rusqlite::types::ToSql>],
@@ -218,7 +547,7 @@ pub fn get_all_bookings(
 
 pub fn get_booking_by_id(conn: &Connection, id: &str) -> anyhow::Result<Option<Booking>> {
     let result = conn.query_row(
-        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at \
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service \
          FROM bookings WHERE id = ?1",
         params![id],
         |row| Ok(parse_booking_row(row)),
@@ -231,6 +560,54 @@ pub fn get_booking_by_id(conn: &Connection, id: &str) -> anyhow::Result<Option<B
     }
 }
 
+// ── Booking Links ──
+
+/// Issue a new link token for `booking_id`, valid until `expires_at`.
+pub fn create_booking_link(
+    conn: &Connection,
+    booking_id: &str,
+    action: &str,
+    expires_at: &NaiveDateTime,
+) -> anyhow::Result<String> {
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    conn.execute(
+        "INSERT INTO booking_links (token, booking_id, action, expires_at) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            token,
+            booking_id,
+            action,
+            expires_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ],
+    )?;
+    Ok(token)
+}
+
+pub fn get_booking_link(conn: &Connection, token: &str) -> anyhow::Result<Option<BookingLink>> {
+    let result = conn.query_row(
+        "SELECT token, booking_id, action, expires_at FROM booking_links WHERE token = ?1",
+        params![token],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        },
+    );
+
+    match result {
+        Ok((token, booking_id, action, expires_at)) => Ok(Some(BookingLink {
+            token,
+            booking_id,
+            action,
+            expires_at: NaiveDateTime::parse_from_str(&expires_at, "%Y-%m-%d %H:%M:%S")?,
+        })),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub fn get_dashboard_stats(conn: &Connection) -> anyhow::Result<DashboardStats> {
     let now = Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
     let window = current_hour_window();
@@ -244,7 +621,11 @@ pub fn get_dashboard_stats(conn: &Connection) -> anyhow::Result<DashboardStats>
         .unwrap_or(0);
 
     let blocked_count: i64 = conn
-        .query_row("SELECT COUNT(*) FROM blocked_numbers", [], |row| row.get(0))
+        .query_row(
+            "SELECT COUNT(*) FROM blocked_numbers WHERE blocked_until IS NULL OR blocked_until > ?1",
+            params![now],
+            |row| row.get(0),
+        )
         .unwrap_or(0);
 
     let upcoming_bookings_count: i64 = conn
@@ -278,6 +659,7 @@ fn parse_booking_row(row: &rusqlite::Row) -> anyhow::Result<Booking> {
     let notes: Option<String> = row.get(6)?;
     let created_at_str: String = row.get(7)?;
     let updated_at_str: String = row.get(8)?;
+    let service: Option<String> = row.get(9)?;
 
     let date_time = NaiveDateTime::parse_from_str(&date_time_str, "%Y-%m-%d %H:%M:%S")
         .unwrap_or_else(|_| Utc::now().naive_utc());
@@ -294,6 +676,7 @@ fn parse_booking_row(row: &rusqlite::Row) -> anyhow::Result<Booking> {
         duration_minutes,
         status: BookingStatus::parse(&status_str),
         notes,
+        service,
         created_at,
         updated_at,
     })
@@ -301,7 +684,16 @@ fn parse_booking_row(row: &rusqlite::Row) -> anyhow::Result<Booking> {
 
 // ── Blocked Numbers ──
 
+/// Expired auto-blocks are removed here, so a customer who tripped the rate
+/// limit is let back in once `blocked_until` passes. Manual blocks never expire.
 pub fn is_blocked(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let now = Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.execute(
+        "DELETE FROM blocked_numbers
+         WHERE phone = ?1 AND is_auto = 1 AND blocked_until IS NOT NULL AND blocked_until <= ?2",
+        params![phone, now],
+    )?;
+
     let count: i64 = conn.query_row(
         "SELECT COUNT(*) FROM blocked_numbers WHERE phone = ?1",
         params![phone],
@@ -310,6 +702,7 @@ pub fn is_blocked(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
     Ok(count > 0)
 }
 
+/// Block a number indefinitely (until unblocked).
 pub fn block_number(
     conn: &Connection,
     phone: &str,
@@ -317,13 +710,37 @@ pub fn block_number(
     is_auto: bool,
 ) -> anyhow::Result<()> {
     conn.execute(
-        "INSERT INTO blocked_numbers (phone, reason, is_auto) VALUES (?1, ?2, ?3)
-         ON CONFLICT(phone) DO UPDATE SET reason = excluded.reason, is_auto = excluded.is_auto",
+        "INSERT INTO blocked_numbers (phone, reason, is_auto, blocked_until) VALUES (?1, ?2, ?3, NULL)
+         ON CONFLICT(phone) DO UPDATE SET
+           reason = excluded.reason,
+           is_auto = excluded.is_auto,
+           blocked_until = NULL,
+           created_at = datetime('now')",
         params![phone, reason, is_auto as i32],
     )?;
     Ok(())
 }
 
+/// Auto-block a number until `until`. Never shortens or replaces a manual block.
+pub fn auto_block_number(
+    conn: &Connection,
+    phone: &str,
+    reason: Option<&str>,
+    until: &NaiveDateTime,
+) -> anyhow::Result<()> {
+    let until = until.format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.execute(
+        "INSERT INTO blocked_numbers (phone, reason, is_auto, blocked_until) VALUES (?1, ?2, 1, ?3)
+         ON CONFLICT(phone) DO UPDATE SET
+           reason = excluded.reason,
+           blocked_until = excluded.blocked_until,
+           created_at = datetime('now')
+         WHERE blocked_numbers.is_auto = 1",
+        params![phone, reason, until],
+    )?;
+    Ok(())
+}
+
 pub fn unblock_number(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
     let count = conn.execute(
         "DELETE FROM blocked_numbers WHERE phone = ?1",
@@ -332,14 +749,31 @@ pub fn unblock_number(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
     Ok(count > 0)
 }
 
-pub fn list_blocked(conn: &Connection) -> anyhow::Result<Vec<(String, Option<String>, bool)>> {
-    let mut stmt =
-        conn.prepare("SELECT phone, reason, is_auto FROM blocked_numbers ORDER BY created_at DESC")?;
-    let rows = stmt.query_map([], |row| {
-        let phone: String = row.get(0)?;
-        let reason: Option<String> = row.get(1)?;
-        let is_auto: bool = row.get::<_, i32>(2)? != 0;
-        Ok((phone, reason, is_auto))
+pub struct BlockedNumber {
+    pub phone: String,
+    pub reason: Option<String>,
+    pub is_auto: bool,
+    pub blocked_until: Option<String>,
+    /// When the current block started (UTC).
+    pub blocked_at: String,
+}
+
+/// Active blocks, newest first. Expired auto-blocks are left out.
+pub fn list_blocked(conn: &Connection) -> anyhow::Result<Vec<BlockedNumber>> {
+    let now = Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut stmt = conn.prepare(
+        "SELECT phone, reason, is_auto, blocked_until, created_at FROM blocked_numbers
+         WHERE blocked_until IS NULL OR blocked_until > ?1
+         ORDER BY created_at DESC",
+    )?;
+    let rows = stmt.query_map(params![now], |row| {
+        Ok(BlockedNumber {
+            phone: row.get(0)?,
+            reason: row.get(1)?,
+            is_auto: row.get::<_, i32>(2)? != 0,
+            blocked_until: row.get(3)?,
+            blocked_at: row.get(4)?,
+        })
     })?;
 
     let mut blocked = vec![];
@@ -349,6 +783,237 @@ pub fn list_blocked(conn: &Connection) -> anyhow::Result<Vec<(String, Option<Str
     Ok(blocked)
 }
 
+// ── Allowlist (rate-limit exemptions) ──
+
+pub fn is_allowlisted(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM allowlist WHERE phone = ?1",
+        params![phone],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+pub fn allow_number(conn: &Connection, phone: &str, note: Option<&str>) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO allowlist (phone, note) VALUES (?1, ?2)
+         ON CONFLICT(phone) DO UPDATE SET note = excluded.note",
+        params![phone, note],
+    )?;
+    Ok(())
+}
+
+pub fn unallow_number(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let count = conn.execute("DELETE FROM allowlist WHERE phone = ?1", params![phone])?;
+    Ok(count > 0)
+}
+
+// ── Opt-outs (STOP / START) ──
+
+pub fn is_opted_out(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM opted_out WHERE phone = ?1",
+        params![phone],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+pub fn opt_out(conn: &Connection, phone: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO opted_out (phone) VALUES (?1)",
+        params![phone],
+    )?;
+    Ok(())
+}
+
+pub fn opt_in(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let count = conn.execute("DELETE FROM opted_out WHERE phone = ?1", params![phone])?;
+    Ok(count > 0)
+}
+
+// ── Human Handoff ──
+
+/// Threads where the customer asked for a person; the agent stays silent on
+/// them until the owner replies or resumes it.
+pub fn needs_human(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM needs_human WHERE phone = ?1",
+        params![phone],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+pub fn request_human(conn: &Connection, phone: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO needs_human (phone) VALUES (?1)",
+        params![phone],
+    )?;
+    Ok(())
+}
+
+pub fn clear_needs_human(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    let count = conn.execute("DELETE FROM needs_human WHERE phone = ?1", params![phone])?;
+    Ok(count > 0)
+}
+
+// ── Per-thread AI ──
+
+/// Whether the agent auto-replies to this number. On unless the owner turned it off.
+pub fn is_ai_enabled(conn: &Connection, phone: &str) -> anyhow::Result<bool> {
+    match conn.query_row(
+        "SELECT ai_enabled FROM thread_ai WHERE phone = ?1",
+        params![phone],
+        |row| row.get::<_, i32>(0),
+    ) {
+        Ok(v) => Ok(v != 0),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn set_ai_enabled(conn: &Connection, phone: &str, enabled: bool) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO thread_ai (phone, ai_enabled) VALUES (?1, ?2)
+         ON CONFLICT(phone) DO UPDATE SET ai_enabled = excluded.ai_enabled, updated_at = datetime('now')",
+        params![phone, enabled as i32],
+    )?;
+    Ok(())
+}
+
+// ── Waitlist ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitlistEntry {
+    pub id: i64,
+    pub phone: String,
+    pub date_time: NaiveDateTime,
+    pub duration_minutes: i32,
+    pub created_at: String,
+}
+
+/// Queue a phone for a taken slot. Asking twice for the same time keeps the
+/// original place in line.
+pub fn add_to_waitlist(
+    conn: &Connection,
+    phone: &str,
+    date_time: &NaiveDateTime,
+    duration_minutes: i32,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO waitlist (phone, date_time, duration_minutes) VALUES (?1, ?2, ?3)",
+        params![
+            phone,
+            date_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            duration_minutes
+        ],
+    )?;
+    Ok(())
+}
+
+/// Future waitlist entries whose requested time overlaps `[start, start + duration)`,
+/// longest-waiting first.
+pub fn get_waitlist_overlapping(
+    conn: &Connection,
+    start: &NaiveDateTime,
+    duration_minutes: i32,
+) -> anyhow::Result<Vec<WaitlistEntry>> {
+    let end = *start + chrono::Duration::minutes(duration_minutes as i64);
+    let mut stmt = conn.prepare(
+        "SELECT id, phone, date_time, duration_minutes, created_at FROM waitlist
+         WHERE date_time < ?2
+           AND datetime(date_time, '+' || duration_minutes || ' minutes') > ?1
+           AND date_time > ?3
+         ORDER BY created_at ASC, id ASC",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            start.format("%Y-%m-%d %H:%M:%S").to_string(),
+            end.format("%Y-%m-%d %H:%M:%S").to_string(),
+            Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        ],
+        |row| {
+            let date_time: String = row.get(2)?;
+            Ok(WaitlistEntry {
+                id: row.get(0)?,
+                phone: row.get(1)?,
+                date_time: NaiveDateTime::parse_from_str(&date_time, "%Y-%m-%d %H:%M:%S")
+                    .unwrap_or_else(|_| Utc::now().naive_utc()),
+                duration_minutes: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        },
+    )?;
+
+    let mut entries = vec![];
+    for row in rows {
+        entries.push(row?);
+    }
+    Ok(entries)
+}
+
+pub fn remove_waitlist_entry(conn: &Connection, id: i64) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM waitlist WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Put back an entry taken by `remove_waitlist_entry`, keeping its place in line.
+pub fn restore_waitlist_entry(conn: &Connection, entry: &WaitlistEntry) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO waitlist (id, phone, date_time, duration_minutes, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            entry.id,
+            entry.phone,
+            entry.date_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            entry.duration_minutes,
+            entry.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+// ── Queued Replies ──
+
+#[derive(Debug, Clone)]
+pub struct QueuedReply {
+    pub id: i64,
+    pub phone: String,
+    pub body: String,
+}
+
+pub fn queue_reply(conn: &Connection, phone: &str, body: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO queued_replies (phone, body) VALUES (?1, ?2)",
+        params![phone, body],
+    )?;
+    Ok(())
+}
+
+/// Held replies, oldest first.
+pub fn get_queued_replies(conn: &Connection) -> anyhow::Result<Vec<QueuedReply>> {
+    let mut stmt = conn.prepare("SELECT id, phone, body FROM queued_replies ORDER BY id ASC")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(QueuedReply {
+            id: row.get(0)?,
+            phone: row.get(1)?,
+            body: row.get(2)?,
+        })
+    })?;
+
+    let mut replies = vec![];
+    for row in rows {
+        replies.push(row?);
+    }
+    Ok(replies)
+}
+
+pub fn remove_queued_reply(conn: &Connection, id: i64) -> anyhow::Result<()> {
+    conn.execute("DELETE FROM queued_replies WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
 // ── Rate Limits ──
 
 pub fn increment_message_count(conn: &Connection, phone: &str) -> anyhow::Result<i64> {
@@ -393,15 +1058,16 @@ pub fn get_global_message_count(conn: &Connection) -> anyhow::Result<i64> {
     Ok(count)
 }
 
-pub fn cleanup_old_windows(conn: &Connection) -> anyhow::Result<()> {
+/// Delete rate-limit windows older than two hours. Returns how many went.
+pub fn cleanup_old_windows(conn: &Connection) -> anyhow::Result<usize> {
     let cutoff = (Utc::now() - chrono::Duration::hours(2))
         .format("%Y-%m-%d %H:00:00")
         .to_string();
-    conn.execute(
+    let count = conn.execute(
         "DELETE FROM rate_limits WHERE window_start < ?1",
         params![cutoff],
     )?;
-    Ok(())
+    Ok(count)
 }
 
 fn current_hour_window() -> String {
@@ -414,6 +1080,7 @@ fn current_month() -> String {
 
 // ── Monthly Activity ──
 
+#[derive(Serialize)]
 pub struct MonthlyActivity {
     pub month: String,
     pub messages_received: i64,
@@ -433,12 +1100,13 @@ pub fn increment_monthly_received(conn: &Connection) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn increment_monthly_sent(conn: &Connection) -> anyhow::Result<()> {
+/// Count an outbound SMS by the carrier segments it used.
+pub fn increment_monthly_sent(conn: &Connection, segments: usize) -> anyhow::Result<()> {
     let month = current_month();
     conn.execute(
-        "INSERT INTO monthly_activity (month, messages_sent) VALUES (?1, 1)
-         ON CONFLICT(month) DO UPDATE SET messages_sent = messages_sent + 1",
-        params![month],
+        "INSERT INTO monthly_activity (month, messages_sent) VALUES (?1, ?2)
+         ON CONFLICT(month) DO UPDATE SET messages_sent = messages_sent + ?2",
+        params![month, segments as i64],
     )?;
     Ok(())
 }
@@ -518,9 +1186,29 @@ pub fn get_recent_monthly_activity(conn: &Connection, months: usize) -> anyhow::
 
 // ── Users ──
 
+/// The owner's phone saved in settings, or `fallback` (`OWNER_PHONE`) when
+/// none is saved.
+pub fn owner_phone(conn: &Connection, fallback: &str) -> anyhow::Result<String> {
+    let result = conn.query_row(
+        "SELECT owner_phone FROM users WHERE id = 'default'",
+        [],
+        |row| row.get::<_, String>(0),
+    );
+    let saved = match result {
+        Ok(phone) => phone,
+        Err(rusqlite::Error::QueryReturnedNoRows) => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let saved = saved.trim();
+    Ok(if saved.is_empty() { fallback } else { saved }.to_string())
+}
+
 pub fn get_user(conn: &Connection, id: &str) -> anyhow::Result<Option<User>> {
     let result = conn.query_row(
-        "SELECT id, business_name, owner_name, owner_phone, twilio_account_sid, twilio_auth_token, twilio_phone_number, availability, timezone, ai_preferences
+        "SELECT id, business_name, owner_name, owner_phone, twilio_account_sid, twilio_auth_token, twilio_phone_number, availability, timezone, ai_preferences, language, services, location,
+                notify_on_booking, notify_on_cancel, notify_on_block, notify_on_pause, quiet_hours,
+                one_booking_per_customer_per_day, reminder_hours_before, reminder_template,
+                spam_keywords, block_spam, use_24h_time
          FROM users WHERE id = ?1",
         params![id],
         |row| {
@@ -535,6 +1223,20 @@ pub fn get_user(conn: &Connection, id: &str) -> anyhow::Result<Option<User>> {
                 availability: row.get(7)?,
                 timezone: row.get(8)?,
                 ai_preferences: row.get(9)?,
+                language: row.get(10)?,
+                services: row.get(11)?,
+                location: row.get(12)?,
+                notify_on_booking: row.get(13)?,
+                notify_on_cancel: row.get(14)?,
+                notify_on_block: row.get(15)?,
+                notify_on_pause: row.get(16)?,
+                quiet_hours: row.get(17)?,
+                one_booking_per_customer_per_day: row.get(18)?,
+                reminder_hours_before: row.get(19)?,
+                reminder_template: row.get(20)?,
+                spam_keywords: row.get(21)?,
+                block_spam: row.get(22)?,
+                use_24h_time: row.get(23)?,
             })
         },
     );
@@ -548,8 +1250,11 @@ pub fn get_user(conn: &Connection, id: &str) -> anyhow::Result<Option<User>> {
 
 pub fn save_user(conn: &Connection, user: &User) -> anyhow::Result<()> {
     conn.execute(
-        "INSERT INTO users (id, business_name, owner_name, owner_phone, twilio_account_sid, twilio_auth_token, twilio_phone_number, availability, timezone, ai_preferences)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        "INSERT INTO users (id, business_name, owner_name, owner_phone, twilio_account_sid, twilio_auth_token, twilio_phone_number, availability, timezone, ai_preferences, language, services, location,
+                            notify_on_booking, notify_on_cancel, notify_on_block, notify_on_pause, quiet_hours,
+                            one_booking_per_customer_per_day, reminder_hours_before, reminder_template,
+                            spam_keywords, block_spam, use_24h_time)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24)
          ON CONFLICT(id) DO UPDATE SET
            business_name = excluded.business_name,
            owner_name = excluded.owner_name,
@@ -560,6 +1265,20 @@ pub fn save_user(conn: &Connection, user: &User) -> anyhow::Result<()> {
            availability = excluded.availability,
            timezone = excluded.timezone,
            ai_preferences = excluded.ai_preferences,
+           language = excluded.language,
+           services = excluded.services,
+           location = excluded.location,
+           notify_on_booking = excluded.notify_on_booking,
+           notify_on_cancel = excluded.notify_on_cancel,
+           notify_on_block = excluded.notify_on_block,
+           notify_on_pause = excluded.notify_on_pause,
+           quiet_hours = excluded.quiet_hours,
+           one_booking_per_customer_per_day = excluded.one_booking_per_customer_per_day,
+           reminder_hours_before = excluded.reminder_hours_before,
+           reminder_template = excluded.reminder_template,
+           spam_keywords = excluded.spam_keywords,
+           block_spam = excluded.block_spam,
+           use_24h_time = excluded.use_24h_time,
            updated_at = datetime('now')",
         params![
             user.id,
@@ -572,11 +1291,71 @@ pub fn save_user(conn: &Connection, user: &User) -> anyhow::Result<()> {
             user.availability,
             user.timezone,
             user.ai_preferences,
+            user.language,
+            user.services,
+            user.location,
+            user.notify_on_booking,
+            user.notify_on_cancel,
+            user.notify_on_block,
+            user.notify_on_pause,
+            user.quiet_hours,
+            user.one_booking_per_customer_per_day,
+            user.reminder_hours_before,
+            user.reminder_template,
+            user.spam_keywords,
+            user.block_spam,
+            user.use_24h_time,
         ],
     )?;
     Ok(())
 }
 
+// ── Message Status ──
+
+/// Store the latest delivery status for an outbound message, returning the
+/// previously recorded status (if any) so callers can detect transitions.
+pub fn upsert_message_status(
+    conn: &Connection,
+    sid: &str,
+    status: &str,
+    error_code: Option<&str>,
+    to_phone: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let previous = match conn.query_row(
+        "SELECT status FROM message_status WHERE sid = ?1",
+        params![sid],
+        |row| row.get::<_, String>(0),
+    ) {
+        Ok(status) => Some(status),
+        Err(rusqlite::Error::QueryReturnedNoRows) => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    conn.execute(
+        "INSERT INTO message_status (sid, status, error_code, to_phone) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(sid) DO UPDATE SET
+             status = excluded.status,
+             error_code = excluded.error_code,
+             to_phone = COALESCE(excluded.to_phone, message_status.to_phone),
+             updated_at = datetime('now')",
+        params![sid, status, error_code, to_phone],
+    )?;
+    Ok(previous)
+}
+
+pub fn get_message_status(conn: &Connection, sid: &str) -> anyhow::Result<Option<(String, Option<String>)>> {
+    let result = conn.query_row(
+        "SELECT status, error_code FROM message_status WHERE sid = ?1",
+        params![sid],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    );
+    match result {
+        Ok(row) => Ok(Some(row)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
 // ── Inbox Events ──
 
 pub fn insert_inbox_event(
@@ -592,14 +1371,31 @@ pub fn insert_inbox_event(
     Ok(conn.last_insert_rowid())
 }
 
+/// Delete inbox events created before `cutoff`, except each thread's newest
+/// event so the thread stays in the inbox. Returns how many went.
+pub fn prune_inbox_events(conn: &Connection, cutoff: NaiveDateTime) -> anyhow::Result<usize> {
+    let count = conn.execute(
+        "DELETE FROM inbox_events
+         WHERE created_at < ?1
+           AND id NOT IN (SELECT MAX(id) FROM inbox_events GROUP BY phone)",
+        params![cutoff.format("%Y-%m-%d %H:%M:%S").to_string()],
+    )?;
+    Ok(count)
+}
+
+/// One row per phone with its latest event and unread count, newest first.
+/// Both come from a single `GROUP BY phone` pass over the events.
 pub fn get_inbox_threads(conn: &Connection) -> anyhow::Result<Vec<InboxThread>> {
     let mut stmt = conn.prepare(
-        "SELECT e.phone, e.content, e.kind, e.created_at,
-                (SELECT COUNT(*) FROM inbox_events e2 WHERE e2.phone = e.phone AND e2.is_read = 0) as unread_count
-         FROM inbox_events e
-         INNER JOIN (
-             SELECT phone, MAX(id) as max_id FROM inbox_events GROUP BY phone
-         ) latest ON e.id = latest.max_id
+        "SELECT e.phone, e.content, e.kind, e.created_at, latest.unread_count,
+                cn.notes, cn.tags, COALESCE(ta.ai_enabled, 1)
+         FROM (
+             SELECT phone, MAX(id) as max_id, SUM(is_read = 0) as unread_count
+             FROM inbox_events GROUP BY phone
+         ) latest
+         INNER JOIN inbox_events e ON e.id = latest.max_id
+         LEFT JOIN contact_notes cn ON e.phone = cn.phone
+         LEFT JOIN thread_ai ta ON e.phone = ta.phone
          ORDER BY e.created_at DESC",
     )?;
 
@@ -610,6 +1406,9 @@ pub fn get_inbox_threads(conn: &Connection) -> anyhow::Result<Vec<InboxThread>>
             last_kind: row.get(2)?,
             last_activity: row.get(3)?,
             unread_count: row.get(4)?,
+            notes: row.get(5)?,
+            tags: row.get(6)?,
+            ai_enabled: row.get::<_, i32>(7)? != 0,
         })
     })?;
 
@@ -649,6 +1448,40 @@ pub fn get_thread_events(
     Ok(events)
 }
 
+/// The `limit` events just older than `before_id`, oldest first, so a chat
+/// view can page backward by passing the first id it already has.
+pub fn get_thread_events_before(
+    conn: &Connection,
+    phone: &str,
+    before_id: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<InboxEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, phone, kind, content, is_read, created_at FROM (
+             SELECT id, phone, kind, content, is_read, created_at
+             FROM inbox_events WHERE phone = ?1 AND id < ?2
+             ORDER BY id DESC LIMIT ?3
+         ) ORDER BY id ASC",
+    )?;
+
+    let rows = stmt.query_map(params![phone, before_id, limit], |row| {
+        Ok(InboxEvent {
+            id: row.get(0)?,
+            phone: row.get(1)?,
+            kind: row.get(2)?,
+            content: row.get(3)?,
+            is_read: row.get::<_, i32>(4)? != 0,
+            created_at: row.get(5)?,
+        })
+    })?;
+
+    let mut events = vec![];
+    for row in rows {
+        events.push(row?);
+    }
+    Ok(events)
+}
+
 pub fn get_inbox_events_since(conn: &Connection, since_id: i64) -> anyhow::Result<Vec<InboxEvent>> {
     let mut stmt = conn.prepare(
         "SELECT id, phone, kind, content, is_read, created_at
@@ -674,6 +1507,15 @@ pub fn get_inbox_events_since(conn: &Connection, since_id: i64) -> anyhow::Resul
     Ok(events)
 }
 
+pub fn get_thread_unread_count(conn: &Connection, phone: &str) -> anyhow::Result<i64> {
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM inbox_events WHERE phone = ?1 AND is_read = 0",
+        params![phone],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
 pub fn mark_thread_read(conn: &Connection, phone: &str) -> anyhow::Result<()> {
     conn.execute(
         "UPDATE inbox_events SET is_read = 1 WHERE phone = ?1 AND is_read = 0",
@@ -684,44 +1526,74 @@ pub fn mark_thread_read(conn: &Connection, phone: &str) -> anyhow::Result<()> {
 
 // ── Contacts ──
 
+#[derive(Debug, Serialize)]
 pub struct ContactSummary {
     pub phone: String,
     pub name: Option<String>,
     pub total_bookings: i64,
+    /// Bookings marked as no-shows; counted in `total_bookings` too.
+    pub no_shows: i64,
     pub last_booking: Option<String>,
     pub first_seen: String,
+    pub notes: Option<String>,
+    pub tags: Option<String>,
 }
 
 pub fn get_contacts(conn: &Connection, limit: i64) -> anyhow::Result<Vec<ContactSummary>> {
-    let mut stmt = conn.prepare(
+    query_contacts(conn, "", params![limit])
+}
+
+/// The summary for one phone, if it has an inbox thread.
+pub fn get_contact(conn: &Connection, phone: &str) -> anyhow::Result<Option<ContactSummary>> {
+    let contacts = query_contacts(conn, "WHERE ie.phone = ?2", params![1, phone])?;
+    Ok(contacts.into_iter().next())
+}
+
+/// Contacts with an inbox thread, newest activity first; `?1` is the limit
+/// and `filter` may use further parameters.
+fn query_contacts(
+    conn: &Connection,
+    filter: &str,
+    params: impl rusqlite::Params,
+) -> anyhow::Result<Vec<ContactSummary>> {
+    let mut stmt = conn.prepare(&format!(
         "SELECT
             ie.phone,
             b_agg.customer_name,
             COALESCE(b_agg.total_bookings, 0),
+            COALESCE(b_agg.no_shows, 0),
             b_agg.last_booking,
-            MIN(ie.created_at) as first_seen
+            MIN(ie.created_at) as first_seen,
+            cn.notes,
+            cn.tags
          FROM inbox_events ie
          LEFT JOIN (
              SELECT customer_phone,
                     MAX(customer_name) as customer_name,
                     COUNT(*) as total_bookings,
+                    SUM(status = 'no_show') as no_shows,
                     MAX(date_time) as last_booking
              FROM bookings
              WHERE status != 'cancelled'
              GROUP BY customer_phone
          ) b_agg ON ie.phone = b_agg.customer_phone
+         LEFT JOIN contact_notes cn ON ie.phone = cn.phone
+         {filter}
          GROUP BY ie.phone
          ORDER BY MAX(ie.created_at) DESC
-         LIMIT ?1",
-    )?;
+         LIMIT ?1"
+    ))?;
 
-    let rows = stmt.query_map(params![limit], |row| {
+    let rows = stmt.query_map(params, |row| {
         Ok(ContactSummary {
             phone: row.get(0)?,
             name: row.get(1)?,
             total_bookings: row.get(2)?,
-            last_booking: row.get(3)?,
-            first_seen: row.get(4)?,
+            no_shows: row.get(3)?,
+            last_booking: row.get(4)?,
+            first_seen: row.get(5)?,
+            notes: row.get(6)?,
+            tags: row.get(7)?,
         })
     })?;
 
@@ -731,3 +1603,235 @@ pub fn get_contacts(conn: &Connection, limit: i64) -> anyhow::Result<Vec<Contact
     }
     Ok(contacts)
 }
+
+#[derive(Debug, Default, Serialize)]
+pub struct ContactMerge {
+    pub bookings_moved: usize,
+    pub inbox_events_moved: usize,
+    pub conversation_moved: bool,
+    pub waitlist_entries_moved: usize,
+    pub message_statuses_moved: usize,
+    pub queued_replies_moved: usize,
+}
+
+/// Fold `secondary` into `primary` in one transaction: bookings, inbox
+/// events, waitlist entries, delivery statuses and queued replies move over.
+/// The conversation, owner notes, AI toggle and handoff flag move only if
+/// `primary` has none of its own; the secondary's leftovers are deleted.
+/// Blocks, allowlist entries and opt-outs stay with the number they were
+/// made for.
+pub fn merge_contacts(
+    conn: &Connection,
+    primary: &str,
+    secondary: &str,
+) -> anyhow::Result<ContactMerge> {
+    let tx = conn.unchecked_transaction()?;
+    let repoint = |table: &str, column: &str| {
+        tx.execute(
+            &format!("UPDATE {table} SET {column} = ?1 WHERE {column} = ?2"),
+            params![primary, secondary],
+        )
+    };
+    let mut merge = ContactMerge {
+        bookings_moved: repoint("bookings", "customer_phone")?,
+        inbox_events_moved: repoint("inbox_events", "phone")?,
+        waitlist_entries_moved: repoint("waitlist", "phone")?,
+        message_statuses_moved: repoint("message_status", "to_phone")?,
+        queued_replies_moved: repoint("queued_replies", "phone")?,
+        ..Default::default()
+    };
+    for table in ["conversations", "contact_notes", "thread_ai", "needs_human"] {
+        let moved = tx.execute(
+            &format!("UPDATE OR IGNORE {table} SET phone = ?1 WHERE phone = ?2"),
+            params![primary, secondary],
+        )?;
+        if table == "conversations" {
+            merge.conversation_moved = moved > 0;
+        }
+        tx.execute(
+            &format!("DELETE FROM {table} WHERE phone = ?1"),
+            params![secondary],
+        )?;
+    }
+    tx.commit()?;
+    Ok(merge)
+}
+
+// ── Contact Notes ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContactNotes {
+    pub phone: String,
+    pub notes: String,
+    /// Comma-separated, e.g. "vip,mornings".
+    pub tags: String,
+    pub updated_at: String,
+}
+
+pub fn get_contact_notes(conn: &Connection, phone: &str) -> anyhow::Result<Option<ContactNotes>> {
+    let result = conn.query_row(
+        "SELECT phone, notes, tags, updated_at FROM contact_notes WHERE phone = ?1",
+        params![phone],
+        |row| {
+            Ok(ContactNotes {
+                phone: row.get(0)?,
+                notes: row.get(1)?,
+                tags: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        },
+    );
+
+    match result {
+        Ok(notes) => Ok(Some(notes)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn set_contact_notes(conn: &Connection, phone: &str, notes: &str, tags: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO contact_notes (phone, notes, tags) VALUES (?1, ?2, ?3)
+         ON CONFLICT(phone) DO UPDATE SET
+           notes = excluded.notes,
+           tags = excluded.tags,
+           updated_at = datetime('now')",
+        params![phone, notes, tags],
+    )?;
+    Ok(())
+}
+
+// ── Broadcast Audiences ──
+
+/// Every number that has texted in or holds a booking.
+pub fn get_all_contact_phones(conn: &Connection) -> anyhow::Result<Vec<String>> {
+    query_phones(
+        conn,
+        "SELECT phone FROM inbox_events
+         UNION
+         SELECT customer_phone FROM bookings
+         ORDER BY 1",
+        params![],
+    )
+}
+
+/// Numbers with a confirmed booking still ahead of `now`.
+pub fn get_phones_with_upcoming_bookings(
+    conn: &Connection,
+    now: &NaiveDateTime,
+) -> anyhow::Result<Vec<String>> {
+    query_phones(
+        conn,
+        "SELECT DISTINCT customer_phone FROM bookings
+         WHERE status = 'confirmed' AND date_time > ?1
+         ORDER BY 1",
+        params![now.format("%Y-%m-%d %H:%M:%S").to_string()],
+    )
+}
+
+/// Numbers that have texted in since `since`.
+pub fn get_phones_seen_since(
+    conn: &Connection,
+    since: &NaiveDateTime,
+) -> anyhow::Result<Vec<String>> {
+    query_phones(
+        conn,
+        "SELECT DISTINCT phone FROM inbox_events
+         WHERE kind = 'customer_message' AND created_at >= ?1
+         ORDER BY 1",
+        params![since.format("%Y-%m-%d %H:%M:%S").to_string()],
+    )
+}
+
+fn query_phones(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> anyhow::Result<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params, |row| row.get(0))?;
+
+    let mut phones = vec![];
+    for row in rows {
+        phones.push(row?);
+    }
+    Ok(phones)
+}
+
+// ── Contact Data (privacy requests) ──
+
+/// Every booking for a phone, including cancelled and rescheduled ones.
+pub fn get_all_bookings_for_phone(conn: &Connection, phone: &str) -> anyhow::Result<Vec<Booking>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, customer_phone, customer_name, date_time, duration_minutes, status, notes, created_at, updated_at, service
+         FROM bookings WHERE customer_phone = ?1 ORDER BY date_time ASC",
+    )?;
+
+    let rows = stmt.query_map(params![phone], |row| Ok(parse_booking_row(row)))?;
+
+    let mut bookings = vec![];
+    for row in rows {
+        bookings.push(row??);
+    }
+    Ok(bookings)
+}
+
+#[derive(Serialize)]
+pub struct ContactExport {
+    pub phone: String,
+    pub bookings: Vec<Booking>,
+    pub inbox_events: Vec<InboxEvent>,
+    pub conversation: Option<Conversation>,
+    pub notes: Option<ContactNotes>,
+}
+
+/// Everything stored about a phone number, for privacy/export requests.
+pub fn export_contact(conn: &Connection, phone: &str) -> anyhow::Result<ContactExport> {
+    Ok(ContactExport {
+        phone: phone.to_string(),
+        bookings: get_all_bookings_for_phone(conn, phone)?,
+        inbox_events: get_thread_events(conn, phone, i64::MAX)?,
+        conversation: get_conversation(conn, phone, false)?,
+        notes: get_contact_notes(conn, phone)?,
+    })
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ContactPurge {
+    pub conversations_deleted: usize,
+    pub inbox_events_deleted: usize,
+    pub message_statuses_deleted: usize,
+    pub waitlist_entries_deleted: usize,
+    pub contact_notes_deleted: usize,
+    pub bookings_anonymized: usize,
+}
+
+/// Delete the conversation, inbox thread, delivery statuses, waitlist entries
+/// and owner notes for a phone.
+/// With `anonymize_bookings`, its bookings keep their slot but lose the
+/// customer's name and notes.
+pub fn purge_contact(
+    conn: &Connection,
+    phone: &str,
+    anonymize_bookings: bool,
+) -> anyhow::Result<ContactPurge> {
+    let tx = conn.unchecked_transaction()?;
+    let mut purge = ContactPurge {
+        conversations_deleted: tx.execute("DELETE FROM conversations WHERE phone = ?1", params![phone])?,
+        inbox_events_deleted: tx.execute("DELETE FROM inbox_events WHERE phone = ?1", params![phone])?,
+        message_statuses_deleted: tx
+            .execute("DELETE FROM message_status WHERE to_phone = ?1", params![phone])?,
+        waitlist_entries_deleted: tx.execute("DELETE FROM waitlist WHERE phone = ?1", params![phone])?,
+        contact_notes_deleted: tx.execute("DELETE FROM contact_notes WHERE phone = ?1", params![phone])?,
+        ..Default::default()
+    };
+    if anonymize_bookings {
+        purge.bookings_anonymized = tx.execute(
+            "UPDATE bookings SET customer_name = NULL, notes = NULL, updated_at = datetime('now')
+             WHERE customer_phone = ?1",
+            params![phone],
+        )?;
+    }
+    tx.commit()?;
+    Ok(purge)
+}
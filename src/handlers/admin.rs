@@ -1,16 +1,33 @@
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
+use anyhow::Context;
+use axum::body::Body;
 use axum::extract::{Path, Query, State};
-use axum::http::{HeaderMap, StatusCode};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
 
 use axum::response::Redirect;
 
-use crate::db::queries;
-use crate::models::BookingStatus;
+use crate::db::{self, queries};
+use crate::logging::mask_phone;
+use crate::models::{
+    AiPreferences, Availability, AvailabilityExplanation, BookingActor, BookingEvent,
+    BookingStatus, Conversation, DayOverride, QuietHours, Service, ServicePrice, User,
+};
+use crate::services::broadcast::{send_broadcast, BroadcastSummary};
+use crate::services::i18n;
+use crate::services::import::{import_bookings, ImportError, ImportSummary};
+use crate::services::inbox::record_inbox_event;
+use crate::services::messaging::segments::segment_count;
+use crate::services::phone::normalize_phone;
+use crate::services::scheduling::find_conflict;
+use crate::services::signing::constant_time_eq;
+use crate::services::spam;
+use crate::services::waitlist;
 use crate::state::AppState;
 
 static APP_HTML: &str = include_str!("../web/app.html");
@@ -31,7 +48,7 @@ fn check_auth(headers: &HeaderMap, expected_token: &str) -> Result<(), Response>
         .unwrap_or("");
 
     let token = auth.strip_prefix("Bearer ").unwrap_or("");
-    if token != expected_token {
+    if !constant_time_eq(token, expected_token) {
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({"error": "unauthorized"})),
@@ -41,6 +58,18 @@ fn check_auth(headers: &HeaderMap, expected_token: &str) -> Result<(), Response>
     Ok(())
 }
 
+/// A typed number in E.164 form, or a 400 saying what's wrong with it.
+#[allow(clippy::result_large_err)]
+fn normalized_phone(state: &AppState, phone: &str) -> Result<String, Response> {
+    normalize_phone(phone, &state.config.default_country_code).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })
+}
+
 // GET /api/admin/status
 #[derive(Serialize)]
 pub struct StatusResponse {
@@ -92,6 +121,7 @@ pub struct BookingResponse {
     duration_minutes: i32,
     status: String,
     notes: Option<String>,
+    service: Option<String>,
     created_at: String,
     updated_at: String,
 }
@@ -127,6 +157,7 @@ pub async fn get_bookings(
             duration_minutes: b.duration_minutes,
             status: b.status.as_str().to_string(),
             notes: b.notes,
+            service: b.service,
             created_at: b.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
             updated_at: b.updated_at.format("%Y-%m-%d %H:%M:%S").to_string(),
         })
@@ -136,25 +167,23 @@ pub async fn get_bookings(
 }
 
 // GET /api/admin/activity
-#[derive(Serialize)]
-pub struct ActivityMonth {
-    month: String,
-    messages_received: i64,
-    messages_sent: i64,
-    bookings_created: i64,
-    bookings_cancelled: i64,
-    bookings_rescheduled: i64,
+#[derive(Deserialize)]
+pub struct ActivityQuery {
+    pub months: Option<usize>,
 }
 
 pub async fn get_activity(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-) -> Result<Json<Vec<ActivityMonth>>, Response> {
+    Query(query): Query<ActivityQuery>,
+) -> Result<Json<Vec<queries::MonthlyActivity>>, Response> {
     check_auth(&headers, &state.config.admin_token)?;
 
-    let months = {
+    let months = query.months.unwrap_or(6).clamp(1, 24);
+
+    let activity = {
         let db = state.db.lock().unwrap();
-        queries::get_recent_monthly_activity(&db, 3).map_err(|e| {
+        queries::get_recent_monthly_activity(&db, months).map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": e.to_string()})),
@@ -163,19 +192,48 @@ pub async fn get_activity(
         })?
     };
 
-    let response: Vec<ActivityMonth> = months
-        .into_iter()
-        .map(|a| ActivityMonth {
-            month: a.month,
-            messages_received: a.messages_received,
-            messages_sent: a.messages_sent,
-            bookings_created: a.bookings_created,
-            bookings_cancelled: a.bookings_cancelled,
-            bookings_rescheduled: a.bookings_rescheduled,
-        })
-        .collect();
+    Ok(Json(activity))
+}
 
-    Ok(Json(response))
+// POST /api/admin/bookings/import
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// Set to false to load historical bookings without checking hours and
+    /// conflicts. Cancelled and no-show rows are never checked.
+    #[serde(default = "default_true")]
+    pub validate: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub async fn import_bookings_csv(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ImportQuery>,
+    body: String,
+) -> Result<Json<ImportSummary>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let summary = {
+        let db = state.db.lock().unwrap();
+        import_bookings(&db, &body, query.validate).map_err(|e| {
+            let status = match e {
+                ImportError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            (status, Json(serde_json::json!({"error": e.to_string()}))).into_response()
+        })?
+    };
+    tracing::info!(
+        imported = summary.imported,
+        skipped = summary.skipped,
+        failed = summary.failed,
+        "imported bookings from CSV"
+    );
+
+    Ok(Json(summary))
 }
 
 // POST /api/admin/bookings/:id/cancel
@@ -186,22 +244,36 @@ pub async fn cancel_booking(
 ) -> Result<Json<serde_json::Value>, Response> {
     check_auth(&headers, &state.config.admin_token)?;
 
-    let updated = {
+    let cancelled = {
         let db = state.db.lock().unwrap();
-        let result = queries::update_booking_status(&db, &id, &BookingStatus::Cancelled).map_err(|e| {
+        let result = queries::update_booking_status(
+            &db,
+            &id,
+            &BookingStatus::Cancelled,
+            BookingActor::Admin,
+        )
+        .and_then(|updated| {
+            if updated {
+                queries::get_booking_by_id(&db, &id)
+            } else {
+                Ok(None)
+            }
+        })
+        .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": e.to_string()})),
             )
                 .into_response()
         })?;
-        if result {
+        if result.is_some() {
             let _ = queries::increment_monthly_cancelled(&db);
         }
         result
     };
 
-    if updated {
+    if let Some(booking) = cancelled {
+        waitlist::offer_freed_slot(&state, &booking).await;
         Ok(Json(serde_json::json!({"ok": true})))
     } else {
         Err((
@@ -212,12 +284,100 @@ pub async fn cancel_booking(
     }
 }
 
+// POST /api/admin/bookings/:id/no-show
+pub async fn mark_no_show(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let db = state.db.lock().unwrap();
+    let booking = queries::get_booking_by_id(&db, &id).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })?;
+    let Some(booking) = booking else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "booking not found"})),
+        )
+            .into_response());
+    };
+    if !booking.status.holds_slot() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": format!("booking is already {}", booking.status.as_str())})),
+        )
+            .into_response());
+    }
+    if booking.date_time > chrono::Utc::now().naive_utc() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "booking hasn't started yet"})),
+        )
+            .into_response());
+    }
+
+    queries::update_booking_status(&db, &id, &BookingStatus::NoShow, BookingActor::Admin).map_err(
+        |e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        },
+    )?;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+// GET /api/admin/bookings/:id/history
+pub async fn get_booking_history(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<BookingEvent>>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let db = state.db.lock().unwrap();
+    let booking = queries::get_booking_by_id(&db, &id).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })?;
+    if booking.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "booking not found"})),
+        )
+            .into_response());
+    }
+
+    let events = queries::get_booking_events(&db, &id).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })?;
+
+    Ok(Json(events))
+}
+
 // GET /api/admin/blocked
 #[derive(Serialize)]
 pub struct BlockedResponse {
     phone: String,
     reason: Option<String>,
     is_auto: bool,
+    blocked_until: Option<String>,
+    blocked_at: String,
 }
 
 pub async fn get_blocked(
@@ -239,10 +399,12 @@ pub async fn get_blocked(
 
     let response: Vec<BlockedResponse> = blocked
         .into_iter()
-        .map(|(phone, reason, is_auto)| BlockedResponse {
-            phone,
-            reason,
-            is_auto,
+        .map(|b| BlockedResponse {
+            phone: b.phone,
+            reason: b.reason,
+            is_auto: b.is_auto,
+            blocked_until: b.blocked_until,
+            blocked_at: b.blocked_at,
         })
         .collect();
 
@@ -262,10 +424,11 @@ pub async fn block_number(
     Json(body): Json<BlockRequest>,
 ) -> Result<Json<serde_json::Value>, Response> {
     check_auth(&headers, &state.config.admin_token)?;
+    let phone = normalized_phone(&state, &body.phone)?;
 
     {
         let db = state.db.lock().unwrap();
-        queries::block_number(&db, &body.phone, body.reason.as_deref(), false).map_err(|e| {
+        queries::block_number(&db, &phone, body.reason.as_deref(), false).map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": e.to_string()})),
@@ -289,10 +452,11 @@ pub async fn unblock_number(
     Json(body): Json<UnblockRequest>,
 ) -> Result<Json<serde_json::Value>, Response> {
     check_auth(&headers, &state.config.admin_token)?;
+    let phone = normalized_phone(&state, &body.phone)?;
 
     let removed = {
         let db = state.db.lock().unwrap();
-        queries::unblock_number(&db, &body.phone).map_err(|e| {
+        queries::unblock_number(&db, &phone).map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": e.to_string()})),
@@ -312,6 +476,66 @@ pub async fn unblock_number(
     }
 }
 
+// POST /api/admin/allow
+#[derive(Deserialize)]
+pub struct AllowRequest {
+    pub phone: String,
+    pub note: Option<String>,
+}
+
+pub async fn allow_number(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<AllowRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    let phone = normalized_phone(&state, &body.phone)?;
+
+    {
+        let db = state.db.lock().unwrap();
+        queries::allow_number(&db, &phone, body.note.as_deref()).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?;
+    }
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+// DELETE /api/admin/allow/:phone
+pub async fn unallow_number(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    let phone = normalized_phone(&state, &phone)?;
+
+    let removed = {
+        let db = state.db.lock().unwrap();
+        queries::unallow_number(&db, &phone).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?
+    };
+
+    if removed {
+        Ok(Json(serde_json::json!({"ok": true})))
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "number not found in allowlist"})),
+        )
+            .into_response())
+    }
+}
+
 // POST /api/admin/pause
 pub async fn pause_agent(
     State(state): State<Arc<AppState>>,
@@ -343,6 +567,20 @@ pub struct SettingsResponse {
     availability: Option<String>,
     timezone: String,
     ai_preferences: Option<String>,
+    language: String,
+    services: Option<String>,
+    location: Option<String>,
+    notify_on_booking: bool,
+    notify_on_cancel: bool,
+    notify_on_block: bool,
+    notify_on_pause: bool,
+    quiet_hours: Option<String>,
+    one_booking_per_customer_per_day: bool,
+    reminder_hours_before: i64,
+    reminder_template: Option<String>,
+    spam_keywords: Option<String>,
+    block_spam: bool,
+    use_24h_time: bool,
 }
 
 pub async fn get_settings(
@@ -367,6 +605,20 @@ pub async fn get_settings(
             availability: u.availability,
             timezone: u.timezone,
             ai_preferences: u.ai_preferences,
+            language: u.language,
+            services: u.services,
+            location: u.location,
+            notify_on_booking: u.notify_on_booking,
+            notify_on_cancel: u.notify_on_cancel,
+            notify_on_block: u.notify_on_block,
+            notify_on_pause: u.notify_on_pause,
+            quiet_hours: u.quiet_hours,
+            one_booking_per_customer_per_day: u.one_booking_per_customer_per_day,
+            reminder_hours_before: u.reminder_hours_before,
+            reminder_template: u.reminder_template,
+            spam_keywords: u.spam_keywords,
+            block_spam: u.block_spam,
+            use_24h_time: u.use_24h_time,
         })),
         None => Ok(Json(SettingsResponse {
             business_name: String::new(),
@@ -377,18 +629,89 @@ pub async fn get_settings(
             availability: None,
             timezone: "UTC".to_string(),
             ai_preferences: None,
+            language: i18n::DEFAULT_LANGUAGE.to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
         })),
     }
 }
 
+// GET /api/admin/pricing
+pub async fn get_pricing(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ServicePrice>>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let user = {
+        let db = state.db.lock().unwrap();
+        queries::get_user(&db, "default").ok().flatten()
+    };
+
+    let pricing = user
+        .and_then(|u| u.ai_preferences)
+        .and_then(|s| AiPreferences::from_json(&s).ok())
+        .map(|p| p.boundaries.pricing)
+        .unwrap_or_default();
+
+    Ok(Json(pricing))
+}
+
+/// Load the settings row, or a fresh default seeded from config if none exists yet.
+fn load_or_default_user(db: &rusqlite::Connection, state: &AppState) -> User {
+    queries::get_user(db, "default")
+        .ok()
+        .flatten()
+        .unwrap_or(User {
+            id: "default".to_string(),
+            business_name: String::new(),
+            owner_name: String::new(),
+            owner_phone: state.config.owner_phone.clone(),
+            twilio_account_sid: state.config.twilio_account_sid.clone(),
+            twilio_auth_token: state.config.twilio_auth_token.clone(),
+            twilio_phone_number: state.config.twilio_phone_number.clone(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: None,
+            language: i18n::DEFAULT_LANGUAGE.to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        })
+}
+
 // GET /api/admin/contacts
 #[derive(Serialize)]
 pub struct ContactResponse {
     phone: String,
     name: Option<String>,
     total_bookings: i64,
+    no_shows: i64,
     last_booking: Option<String>,
     first_seen: String,
+    notes: Option<String>,
+    tags: Option<String>,
 }
 
 pub async fn get_contacts(
@@ -408,87 +731,787 @@ pub async fn get_contacts(
         })?
     };
 
-    let response: Vec<ContactResponse> = contacts
-        .into_iter()
-        .map(|c| ContactResponse {
+    Ok(Json(
+        contacts.into_iter().map(ContactResponse::from).collect(),
+    ))
+}
+
+impl From<queries::ContactSummary> for ContactResponse {
+    fn from(c: queries::ContactSummary) -> Self {
+        Self {
             phone: c.phone,
             name: c.name,
             total_bookings: c.total_bookings,
+            no_shows: c.no_shows,
             last_booking: c.last_booking,
             first_seen: c.first_seen,
-        })
-        .collect();
-
-    Ok(Json(response))
+            notes: c.notes,
+            tags: c.tags,
+        }
+    }
 }
 
-// POST /api/admin/settings
+// POST /api/admin/contacts/merge
 #[derive(Deserialize)]
-pub struct UpdateSettingsRequest {
-    pub business_name: Option<String>,
-    pub owner_name: Option<String>,
-    pub availability: Option<String>,
-    pub timezone: Option<String>,
-    pub ai_preferences: Option<String>,
+pub struct MergeContactsRequest {
+    pub primary: String,
+    pub secondary: String,
 }
 
-pub async fn update_settings(
+#[derive(Serialize)]
+pub struct MergeContactsResponse {
+    /// The primary contact after the merge; `None` if it has no inbox thread.
+    contact: Option<ContactResponse>,
+    merged: queries::ContactMerge,
+}
+
+pub async fn merge_contacts(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Json(body): Json<UpdateSettingsRequest>,
-) -> Result<Json<serde_json::Value>, Response> {
+    Json(body): Json<MergeContactsRequest>,
+) -> Result<Json<MergeContactsResponse>, Response> {
     check_auth(&headers, &state.config.admin_token)?;
 
-    let db = state.db.lock().unwrap();
+    let primary = body.primary.trim().to_string();
+    let secondary = body.secondary.trim().to_string();
+    if primary.is_empty() || secondary.is_empty() || primary == secondary {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "primary and secondary must be two different phone numbers"})),
+        )
+            .into_response());
+    }
 
-    // Load existing user or create default
-    let mut user = queries::get_user(&db, "default")
-        .ok()
-        .flatten()
-        .unwrap_or(crate::models::User {
-            id: "default".to_string(),
-            business_name: String::new(),
-            owner_name: String::new(),
-            owner_phone: state.config.owner_phone.clone(),
-            twilio_account_sid: state.config.twilio_account_sid.clone(),
-            twilio_auth_token: state.config.twilio_auth_token.clone(),
-            twilio_phone_number: state.config.twilio_phone_number.clone(),
-            availability: None,
-            timezone: "UTC".to_string(),
-            ai_preferences: None,
-        });
+    let (merged, contact) = {
+        let db = state.db.lock().unwrap();
+        queries::merge_contacts(&db, &primary, &secondary)
+            .and_then(|merged| Ok((merged, queries::get_contact(&db, &primary)?)))
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({"error": e.to_string()})),
+                )
+                    .into_response()
+            })?
+    };
+    tracing::info!(
+        primary = %mask_phone(&primary),
+        secondary = %mask_phone(&secondary),
+        bookings = merged.bookings_moved,
+        inbox_events = merged.inbox_events_moved,
+        "merged contacts"
+    );
 
-    if let Some(name) = body.business_name {
-        user.business_name = name;
-    }
-    if let Some(name) = body.owner_name {
-        user.owner_name = name;
-    }
-    if let Some(avail) = body.availability {
-        user.availability = Some(avail);
-    }
-    if let Some(tz) = body.timezone {
-        user.timezone = tz;
-    }
-    if let Some(ref ai_prefs) = body.ai_preferences {
-        // Validate JSON parses as AiPreferences
-        if let Err(e) = crate::models::AiPreferences::from_json(ai_prefs) {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": format!("invalid ai_preferences JSON: {e}")})),
+    Ok(Json(MergeContactsResponse {
+        contact: contact.map(ContactResponse::from),
+        merged,
+    }))
+}
+
+// GET /api/admin/contacts/:phone/notes
+pub async fn get_contact_notes(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+) -> Result<Json<queries::ContactNotes>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let notes = {
+        let db = state.db.lock().unwrap();
+        queries::get_contact_notes(&db, &phone).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
             )
-                .into_response());
-        }
-        user.ai_preferences = Some(ai_prefs.clone());
-    }
+                .into_response()
+        })?
+    };
 
-    queries::save_user(&db, &user).map_err(|e| {
+    notes.map(Json).ok_or_else(|| {
         (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({"error": e.to_string()})),
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "no notes for this contact"})),
         )
             .into_response()
-    })?;
+    })
+}
 
-    Ok(Json(serde_json::json!({"ok": true})))
+// PUT /api/admin/contacts/:phone/notes
+#[derive(Deserialize)]
+pub struct ContactNotesRequest {
+    #[serde(default)]
+    pub notes: String,
+    /// Comma-separated, e.g. "vip, mornings".
+    #[serde(default)]
+    pub tags: String,
+}
+
+pub async fn put_contact_notes(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+    Json(body): Json<ContactNotesRequest>,
+) -> Result<Json<queries::ContactNotes>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    // Normalize "vip , mornings,," to "vip,mornings"
+    let tags = body
+        .tags
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let notes = {
+        let db = state.db.lock().unwrap();
+        queries::set_contact_notes(&db, &phone, body.notes.trim(), &tags)
+            .and_then(|_| queries::get_contact_notes(&db, &phone))
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(serde_json::json!({"error": e.to_string()})),
+                )
+                    .into_response()
+            })?
+    };
+
+    notes.map(Json).ok_or_else(|| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": "notes were not saved"})),
+        )
+            .into_response()
+    })
+}
+
+// GET /api/admin/contacts/:phone/export
+pub async fn export_contact(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+) -> Result<Json<queries::ContactExport>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let export = {
+        let db = state.db.lock().unwrap();
+        queries::export_contact(&db, &phone).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?
+    };
+
+    Ok(Json(export))
+}
+
+// DELETE /api/admin/contacts/:phone
+#[derive(Deserialize)]
+pub struct PurgeContactQuery {
+    #[serde(default)]
+    pub anonymize_bookings: bool,
+}
+
+pub async fn purge_contact(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+    Query(query): Query<PurgeContactQuery>,
+) -> Result<Json<queries::ContactPurge>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let purge = {
+        let db = state.db.lock().unwrap();
+        queries::purge_contact(&db, &phone, query.anonymize_bookings).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?
+    };
+    tracing::info!(
+        phone = %mask_phone(&phone),
+        anonymized = purge.bookings_anonymized,
+        "purged contact data"
+    );
+
+    Ok(Json(purge))
+}
+
+// GET /api/admin/conversation/:phone
+/// The stored conversation as-is, expired or not, for debugging a stuck customer.
+pub async fn get_conversation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+) -> Result<Json<Conversation>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let conversation = {
+        let db = state.db.lock().unwrap();
+        queries::get_conversation(&db, &phone, true).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?
+    };
+
+    conversation.map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "no conversation for this number"})),
+        )
+            .into_response()
+    })
+}
+
+// POST /api/admin/conversation/:phone/reset
+#[derive(Deserialize)]
+pub struct ResetConversationRequest {
+    /// Texted to the customer after the reset; empty sends nothing.
+    #[serde(default)]
+    pub message: String,
+}
+
+/// Drop a customer's conversation, pending booking and all, so their next
+/// text is a new interaction. Their bookings are untouched.
+pub async fn reset_conversation(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+    body: Option<Json<ResetConversationRequest>>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let message = body
+        .map(|Json(body)| body.message.trim().to_string())
+        .unwrap_or_default();
+    let (reset, opted_out) = {
+        let db = state.db.lock().unwrap();
+        let reset = queries::delete_conversation(&db, &phone).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?;
+        (reset, queries::is_opted_out(&db, &phone).unwrap_or(false))
+    };
+    if reset {
+        record_inbox_event(&state, &phone, "system", "Conversation reset by the owner");
+    }
+
+    let mut message_sent = false;
+    if !message.is_empty() && !opted_out {
+        match state.messaging.send_message(&phone, &message).await {
+            Ok(()) => {
+                message_sent = true;
+                {
+                    let segments = segment_count(&message);
+                    let db = state.db.lock().unwrap();
+                    let _ = queries::increment_monthly_sent(&db, segments);
+                }
+                record_inbox_event(&state, &phone, "owner_reply", &message);
+            }
+            Err(e) => {
+                tracing::error!(error = %e, phone = %mask_phone(&phone), "failed to send reset message");
+            }
+        }
+    }
+
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "reset": reset,
+        "message_sent": message_sent,
+    })))
+}
+
+// GET /api/admin/backup
+pub async fn backup_database(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    // The copy runs on its own connection and the response streams from the
+    // scratch file, so neither the shared connection nor memory is tied up
+    let (path, shared) = (state.config.database_url.clone(), Arc::clone(&state.db));
+    let file = tokio::task::spawn_blocking(move || db::backup_to_file(&path, &shared))
+        .await
+        .context("database task panicked")
+        .and_then(|result| result)
+        .map_err(|e| {
+            tracing::error!(error = %e, "database backup failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or_default();
+    tracing::info!(bytes = size, "database backup downloaded");
+
+    let filename = format!(
+        "phonebook-{}.db",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+            (header::CONTENT_LENGTH, size.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(tokio::fs::File::from_std(file))),
+    )
+        .into_response())
+}
+
+// POST /api/admin/broadcast
+#[derive(Deserialize)]
+pub struct BroadcastRequest {
+    pub message: String,
+    /// "all", "upcoming" (customers with a future booking), or "recent".
+    #[serde(default = "default_audience")]
+    pub audience: String,
+    /// Look-back window for the "recent" audience.
+    pub days: Option<i64>,
+}
+
+fn default_audience() -> String {
+    "all".to_string()
+}
+
+pub async fn broadcast(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<BroadcastRequest>,
+) -> Result<Json<BroadcastSummary>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let message = body.message.trim();
+    if message.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "message is required"})),
+        )
+            .into_response());
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let recipients = {
+        let db = state.db.lock().unwrap();
+        match (body.audience.as_str(), body.days) {
+            ("all", _) => queries::get_all_contact_phones(&db),
+            ("upcoming", _) => queries::get_phones_with_upcoming_bookings(&db, &now),
+            ("recent", Some(days)) if days > 0 => {
+                queries::get_phones_seen_since(&db, &(now - chrono::Duration::days(days)))
+            }
+            ("recent", _) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": "recent audience needs a positive days"})),
+                )
+                    .into_response());
+            }
+            (other, _) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({
+                        "error": format!("unknown audience {other:?}; expected all, upcoming, or recent")
+                    })),
+                )
+                    .into_response());
+            }
+        }
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?
+    };
+
+    let summary = send_broadcast(&state, recipients, message).await;
+    Ok(Json(summary))
+}
+
+// POST /api/admin/settings
+#[derive(Deserialize)]
+pub struct UpdateSettingsRequest {
+    pub business_name: Option<String>,
+    pub owner_name: Option<String>,
+    pub owner_phone: Option<String>,
+    pub availability: Option<String>,
+    pub timezone: Option<String>,
+    pub ai_preferences: Option<String>,
+    pub language: Option<String>,
+    pub services: Option<String>,
+    pub location: Option<String>,
+    pub notify_on_booking: Option<bool>,
+    pub notify_on_cancel: Option<bool>,
+    pub notify_on_block: Option<bool>,
+    pub notify_on_pause: Option<bool>,
+    /// JSON `{"start":"HH:MM","end":"HH:MM"}`; empty turns quiet hours off.
+    pub quiet_hours: Option<String>,
+    pub one_booking_per_customer_per_day: Option<bool>,
+    /// 0 turns reminders off.
+    pub reminder_hours_before: Option<i64>,
+    /// Empty goes back to the default reminder text.
+    pub reminder_template: Option<String>,
+    /// Comma- or newline-separated; empty turns spam filtering off.
+    pub spam_keywords: Option<String>,
+    pub block_spam: Option<bool>,
+    pub use_24h_time: Option<bool>,
+}
+
+pub async fn update_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<UpdateSettingsRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let db = state.db.lock().unwrap();
+
+    let mut user = load_or_default_user(&db, &state);
+
+    if let Some(name) = body.business_name {
+        user.business_name = name;
+    }
+    if let Some(name) = body.owner_name {
+        user.owner_name = name;
+    }
+    if let Some(phone) = body.owner_phone {
+        user.owner_phone = phone.trim().to_string();
+    }
+    if let Some(avail) = body.availability {
+        // Stored availability is parsed with `.ok()` at booking time, so a bad
+        // value would silently disable every hours check
+        if let Err(e) = Availability::from_json(&avail) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("invalid availability JSON: {e}")})),
+            )
+                .into_response());
+        }
+        user.availability = Some(avail);
+    }
+    if let Some(tz) = body.timezone {
+        user.timezone = tz;
+    }
+    if let Some(lang) = body.language {
+        if !i18n::is_supported(&lang) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!(
+                        "unsupported language {lang:?}; expected one of {}",
+                        i18n::SUPPORTED_LANGUAGES.join(", ")
+                    )
+                })),
+            )
+                .into_response());
+        }
+        user.language = lang;
+    }
+    if let Some(ref ai_prefs) = body.ai_preferences {
+        // Validate JSON parses as AiPreferences
+        if let Err(e) = AiPreferences::from_json(ai_prefs) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("invalid ai_preferences JSON: {e}")})),
+            )
+                .into_response());
+        }
+        user.ai_preferences = Some(ai_prefs.clone());
+    }
+    if let Some(services) = body.services {
+        if let Err(e) = Service::list_from_json(&services) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("invalid services JSON: {e}")})),
+            )
+                .into_response());
+        }
+        user.services = Some(services);
+    }
+    if let Some(location) = body.location {
+        user.location = Some(location.trim().to_string()).filter(|l| !l.is_empty());
+    }
+    if let Some(on) = body.notify_on_booking {
+        user.notify_on_booking = on;
+    }
+    if let Some(on) = body.notify_on_cancel {
+        user.notify_on_cancel = on;
+    }
+    if let Some(on) = body.notify_on_block {
+        user.notify_on_block = on;
+    }
+    if let Some(on) = body.notify_on_pause {
+        user.notify_on_pause = on;
+    }
+    if let Some(quiet) = body.quiet_hours {
+        if quiet.trim().is_empty() {
+            user.quiet_hours = None;
+        } else if let Err(e) = QuietHours::from_json(&quiet) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("invalid quiet_hours JSON: {e}")})),
+            )
+                .into_response());
+        } else {
+            user.quiet_hours = Some(quiet);
+        }
+    }
+    if let Some(on) = body.one_booking_per_customer_per_day {
+        user.one_booking_per_customer_per_day = on;
+    }
+    if let Some(hours) = body.reminder_hours_before {
+        if !(0..=168).contains(&hours) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": "reminder_hours_before must be between 0 and 168"})),
+            )
+                .into_response());
+        }
+        user.reminder_hours_before = hours;
+    }
+    if let Some(template) = body.reminder_template {
+        user.reminder_template = Some(template.trim().to_string()).filter(|t| !t.is_empty());
+    }
+    if let Some(keywords) = body.spam_keywords {
+        let keywords = spam::parse_keywords(&keywords);
+        if let Err(e) = keywords.iter().try_for_each(|k| spam::check_keyword(k)) {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response());
+        }
+        user.spam_keywords = Some(keywords.join(", ")).filter(|k| !k.is_empty());
+    }
+    if let Some(on) = body.block_spam {
+        user.block_spam = on;
+    }
+    if let Some(on) = body.use_24h_time {
+        user.use_24h_time = on;
+    }
+
+    queries::save_user(&db, &user).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })?;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+// ── Availability overrides ──
+
+#[allow(clippy::result_large_err)]
+fn load_availability(user: &User) -> Result<Availability, Response> {
+    match user.availability.as_deref() {
+        Some(json) => Availability::from_json(json).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": format!("stored availability is invalid: {e}")})),
+            )
+                .into_response()
+        }),
+        None => Ok(Availability::default()),
+    }
+}
+
+/// Serialize, re-validate, and persist a mutated availability.
+#[allow(clippy::result_large_err)]
+fn save_availability(
+    db: &rusqlite::Connection,
+    mut user: User,
+    availability: &Availability,
+) -> Result<(), Response> {
+    let json = serde_json::to_string(availability).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })?;
+    if let Err(e) = Availability::from_json(&json) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": format!("invalid availability: {e}")})),
+        )
+            .into_response());
+    }
+    user.availability = Some(json);
+    queries::save_user(db, &user).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn check_override_date(date: &str) -> Result<(), Response> {
+    if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": format!("invalid date: {date}")})),
+        )
+            .into_response());
+    }
+    Ok(())
+}
+
+// GET /api/admin/availability/override
+#[derive(Serialize)]
+pub struct OverrideResponse {
+    date: String,
+    #[serde(flatten)]
+    day: DayOverride,
+}
+
+pub async fn list_overrides(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<OverrideResponse>>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let availability = {
+        let db = state.db.lock().unwrap();
+        load_availability(&load_or_default_user(&db, &state))?
+    };
+
+    let mut response: Vec<OverrideResponse> = availability
+        .overrides
+        .into_iter()
+        .map(|(date, day)| OverrideResponse { date, day })
+        .collect();
+    response.sort_by(|a, b| a.date.cmp(&b.date));
+
+    Ok(Json(response))
+}
+
+// POST /api/admin/availability/override
+#[derive(Deserialize)]
+pub struct OverrideRequest {
+    pub date: String,
+    pub available: bool,
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+pub async fn upsert_override(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<OverrideRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    check_override_date(&body.date)?;
+
+    let db = state.db.lock().unwrap();
+    let user = load_or_default_user(&db, &state);
+    let mut availability = load_availability(&user)?;
+    availability.overrides.insert(
+        body.date,
+        DayOverride {
+            available: body.available,
+            start: body.start,
+            end: body.end,
+        },
+    );
+    save_availability(&db, user, &availability)?;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+// DELETE /api/admin/availability/override/:date
+pub async fn delete_override(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(date): Path<String>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    check_override_date(&date)?;
+
+    let db = state.db.lock().unwrap();
+    let user = load_or_default_user(&db, &state);
+    let mut availability = load_availability(&user)?;
+    if availability.overrides.remove(&date).is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "override not found"})),
+        )
+            .into_response());
+    }
+    save_availability(&db, user, &availability)?;
+
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+// GET /api/admin/availability/check
+#[derive(Deserialize)]
+pub struct AvailabilityCheckQuery {
+    pub datetime: String,
+    pub duration: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct AvailabilityCheckResponse {
+    #[serde(flatten)]
+    explanation: AvailabilityExplanation,
+    conflict_booking_id: Option<String>,
+}
+
+pub async fn check_availability(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<AvailabilityCheckQuery>,
+) -> Result<Json<AvailabilityCheckResponse>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    let dt = chrono::NaiveDateTime::parse_from_str(&query.datetime, "%Y-%m-%d %H:%M")
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(&query.datetime, "%Y-%m-%dT%H:%M"))
+        .map_err(|_| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": format!("invalid datetime: {}", query.datetime)})),
+            )
+                .into_response()
+        })?;
+    let duration = query.duration.unwrap_or(60);
+
+    let db = state.db.lock().unwrap();
+    let availability = load_availability(&load_or_default_user(&db, &state))?;
+    let mut explanation = availability.explain(&dt, duration);
+
+    let conflict = find_conflict(&db, &dt, duration).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"error": e.to_string()})),
+        )
+            .into_response()
+    })?;
+    if explanation.available && conflict.is_some() {
+        explanation.available = false;
+        explanation.reason = Some("conflict");
+    }
+
+    Ok(Json(AvailabilityCheckResponse {
+        explanation,
+        conflict_booking_id: conflict.map(|b| b.id),
+    }))
 }
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::db::queries;
+use crate::models::{Booking, BookingActor, BookingStatus};
+use crate::services::dates::format_when;
+use crate::services::notify::{notify_owner, OwnerEvent};
+use crate::services::signing;
+use crate::services::waitlist::offer_freed_slot;
+use crate::state::AppState;
+
+use super::reschedule::{page, use_24h_time};
+
+#[derive(Deserialize)]
+pub struct TokenQuery {
+    #[serde(default)]
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct LookupQuery {
+    #[serde(default)]
+    pub phone: String,
+    #[serde(default)]
+    pub token: String,
+}
+
+/// Resolve a signed cancel link to its booking, if it can still be cancelled.
+#[allow(clippy::result_large_err)]
+fn load_booking_for_cancel(state: &AppState, id: &str, token: &str) -> Result<Booking, Response> {
+    if !signing::verify(&state.config.admin_token, id, token) {
+        return Err(page(
+            StatusCode::FORBIDDEN,
+            "Link not valid",
+            "<p>This cancellation link isn't valid. Text us to cancel your appointment.</p>",
+        ));
+    }
+
+    let db = state.db.lock().unwrap();
+    match queries::get_booking_by_id(&db, id) {
+        Ok(Some(booking)) if !booking.status.holds_slot() => Err(page(
+            StatusCode::GONE,
+            "Already cancelled",
+            "<p>This appointment has already been cancelled or moved.</p>",
+        )),
+        Ok(Some(booking)) => Ok(booking),
+        Ok(None) => Err(page(
+            StatusCode::NOT_FOUND,
+            "Appointment not found",
+            "<p>We couldn't find this appointment. Text us if you need help.</p>",
+        )),
+        Err(e) => {
+            tracing::error!(error = %e, "failed to load booking for cancel link");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response())
+        }
+    }
+}
+
+// GET /booking/:id/cancel?token=
+pub async fn cancel_page(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    let booking = match load_booking_for_cancel(&state, &id, &query.token) {
+        Ok(booking) => booking,
+        Err(rejection) => return rejection,
+    };
+    page(
+        StatusCode::OK,
+        "Cancel appointment",
+        &format!(
+            r#"<p>Cancel your appointment on <strong>{when}</strong>?</p>
+<form method="post" action="/booking/{id}/cancel?token={token}">
+<button type="submit">Yes, cancel it</button>
+</form>"#,
+            when = format_when(booking.date_time, use_24h_time(&state)),
+            token = query.token,
+        ),
+    )
+}
+
+// POST /booking/:id/cancel?token=
+pub async fn submit_cancel(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<TokenQuery>,
+) -> Response {
+    let booking = match load_booking_for_cancel(&state, &id, &query.token) {
+        Ok(booking) => booking,
+        Err(rejection) => return rejection,
+    };
+
+    let use_24h = {
+        let db = state.db.lock().unwrap();
+        if let Err(e) = queries::update_booking_status(
+            &db,
+            &booking.id,
+            &BookingStatus::Cancelled,
+            BookingActor::Customer,
+        ) {
+            tracing::error!(error = %e, "failed to cancel booking via link");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
+        let _ = queries::increment_monthly_cancelled(&db);
+        queries::get_user(&db, "default")
+            .ok()
+            .flatten()
+            .is_some_and(|u| u.use_24h_time)
+    };
+
+    let summary = format!(
+        "Cancelled via link: {} ({}) at {}",
+        booking.customer_name.as_deref().unwrap_or("Unknown"),
+        booking.customer_phone,
+        format_when(booking.date_time, use_24h),
+    );
+    notify_owner(&state, OwnerEvent::Cancel, &summary, Some(&booking.customer_phone)).await;
+    offer_freed_slot(&state, &booking).await;
+
+    page(
+        StatusCode::OK,
+        "Appointment cancelled",
+        &format!(
+            "<p>Your appointment on <strong>{}</strong> has been cancelled.</p>",
+            format_when(booking.date_time, use_24h),
+        ),
+    )
+}
+
+// GET /booking/lookup?phone=&token=
+pub async fn lookup(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<LookupQuery>,
+) -> Response {
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("application/json"));
+
+    if !signing::verify(&state.config.admin_token, &query.phone, &query.token) {
+        if wants_json {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": "Invalid or expired link"})),
+            )
+                .into_response();
+        }
+        return page(
+            StatusCode::FORBIDDEN,
+            "Link not valid",
+            "<p>This link isn't valid. Text us to ask about your appointments.</p>",
+        );
+    }
+
+    let (bookings, use_24h) = {
+        let db = state.db.lock().unwrap();
+        let use_24h = queries::get_user(&db, "default")
+            .ok()
+            .flatten()
+            .is_some_and(|u| u.use_24h_time);
+        match queries::get_bookings_for_phone(&db, &query.phone) {
+            Ok(bookings) => (bookings, use_24h),
+            Err(e) => {
+                tracing::error!(error = %e, "failed to look up bookings for link");
+                return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+            }
+        }
+    };
+    let now = Utc::now().naive_utc();
+    let upcoming: Vec<Booking> = bookings.into_iter().filter(|b| b.date_time >= now).collect();
+
+    if wants_json {
+        return Json(json!({ "bookings": upcoming })).into_response();
+    }
+
+    let body = if upcoming.is_empty() {
+        "<p>You have no upcoming appointments.</p>".to_string()
+    } else {
+        let items: String = upcoming
+            .iter()
+            .map(|b| {
+                format!(
+                    "<li><strong>{}</strong> ({} min)</li>\n",
+                    format_when(b.date_time, use_24h),
+                    b.duration_minutes,
+                )
+            })
+            .collect();
+        format!("<ul>\n{items}</ul>")
+    };
+    page(StatusCode::OK, "Your appointments", &body)
+}
@@ -3,13 +3,53 @@ use std::sync::Arc;
 use axum::extract::{Path, Query, State};
 use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use serde::Deserialize;
 
 use crate::db::queries;
-use crate::services::calendar::{generate_ics, generate_ics_feed};
+use crate::models::BookingStatus;
+use crate::services::calendar::{generate_ics, generate_ics_feed, EventContact};
+use crate::services::signing::constant_time_eq;
 use crate::state::AppState;
 
+/// The settings that go on every calendar event.
+struct BusinessSettings {
+    name: String,
+    phone: String,
+    location: Option<String>,
+}
+
+impl BusinessSettings {
+    fn load(state: &AppState, fallback_name: &str) -> Self {
+        let user = {
+            let db = state.db.lock().unwrap();
+            queries::get_user(&db, "default").ok().flatten()
+        };
+        let phone = user
+            .as_ref()
+            .map(|u| u.twilio_phone_number.clone())
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| state.config.twilio_phone_number.clone());
+        Self {
+            name: user
+                .as_ref()
+                .map(|u| u.business_name.clone())
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| fallback_name.to_string()),
+            phone,
+            location: user.and_then(|u| u.location),
+        }
+    }
+
+    fn contact(&self) -> EventContact<'_> {
+        EventContact {
+            business_name: &self.name,
+            business_phone: &self.phone,
+            location: self.location.as_deref(),
+        }
+    }
+}
+
 pub async fn download_ics(
     State(state): State<Arc<AppState>>,
     Path(raw_id): Path<String>,
@@ -17,10 +57,13 @@ pub async fn download_ics(
     // Strip .ics suffix if present
     let booking_id = raw_id.strip_suffix(".ics").unwrap_or(&raw_id);
 
-    let booking = {
+    let (booking, sequence) = {
         let db = state.db.lock().unwrap();
         match queries::get_booking_by_id(&db, booking_id) {
-            Ok(Some(b)) => b,
+            Ok(Some(b)) => {
+                let sequence = queries::get_booking_sequence(&db, booking_id).unwrap_or(0);
+                (b, sequence)
+            }
             Ok(None) => {
                 return (StatusCode::NOT_FOUND, "Booking not found").into_response();
             }
@@ -32,17 +75,13 @@ pub async fn download_ics(
     };
 
     // Get business name from user settings or fall back
-    let business_name = {
-        let db = state.db.lock().unwrap();
-        queries::get_user(&db, "default")
-            .ok()
-            .flatten()
-            .map(|u| u.business_name)
-            .filter(|n| !n.is_empty())
-            .unwrap_or_else(|| "Booking".to_string())
-    };
-
-    let ics = generate_ics(&booking, &business_name);
+    let settings = BusinessSettings::load(&state, "Booking");
+    let ics = generate_ics(
+        &booking,
+        &settings.contact(),
+        state.config.ics_alarm_minutes,
+        sequence,
+    );
     let filename = format!("booking-{}.ics", booking_id);
 
     (
@@ -61,6 +100,12 @@ pub async fn download_ics(
 #[derive(Deserialize)]
 pub struct FeedQuery {
     pub token: Option<String>,
+    /// First day to include (YYYY-MM-DD); defaults to now.
+    pub from: Option<NaiveDate>,
+    /// Last day to include (YYYY-MM-DD); defaults to a year out.
+    pub to: Option<NaiveDate>,
+    /// "confirmed", "pending", "cancelled" or "no_show"; defaults to everything.
+    pub status: Option<String>,
 }
 
 pub async fn calendar_feed(
@@ -69,17 +114,38 @@ pub async fn calendar_feed(
 ) -> Response {
     // Auth via query param (calendar apps can't set headers)
     let token = query.token.as_deref().unwrap_or("");
-    if token != state.config.admin_token {
+    if !constant_time_eq(token, &state.config.admin_token) {
         return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
     }
 
+    let status = match query.status.as_deref() {
+        None => None,
+        Some(s @ ("confirmed" | "pending" | "cancelled" | "no_show")) => {
+            Some(BookingStatus::parse(s))
+        }
+        Some(_) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                "status must be confirmed, pending, cancelled, or no_show",
+            )
+                .into_response();
+        }
+    };
+
     let now = Utc::now().naive_utc();
-    let far_future = now + chrono::Duration::days(365);
+    let start = query
+        .from
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap_or(now);
+    let end = query
+        .to
+        .map(|d| d.and_hms_opt(23, 59, 59).unwrap())
+        .unwrap_or(now + chrono::Duration::days(365));
 
-    let bookings = {
+    let (bookings, sequences) = {
         let db = state.db.lock().unwrap();
-        match queries::get_bookings_in_range(&db, &now, &far_future) {
-            Ok(b) => b,
+        match queries::get_feed_bookings(&db, &start, &end, status.as_ref()) {
+            Ok(b) => (b, queries::get_booking_sequences(&db).unwrap_or_default()),
             Err(e) => {
                 tracing::error!(error = %e, "failed to load bookings for feed");
                 return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
@@ -87,17 +153,13 @@ pub async fn calendar_feed(
         }
     };
 
-    let business_name = {
-        let db = state.db.lock().unwrap();
-        queries::get_user(&db, "default")
-            .ok()
-            .flatten()
-            .map(|u| u.business_name)
-            .filter(|n| !n.is_empty())
-            .unwrap_or_else(|| "Bookings".to_string())
-    };
-
-    let ics = generate_ics_feed(&bookings, &business_name);
+    let settings = BusinessSettings::load(&state, "Bookings");
+    let ics = generate_ics_feed(
+        &bookings,
+        &settings.contact(),
+        state.config.ics_alarm_minutes,
+        &sequences,
+    );
 
     (
         [
@@ -1,16 +1,38 @@
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use axum::extract::State;
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{Html, IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
+use crate::models::{ConversationState, Intent, PendingBooking};
+use crate::services::ai::LlmExchange;
 use crate::services::conversation;
+use crate::services::notify::owner_phone;
+use crate::services::signing::constant_time_eq;
 use crate::state::{AppState, DevNotification};
 
 use super::webhook;
 
+#[allow(clippy::result_large_err)]
+fn check_auth(headers: &HeaderMap, expected_token: &str) -> Result<(), Response> {
+    let auth = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let token = auth.strip_prefix("Bearer ").unwrap_or("");
+    if !constant_time_eq(token, expected_token) {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "unauthorized"})),
+        )
+            .into_response());
+    }
+    Ok(())
+}
+
 pub async fn dev_page() -> Html<&'static str> {
     Html(include_str!("../web/dev_chat.html"))
 }
@@ -24,7 +46,7 @@ pub struct DevConfig {
 
 pub async fn dev_config(State(state): State<Arc<AppState>>) -> Json<DevConfig> {
     Json(DevConfig {
-        owner_phone: state.config.owner_phone.clone(),
+        owner_phone: owner_phone(&state).await,
         twilio_phone_number: state.config.twilio_phone_number.clone(),
         paused: state.paused.load(Ordering::SeqCst),
     })
@@ -35,6 +57,9 @@ pub struct DevMessage {
     pub from_phone: String,
     pub message: String,
     pub to_phone: Option<String>,
+    /// Show the reply without booking, saving, or notifying anyone
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 #[derive(Serialize)]
@@ -49,14 +74,31 @@ pub struct DevResponse {
     pub customer_delivery: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub customer_delivery_phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intent: Option<Intent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_state: Option<ConversationState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<ConversationState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pending_booking: Option<PendingBooking>,
 }
 
-fn drain_notifications(state: &AppState) -> Vec<DevNotification> {
-    state
-        .dev_notifications
-        .lock()
-        .map(|mut n| n.drain(..).collect())
-        .unwrap_or_default()
+impl DevResponse {
+    fn new(reply: String) -> Self {
+        Self {
+            reply,
+            success: true,
+            error: None,
+            owner_notifications: Vec::new(),
+            customer_delivery: None,
+            customer_delivery_phone: None,
+            intent: None,
+            previous_state: None,
+            state: None,
+            pending_booking: None,
+        }
+    }
 }
 
 pub async fn send_message(
@@ -65,19 +107,19 @@ pub async fn send_message(
 ) -> Response {
     let from = payload.from_phone.trim().to_string();
     let body = payload.message.trim().to_string();
-    let is_owner = from == state.config.owner_phone;
+    let is_owner = from == owner_phone(&state).await;
 
     // Owner admin commands (# prefix)
     if is_owner && body.starts_with('#') {
-        let reply = webhook::handle_admin_command(&state, &body).await;
-        let notifications = drain_notifications(&state);
+        // Nothing vouches for the dev chat's sender, so this is refused in production
+        let reply = match webhook::owner_command(&state, &body, false) {
+            Ok(command) => webhook::handle_admin_command(&state, &command).await,
+            Err(reason) => format!("Command refused: {reason}"),
+        };
+        let notifications = state.dev_notifications.drain();
         return Json(DevResponse {
-            reply,
-            success: true,
-            error: None,
             owner_notifications: notifications,
-            customer_delivery: None,
-            customer_delivery_phone: None,
+            ..DevResponse::new(reply)
         })
         .into_response();
     }
@@ -88,12 +130,9 @@ pub async fn send_message(
             Some(ref p) if !p.trim().is_empty() => p.trim().to_string(),
             _ => {
                 return Json(DevResponse {
-                    reply: String::new(),
                     success: false,
                     error: Some("to_phone is required for owner replies".to_string()),
-                    owner_notifications: Vec::new(),
-                    customer_delivery: None,
-                    customer_delivery_phone: None,
+                    ..DevResponse::new(String::new())
                 })
                 .into_response();
             }
@@ -105,55 +144,91 @@ pub async fn send_message(
         }
 
         return Json(DevResponse {
-            reply: String::new(),
-            success: true,
-            error: None,
-            owner_notifications: Vec::new(),
             customer_delivery: Some(body),
             customer_delivery_phone: Some(to_phone),
+            ..DevResponse::new(String::new())
         })
         .into_response();
     }
 
     // Agent paused
     if state.paused.load(Ordering::SeqCst) {
-        let notifications = drain_notifications(&state);
+        let notifications = state.dev_notifications.drain();
         return Json(DevResponse {
-            reply: "Agent is currently paused.".to_string(),
-            success: true,
-            error: None,
             owner_notifications: notifications,
-            customer_delivery: None,
-            customer_delivery_phone: None,
+            ..DevResponse::new("Agent is currently paused.".to_string())
         })
         .into_response();
     }
 
     // Customer message → conversation engine
-    match conversation::process_message(&state, &from, &body).await {
-        Ok(reply) => {
-            let notifications = drain_notifications(&state);
-            Json(DevResponse {
-                reply,
-                success: true,
-                error: None,
-                owner_notifications: notifications,
-                customer_delivery: None,
-                customer_delivery_phone: None,
-            })
-            .into_response()
-        }
-        Err(e) => {
-            let notifications = drain_notifications(&state);
-            Json(DevResponse {
-                reply: String::new(),
-                success: false,
-                error: Some(e.to_string()),
-                owner_notifications: notifications,
-                customer_delivery: None,
-                customer_delivery_phone: None,
-            })
-            .into_response()
-        }
+    let result = if payload.dry_run {
+        conversation::process_message_dry_run(&state, &from, &body).await
+    } else {
+        conversation::process_message_detailed(&state, &from, &body).await
+    };
+    let notifications = state.dev_notifications.drain();
+    match result {
+        Ok(outcome) => Json(DevResponse {
+            owner_notifications: notifications,
+            intent: Some(outcome.intent),
+            previous_state: Some(outcome.previous_state),
+            state: Some(outcome.state),
+            pending_booking: outcome.pending_booking,
+            ..DevResponse::new(outcome.reply)
+        })
+        .into_response(),
+        Err(e) => Json(DevResponse {
+            success: false,
+            error: Some(e.to_string()),
+            owner_notifications: notifications,
+            ..DevResponse::new(String::new())
+        })
+        .into_response(),
     }
 }
+
+// GET /api/dev/llm-log
+/// Recent LLM prompts and raw responses, oldest first. These include customer
+/// messages, so they're only kept when `LLM_DEBUG_LOG` is on.
+pub async fn llm_log(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<LlmExchange>>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    if !state.config.llm_debug_log {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({"error": "LLM debug log is off; set LLM_DEBUG_LOG=1"})),
+        )
+            .into_response());
+    }
+    Ok(Json(state.llm_log.recent()))
+}
+
+#[derive(Deserialize)]
+pub struct NotificationsQuery {
+    #[serde(default = "default_drain")]
+    pub drain: bool,
+}
+
+fn default_drain() -> bool {
+    true
+}
+
+// GET /api/dev/notifications?drain=false
+/// Queued owner notifications, oldest first. Drains the queue unless
+/// `drain=false`, so monitoring can peek without stealing them from the UI.
+pub async fn notifications(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<NotificationsQuery>,
+) -> Result<Json<Vec<DevNotification>>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    let notifications = if query.drain {
+        state.dev_notifications.drain()
+    } else {
+        state.dev_notifications.peek()
+    };
+    Ok(Json(notifications))
+}
@@ -13,7 +13,10 @@ use tokio_stream::StreamExt;
 
 use crate::db::queries;
 use crate::services::conversation;
-use crate::services::inbox::record_inbox_event;
+use crate::models::InboxUpdate;
+use crate::services::inbox::{broadcast_thread_summary, record_inbox_event};
+use crate::services::messaging::segments::segment_count;
+use crate::services::signing::constant_time_eq;
 use crate::state::AppState;
 
 #[allow(clippy::result_large_err)]
@@ -23,7 +26,7 @@ fn check_auth(headers: &HeaderMap, expected_token: &str) -> Result<(), Response>
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
     let token = auth.strip_prefix("Bearer ").unwrap_or("");
-    if token != expected_token {
+    if !constant_time_eq(token, expected_token) {
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({"error": "unauthorized"})),
@@ -58,6 +61,8 @@ pub async fn get_threads(
 #[derive(Deserialize)]
 pub struct ThreadQuery {
     pub limit: Option<i64>,
+    /// Page backward: only events with a smaller id.
+    pub before_id: Option<i64>,
 }
 
 pub async fn get_thread(
@@ -71,7 +76,11 @@ pub async fn get_thread(
     let limit = query.limit.unwrap_or(200);
     let events = {
         let db = state.db.lock().unwrap();
-        queries::get_thread_events(&db, &phone, limit).map_err(|e| {
+        match query.before_id {
+            Some(before_id) => queries::get_thread_events_before(&db, &phone, before_id, limit),
+            None => queries::get_thread_events(&db, &phone, limit),
+        }
+        .map_err(|e| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(serde_json::json!({"error": e.to_string()})),
@@ -101,10 +110,48 @@ pub async fn mark_read(
                 .into_response()
         })?;
     }
+    // Other open tabs clear their badge too
+    broadcast_thread_summary(&state, &phone, 0);
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+// POST /api/inbox/thread/:phone/ai
+#[derive(Deserialize)]
+pub struct ThreadAiRequest {
+    pub enabled: bool,
+}
+
+/// Turn the agent's auto-replies on or off for one number. Inbound messages
+/// are still recorded, and owner replies still go out.
+pub async fn set_thread_ai(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+    Json(req): Json<ThreadAiRequest>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+
+    {
+        let db = state.db.lock().unwrap();
+        queries::set_ai_enabled(&db, &phone, req.enabled).map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response()
+        })?;
+    }
+    let note = if req.enabled {
+        "AI replies turned on for this thread"
+    } else {
+        "AI replies turned off for this thread"
+    };
+    record_inbox_event(&state, &phone, "system", note);
+
+    Ok(Json(serde_json::json!({"ok": true, "ai_enabled": req.enabled})))
+}
+
 // POST /api/inbox/reply
 #[derive(Deserialize)]
 pub struct ReplyRequest {
@@ -130,6 +177,18 @@ pub async fn send_reply(
             .into_response());
     }
 
+    let opted_out = {
+        let db = state.db.lock().unwrap();
+        queries::is_opted_out(&db, &phone).unwrap_or(false)
+    };
+    if opted_out {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({"error": "this number has opted out (STOP); it can't be texted until it replies START"})),
+        )
+            .into_response());
+    }
+
     // Inject into conversation history
     if let Err(e) = conversation::inject_owner_reply(&state, &phone, &message) {
         tracing::error!(error = %e, "failed to inject owner reply");
@@ -145,17 +204,47 @@ pub async fn send_reply(
             .into_response());
     }
     {
+        let segments = segment_count(&message);
         let db = state.db.lock().unwrap();
-        let _ = queries::increment_monthly_sent(&db);
+        let _ = queries::increment_monthly_sent(&db, segments);
     }
 
     // Record inbox event
     record_inbox_event(&state, &phone, "owner_reply", &message);
+    resume_assistant(&state, &phone);
 
     Ok(Json(serde_json::json!({"ok": true})))
 }
 
+/// Hand a thread back to the assistant after a handoff. Returns whether the
+/// thread was waiting for a person.
+fn resume_assistant(state: &Arc<AppState>, phone: &str) -> bool {
+    let cleared = {
+        let db = state.db.lock().unwrap();
+        queries::clear_needs_human(&db, phone).unwrap_or(false)
+    };
+    if cleared {
+        record_inbox_event(state, phone, "system", "Assistant resumed on this thread");
+    }
+    cleared
+}
+
+// POST /api/inbox/thread/:phone/resume
+/// Let the assistant answer a handed-off thread again without texting the customer.
+pub async fn resume_thread(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(phone): Path<String>,
+) -> Result<Json<serde_json::Value>, Response> {
+    check_auth(&headers, &state.config.admin_token)?;
+    let resumed = resume_assistant(&state, &phone);
+    Ok(Json(serde_json::json!({"ok": true, "resumed": resumed})))
+}
+
 // GET /api/inbox/events — SSE stream
+/// How long `EventSource` waits before reconnecting after a dropped stream.
+const SSE_RETRY: Duration = Duration::from_millis(3000);
+
 #[derive(Deserialize)]
 pub struct SseQuery {
     pub token: Option<String>,
@@ -164,11 +253,12 @@ pub struct SseQuery {
 
 pub async fn events_stream(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(query): Query<SseQuery>,
 ) -> Result<Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>>, Response> {
     // Auth via query param (EventSource can't set headers)
     let token = query.token.as_deref().unwrap_or("");
-    if token != state.config.admin_token {
+    if !constant_time_eq(token, &state.config.admin_token) {
         return Err((
             StatusCode::UNAUTHORIZED,
             Json(serde_json::json!({"error": "unauthorized"})),
@@ -176,35 +266,60 @@ pub async fn events_stream(
             .into_response());
     }
 
-    let last_id = query.last_id.unwrap_or(0);
+    // A browser resuming on its own sends Last-Event-ID but keeps the original
+    // URL, so its last_id may be stale; resume from whichever is newer.
+    let header_last_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok());
+    let last_id = query.last_id.max(header_last_id).unwrap_or(0);
+
+    // Subscribe before the catch-up query so an event recorded in between
+    // isn't missed; live events the query already returned are dropped below.
+    let rx = state.inbox_tx.subscribe();
 
     // Catch up on missed events from DB
     let catchup_events = {
         let db = state.db.lock().unwrap();
         queries::get_inbox_events_since(&db, last_id).unwrap_or_default()
     };
+    let caught_up_to = catchup_events.last().map_or(last_id, |e| e.id);
 
-    let rx = state.inbox_tx.subscribe();
+    let retry_stream = tokio_stream::once(Ok::<_, Infallible>(Event::default().retry(SSE_RETRY)));
 
     let catchup_stream = tokio_stream::iter(catchup_events.into_iter().map(|event| {
         let data = serde_json::to_string(&event).unwrap_or_default();
-        Ok::<_, Infallible>(Event::default().data(data).event("inbox_event"))
+        Ok::<_, Infallible>(
+            Event::default()
+                .data(data)
+                .event("inbox_event")
+                .id(event.id.to_string()),
+        )
     }));
 
-    let live_stream = BroadcastStream::new(rx).filter_map(|result| match result {
-        Ok(event) => {
+    let live_stream = BroadcastStream::new(rx).filter_map(move |result| match result {
+        Ok(InboxUpdate::Event(event)) if event.id <= caught_up_to => None,
+        Ok(InboxUpdate::Event(event)) => {
             let data = serde_json::to_string(&event).unwrap_or_default();
-            Some(Ok(Event::default().data(data).event("inbox_event")))
+            Some(Ok(Event::default()
+                .data(data)
+                .event("inbox_event")
+                .id(event.id.to_string())))
+        }
+        Ok(InboxUpdate::Summary(summary)) => {
+            let data = serde_json::to_string(&summary).unwrap_or_default();
+            Some(Ok(Event::default().data(data).event("thread_summary")))
         }
         Err(tokio_stream::wrappers::errors::BroadcastStreamRecvError::Lagged(_)) => None,
     });
 
+    let keepalive = Duration::from_secs(state.config.sse_keepalive_secs);
     let keepalive_stream = tokio_stream::StreamExt::map(
-        tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(Duration::from_secs(30))),
+        tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(keepalive)),
         |_| Ok(Event::default().comment("keepalive")),
     );
 
-    let combined = catchup_stream.chain(live_stream);
+    let combined = retry_stream.chain(catchup_stream).chain(live_stream);
     let merged = StreamExt::merge(combined, keepalive_stream);
 
     Ok(Sse::new(merged))
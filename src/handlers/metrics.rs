@@ -0,0 +1,99 @@
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+
+use crate::db::queries;
+use crate::services::signing::constant_time_eq;
+use crate::state::AppState;
+
+// GET /metrics — Prometheus text exposition format
+pub async fn metrics(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    // Token is optional: an empty METRICS_TOKEN leaves the endpoint open for scrapers
+    if !state.config.metrics_token.is_empty() {
+        let token = headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if !constant_time_eq(token, &state.config.metrics_token) {
+            return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+        }
+    }
+
+    let loaded = {
+        let db = state.db.lock().unwrap();
+        queries::get_dashboard_stats(&db).and_then(|stats| {
+            let months = queries::get_recent_monthly_activity(&db, 1)?;
+            Ok((stats, months))
+        })
+    };
+    let (stats, month) = match loaded {
+        Ok((stats, mut months)) => match months.pop() {
+            Some(month) => (stats, month),
+            None => return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response(),
+        },
+        Err(e) => {
+            tracing::error!(error = %e, "failed to load metrics");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
+    };
+
+    let paused = state.paused.load(Ordering::SeqCst);
+    let label = format!("{{month=\"{}\"}}", month.month);
+
+    let mut out = String::with_capacity(1024);
+    let counters = [
+        (
+            "phonebook_messages_received",
+            "Inbound messages this month",
+            month.messages_received,
+        ),
+        ("phonebook_messages_sent", "Outbound messages this month", month.messages_sent),
+        ("phonebook_bookings_created", "Bookings created this month", month.bookings_created),
+        (
+            "phonebook_bookings_cancelled",
+            "Bookings cancelled this month",
+            month.bookings_cancelled,
+        ),
+        (
+            "phonebook_bookings_rescheduled",
+            "Bookings rescheduled this month",
+            month.bookings_rescheduled,
+        ),
+    ];
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} counter");
+        let _ = writeln!(out, "{name}{label} {value}");
+    }
+
+    let gauges = [
+        (
+            "phonebook_messages_this_hour",
+            "Inbound messages in the current hour",
+            stats.messages_this_hour,
+        ),
+        ("phonebook_blocked_numbers", "Numbers on the blocklist", stats.blocked_count),
+        (
+            "phonebook_bookings_upcoming",
+            "Confirmed bookings in the future",
+            stats.upcoming_bookings_count,
+        ),
+        ("phonebook_agent_paused", "1 if the agent is paused", paused as i64),
+    ];
+    for (name, help, value) in gauges {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} gauge");
+        let _ = writeln!(out, "{name} {value}");
+    }
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        out,
+    )
+        .into_response()
+}
@@ -1,6 +1,9 @@
 pub mod admin;
+pub mod booking;
 pub mod calendar;
 pub mod dev;
 pub mod health;
 pub mod inbox;
+pub mod metrics;
+pub mod reschedule;
 pub mod webhook;
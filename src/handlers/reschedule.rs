@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Form;
+use chrono::{NaiveDateTime, Utc};
+use serde::Deserialize;
+
+use crate::db::{pool, queries};
+use crate::models::{AiPreferences, Availability, Booking, BookingActor, BookingLink, BookingStatus};
+use crate::services::dates::format_when;
+use crate::services::i18n;
+use crate::services::notify::{notify_owner, OwnerEvent};
+use crate::services::scheduling::create_booking_if_free;
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct RescheduleForm {
+    pub date: String,
+    pub time: String,
+}
+
+pub(crate) fn page(status: StatusCode, title: &str, body: &str) -> Response {
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+<style>
+body {{ font-family: -apple-system, system-ui, sans-serif; max-width: 420px; margin: 40px auto; padding: 0 16px; color: #222; }}
+label {{ display: block; margin: 12px 0 4px; }}
+input, button {{ font-size: 16px; padding: 8px; width: 100%; box-sizing: border-box; }}
+button {{ margin-top: 16px; }}
+.error {{ color: #b00020; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{body}
+</body>
+</html>"#
+    );
+    (status, Html(html)).into_response()
+}
+
+/// Escape text for interpolation into page HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Whether the owner shows times on a 24-hour clock.
+pub(crate) fn use_24h_time(state: &AppState) -> bool {
+    let db = state.db.lock().unwrap();
+    queries::get_user(&db, "default")
+        .ok()
+        .flatten()
+        .is_some_and(|u| u.use_24h_time)
+}
+
+fn reschedule_form(token: &str, booking: &Booking, error: Option<&str>, use_24h: bool) -> String {
+    let error = error
+        .map(|e| format!(r#"<p class="error">{}</p>"#, escape_html(e)))
+        .unwrap_or_default();
+    format!(
+        r#"<p>Your appointment is currently booked for <strong>{current}</strong>.</p>
+{error}
+<form method="post" action="/reschedule/{token}">
+<label for="date">New date</label>
+<input type="date" id="date" name="date" required>
+<label for="time">New time</label>
+<input type="time" id="time" name="time" required>
+<button type="submit">Request new time</button>
+</form>"#,
+        current = format_when(booking.date_time, use_24h),
+    )
+}
+
+/// Resolve a reschedule token to its still-active booking.
+/// Tokens expire at the appointment time and die with the booking.
+#[allow(clippy::result_large_err)]
+fn load_booking_for_token(state: &AppState, token: &str) -> Result<Booking, Response> {
+    let db = state.db.lock().unwrap();
+    let link = match queries::get_booking_link(&db, token) {
+        Ok(Some(link)) if link.action == BookingLink::RESCHEDULE => link,
+        Ok(_) => {
+            return Err(page(
+                StatusCode::NOT_FOUND,
+                "Link not found",
+                "<p>This reschedule link isn't valid. Text us to change your appointment.</p>",
+            ));
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "failed to load booking link");
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response());
+        }
+    };
+
+    if Utc::now().naive_utc() >= link.expires_at {
+        return Err(page(
+            StatusCode::GONE,
+            "Link expired",
+            "<p>This reschedule link has expired. Text us to book a new appointment.</p>",
+        ));
+    }
+
+    match queries::get_booking_by_id(&db, &link.booking_id) {
+        Ok(Some(booking)) if booking.status.holds_slot() => Ok(booking),
+        Ok(_) => Err(page(
+            StatusCode::GONE,
+            "Link expired",
+            "<p>This appointment has already been cancelled or moved.</p>",
+        )),
+        Err(e) => {
+            tracing::error!(error = %e, "failed to load booking for reschedule link");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response())
+        }
+    }
+}
+
+// GET /reschedule/:token
+pub async fn reschedule_page(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Response {
+    match load_booking_for_token(&state, &token) {
+        Ok(booking) => page(
+            StatusCode::OK,
+            "Reschedule appointment",
+            &reschedule_form(&token, &booking, None, use_24h_time(&state)),
+        ),
+        Err(rejection) => rejection,
+    }
+}
+
+// POST /reschedule/:token
+pub async fn submit_reschedule(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Form(form): Form<RescheduleForm>,
+) -> Response {
+    let booking = match load_booking_for_token(&state, &token) {
+        Ok(booking) => booking,
+        Err(rejection) => return rejection,
+    };
+
+    let requested = format!("{} {}", form.date.trim(), form.time.trim());
+    let Ok(new_time) = NaiveDateTime::parse_from_str(&requested, "%Y-%m-%d %H:%M") else {
+        return page(
+            StatusCode::BAD_REQUEST,
+            "Reschedule appointment",
+            &reschedule_form(
+                &token,
+                &booking,
+                Some("Please pick a valid date and time."),
+                use_24h_time(&state),
+            ),
+        );
+    };
+
+    if new_time <= Utc::now().naive_utc() {
+        return page(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Reschedule appointment",
+            &reschedule_form(
+                &token,
+                &booking,
+                Some("Please pick a time in the future."),
+                use_24h_time(&state),
+            ),
+        );
+    }
+
+    let (availability, one_per_day, use_24h, language, rejections) = {
+        let db = state.db.lock().unwrap();
+        let user = queries::get_user(&db, "default").ok().flatten();
+        let availability = user
+            .as_ref()
+            .and_then(|u| u.availability.as_deref())
+            .and_then(|s| Availability::from_json(s).ok());
+        let one_per_day = user
+            .as_ref()
+            .is_some_and(|u| u.one_booking_per_customer_per_day);
+        let use_24h = user.as_ref().is_some_and(|u| u.use_24h_time);
+        let language = user
+            .as_ref()
+            .map(|u| u.language.clone())
+            .unwrap_or_else(|| i18n::DEFAULT_LANGUAGE.to_string());
+        let rejections = user
+            .and_then(|u| u.ai_preferences)
+            .and_then(|s| AiPreferences::from_json(&s).ok())
+            .map(|p| p.rejection_messages)
+            .unwrap_or_default();
+        (availability, one_per_day, use_24h, language, rejections)
+    };
+
+    let now = Utc::now().naive_utc();
+    let rebooked = Booking {
+        id: uuid::Uuid::new_v4().to_string(),
+        date_time: new_time,
+        status: BookingStatus::Confirmed,
+        created_at: now,
+        updated_at: now,
+        ..booking.clone()
+    };
+
+    // Re-check the slot, retire the old booking and insert the new one in a
+    // single transaction, the same way a reschedule by text does
+    let outcome = {
+        let (rebooked, old_id) = (rebooked.clone(), booking.id.clone());
+        pool::with_db(&state.db, move |conn| {
+            if let Err(e) = create_booking_if_free(
+                conn,
+                &rebooked,
+                Some(&old_id),
+                availability.as_ref(),
+                BookingActor::Customer,
+                one_per_day,
+            )? {
+                return Ok(Err(e));
+            }
+            let _ = queries::increment_monthly_rescheduled(conn);
+            let new_token = queries::create_booking_link(
+                conn,
+                &rebooked.id,
+                BookingLink::RESCHEDULE,
+                &rebooked.date_time,
+            )?;
+            Ok(Ok(new_token))
+        })
+        .await
+    };
+    let new_token = match outcome {
+        Ok(Ok(new_token)) => new_token,
+        Ok(Err(e)) => {
+            return page(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Reschedule appointment",
+                &reschedule_form(
+                    &token,
+                    &booking,
+                    Some(&e.render(&rejections, &language, use_24h)),
+                    use_24h,
+                ),
+            );
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "failed to reschedule booking via link");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+        }
+    };
+
+    let summary = format!(
+        "Rescheduled via link: {} ({}) from {} to {}",
+        booking.customer_name.as_deref().unwrap_or("Unknown"),
+        booking.customer_phone,
+        format_when(booking.date_time, use_24h),
+        format_when(rebooked.date_time, use_24h),
+    );
+    notify_owner(&state, OwnerEvent::Booking, &summary, Some(&booking.customer_phone)).await;
+
+    page(
+        StatusCode::OK,
+        "You're all set",
+        &format!(
+            r#"<p>Your appointment has been moved to <strong>{}</strong>.</p>
+<p><a href="/calendar/{}.ics">Add to calendar</a> · <a href="/reschedule/{new_token}">Change it again</a></p>"#,
+            format_when(rebooked.date_time, use_24h),
+            rebooked.id,
+        ),
+    )
+}
@@ -2,33 +2,75 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use axum::extract::State;
-use axum::http::{header, HeaderMap};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Response};
-use axum::Form;
+use axum::{Form, Json};
 use base64::Engine;
+use chrono::{Duration, Utc};
 use hmac::{Hmac, Mac};
 use serde::Deserialize;
 use sha1::Sha1;
 
-use crate::db::queries;
+use crate::db::{pool, queries};
+use crate::logging::mask_phone;
 use crate::services::conversation;
+use crate::services::debounce;
+use crate::services::i18n;
 use crate::services::inbox::record_inbox_event;
-use crate::state::{AppState, DevNotification, DevNotificationKind};
+use crate::services::messaging::segments::segment_count;
+use crate::services::notify::{notify_owner, owner_phone, OwnerEvent};
+use crate::services::phone::normalize_phone;
+use crate::services::signing::constant_time_eq;
+use crate::services::spam;
+use crate::state::AppState;
 
 const PER_CUSTOMER_LIMIT: i64 = 15;
 const GLOBAL_LIMIT: i64 = 100;
 
-#[derive(Deserialize)]
+/// Carrier-standard keywords, matched against the whole message.
+const OPT_OUT_KEYWORDS: &[&str] = &["STOP", "STOPALL", "UNSUBSCRIBE", "CANCEL", "END", "QUIT"];
+const OPT_IN_KEYWORDS: &[&str] = &["START", "UNSTOP"];
+
+/// An incoming text, whichever gateway delivered it.
 #[allow(dead_code)]
-pub struct TwilioWebhookForm {
-    #[serde(rename = "From")]
+pub struct InboundSms {
     pub from: String,
-    #[serde(rename = "To")]
     pub to: String,
-    #[serde(rename = "Body")]
     pub body: String,
-    #[serde(rename = "MessageSid")]
-    pub message_sid: Option<String>,
+    pub message_id: Option<String>,
+    /// `MediaUrl0..N` from an MMS, up to `NumMedia`.
+    pub media_urls: Vec<String>,
+    /// Whether the gateway proved the message is genuine (a checked Twilio
+    /// signature or the JSON webhook token), so `from` can be trusted.
+    pub authenticated: bool,
+}
+
+impl InboundSms {
+    /// Pick out the fields we use. Twilio posts many more (`NumMedia`,
+    /// `FromCity`, ...), all of which are covered by the signature.
+    fn from_twilio_fields(fields: &[(String, String)], authenticated: bool) -> Option<Self> {
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.clone())
+        };
+        // Twilio attaches at most 10 media items per message
+        let num_media = field("NumMedia")
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or(0)
+            .min(10);
+        Some(Self {
+            from: field("From")?,
+            to: field("To").unwrap_or_default(),
+            body: field("Body").unwrap_or_default(),
+            message_id: field("MessageSid"),
+            media_urls: (0..num_media)
+                .filter_map(|i| field(&format!("MediaUrl{i}")))
+                .collect(),
+            authenticated,
+        })
+    }
 }
 
 fn validate_twilio_signature(
@@ -57,101 +99,227 @@ fn validate_twilio_signature(
     expected == signature
 }
 
+/// The URL Twilio signed. A configured `PUBLIC_URL` is used as-is, since some
+/// proxies rewrite the forwarded headers we'd otherwise rebuild it from.
+fn signed_url(public_url: &str, headers: &HeaderMap, path: &str) -> String {
+    if !public_url.is_empty() {
+        return format!("{}{path}", public_url.trim_end_matches('/'));
+    }
+
+    // Reconstruct webhook URL — use X-Forwarded-Proto/Host if behind proxy
+    let proto = headers
+        .get("x-forwarded-proto")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("https");
+    let host = headers
+        .get("x-forwarded-host")
+        .or_else(|| headers.get("host"))
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("localhost");
+    format!("{proto}://{host}{path}")
+}
+
+/// Validate the Twilio signature for a webhook request.
+/// Skipped when the auth token is empty (dev mode).
+#[allow(clippy::result_large_err)]
+fn verify_twilio_request(
+    state: &AppState,
+    headers: &HeaderMap,
+    path: &str,
+    params: &[(&str, &str)],
+) -> Result<(), Response> {
+    if state.config.twilio_auth_token.is_empty() {
+        return Ok(());
+    }
+
+    let signature = headers
+        .get("x-twilio-signature")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if signature.is_empty() {
+        tracing::warn!("missing X-Twilio-Signature header");
+        return Err((
+            axum::http::StatusCode::FORBIDDEN,
+            "Missing signature",
+        )
+            .into_response());
+    }
+
+    let url = signed_url(&state.config.public_url, headers, path);
+    if !validate_twilio_signature(&state.config.twilio_auth_token, signature, &url, params) {
+        tracing::warn!("invalid Twilio signature");
+        return Err((
+            axum::http::StatusCode::FORBIDDEN,
+            "Invalid signature",
+        )
+            .into_response());
+    }
+
+    Ok(())
+}
+
 pub async fn sms_webhook(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Form(form): Form<TwilioWebhookForm>,
+    Form(fields): Form<Vec<(String, String)>>,
 ) -> Response {
-    let from = form.from.trim().to_string();
-    let body = form.body.trim().to_string();
-
-    tracing::info!(from = %from, body = %body, "incoming SMS");
-
-    // Validate Twilio signature (skip if auth token is empty — dev mode)
-    if !state.config.twilio_auth_token.is_empty() {
-        let signature = headers
-            .get("x-twilio-signature")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
-
-        if signature.is_empty() {
-            tracing::warn!("missing X-Twilio-Signature header");
-            return (
-                axum::http::StatusCode::FORBIDDEN,
-                "Missing signature",
-            )
-                .into_response();
-        }
+    // Twilio signs every posted field exactly as sent, not just the ones we read
+    let params: Vec<(&str, &str)> = fields
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    if let Err(rejection) = verify_twilio_request(&state, &headers, "/webhook/sms", &params) {
+        return rejection;
+    }
 
-        // Reconstruct webhook URL — use X-Forwarded-Proto/Host if behind proxy
-        let proto = headers
-            .get("x-forwarded-proto")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("https");
-        let host = headers
-            .get("x-forwarded-host")
-            .or_else(|| headers.get("host"))
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("localhost");
-        let url = format!("{proto}://{host}/webhook/sms");
+    // verify_twilio_request only checks signatures when a token is configured
+    let authenticated = !state.config.twilio_auth_token.is_empty();
+    let Some(sms) = InboundSms::from_twilio_fields(&fields, authenticated) else {
+        return (StatusCode::BAD_REQUEST, "From is required").into_response();
+    };
+    handle_inbound(&state, sms).await;
+    twiml_response()
+}
 
-        let params = [
-            ("From", from.as_str()),
-            ("To", form.to.as_str()),
-            ("Body", body.as_str()),
-            ("MessageSid", form.message_sid.as_deref().unwrap_or("")),
-        ];
+/// Body of `POST /webhook/sms/json`, for gateways that don't speak Twilio.
+#[derive(Deserialize)]
+pub struct JsonSmsPayload {
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+    #[serde(default)]
+    pub body: String,
+    pub message_id: Option<String>,
+    #[serde(default)]
+    pub media_urls: Vec<String>,
+}
 
-        if !validate_twilio_signature(
-            &state.config.twilio_auth_token,
-            signature,
-            &url,
-            &params,
-        ) {
-            tracing::warn!("invalid Twilio signature");
-            return (
-                axum::http::StatusCode::FORBIDDEN,
-                "Invalid signature",
-            )
-                .into_response();
-        }
+// POST /webhook/sms/json
+/// Same handling as the Twilio webhook, for a JSON body. Authenticated with
+/// `JSON_WEBHOOK_TOKEN` (or the admin token when that isn't set).
+pub async fn sms_webhook_json(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<JsonSmsPayload>,
+) -> Response {
+    let expected = if state.config.json_webhook_token.is_empty() {
+        &state.config.admin_token
+    } else {
+        &state.config.json_webhook_token
+    };
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or("");
+    if !constant_time_eq(token, expected) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({"error": "unauthorized"})),
+        )
+            .into_response();
+    }
+    if payload.from.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": "from is required"})),
+        )
+            .into_response();
     }
 
-    // 1. Check blocked
-    {
-        let db = state.db.lock().unwrap();
-        match queries::is_blocked(&db, &from) {
-            Ok(true) => {
-                tracing::info!(from = %from, "blocked number, ignoring");
-                return twiml_response();
-            }
-            Ok(false) => {}
-            Err(e) => {
-                tracing::error!(error = %e, "failed to check blocked status");
-            }
+    let sms = InboundSms {
+        from: payload.from,
+        to: payload.to,
+        body: payload.body,
+        message_id: payload.message_id,
+        media_urls: payload.media_urls,
+        authenticated: true,
+    };
+    handle_inbound(&state, sms).await;
+    Json(serde_json::json!({"status": "received"})).into_response()
+}
+
+/// Everything after the gateway-specific parsing and authentication: blocks,
+/// rate limits, keywords, admin commands and finally the conversation engine.
+async fn handle_inbound(state: &Arc<AppState>, sms: InboundSms) {
+    let from = sms.from.trim().to_string();
+    let body = sms.body.trim().to_string();
+
+    tracing::info!(
+        from = %mask_phone(&from),
+        body = state.config.log_message_bodies.then_some(body.as_str()),
+        "incoming SMS"
+    );
+
+    // 1. Check blocked (DB work runs off the async workers on these hot paths)
+    match pool::is_blocked(&state.db, &from).await {
+        Ok(true) => {
+            tracing::info!(from = %mask_phone(&from), "blocked number, ignoring");
+            return;
+        }
+        Ok(false) => {}
+        Err(e) => {
+            tracing::error!(error = %e, "failed to check blocked status");
         }
     }
 
     // 2. Increment rate limit counter + monthly activity
-    let message_count = {
-        let db = state.db.lock().unwrap();
-        let _ = queries::increment_monthly_received(&db);
-        queries::increment_message_count(&db, &from).unwrap_or(1)
-    };
+    let _ = pool::write(&state.db, queries::increment_monthly_received).await;
+    let message_count = pool::increment_message_count(&state.db, &from)
+        .await
+        .unwrap_or(1);
 
-    // 3. Per-customer rate limit check (>15/hr → auto-block)
-    if message_count > PER_CUSTOMER_LIMIT {
-        tracing::warn!(from = %from, count = message_count, "per-customer rate limit exceeded, auto-blocking");
+    // 3. STOP/START keywords are honored before anything else can reply
+    let keyword = body.trim_end_matches(['.', '!']).to_uppercase();
+    let opting_out = OPT_OUT_KEYWORDS.contains(&keyword.as_str());
+    if opting_out || OPT_IN_KEYWORDS.contains(&keyword.as_str()) {
+        set_opted_out(state, &from, &body, opting_out).await;
+        return;
+    }
+
+    // 4. Opted-out number → no agent replies until it texts START
+    match pool::is_opted_out(&state.db, &from).await {
+        Ok(true) => {
+            tracing::info!(from = %mask_phone(&from), "opted-out number, ignoring");
+            return;
+        }
+        Ok(false) => {}
+        Err(e) => {
+            tracing::error!(error = %e, "failed to check opt-out status");
+        }
+    }
+
+    // 5. The owner's spam keywords → drop the message, or block the sender
+    if let Some((keyword, block)) = spam_keyword(state, &body).await {
+        if !rate_limit_exempt(state, &from).await {
+            reject_spam(state, &from, &keyword, block).await;
+            return;
+        }
+    }
+
+    // 6. Per-customer rate limit check (>15/hr → auto-block)
+    if message_count > PER_CUSTOMER_LIMIT && !rate_limit_exempt(state, &from).await {
+        tracing::warn!(from = %mask_phone(&from), count = message_count, "per-customer rate limit exceeded, auto-blocking");
+        let until = Utc::now().naive_utc() + Duration::minutes(state.config.auto_block_minutes);
+        let reason = format!(
+            "auto-blocked: rate limit exceeded ({message_count} msgs/hour, limit {PER_CUSTOMER_LIMIT})"
+        );
         {
             let db = state.db.lock().unwrap();
-            let _ = queries::block_number(&db, &from, Some("auto-blocked: rate limit exceeded"), true);
+            let _ = queries::auto_block_number(&db, &from, Some(&reason), &until);
         }
-        let alert = format!("Auto-blocked {from}: exceeded {PER_CUSTOMER_LIMIT} messages/hour ({message_count} msgs)");
-        notify_owner(&state, &alert, Some(&from)).await;
-        return twiml_response();
+        // Later messages stop at step 1, so this goes out once per block
+        conversation::send_blocked_autoreply(state, &from).await;
+        let alert = format!(
+            "Auto-blocked {from} until {} UTC: exceeded {PER_CUSTOMER_LIMIT} messages/hour ({message_count} msgs)",
+            until.format("%Y-%m-%d %H:%M"),
+        );
+        notify_owner(state, OwnerEvent::Block, &alert, Some(&from)).await;
+        return;
     }
 
-    // 4. Global rate limit check (>100/hr → pause agent)
+    // 7. Global rate limit check (>100/hr → pause agent)
     let global_count = {
         let db = state.db.lock().unwrap();
         queries::get_global_message_count(&db).unwrap_or(0)
@@ -160,61 +328,193 @@ pub async fn sms_webhook(
         tracing::warn!(global_count, "global rate limit exceeded, pausing agent");
         state.paused.store(true, Ordering::SeqCst);
         let alert = format!("Agent paused: global rate limit exceeded ({global_count} msgs/hour)");
-        notify_owner(&state, &alert, None).await;
-        return twiml_response();
+        notify_owner(state, OwnerEvent::Pause, &alert, None).await;
+        return;
     }
 
-    // 5. Agent paused → silent ignore
+    // 8. Agent paused → ignore, apart from the owner's optional one-time auto-reply
+    let owner = owner_phone(state).await;
     if state.paused.load(Ordering::SeqCst) {
         tracing::info!("agent is paused, ignoring message");
-        return twiml_response();
+        if from != owner {
+            if let Err(e) = conversation::send_paused_autoreply(state, &from).await {
+                tracing::error!(error = %e, "failed to send paused auto-reply");
+            }
+        }
+        return;
     }
 
-    // 6. Owner SMS with # prefix → admin command
-    if from == state.config.owner_phone && body.starts_with('#') {
-        let reply = handle_admin_command(&state, &body).await;
+    // 9. Owner SMS with # prefix → admin command
+    if from == owner && body.starts_with('#') {
+        let command = match owner_command(state, &body, sms.authenticated) {
+            Ok(command) => command,
+            Err(reason) => {
+                tracing::warn!(reason, "rejected owner command");
+                return;
+            }
+        };
+        let reply = handle_admin_command(state, &command).await;
         if let Err(e) = state.messaging.send_message(&from, &reply).await {
             tracing::error!(error = %e, "failed to send admin reply");
         } else {
+            let segments = segment_count(&reply);
             let db = state.db.lock().unwrap();
-            let _ = queries::increment_monthly_sent(&db);
+            let _ = queries::increment_monthly_sent(&db, segments);
         }
-        return twiml_response();
+        return;
     }
 
-    // 7. Customer message → conversation engine
-    match conversation::process_message(&state, &from, &body).await {
-        Ok(reply) => {
-            if let Err(e) = state.messaging.send_message(&from, &reply).await {
-                tracing::error!(error = %e, "failed to send reply");
-            } else {
-                let db = state.db.lock().unwrap();
-                let _ = queries::increment_monthly_sent(&db);
-            }
+    // 10. Nothing to read, or too long to be worth an LLM call
+    if body.is_empty() && sms.media_urls.is_empty() {
+        tracing::info!(from = %mask_phone(&from), "empty message, ignoring");
+        return;
+    }
+    let length = body.chars().count();
+    if state.config.max_inbound_body > 0 && length > state.config.max_inbound_body {
+        tracing::warn!(from = %mask_phone(&from), length, "message over MAX_INBOUND_BODY, rejecting");
+        conversation::reject_long_message(state, &from, length).await;
+        return;
+    }
+
+    // 11. MMS attachments are kept as links in the inbox; without text there's
+    // nothing for the engine to read, so ask the customer to write it out
+    if !sms.media_urls.is_empty() {
+        conversation::record_media(state, &from, &sms.media_urls);
+        if body.is_empty() {
+            conversation::ask_for_text(state, &from).await;
+            return;
         }
+    }
+
+    // 12. Customer message → conversation engine (optionally coalescing rapid texts)
+    if state.config.debounce_ms > 0 {
+        debounce::submit(state, &from, &body);
+    } else {
+        conversation::reply_to_customer(state, &from, &body).await;
+    }
+}
+
+/// Record a STOP/START keyword and send the confirmation carriers require.
+/// The confirmation goes out even on opt-out; it is the last message sent.
+async fn set_opted_out(state: &Arc<AppState>, phone: &str, body: &str, opted_out: bool) {
+    let (result, lang) = {
+        let db = state.db.lock().unwrap();
+        let result = if opted_out {
+            queries::opt_out(&db, phone)
+        } else {
+            queries::opt_in(&db, phone).map(|_| ())
+        };
+        (result, conversation::reply_language(&db))
+    };
+    if let Err(e) = result {
+        tracing::error!(error = %e, phone = %mask_phone(phone), "failed to update opt-out status");
+        return;
+    }
+    tracing::info!(phone = %mask_phone(phone), opted_out, "updated opt-out status");
+
+    record_inbox_event(state, phone, "customer_message", body);
+    let note = if opted_out {
+        "Customer opted out of messages"
+    } else {
+        "Customer opted back in to messages"
+    };
+    record_inbox_event(state, phone, "system", note);
+
+    let key = if opted_out { "opted_out" } else { "opted_in" };
+    let reply = i18n::t(&lang, key, &[]);
+    if let Err(e) = state.messaging.send_message(phone, &reply).await {
+        tracing::error!(error = %e, "failed to send opt-out confirmation");
+    } else {
+        let segments = segment_count(&reply);
+        let db = state.db.lock().unwrap();
+        let _ = queries::increment_monthly_sent(&db, segments);
+    }
+}
+
+/// The owner and allowlisted numbers never get auto-blocked.
+/// The global limit and pause still apply to them.
+async fn rate_limit_exempt(state: &AppState, phone: &str) -> bool {
+    if phone == owner_phone(state).await {
+        return true;
+    }
+    let phone = phone.to_string();
+    match pool::with_db(&state.db, move |conn| queries::is_allowlisted(conn, &phone)).await {
+        Ok(allowed) => allowed,
         Err(e) => {
-            tracing::error!(error = %e, from = %from, "conversation processing failed");
-            let fallback = "Sorry, I'm having trouble right now. Please try again in a moment.";
-            if state.messaging.send_message(&from, fallback).await.is_ok() {
-                let db = state.db.lock().unwrap();
-                let _ = queries::increment_monthly_sent(&db);
-            }
+            tracing::error!(error = %e, "failed to check allowlist");
+            false
         }
     }
+}
+
+/// The configured spam keyword `body` contains, and whether to block for it.
+async fn spam_keyword(state: &AppState, body: &str) -> Option<(String, bool)> {
+    let user = match pool::with_db(&state.db, |conn| queries::get_user(conn, "default")).await {
+        Ok(user) => user?,
+        Err(e) => {
+            tracing::error!(error = %e, "failed to load spam keywords");
+            return None;
+        }
+    };
+    let keyword = spam::matched_keyword(user.spam_keywords.as_deref()?, body)?;
+    Some((keyword.to_string(), user.block_spam))
+}
 
-    // 8. Cleanup old rate limit windows periodically
-    {
+/// Turn away a text matching a spam keyword without a reply: the reason goes
+/// in the inbox, and with `block_spam` the sender is blocked until unblocked.
+async fn reject_spam(state: &Arc<AppState>, from: &str, keyword: &str, block: bool) {
+    tracing::warn!(from = %mask_phone(from), keyword, block, "spam keyword matched");
+    let reason = format!("spam keyword \"{keyword}\"");
+    if block {
         let db = state.db.lock().unwrap();
-        let _ = queries::cleanup_old_windows(&db);
+        let _ = queries::block_number(&db, from, Some(&reason), true);
+    }
+    let action = if block { "Blocked after" } else { "Ignored" };
+    record_inbox_event(
+        state,
+        from,
+        "system",
+        &format!("{action} a message containing {reason}"),
+    );
+    if block {
+        let alert = format!("Blocked {from}: message contained {reason}");
+        notify_owner(state, OwnerEvent::Block, &alert, Some(from)).await;
     }
+}
 
-    twiml_response()
+/// Check a `#` message from the owner's number before running it. With a
+/// Twilio token configured (production) the number must come from a verified
+/// request; without one anyone can post as the owner, which is only
+/// acceptable in dev. When `ADMIN_COMMAND_SECRET` is set the command must
+/// also start with it (`#<secret> pause`). Returns the command without the
+/// secret, or why it was refused.
+pub fn owner_command(
+    state: &AppState,
+    body: &str,
+    authenticated: bool,
+) -> Result<String, &'static str> {
+    if !authenticated && !state.config.twilio_auth_token.is_empty() {
+        return Err("sender not verified");
+    }
+    let secret = &state.config.admin_command_secret;
+    if secret.is_empty() {
+        return Ok(body.to_string());
+    }
+    let (given, command) = body
+        .strip_prefix('#')
+        .and_then(|rest| rest.split_once(' '))
+        .ok_or("missing command secret")?;
+    if !constant_time_eq(given, secret) {
+        return Err("wrong command secret");
+    }
+    Ok(format!("#{}", command.trim_start()))
 }
 
 pub async fn handle_admin_command(state: &Arc<AppState>, body: &str) -> String {
     let parts: Vec<&str> = body.splitn(2, ' ').collect();
     let command = parts[0].to_lowercase();
     let arg = parts.get(1).map(|s| s.trim());
+    let number = arg.map(|a| normalize_phone(a, &state.config.default_country_code));
 
     match command.as_str() {
         "#pause" => {
@@ -237,60 +537,120 @@ pub async fn handle_admin_command(state: &Arc<AppState>, body: &str) -> String {
                 blocked.len()
             )
         }
-        "#block" => {
-            if let Some(number) = arg {
+        "#block" => match number {
+            Some(Ok(number)) => {
                 let db = state.db.lock().unwrap();
-                match queries::block_number(&db, number, Some("blocked by owner"), false) {
+                match queries::block_number(&db, &number, Some("blocked by owner"), false) {
                     Ok(_) => format!("Blocked {number}"),
                     Err(e) => format!("Error blocking: {e}"),
                 }
-            } else {
-                "Usage: #block <phone_number>".to_string()
             }
-        }
-        "#unblock" => {
-            if let Some(number) = arg {
+            Some(Err(e)) => format!("Can't block: {e}"),
+            None => "Usage: #block <phone_number>".to_string(),
+        },
+        "#unblock" => match number {
+            Some(Ok(number)) => {
                 let db = state.db.lock().unwrap();
-                match queries::unblock_number(&db, number) {
+                match queries::unblock_number(&db, &number) {
                     Ok(true) => format!("Unblocked {number}"),
                     Ok(false) => format!("{number} was not blocked"),
                     Err(e) => format!("Error unblocking: {e}"),
                 }
-            } else {
-                "Usage: #unblock <phone_number>".to_string()
             }
-        }
-        _ => "Unknown command. Available: #pause, #resume, #status, #block <number>, #unblock <number>".to_string(),
+            Some(Err(e)) => format!("Can't unblock: {e}"),
+            None => "Usage: #unblock <phone_number>".to_string(),
+        },
+        "#allow" => match number {
+            Some(Ok(number)) => {
+                let db = state.db.lock().unwrap();
+                match queries::allow_number(&db, &number, Some("allowed by owner")) {
+                    Ok(_) => format!("Allowlisted {number}: exempt from the hourly message limit"),
+                    Err(e) => format!("Error allowlisting: {e}"),
+                }
+            }
+            Some(Err(e)) => format!("Can't allowlist: {e}"),
+            None => "Usage: #allow <phone_number>".to_string(),
+        },
+        "#unallow" => match number {
+            Some(Ok(number)) => {
+                let db = state.db.lock().unwrap();
+                match queries::unallow_number(&db, &number) {
+                    Ok(true) => format!("Removed {number} from the allowlist"),
+                    Ok(false) => format!("{number} was not allowlisted"),
+                    Err(e) => format!("Error updating allowlist: {e}"),
+                }
+            }
+            Some(Err(e)) => format!("Can't update allowlist: {e}"),
+            None => "Usage: #unallow <phone_number>".to_string(),
+        },
+        _ => "Unknown command. Available: #pause, #resume, #status, #block <number>, #unblock <number>, #allow <number>, #unallow <number>".to_string(),
     }
 }
 
-async fn notify_owner(state: &Arc<AppState>, message: &str, phone: Option<&str>) {
-    // Always push to dev notification queue
-    if let Ok(mut notifications) = state.dev_notifications.lock() {
-        notifications.push(DevNotification {
-            phone: phone.map(|p| p.to_string()),
-            kind: DevNotificationKind::System,
-            content: message.to_string(),
-        });
-    }
-    if let Some(p) = phone {
-        record_inbox_event(state, p, "system", message);
+/// Twilio delivery status callback (`statusCallback` on outbound messages).
+/// Twilio posts many fields; all of them are needed to verify the signature.
+pub async fn status_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Response {
+    let params: Vec<(&str, &str)> = fields
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    if let Err(rejection) = verify_twilio_request(&state, &headers, "/webhook/status", &params) {
+        return rejection;
     }
 
-    if state.config.owner_phone.is_empty() {
-        tracing::warn!("owner_phone not configured, skipping notification");
-        return;
-    }
-    if let Err(e) = state
-        .messaging
-        .send_message(&state.config.owner_phone, message)
-        .await
-    {
-        tracing::error!(error = %e, "failed to notify owner");
-    } else {
+    let field = |name: &str| {
+        params
+            .iter()
+            .find(|(k, _)| *k == name)
+            .map(|(_, v)| v.trim())
+            .filter(|v| !v.is_empty())
+    };
+    let (Some(sid), Some(status)) = (field("MessageSid"), field("MessageStatus")) else {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            "MessageSid and MessageStatus are required",
+        )
+            .into_response();
+    };
+    let error_code = field("ErrorCode");
+    let to = field("To");
+
+    tracing::info!(sid = %sid, status = %status, error_code = ?error_code, "message status update");
+
+    let previous = {
         let db = state.db.lock().unwrap();
-        let _ = queries::increment_monthly_sent(&db);
+        queries::upsert_message_status(&db, sid, status, error_code, to)
+    };
+    let previous = match previous {
+        Ok(previous) => previous,
+        Err(e) => {
+            tracing::error!(error = %e, "failed to store message status");
+            return axum::http::StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // Surface customer delivery failures in the inbox (once per message)
+    let failed = matches!(status, "failed" | "undelivered");
+    if failed && previous.as_deref() != Some(status) {
+        if let Some(to) = to {
+            let is_owner =
+                to == owner_phone(&state).await || to == state.config.backup_owner_phone;
+            if !is_owner {
+                let reason = match error_code {
+                    Some(code) => format!(" (error {code})"),
+                    None => String::new(),
+                };
+                let note = format!("Message to customer {status}{reason}");
+                record_inbox_event(&state, to, "system", &note);
+            }
+        }
     }
+
+    axum::http::StatusCode::NO_CONTENT.into_response()
 }
 
 fn twiml_response() -> Response {
@@ -300,3 +660,37 @@ fn twiml_response() -> Response {
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_twilio_signature_known_vector() {
+        // Example from Twilio's webhook security docs
+        let params = [
+            ("CallSid", "CA1234567890ABCDE"),
+            ("Caller", "+12349013030"),
+            ("Digits", "1234"),
+            ("From", "+12349013030"),
+            ("To", "+18005551212"),
+        ];
+        let url = "https://mycompany.com/myapp.php?foo=1&bar=2";
+        assert!(validate_twilio_signature("12345", "0/KCTR6DLpKmkAf8muzZqo1nDgQ=", url, &params));
+        assert!(!validate_twilio_signature("54321", "0/KCTR6DLpKmkAf8muzZqo1nDgQ=", url, &params));
+    }
+
+    #[test]
+    fn test_signed_url_prefers_public_url() {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", "internal:3000".parse().unwrap());
+        assert_eq!(
+            signed_url("https://book.example.com/", &headers, "/webhook/sms"),
+            "https://book.example.com/webhook/sms"
+        );
+        assert_eq!(
+            signed_url("", &headers, "/webhook/sms"),
+            "https://internal:3000/webhook/sms"
+        );
+    }
+}
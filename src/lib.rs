@@ -1,7 +1,11 @@
+pub mod access_log;
+pub mod auth_limit;
 pub mod config;
 pub mod db;
 pub mod errors;
 pub mod handlers;
+pub mod logging;
 pub mod models;
 pub mod services;
+pub mod shutdown;
 pub mod state;
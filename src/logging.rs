@@ -0,0 +1,32 @@
+/// Shorten a phone number for logs to its country/area prefix and last 4
+/// digits, e.g. `+15551112222` → `+1555***2222`. Short numbers keep only the
+/// last 4 digits.
+pub fn mask_phone(phone: &str) -> String {
+    let chars: Vec<char> = phone.trim().chars().collect();
+    let n = chars.len();
+    if n <= 4 {
+        return "***".to_string();
+    }
+    let last4: String = chars[n - 4..].iter().collect();
+    if n < 10 {
+        return format!("***{last4}");
+    }
+    let prefix: String = chars[..5].iter().collect();
+    format!("{prefix}***{last4}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_phone() {
+        assert_eq!(mask_phone("+15551112222"), "+1555***2222");
+        assert_eq!(mask_phone("+447911123456"), "+4479***3456");
+        // Short codes and local numbers don't keep a prefix
+        assert_eq!(mask_phone("55512"), "***5512");
+        assert_eq!(mask_phone("5551112"), "***1112");
+        assert_eq!(mask_phone("911"), "***");
+        assert_eq!(mask_phone(""), "***");
+    }
+}
@@ -1,48 +1,81 @@
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use axum::routing::{get, post};
+use axum::middleware;
+use axum::routing::{delete, get, post};
 use axum::Router;
-use tokio::sync::broadcast;
+use dashmap::DashMap;
+use tokio::sync::{broadcast, Notify, Semaphore};
 use tracing_subscriber::EnvFilter;
 
+use phonebook::access_log;
+use phonebook::auth_limit::{self, AuthLimiter};
 use phonebook::config::AppConfig;
 use phonebook::db;
 use phonebook::handlers;
+use phonebook::shutdown;
+use phonebook::services::ai::breaker::CircuitBreaker;
 use phonebook::services::ai::groq::GroqProvider;
 use phonebook::services::ai::ollama::OllamaProvider;
-use phonebook::services::ai::LlmProvider;
+use phonebook::services::ai::{LlmLog, LlmProvider};
 use phonebook::services::messaging::twilio::TwilioSmsProvider;
-use phonebook::state::AppState;
+use phonebook::services::{cleanup, quiet_hours};
+use phonebook::state::{AppState, DevNotificationQueue};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let _ = dotenvy::dotenv();
 
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
-        .init();
-
     let config = AppConfig::from_env();
 
-    let conn = db::init_db(&config.database_url)?;
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into());
+    if config.log_format == "json" {
+        tracing_subscriber::fmt().json().with_env_filter(filter).init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(filter).init();
+    }
+
+    let conn = db::init_db_with_busy_timeout(
+        &config.database_url,
+        Duration::from_millis(config.db_busy_timeout_ms),
+    )?;
 
     let llm: Box<dyn LlmProvider> = match config.llm_provider.as_str() {
         "groq" => {
             anyhow::ensure!(!config.groq_api_key.is_empty(), "GROQ_API_KEY must be set when LLM_PROVIDER=groq");
             tracing::info!("using Groq LLM provider (model: {})", config.groq_model);
-            Box::new(GroqProvider::new(config.groq_api_key.clone(), config.groq_model.clone()))
+            Box::new(GroqProvider::new(
+                config.groq_api_key.clone(),
+                config.groq_model.clone(),
+                config.llm_temperature,
+                Duration::from_secs(config.http_timeout_secs),
+            )?)
         }
         _ => {
-            tracing::info!("using Ollama LLM provider (url: {})", config.ollama_url);
-            Box::new(OllamaProvider::new(config.ollama_url.clone(), "llama3.2".to_string()))
+            tracing::info!(
+                "using Ollama LLM provider (url: {}, model: {})",
+                config.ollama_url,
+                config.ollama_model
+            );
+            Box::new(OllamaProvider::new(
+                config.ollama_url.clone(),
+                config.ollama_model.clone(),
+                config.llm_temperature,
+                Duration::from_secs(config.http_timeout_secs),
+            )?)
         }
     };
     let messaging = TwilioSmsProvider::new(
         config.twilio_account_sid.clone(),
         config.twilio_auth_token.clone(),
         config.twilio_phone_number.clone(),
-    );
+        (!config.public_url.is_empty())
+            .then(|| format!("{}/webhook/status", config.public_url.trim_end_matches('/'))),
+        Duration::from_secs(config.http_timeout_secs),
+    )?;
 
     let (inbox_tx, _) = broadcast::channel(256);
 
@@ -50,28 +83,100 @@ async fn main() -> anyhow::Result<()> {
         db: Arc::new(Mutex::new(conn)),
         config: config.clone(),
         llm,
+        llm_permits: Semaphore::new(config.llm_max_concurrency),
+        llm_breaker: CircuitBreaker::new(
+            config.llm_breaker_threshold,
+            Duration::from_secs(config.llm_breaker_cooldown_secs),
+        ),
+        llm_log: LlmLog::default(),
         messaging: Box::new(messaging),
+        conversation_locks: DashMap::new(),
+        debounce_buffers: DashMap::new(),
         paused: AtomicBool::new(false),
-        dev_notifications: Mutex::new(Vec::new()),
+        dev_notifications: DevNotificationQueue::new(config.dev_notification_buffer),
         inbox_tx,
+        auth_limiter: AuthLimiter::new(
+            config.auth_max_failures,
+            Duration::from_secs(config.auth_lockout_secs),
+            config.trusted_proxies.clone(),
+        ),
     });
 
+    quiet_hours::spawn_flusher(Arc::clone(&state));
+    cleanup::spawn_sweeper(Arc::clone(&state));
+    cleanup::spawn_cleanup(Arc::clone(&state));
+
+    let in_flight = shutdown::InFlight::default();
     let app = Router::new()
         .route("/health", get(handlers::health::health))
+        .route("/metrics", get(handlers::metrics::metrics))
         .route("/webhook/sms", post(handlers::webhook::sms_webhook))
+        .route("/webhook/sms/json", post(handlers::webhook::sms_webhook_json))
+        .route("/webhook/status", post(handlers::webhook::status_webhook))
+        .route("/reschedule/:token", get(handlers::reschedule::reschedule_page))
+        .route("/reschedule/:token", post(handlers::reschedule::submit_reschedule))
+        .route("/booking/lookup", get(handlers::booking::lookup))
+        .route(
+            "/booking/:id/cancel",
+            get(handlers::booking::cancel_page).post(handlers::booking::submit_cancel),
+        )
         .route("/app", get(handlers::admin::app_page))
         .route("/admin", get(handlers::admin::redirect_to_app))
         .route("/api/admin/status", get(handlers::admin::get_status))
         .route("/api/admin/activity", get(handlers::admin::get_activity))
         .route("/api/admin/bookings", get(handlers::admin::get_bookings))
+        .route(
+            "/api/admin/bookings/import",
+            post(handlers::admin::import_bookings_csv),
+        )
         .route(
             "/api/admin/bookings/:id/cancel",
             post(handlers::admin::cancel_booking),
         )
+        .route(
+            "/api/admin/bookings/:id/no-show",
+            post(handlers::admin::mark_no_show),
+        )
+        .route(
+            "/api/admin/bookings/:id/history",
+            get(handlers::admin::get_booking_history),
+        )
         .route("/api/admin/contacts", get(handlers::admin::get_contacts))
+        .route(
+            "/api/admin/contacts/merge",
+            post(handlers::admin::merge_contacts),
+        )
+        .route(
+            "/api/admin/contacts/:phone/notes",
+            get(handlers::admin::get_contact_notes).put(handlers::admin::put_contact_notes),
+        )
+        .route(
+            "/api/admin/contacts/:phone/export",
+            get(handlers::admin::export_contact),
+        )
+        .route(
+            "/api/admin/contacts/:phone",
+            delete(handlers::admin::purge_contact),
+        )
+        .route(
+            "/api/admin/conversation/:phone",
+            get(handlers::admin::get_conversation),
+        )
+        .route(
+            "/api/admin/conversation/:phone/reset",
+            post(handlers::admin::reset_conversation),
+        )
+        .route("/api/admin/backup", get(handlers::admin::backup_database))
+        .route("/api/admin/broadcast", post(handlers::admin::broadcast))
+        .route("/api/admin/pricing", get(handlers::admin::get_pricing))
         .route("/api/admin/blocked", get(handlers::admin::get_blocked))
         .route("/api/admin/block", post(handlers::admin::block_number))
         .route("/api/admin/unblock", post(handlers::admin::unblock_number))
+        .route("/api/admin/allow", post(handlers::admin::allow_number))
+        .route(
+            "/api/admin/allow/:phone",
+            delete(handlers::admin::unallow_number),
+        )
         .route("/api/admin/pause", post(handlers::admin::pause_agent))
         .route("/api/admin/resume", post(handlers::admin::resume_agent))
         .route("/api/admin/settings", get(handlers::admin::get_settings))
@@ -79,6 +184,22 @@ async fn main() -> anyhow::Result<()> {
             "/api/admin/settings",
             post(handlers::admin::update_settings),
         )
+        .route(
+            "/api/admin/availability/override",
+            get(handlers::admin::list_overrides),
+        )
+        .route(
+            "/api/admin/availability/override",
+            post(handlers::admin::upsert_override),
+        )
+        .route(
+            "/api/admin/availability/override/:date",
+            delete(handlers::admin::delete_override),
+        )
+        .route(
+            "/api/admin/availability/check",
+            get(handlers::admin::check_availability),
+        )
         .route("/calendar/feed.ics", get(handlers::calendar::calendar_feed))
         .route(
             "/calendar/:booking_id",
@@ -87,6 +208,8 @@ async fn main() -> anyhow::Result<()> {
         .route("/dev", get(handlers::dev::dev_page))
         .route("/api/dev/config", get(handlers::dev::dev_config))
         .route("/api/dev/message", post(handlers::dev::send_message))
+        .route("/api/dev/llm-log", get(handlers::dev::llm_log))
+        .route("/api/dev/notifications", get(handlers::dev::notifications))
         .route("/inbox", get(handlers::admin::redirect_to_app))
         .route("/api/inbox/threads", get(handlers::inbox::get_threads))
         .route(
@@ -97,15 +220,63 @@ async fn main() -> anyhow::Result<()> {
             "/api/inbox/thread/:phone/read",
             post(handlers::inbox::mark_read),
         )
+        .route(
+            "/api/inbox/thread/:phone/ai",
+            post(handlers::inbox::set_thread_ai),
+        )
+        .route(
+            "/api/inbox/thread/:phone/resume",
+            post(handlers::inbox::resume_thread),
+        )
         .route("/api/inbox/reply", post(handlers::inbox::send_reply))
         .route("/api/inbox/events", get(handlers::inbox::events_stream))
-        .with_state(state);
+        .with_state(Arc::clone(&state))
+        .layer(middleware::from_fn_with_state(
+            state,
+            auth_limit::limit_failed_auth,
+        ))
+        .layer(middleware::from_fn_with_state(
+            in_flight.clone(),
+            shutdown::track_in_flight,
+        ))
+        .layer(middleware::from_fn(access_log::log_requests));
 
     let addr = format!("0.0.0.0:{}", config.port);
     tracing::info!("starting server on {addr}");
 
     let listener = tokio::net::TcpListener::bind(&addr).await?;
-    axum::serve(listener, app).await?;
 
+    // On SIGTERM/SIGINT stop accepting connections and let in-flight requests
+    // (conversation saves, outbound SMS) finish, up to the configured timeout.
+    let draining = Arc::new(Notify::new());
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let draining = Arc::clone(&draining);
+        let in_flight = in_flight.clone();
+        async move {
+            shutdown::signal().await;
+            tracing::info!(
+                in_flight = in_flight.count(),
+                "shutdown signal received, draining in-flight requests"
+            );
+            draining.notify_one();
+        }
+    });
+
+    let timeout = Duration::from_secs(config.shutdown_timeout_secs);
+    tokio::select! {
+        result = server.into_future() => result?,
+        _ = async {
+            draining.notified().await;
+            tokio::time::sleep(timeout).await;
+        } => {
+            tracing::warn!(
+                in_flight = in_flight.count(),
+                "graceful shutdown timed out, exiting"
+            );
+        }
+    }
+
+    tracing::info!("server stopped");
     Ok(())
 }
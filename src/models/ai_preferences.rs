@@ -14,6 +14,27 @@ pub struct AiPreferences {
     pub boundaries: Boundaries,
     #[serde(default)]
     pub custom_instructions: String,
+    #[serde(default)]
+    pub rejection_messages: RejectionMessages,
+    /// Replaces the built-in intent-extraction guidance when non-empty.
+    /// The JSON response format is always kept.
+    #[serde(default)]
+    pub system_prompt_override: String,
+    /// Sent once per conversation while the agent is paused. Empty = stay silent.
+    #[serde(default)]
+    pub paused_autoreply: String,
+    /// Sent once, on the message that gets a number auto-blocked for flooding,
+    /// e.g. how else to reach the business. Empty = block silently.
+    #[serde(default)]
+    pub blocked_autoreply: String,
+    /// Prepended to the first reply of a brand-new conversation. Empty = none.
+    #[serde(default)]
+    pub welcome_message: String,
+    /// Booking details added to every confirmation, filled from the stored
+    /// booking: `{name}`, `{time}`, `{duration}`, `{service}` and `{business}`.
+    /// Empty = the localized default.
+    #[serde(default)]
+    pub confirmation_template: String,
 }
 
 fn default_tone() -> String {
@@ -52,6 +73,9 @@ pub struct Capabilities {
     pub can_answer_questions: bool,
     #[serde(default)]
     pub can_send_reminders: bool,
+    /// Put customers on a waitlist when their requested time is taken.
+    #[serde(default)]
+    pub can_offer_waitlist: bool,
 }
 
 impl Default for Capabilities {
@@ -62,6 +86,7 @@ impl Default for Capabilities {
             can_reschedule: true,
             can_answer_questions: true,
             can_send_reminders: false,
+            can_offer_waitlist: false,
         }
     }
 }
@@ -91,6 +116,8 @@ pub struct Boundaries {
     pub share_pricing: bool,
     #[serde(default)]
     pub pricing_info: String,
+    #[serde(default)]
+    pub pricing: Vec<ServicePrice>,
 }
 
 impl Default for Boundaries {
@@ -99,10 +126,30 @@ impl Default for Boundaries {
             booking_only: false,
             share_pricing: true,
             pricing_info: String::new(),
+            pricing: Vec::new(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServicePrice {
+    pub service: String,
+    pub price: String,
+}
+
+/// Owner-written replies for times the scheduler rejects. Empty fields keep the
+/// built-in wording. Placeholders: `{hours}` for outside-hours, `{until}` and
+/// `{reason}` for closures.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RejectionMessages {
+    #[serde(default)]
+    pub outside_hours: String,
+    #[serde(default)]
+    pub closed: String,
+    #[serde(default)]
+    pub conflict: String,
+}
+
 fn default_true() -> bool {
     true
 }
@@ -116,6 +163,12 @@ impl Default for AiPreferences {
             returning_customers: ReturningCustomers::default(),
             boundaries: Boundaries::default(),
             custom_instructions: String::new(),
+            rejection_messages: RejectionMessages::default(),
+            system_prompt_override: String::new(),
+            paused_autoreply: String::new(),
+            blocked_autoreply: String::new(),
+            welcome_message: String::new(),
+            confirmation_template: String::new(),
         }
     }
 }
@@ -125,6 +178,20 @@ impl AiPreferences {
         Ok(serde_json::from_str(json)?)
     }
 
+    /// Structured prices whose service name appears in the customer's message.
+    /// Empty when pricing may not be shared.
+    pub fn matched_prices(&self, message: &str) -> Vec<&ServicePrice> {
+        if !self.boundaries.share_pricing {
+            return Vec::new();
+        }
+        let message = message.to_lowercase();
+        self.boundaries
+            .pricing
+            .iter()
+            .filter(|p| !p.service.is_empty() && message.contains(&p.service.to_lowercase()))
+            .collect()
+    }
+
     pub fn to_prompt(&self) -> String {
         let mut lines = Vec::new();
 
@@ -206,6 +273,18 @@ impl AiPreferences {
                 self.boundaries.pricing_info
             ));
         }
+        if self.boundaries.share_pricing && !self.boundaries.pricing.is_empty() {
+            let prices = self
+                .boundaries
+                .pricing
+                .iter()
+                .map(|p| format!("{}: {}", p.service, p.price))
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!(
+                "Service prices (quote these exactly, never estimate): {prices}"
+            ));
+        }
 
         // Custom instructions
         if !self.custom_instructions.is_empty() {
@@ -301,4 +380,26 @@ mod tests {
         let prompt = prefs.to_prompt();
         assert!(!prompt.contains("pricing information"));
     }
+
+    #[test]
+    fn test_structured_pricing_in_prompt() {
+        let json = r#"{"boundaries":{"pricing":[{"service":"Haircut","price":"$35"},{"service":"Color","price":"$80"}]}}"#;
+        let prefs = AiPreferences::from_json(json).unwrap();
+        let prompt = prefs.to_prompt();
+        assert!(prompt.contains("Haircut: $35, Color: $80"));
+    }
+
+    #[test]
+    fn test_matched_prices() {
+        let json = r#"{"boundaries":{"pricing":[{"service":"Haircut","price":"$35"},{"service":"Color","price":"$80"}]}}"#;
+        let prefs = AiPreferences::from_json(json).unwrap();
+        let matched = prefs.matched_prices("How much for a color?");
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].price, "$80");
+
+        let hidden = r#"{"boundaries":{"share_pricing":false,"pricing":[{"service":"Color","price":"$80"}]}}"#;
+        let prefs = AiPreferences::from_json(hidden).unwrap();
+        assert!(prefs.matched_prices("How much for a color?").is_empty());
+        assert!(!prefs.to_prompt().contains("$80"));
+    }
 }
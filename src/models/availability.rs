@@ -1,20 +1,26 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TimeSlot {
     pub day: String,
     pub start: String,
     pub end: String,
+    /// This weekday's `block_size`, in place of the top-level one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_size: Option<u32>,
+    /// This weekday's `min_notice`, in place of the top-level one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_notice: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct BreakSlot {
     pub start: String,
     pub end: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DayOverride {
     pub available: bool,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -23,7 +29,15 @@ pub struct DayOverride {
     pub end: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Closure {
+    pub start: String,
+    pub end: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Availability {
     pub slots: Vec<TimeSlot>,
     #[serde(default)]
@@ -36,21 +50,102 @@ pub struct Availability {
     pub time_from: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub time_to: Option<String>,
+    /// Minutes between bookable start times, counted from the start of the
+    /// day's slot. Unset = any start time, with offers every 30 minutes.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub block_size: Option<u32>,
+    /// Minutes of notice a booking needs; unset = none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_notice: Option<u32>,
     #[serde(default)]
     pub breaks: Vec<BreakSlot>,
+    #[serde(default)]
+    pub closures: Vec<Closure>,
+    /// Appointment length in minutes when the customer doesn't give one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_duration: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_duration: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<u32>,
+}
+
+/// Allowed appointment lengths, in minutes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DurationBounds {
+    pub default: i32,
+    pub min: i32,
+    pub max: i32,
+}
+
+impl Default for DurationBounds {
+    fn default() -> Self {
+        Self {
+            default: 60,
+            min: 15,
+            max: 480,
+        }
+    }
+}
+
+impl DurationBounds {
+    /// `minutes` pulled into `min..=max`; `None` becomes the default.
+    pub fn clamp(&self, minutes: Option<i32>) -> i32 {
+        minutes.map_or(self.default, |m| m.clamp(self.min, self.max))
+    }
+}
+
+/// Why a time is or isn't bookable, for support/debugging.
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailabilityExplanation {
+    pub available: bool,
+    /// `closed`, `day_off`, `outside_hours`, `break` or `off_grid` when
+    /// unavailable (the admin check endpoint adds `conflict`).
+    pub reason: Option<&'static str>,
+    pub closure: Option<Closure>,
+    #[serde(rename = "override")]
+    pub day_override: Option<DayOverride>,
+    pub matched_slot: Option<TimeSlot>,
+    pub break_overlap: Option<BreakSlot>,
 }
 
 const DAY_ORDER: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
 
+/// Spacing of offered start times when no `block_size` is set.
+pub const DEFAULT_BLOCK_SIZE: u32 = 30;
+
 impl Availability {
     pub fn from_json(s: &str) -> anyhow::Result<Self> {
         let availability: Availability = serde_json::from_str(s)?;
+        let bounds = availability.duration_bounds();
+        if bounds.min < 1 || bounds.min > bounds.max {
+            return Err(anyhow::anyhow!(
+                "invalid duration bounds: {}-{} minutes",
+                bounds.min,
+                bounds.max
+            ));
+        }
+        if !(bounds.min..=bounds.max).contains(&bounds.default) {
+            return Err(anyhow::anyhow!(
+                "default duration {} is outside {}-{} minutes",
+                bounds.default,
+                bounds.min,
+                bounds.max
+            ));
+        }
         for slot in &availability.slots {
             parse_weekday(&slot.day)?;
             parse_time(&slot.start)?;
             parse_time(&slot.end)?;
+            if slot.block_size == Some(0) {
+                return Err(anyhow::anyhow!(
+                    "block_size for {} must be positive",
+                    slot.day
+                ));
+            }
+        }
+        if availability.block_size == Some(0) {
+            return Err(anyhow::anyhow!("block_size must be positive"));
         }
         for (date_key, ovr) in &availability.overrides {
             if chrono::NaiveDate::parse_from_str(date_key, "%Y-%m-%d").is_err() {
@@ -79,9 +174,34 @@ impl Availability {
             parse_time(&brk.start)?;
             parse_time(&brk.end)?;
         }
+        for closure in &availability.closures {
+            let start = parse_date(&closure.start)?;
+            let end = parse_date(&closure.end)?;
+            if start > end {
+                return Err(anyhow::anyhow!(
+                    "closure start {} is after end {}",
+                    closure.start,
+                    closure.end
+                ));
+            }
+        }
         Ok(availability)
     }
 
+    /// The configured appointment lengths. Unset values fall back to 60
+    /// minutes within 15-480, with the default kept inside custom bounds.
+    pub fn duration_bounds(&self) -> DurationBounds {
+        let fallback = DurationBounds::default();
+        let minutes = |v: Option<u32>, or: i32| v.and_then(|m| i32::try_from(m).ok()).unwrap_or(or);
+        let min = minutes(self.min_duration, fallback.min);
+        let max = minutes(self.max_duration, fallback.max);
+        DurationBounds {
+            default: minutes(self.default_duration, fallback.default.max(min).min(max)),
+            min,
+            max,
+        }
+    }
+
     /// Returns effective slots — generated from day/time range if new fields are present,
     /// otherwise falls back to legacy `slots`.
     pub fn effective_slots(&self) -> Vec<TimeSlot> {
@@ -112,6 +232,8 @@ impl Availability {
                         day,
                         start: time_from.clone(),
                         end: time_to.clone(),
+                        block_size: None,
+                        min_notice: None,
                     })
                     .collect();
             }
@@ -119,6 +241,71 @@ impl Availability {
         self.slots.clone()
     }
 
+    /// Minutes between start times on `date`: its weekday's slot override,
+    /// else the top-level `block_size`, else none.
+    pub fn block_size_for(&self, date: &chrono::NaiveDate) -> Option<u32> {
+        self.day_rule(date, |slot| slot.block_size)
+            .or(self.block_size)
+    }
+
+    /// The grid starts either side of `dt` when it doesn't fall on the
+    /// day's `block_size` grid, counted from the start of its slot (or
+    /// midnight outside any slot).
+    pub fn off_grid(
+        &self,
+        dt: &chrono::NaiveDateTime,
+    ) -> Option<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+        let block = i64::from(self.block_size_for(&dt.date())?) * 60;
+        let start = self.window_start(dt).unwrap_or_default();
+        let offset = (dt.time() - start).num_seconds().rem_euclid(block);
+        if offset == 0 {
+            return None;
+        }
+        let earlier = *dt - chrono::Duration::seconds(offset);
+        Some((earlier, earlier + chrono::Duration::seconds(block)))
+    }
+
+    /// Start of the override window or slot that `dt` falls in.
+    fn window_start(&self, dt: &chrono::NaiveDateTime) -> Option<chrono::NaiveTime> {
+        let time = dt.format("%H:%M").to_string();
+        let start = match self.overrides.get(&dt.format("%Y-%m-%d").to_string()) {
+            Some(DayOverride {
+                start: Some(start), ..
+            }) => start.clone(),
+            _ => {
+                let weekday = dt.format("%a").to_string().to_lowercase();
+                self.effective_slots()
+                    .into_iter()
+                    .find(|slot| {
+                        slot.day.to_lowercase() == weekday && time >= slot.start && time < slot.end
+                    })?
+                    .start
+            }
+        };
+        chrono::NaiveTime::parse_from_str(&start, "%H:%M").ok()
+    }
+
+    /// Minutes of notice a booking on `date` needs: its weekday's slot
+    /// override, else the top-level `min_notice`, else none.
+    pub fn min_notice_for(&self, date: &chrono::NaiveDate) -> u32 {
+        self.day_rule(date, |slot| slot.min_notice)
+            .or(self.min_notice)
+            .unwrap_or(0)
+    }
+
+    /// The first override `rule` finds among the slots for `date`'s weekday.
+    fn day_rule(
+        &self,
+        date: &chrono::NaiveDate,
+        rule: impl Fn(&TimeSlot) -> Option<u32>,
+    ) -> Option<u32> {
+        let weekday = date.format("%a").to_string().to_lowercase();
+        self.effective_slots()
+            .iter()
+            .filter(|slot| slot.day.to_lowercase() == weekday)
+            .find_map(rule)
+    }
+
     /// Check if a given HH:MM time falls within any break.
     pub fn is_during_break(&self, time: &str) -> bool {
         self.breaks
@@ -133,7 +320,19 @@ impl Availability {
             .any(|b| start < b.end.as_str() && end > b.start.as_str())
     }
 
+    /// Returns the closure covering the given date, if any. Ranges are inclusive.
+    pub fn closure_for(&self, date: &chrono::NaiveDate) -> Option<&Closure> {
+        let key = date.format("%Y-%m-%d").to_string();
+        self.closures
+            .iter()
+            .find(|c| key.as_str() >= c.start.as_str() && key.as_str() <= c.end.as_str())
+    }
+
     pub fn is_available(&self, dt: &chrono::NaiveDateTime) -> bool {
+        if self.closure_for(&dt.date()).is_some() {
+            return false;
+        }
+
         let date_key = dt.format("%Y-%m-%d").to_string();
 
         if let Some(ovr) = self.overrides.get(&date_key) {
@@ -158,6 +357,10 @@ impl Availability {
     }
 
     pub fn end_time_within_slot(&self, dt: &chrono::NaiveDateTime, duration_minutes: i32) -> bool {
+        if self.closure_for(&dt.date()).is_some() {
+            return false;
+        }
+
         let end_dt = *dt + chrono::Duration::minutes(duration_minutes as i64);
         let date_key = dt.format("%Y-%m-%d").to_string();
 
@@ -188,6 +391,91 @@ impl Availability {
         in_slot && !self.overlaps_break(&start_time, &end_time)
     }
 
+    /// Explain whether `[dt, dt + duration)` fits the schedule. Mirrors the checks in
+    /// `validate_booking_time`: closures always apply, hours only when slots exist.
+    pub fn explain(
+        &self,
+        dt: &chrono::NaiveDateTime,
+        duration_minutes: i32,
+    ) -> AvailabilityExplanation {
+        let mut explanation = AvailabilityExplanation {
+            available: false,
+            reason: None,
+            closure: self.closure_for(&dt.date()).cloned(),
+            day_override: self.overrides.get(&dt.format("%Y-%m-%d").to_string()).cloned(),
+            matched_slot: None,
+            break_overlap: None,
+        };
+        if explanation.closure.is_some() {
+            explanation.reason = Some("closed");
+            return explanation;
+        }
+
+        let slots = self.effective_slots();
+        if slots.is_empty() {
+            if self.off_grid(dt).is_some() {
+                explanation.reason = Some("off_grid");
+                return explanation;
+            }
+            explanation.available = true;
+            return explanation;
+        }
+
+        let weekday = dt.format("%a").to_string().to_lowercase();
+        let start_time = dt.format("%H:%M").to_string();
+        let end_time = (*dt + chrono::Duration::minutes(duration_minutes as i64))
+            .format("%H:%M")
+            .to_string();
+
+        let window = match &explanation.day_override {
+            Some(ovr) if !ovr.available => {
+                explanation.reason = Some("day_off");
+                return explanation;
+            }
+            Some(DayOverride {
+                start: Some(start),
+                end: Some(end),
+                ..
+            }) => Some(TimeSlot {
+                day: weekday.clone(),
+                start: start.clone(),
+                end: end.clone(),
+                block_size: None,
+                min_notice: None,
+            }),
+            _ => slots.into_iter().find(|slot| {
+                slot.day.to_lowercase() == weekday
+                    && start_time >= slot.start
+                    && end_time <= slot.end
+            }),
+        };
+        let window = match window {
+            Some(w) if start_time >= w.start && end_time <= w.end => w,
+            _ => {
+                explanation.reason = Some("outside_hours");
+                return explanation;
+            }
+        };
+        explanation.matched_slot = Some(window);
+
+        explanation.break_overlap = self
+            .breaks
+            .iter()
+            .find(|b| start_time < b.end && end_time > b.start)
+            .cloned();
+        if explanation.break_overlap.is_some() {
+            explanation.reason = Some("break");
+            return explanation;
+        }
+        if self.off_grid(dt).is_some() {
+            explanation.reason = Some("off_grid");
+            return explanation;
+        }
+
+        explanation.available = true;
+        explanation
+    }
+
     pub fn to_human_readable(&self) -> String {
         let slots = self.effective_slots();
         if slots.is_empty() {
@@ -239,6 +527,11 @@ fn parse_weekday(s: &str) -> anyhow::Result<()> {
     }
 }
 
+fn parse_date(s: &str) -> anyhow::Result<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("invalid closure date: {s}"))
+}
+
 fn parse_time(s: &str) -> anyhow::Result<()> {
     let parts: Vec<&str> = s.split(':').collect();
     if parts.len() != 2 {
@@ -290,6 +583,13 @@ mod tests {
         assert!(Availability::from_json(json).is_err());
     }
 
+    #[test]
+    fn test_parse_rejects_zero_block_size() {
+        let json = r#"{"slots":[{"day":"sat","start":"09:00","end":"13:00","block_size":0}]}"#;
+        assert!(Availability::from_json(json).is_err());
+        assert!(Availability::from_json(r#"{"slots":[],"block_size":0}"#).is_err());
+    }
+
     #[test]
     fn test_is_available_within_hours() {
         let json = r#"{"slots":[{"day":"mon","start":"09:00","end":"17:00"}]}"#;
@@ -528,4 +828,103 @@ mod tests {
         let json = r#"{"slots":[],"breaks":[{"start":"12:00","end":"99:00"}]}"#;
         assert!(Availability::from_json(json).is_err());
     }
+
+    // ── Closures ──
+
+    #[test]
+    fn test_closure_blocks_date_inside_range() {
+        let json = r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","closures":[{"start":"2025-06-16","end":"2025-06-20","reason":"Vacation"}]}"#;
+        let avail = Availability::from_json(json).unwrap();
+        // 2025-06-18 is a Wednesday inside the closure
+        assert!(!avail.is_available(&dt("2025-06-18 10:00")));
+        assert!(!avail.end_time_within_slot(&dt("2025-06-18 10:00"), 60));
+        assert_eq!(
+            avail
+                .closure_for(&dt("2025-06-18 10:00").date())
+                .and_then(|c| c.reason.as_deref()),
+            Some("Vacation")
+        );
+        // 2025-06-23 is the Monday after — open again
+        assert!(avail.is_available(&dt("2025-06-23 10:00")));
+    }
+
+    #[test]
+    fn test_closure_boundaries_are_inclusive() {
+        let json = r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","closures":[{"start":"2025-06-16","end":"2025-06-20"}]}"#;
+        let avail = Availability::from_json(json).unwrap();
+        assert!(!avail.is_available(&dt("2025-06-16 09:00")));
+        assert!(!avail.is_available(&dt("2025-06-20 16:00")));
+        // 2025-06-13 is the Friday before — still open
+        assert!(avail.is_available(&dt("2025-06-13 10:00")));
+    }
+
+    #[test]
+    fn test_closure_start_after_end_rejected() {
+        let json = r#"{"slots":[],"closures":[{"start":"2025-07-07","end":"2025-07-01"}]}"#;
+        assert!(Availability::from_json(json).is_err());
+
+        let json = r#"{"slots":[],"closures":[{"start":"not-a-date","end":"2025-07-01"}]}"#;
+        assert!(Availability::from_json(json).is_err());
+    }
+
+    // ── Explanations ──
+
+    #[test]
+    fn test_explain_break_overlap() {
+        let json = r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","breaks":[{"start":"12:00","end":"13:00"}]}"#;
+        let avail = Availability::from_json(json).unwrap();
+        let explanation = avail.explain(&dt("2025-06-16 11:30"), 60);
+        assert!(!explanation.available);
+        assert_eq!(explanation.reason, Some("break"));
+        assert_eq!(explanation.matched_slot.unwrap().day, "mon");
+        assert_eq!(explanation.break_overlap.unwrap().start, "12:00");
+    }
+
+    #[test]
+    fn test_explain_outside_hours() {
+        let json = r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","breaks":[{"start":"12:00","end":"13:00"}]}"#;
+        let avail = Availability::from_json(json).unwrap();
+        let explanation = avail.explain(&dt("2025-06-16 18:00"), 60);
+        assert!(!explanation.available);
+        assert_eq!(explanation.reason, Some("outside_hours"));
+        assert!(explanation.matched_slot.is_none());
+        assert!(explanation.break_overlap.is_none());
+
+        let explanation = avail.explain(&dt("2025-06-16 10:00"), 60);
+        assert!(explanation.available);
+        assert_eq!(explanation.reason, None);
+    }
+
+    #[test]
+    fn test_explain_override_and_closure() {
+        let json = r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","overrides":{"2025-06-16":{"available":false}},"closures":[{"start":"2025-06-18","end":"2025-06-18"}]}"#;
+        let avail = Availability::from_json(json).unwrap();
+        assert_eq!(avail.explain(&dt("2025-06-16 10:00"), 60).reason, Some("day_off"));
+        assert_eq!(avail.explain(&dt("2025-06-18 10:00"), 60).reason, Some("closed"));
+    }
+
+    #[test]
+    fn test_duration_bounds_clamp_defaults() {
+        let bounds = DurationBounds::default();
+        assert_eq!(bounds.clamp(None), 60);
+        assert_eq!(bounds.clamp(Some(5)), 15);
+        assert_eq!(bounds.clamp(Some(600)), 480);
+        assert_eq!(bounds.clamp(Some(90)), 90);
+    }
+
+    #[test]
+    fn test_duration_bounds_from_settings() {
+        let json = r#"{"slots":[],"default_duration":45,"min_duration":30,"max_duration":120}"#;
+        let bounds = Availability::from_json(json).unwrap().duration_bounds();
+        assert_eq!(bounds.clamp(None), 45);
+        assert_eq!(bounds.clamp(Some(5)), 30);
+        assert_eq!(bounds.clamp(Some(600)), 120);
+
+        // An unset default stays inside custom bounds
+        let json = r#"{"slots":[],"min_duration":90}"#;
+        assert_eq!(Availability::from_json(json).unwrap().duration_bounds().default, 90);
+
+        assert!(Availability::from_json(r#"{"slots":[],"min_duration":120,"max_duration":60}"#).is_err());
+        assert!(Availability::from_json(r#"{"slots":[],"default_duration":10,"min_duration":30}"#).is_err());
+    }
 }
@@ -10,6 +10,8 @@ pub struct Booking {
     pub duration_minutes: i32,
     pub status: BookingStatus,
     pub notes: Option<String>,
+    /// Name of the configured service booked, when one was matched.
+    pub service: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -20,6 +22,9 @@ pub enum BookingStatus {
     Pending,
     Confirmed,
     Cancelled,
+    /// The customer didn't turn up. Frees the slot like a cancellation.
+    #[serde(rename = "no_show")]
+    NoShow,
 }
 
 impl BookingStatus {
@@ -28,6 +33,7 @@ impl BookingStatus {
             BookingStatus::Pending => "pending",
             BookingStatus::Confirmed => "confirmed",
             BookingStatus::Cancelled => "cancelled",
+            BookingStatus::NoShow => "no_show",
         }
     }
 
@@ -35,7 +41,56 @@ impl BookingStatus {
         match s {
             "confirmed" => BookingStatus::Confirmed,
             "cancelled" => BookingStatus::Cancelled,
+            "no_show" => BookingStatus::NoShow,
             _ => BookingStatus::Pending,
         }
     }
+
+    /// Whether a booking in this status still takes up its time slot.
+    pub fn holds_slot(&self) -> bool {
+        matches!(self, BookingStatus::Pending | BookingStatus::Confirmed)
+    }
+}
+
+/// Who triggered a booking lifecycle change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookingActor {
+    Customer,
+    Owner,
+    Admin,
+}
+
+impl BookingActor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BookingActor::Customer => "customer",
+            BookingActor::Owner => "owner",
+            BookingActor::Admin => "admin",
+        }
+    }
+}
+
+/// One row of the append-only `booking_events` audit log.
+#[derive(Debug, Clone, Serialize)]
+pub struct BookingEvent {
+    pub id: i64,
+    pub booking_id: String,
+    pub event: String,
+    pub actor: String,
+    pub old_status: Option<String>,
+    pub new_status: String,
+    pub created_at: String,
+}
+
+/// A customer-facing link token bound to one booking, e.g. `/reschedule/:token`.
+#[derive(Debug, Clone)]
+pub struct BookingLink {
+    pub token: String,
+    pub booking_id: String,
+    pub action: String,
+    pub expires_at: NaiveDateTime,
+}
+
+impl BookingLink {
+    pub const RESCHEDULE: &'static str = "reschedule";
 }
@@ -39,12 +39,25 @@ pub struct PendingBooking {
     pub date_time: Option<String>,
     pub duration_minutes: Option<i32>,
     pub notes: Option<String>,
+    /// Open slots offered for a vague request ("Thursday afternoon"), as "YYYY-MM-DD HH:MM".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub proposed_slots: Vec<String>,
+    /// Existing booking being rescheduled or cancelled by this conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub booking_id: Option<String>,
+    /// Configured service the customer asked for; fixes `duration_minutes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversationData {
     pub messages: Vec<ConversationMessage>,
     pub pending_booking: Option<PendingBooking>,
+    #[serde(default)]
+    pub paused_autoreply_sent: bool,
+    #[serde(default)]
+    pub reprompt_sent: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +72,12 @@ pub struct Conversation {
     pub messages: Vec<ConversationMessage>,
     pub state: ConversationState,
     pub pending_booking: Option<PendingBooking>,
+    /// The "we'll get back to you" reply already went out during a pause.
+    pub paused_autoreply_sent: bool,
+    /// The "are you still there?" nudge already went out for this confirmation.
+    pub reprompt_sent: bool,
+    /// LLM summary of older messages that were folded out of `messages`.
+    pub summary: Option<String>,
     pub last_activity: NaiveDateTime,
     pub expires_at: NaiveDateTime,
 }
@@ -10,6 +10,21 @@ pub struct InboxEvent {
     pub created_at: String,
 }
 
+/// A thread's new unread count, pushed after each change so the UI badge
+/// updates without refetching `/api/inbox/threads`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ThreadSummary {
+    pub phone: String,
+    pub unread_count: i64,
+}
+
+/// What SSE subscribers receive on `AppState.inbox_tx`.
+#[derive(Clone, Debug)]
+pub enum InboxUpdate {
+    Event(InboxEvent),
+    Summary(ThreadSummary),
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct InboxThread {
     pub phone: String,
@@ -17,4 +32,7 @@ pub struct InboxThread {
     pub last_kind: String,
     pub unread_count: i64,
     pub last_activity: String,
+    pub notes: Option<String>,
+    pub tags: Option<String>,
+    pub ai_enabled: bool,
 }
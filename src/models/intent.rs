@@ -9,6 +9,10 @@ pub enum Intent {
     Confirm,
     Decline,
     GeneralQuestion,
+    /// Customer asked for a person instead of the assistant.
+    Handoff,
+    /// Also what an intent name we don't know parses as.
+    #[serde(other)]
     Unknown,
 }
 
@@ -18,6 +22,8 @@ pub struct ExtractedIntent {
     pub customer_name: Option<String>,
     pub requested_date: Option<String>,
     pub requested_time: Option<String>,
+    #[serde(default)]
+    pub time_window: Option<String>,
     pub duration_minutes: Option<i32>,
     pub notes: Option<String>,
     pub message_to_customer: String,
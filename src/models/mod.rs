@@ -4,12 +4,16 @@ pub mod booking;
 pub mod conversation;
 pub mod inbox;
 pub mod intent;
+pub mod quiet_hours;
+pub mod service;
 pub mod user;
 
-pub use ai_preferences::AiPreferences;
-pub use availability::Availability;
-pub use booking::{Booking, BookingStatus};
+pub use ai_preferences::{AiPreferences, RejectionMessages, ServicePrice};
+pub use availability::{Availability, AvailabilityExplanation, DayOverride, DurationBounds};
+pub use booking::{Booking, BookingActor, BookingEvent, BookingLink, BookingStatus};
 pub use conversation::{Conversation, ConversationData, ConversationMessage, ConversationState, PendingBooking};
-pub use inbox::{InboxEvent, InboxThread};
+pub use inbox::{InboxEvent, InboxThread, InboxUpdate, ThreadSummary};
 pub use intent::{ExtractedIntent, Intent};
+pub use quiet_hours::QuietHours;
+pub use service::Service;
 pub use user::User;
@@ -0,0 +1,63 @@
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// Daily window, in the business timezone, when replies to customers are held
+/// until morning. `start` after `end` wraps past midnight ("22:00"–"07:00").
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+fn parse(s: &str) -> anyhow::Result<NaiveTime> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| anyhow::anyhow!("invalid time {s:?}, expected HH:MM"))
+}
+
+impl QuietHours {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let quiet: Self = serde_json::from_str(json)?;
+        if parse(&quiet.start)? == parse(&quiet.end)? {
+            anyhow::bail!("quiet hours start and end must differ");
+        }
+        Ok(quiet)
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (Ok(start), Ok(end)) = (parse(&self.start), parse(&self.end)) else {
+            return false;
+        };
+        if start < end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_contains_wraps_past_midnight() {
+        let night = QuietHours::from_json(r#"{"start":"22:00","end":"07:00"}"#).unwrap();
+        assert!(night.contains(at("23:30")));
+        assert!(night.contains(at("03:00")));
+        assert!(!night.contains(at("07:00")));
+        assert!(!night.contains(at("12:00")));
+
+        let lunch = QuietHours::from_json(r#"{"start":"12:00","end":"13:00"}"#).unwrap();
+        assert!(lunch.contains(at("12:30")));
+        assert!(!lunch.contains(at("13:00")));
+    }
+
+    #[test]
+    fn test_from_json_rejects_bad_times() {
+        assert!(QuietHours::from_json(r#"{"start":"25:00","end":"07:00"}"#).is_err());
+        assert!(QuietHours::from_json(r#"{"start":"09:00","end":"09:00"}"#).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+/// A bookable service with a fixed length, e.g. "Color, 90 minutes".
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Service {
+    pub name: String,
+    pub duration_minutes: i32,
+    #[serde(default)]
+    pub price: String,
+    /// Overrides the business's reminder text for bookings of this service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder_template: Option<String>,
+}
+
+impl Service {
+    pub fn list_from_json(json: &str) -> anyhow::Result<Vec<Self>> {
+        let services: Vec<Self> = serde_json::from_str(json)?;
+        if let Some(bad) = services
+            .iter()
+            .find(|s| s.name.trim().is_empty() || s.duration_minutes <= 0)
+        {
+            anyhow::bail!(
+                "service {:?} needs a name and a positive duration_minutes",
+                bad.name
+            );
+        }
+        Ok(services)
+    }
+
+    /// The service named in `text`, case-insensitively. The longest name wins so
+    /// "Color correction" beats "Color".
+    pub fn find_in<'a>(services: &'a [Self], text: &str) -> Option<&'a Self> {
+        let text = text.to_lowercase();
+        services
+            .iter()
+            .filter(|s| text.contains(&s.name.to_lowercase()))
+            .max_by_key(|s| s.name.len())
+    }
+
+    /// One line for the LLM's business context, e.g. "Haircut (30 min, $35)".
+    pub fn describe(&self) -> String {
+        if self.price.is_empty() {
+            format!("{} ({} min)", self.name, self.duration_minutes)
+        } else {
+            format!("{} ({} min, {})", self.name, self.duration_minutes, self.price)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn services() -> Vec<Service> {
+        Service::list_from_json(
+            r#"[{"name":"Haircut","duration_minutes":30,"price":"$35"},
+                {"name":"Color","duration_minutes":90},
+                {"name":"Color correction","duration_minutes":150}]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_in_matches_case_insensitively() {
+        let services = services();
+        let found = Service::find_in(&services, "can I get a COLOR tomorrow?").unwrap();
+        assert_eq!(found.duration_minutes, 90);
+        assert!(Service::find_in(&services, "a manicure please").is_none());
+    }
+
+    #[test]
+    fn test_find_in_prefers_longest_name() {
+        let services = services();
+        let found = Service::find_in(&services, "need a color correction").unwrap();
+        assert_eq!(found.name, "Color correction");
+    }
+
+    #[test]
+    fn test_list_from_json_rejects_bad_duration() {
+        assert!(Service::list_from_json(r#"[{"name":"Haircut","duration_minutes":0}]"#).is_err());
+        assert!(Service::list_from_json(r#"[{"name":" ","duration_minutes":30}]"#).is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        let services = services();
+        assert_eq!(services[0].describe(), "Haircut (30 min, $35)");
+        assert_eq!(services[1].describe(), "Color (90 min)");
+    }
+}
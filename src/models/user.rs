@@ -12,4 +12,32 @@ pub struct User {
     pub availability: Option<String>,
     pub timezone: String,
     pub ai_preferences: Option<String>,
+    /// Language for the fixed replies the engine writes ("en", "es", "fr").
+    pub language: String,
+    /// JSON list of `Service`s the business offers.
+    pub services: Option<String>,
+    /// Address shown as the LOCATION of calendar events.
+    pub location: Option<String>,
+    /// Which events text the owner. The inbox records them either way.
+    pub notify_on_booking: bool,
+    pub notify_on_cancel: bool,
+    pub notify_on_block: bool,
+    pub notify_on_pause: bool,
+    /// JSON `QuietHours`; customer replies are held while it's in effect.
+    pub quiet_hours: Option<String>,
+    /// Refuse a second booking for the same customer on the same date.
+    pub one_booking_per_customer_per_day: bool,
+    /// Text customers this many hours before their appointment; 0 = no reminders.
+    pub reminder_hours_before: i64,
+    /// Reminder text with `{name}`, `{time}`, `{duration}`, `{business}` and
+    /// `{service}` placeholders; `None` uses the localized default.
+    pub reminder_template: Option<String>,
+    /// Comma- or newline-separated phrases that mark a customer text as spam.
+    pub spam_keywords: Option<String>,
+    /// Block a sender whose text matches `spam_keywords` instead of just
+    /// dropping the message.
+    pub block_spam: bool,
+    /// Show times to customers and the owner on a 24-hour clock ("14:00")
+    /// instead of "2:00 PM".
+    pub use_24h_time: bool,
 }
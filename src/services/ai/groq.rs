@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::Context;
 use async_trait::async_trait;
 use serde_json::json;
@@ -7,22 +9,31 @@ use super::{LlmProvider, Message};
 pub struct GroqProvider {
     api_key: String,
     model: String,
+    temperature: f32,
+    timeout: Duration,
     client: reqwest::Client,
 }
 
 impl GroqProvider {
-    pub fn new(api_key: String, model: String) -> Self {
-        Self {
+    pub fn new(
+        api_key: String,
+        model: String,
+        temperature: f32,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
             api_key,
             model,
-            client: reqwest::Client::new(),
-        }
+            temperature,
+            timeout,
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("failed to build HTTP client")?,
+        })
     }
-}
 
-#[async_trait]
-impl LlmProvider for GroqProvider {
-    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+    fn request_body(&self, system_prompt: &str, messages: &[Message]) -> serde_json::Value {
         let mut chat_messages = vec![json!({
             "role": "system",
             "content": system_prompt,
@@ -35,11 +46,18 @@ impl LlmProvider for GroqProvider {
             }));
         }
 
-        let body = json!({
+        json!({
             "model": self.model,
             "messages": chat_messages,
-            "temperature": 0.7,
-        });
+            "temperature": self.temperature,
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GroqProvider {
+    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+        let body = self.request_body(system_prompt, messages);
 
         let resp = self
             .client
@@ -48,7 +66,13 @@ impl LlmProvider for GroqProvider {
             .json(&body)
             .send()
             .await
-            .context("failed to call Groq API")?;
+            .map_err(|e| {
+                if e.is_timeout() {
+                    anyhow::anyhow!("Groq API timed out after {}s", self.timeout.as_secs())
+                } else {
+                    anyhow::Error::new(e).context("failed to call Groq API")
+                }
+            })?;
 
         let status = resp.status();
         let data: serde_json::Value = resp
@@ -66,3 +90,28 @@ impl LlmProvider for GroqProvider {
             .ok_or_else(|| anyhow::anyhow!("missing content in Groq response"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_body_uses_configured_model_and_temperature() {
+        let provider = GroqProvider::new(
+            "key".to_string(),
+            "llama-3.3-70b-versatile".to_string(),
+            0.2,
+            Duration::from_secs(15),
+        )
+        .unwrap();
+        let messages = [Message {
+            role: "user".to_string(),
+            content: "hi".to_string(),
+        }];
+        let body = provider.request_body("system", &messages);
+        assert_eq!(body["model"], "llama-3.3-70b-versatile");
+        assert_eq!(body["temperature"].as_f64(), Some(0.2f32 as f64));
+        assert_eq!(body["messages"][0]["content"], "system");
+        assert_eq!(body["messages"][1]["content"], "hi");
+    }
+}
@@ -1,19 +1,32 @@
+use chrono::Utc;
+
 use crate::models::{AiPreferences, ConversationMessage, ExtractedIntent, Intent};
-use crate::services::ai::{LlmProvider, Message};
+use crate::services::ai::{LlmLog, LlmProvider, Message};
+use crate::services::dates::{normalize_time, parse_full_date, resolve_date, YearlessDatePolicy};
+
+/// Longest appointment the LLM may propose; anything longer is a misreading.
+/// The owner's own bounds are applied later.
+const MAX_DURATION_MINUTES: i32 = 24 * 60;
 
-const SYSTEM_PROMPT: &str = r#"You are an intent extraction engine for an SMS booking assistant. Analyze the customer's latest message in context of the conversation history.
+/// Fixed part of the system prompt: the reply must keep this JSON shape for
+/// `parse_intent_response`, so owners can't override it.
+const RESPONSE_FORMAT: &str = r#"You are an intent extraction engine for an SMS booking assistant. Analyze the customer's latest message in context of the conversation history.
 
 Return ONLY valid JSON (no markdown, no explanation) with this exact structure:
 {
-  "intent": "book|reschedule|cancel|confirm|decline|general_question|unknown",
+  "intent": "book|reschedule|cancel|confirm|decline|general_question|handoff|unknown",
   "customer_name": "extracted name or null",
   "requested_date": "extracted date like 2025-01-15 or null",
   "requested_time": "extracted time like 14:00 or null",
+  "time_window": "morning|afternoon|evening|any|14:00-16:00 or null",
   "duration_minutes": 60,
   "notes": "any special requests or null",
   "message_to_customer": "Your friendly reply to the customer"
 }
+"#;
 
+/// Behavioral guidance, replaced by a non-empty `system_prompt_override`.
+const DEFAULT_GUIDANCE: &str = r#"
 Intent rules:
 - "book": Customer wants to schedule a new appointment
 - "reschedule": Customer wants to change an existing appointment
@@ -21,8 +34,13 @@ Intent rules:
 - "confirm": Customer says yes/ok/confirmed/sounds good to a proposed time
 - "decline": Customer says no/that doesn't work to a proposed time
 - "general_question": Customer asks about services, hours, pricing, etc.
+- "handoff": Customer asks to talk to a real person, the owner or staff instead of the assistant
 - "unknown": Can't determine intent
 
+If the customer gives a date without a year, return requested_date as MM-DD (e.g. 06-15) — do not guess the year.
+
+If the customer is flexible about the time ("Thursday afternoon", "anytime after 2"), leave requested_time null and set time_window instead; the system will offer open slots.
+
 When booking, only suggest times within the business hours shown in the context.
 If the customer requests a time outside business hours, politely suggest the nearest available time.
 
@@ -34,38 +52,86 @@ For the message_to_customer:
 - Keep messages concise (SMS-friendly, under 160 chars when possible)
 "#;
 
+/// The prior turns of a conversation: only the most recent `max` messages are
+/// sent, and `summary` stands in for turns already folded out of `messages`.
+#[derive(Clone, Copy)]
+pub struct History<'a> {
+    pub messages: &'a [ConversationMessage],
+    pub max: usize,
+    pub summary: Option<&'a str>,
+}
+
+/// With a `debug_log`, the full prompt and raw response are logged and kept there.
 pub async fn extract_intent(
     llm: &dyn LlmProvider,
-    history: &[ConversationMessage],
+    history: History<'_>,
     latest_message: &str,
     business_context: &str,
     ai_preferences: Option<&AiPreferences>,
+    debug_log: Option<&LlmLog>,
 ) -> anyhow::Result<ExtractedIntent> {
-    let mut messages: Vec<Message> = history
-        .iter()
-        .map(|m| Message {
-            role: m.role.clone(),
-            content: m.content.clone(),
-        })
-        .collect();
-
-    messages.push(Message {
-        role: "user".to_string(),
-        content: latest_message.to_string(),
-    });
+    let messages = build_messages(history, latest_message);
 
     let personality = ai_preferences
         .map(|p| p.to_prompt())
         .unwrap_or_default();
 
-    let system = format!("{SYSTEM_PROMPT}{personality}\n\nBusiness context:\n{business_context}");
+    let guidance = ai_preferences
+        .map(|p| p.system_prompt_override.trim())
+        .filter(|o| !o.is_empty())
+        .map(|o| format!("\n{o}\n"))
+        .unwrap_or_else(|| DEFAULT_GUIDANCE.to_string());
+
+    let system = format!(
+        "{RESPONSE_FORMAT}{guidance}{personality}\n\nBusiness context:\n{business_context}"
+    );
 
     let response = llm.chat(&system, &messages).await?;
+    if let Some(log) = debug_log {
+        tracing::debug!(system_prompt = %system, messages = ?messages, response = %response, "llm exchange");
+        log.record(&system, &messages, &response);
+    }
 
     parse_intent_response(&response)
 }
 
+fn build_messages(history: History<'_>, latest_message: &str) -> Vec<Message> {
+    let History { messages: turns, max, summary } = history;
+    let omitted = turns.len().saturating_sub(max);
+    let mut messages = Vec::with_capacity(turns.len() - omitted + 3);
+
+    if let Some(summary) = summary.filter(|s| !s.trim().is_empty()) {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: format!("Summary of this customer's earlier conversation: {}", summary.trim()),
+        });
+    }
+    if omitted > 0 {
+        messages.push(Message {
+            role: "system".to_string(),
+            content: format!(
+                "({omitted} earlier messages in this conversation were omitted; only the most recent are shown.)"
+            ),
+        });
+    }
+
+    messages.extend(turns[omitted..].iter().map(|m| Message {
+        role: m.role.clone(),
+        content: m.content.clone(),
+    }));
+
+    messages.push(Message {
+        role: "user".to_string(),
+        content: latest_message.to_string(),
+    });
+    messages
+}
+
 fn parse_intent_response(response: &str) -> anyhow::Result<ExtractedIntent> {
+    decode_intent_response(response).map(clean_fields)
+}
+
+fn decode_intent_response(response: &str) -> anyhow::Result<ExtractedIntent> {
     // Try direct parse first
     if let Ok(intent) = serde_json::from_str::<ExtractedIntent>(response) {
         return Ok(intent);
@@ -103,12 +169,47 @@ fn parse_intent_response(response: &str) -> anyhow::Result<ExtractedIntent> {
         customer_name: None,
         requested_date: None,
         requested_time: None,
+        time_window: None,
         duration_minutes: None,
         notes: None,
         message_to_customer: response.to_string(),
     })
 }
 
+/// Bring the date and time into the `YYYY-MM-DD` and `HH:MM` the engine
+/// stores ("June 15, 2025", "2:00 PM"), and null out a date or time that
+/// can't be read or a length that can't be an appointment, so a garbled field
+/// counts as "not given" instead of ending up in a booking. Year-less dates
+/// are left for the engine to pin to a year.
+fn clean_fields(mut intent: ExtractedIntent) -> ExtractedIntent {
+    let today = Utc::now().date_naive();
+    if let Some(date) = intent.requested_date.take() {
+        if let Some(full) = parse_full_date(&date) {
+            intent.requested_date = Some(full.format("%Y-%m-%d").to_string());
+        } else if resolve_date(&date, today, YearlessDatePolicy::RollForward).is_some() {
+            intent.requested_date = Some(date);
+        } else {
+            tracing::warn!(requested_date = %date, "LLM returned an unreadable date, ignoring it");
+        }
+    }
+    if let Some(time) = intent.requested_time.take() {
+        intent.requested_time = normalize_time(&time);
+        if intent.requested_time.is_none() {
+            tracing::warn!(requested_time = %time, "LLM returned an unreadable time, ignoring it");
+        }
+    }
+    if let Some(minutes) = intent.duration_minutes {
+        if !(1..=MAX_DURATION_MINUTES).contains(&minutes) {
+            tracing::warn!(
+                duration_minutes = minutes,
+                "LLM returned an impossible length, ignoring it"
+            );
+            intent.duration_minutes = None;
+        }
+    }
+    intent
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -128,6 +229,44 @@ mod tests {
         assert_eq!(result.intent, Intent::Confirm);
     }
 
+    #[test]
+    fn test_parse_drops_malformed_fields() {
+        let json = r#"{"intent":"book","customer_name":"John","requested_date":"the 45th","requested_time":"soonish","duration_minutes":-30,"notes":null,"message_to_customer":"Sure!"}"#;
+        let result = parse_intent_response(json).unwrap();
+        assert_eq!(result.intent, Intent::Book);
+        assert_eq!(result.customer_name, Some("John".to_string()));
+        assert_eq!(result.requested_date, None);
+        assert_eq!(result.requested_time, None);
+        assert_eq!(result.duration_minutes, None);
+
+        // Year-less dates are kept for the engine to pin to a year
+        let json = r#"{"intent":"book","customer_name":null,"requested_date":"06-15","requested_time":"14:00","duration_minutes":90,"notes":null,"message_to_customer":"Sure!"}"#;
+        let result = parse_intent_response(json).unwrap();
+        assert_eq!(result.requested_date, Some("06-15".to_string()));
+        assert_eq!(result.requested_time, Some("14:00".to_string()));
+        assert_eq!(result.duration_minutes, Some(90));
+    }
+
+    #[test]
+    fn test_parse_normalizes_loose_date_and_time() {
+        let json = r#"{"intent":"book","customer_name":"John","requested_date":"June 15, 2025","requested_time":"2:00 PM","duration_minutes":null,"notes":null,"message_to_customer":"Sure!"}"#;
+        let result = parse_intent_response(json).unwrap();
+        assert_eq!(result.requested_date, Some("2025-06-15".to_string()));
+        assert_eq!(result.requested_time, Some("14:00".to_string()));
+
+        let json = r#"{"intent":"book","customer_name":"John","requested_date":"2025-06-15","requested_time":"2pm","duration_minutes":null,"notes":null,"message_to_customer":"Sure!"}"#;
+        let result = parse_intent_response(json).unwrap();
+        assert_eq!(result.requested_time, Some("14:00".to_string()));
+    }
+
+    #[test]
+    fn test_parse_unexpected_intent_as_unknown() {
+        let json = r#"{"intent":"upsell","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Hi!"}"#;
+        let result = parse_intent_response(json).unwrap();
+        assert_eq!(result.intent, Intent::Unknown);
+        assert_eq!(result.message_to_customer, "Hi!");
+    }
+
     #[test]
     fn test_parse_fallback() {
         let raw = "I don't understand the format you want";
@@ -135,4 +274,81 @@ mod tests {
         assert_eq!(result.intent, Intent::Unknown);
         assert_eq!(result.message_to_customer, raw);
     }
+
+    fn history_of(messages: &[ConversationMessage], max: usize) -> History<'_> {
+        History {
+            messages,
+            max,
+            summary: None,
+        }
+    }
+
+    fn msg(role: &str, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_messages_keeps_short_history() {
+        let history = vec![msg("user", "hi"), msg("assistant", "hello!")];
+        let messages = build_messages(history_of(&history, 20), "book me in");
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].content, "hi");
+        assert_eq!(messages[2].content, "book me in");
+    }
+
+    #[test]
+    fn test_build_messages_truncates_with_summary() {
+        let history: Vec<_> = (0..10).map(|i| msg("user", &format!("m{i}"))).collect();
+        let messages = build_messages(history_of(&history, 3), "latest");
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.contains("7 earlier messages"));
+        assert_eq!(messages[1].content, "m7");
+        assert_eq!(messages[3].content, "m9");
+        assert_eq!(messages[4].content, "latest");
+    }
+
+    #[test]
+    fn test_build_messages_leads_with_summary() {
+        let history = vec![msg("user", "still mornings?")];
+        let messages = build_messages(
+            History {
+                summary: Some("Dana prefers mornings."),
+                ..history_of(&history, 20)
+            },
+            "yes",
+        );
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.ends_with("Dana prefers mornings."));
+        assert_eq!(messages[1].content, "still mornings?");
+    }
+
+    struct PromptCapture(std::sync::Mutex<String>);
+
+    #[async_trait::async_trait]
+    impl LlmProvider for PromptCapture {
+        async fn chat(&self, system_prompt: &str, _messages: &[Message]) -> anyhow::Result<String> {
+            *self.0.lock().unwrap() = system_prompt.to_string();
+            Ok(r#"{"intent":"unknown","message_to_customer":"ok"}"#.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_guidance_without_override() {
+        let llm = PromptCapture(Default::default());
+        let prefs = AiPreferences {
+            system_prompt_override: "   ".to_string(),
+            ..Default::default()
+        };
+        extract_intent(&llm, history_of(&[], 20), "hi", "ctx", Some(&prefs), None)
+            .await
+            .unwrap();
+        let system = llm.0.lock().unwrap();
+        assert!(system.starts_with(RESPONSE_FORMAT));
+        assert!(system.contains("Intent rules:"));
+    }
 }
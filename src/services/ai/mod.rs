@@ -1,10 +1,19 @@
+pub mod breaker;
 pub mod groq;
 pub mod intent;
 pub mod ollama;
+pub mod summary;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
 
 use async_trait::async_trait;
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// How many LLM exchanges `LlmLog` keeps.
+const LLM_LOG_CAPACITY: usize = 20;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
@@ -15,3 +24,42 @@ pub struct Message {
 pub trait LlmProvider: Send + Sync {
     async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String>;
 }
+
+/// One prompt sent to the LLM and the raw text it answered with.
+#[derive(Debug, Clone, Serialize)]
+pub struct LlmExchange {
+    pub at: NaiveDateTime,
+    pub system_prompt: String,
+    pub messages: Vec<Message>,
+    pub response: String,
+}
+
+/// The most recent LLM exchanges, oldest first (only filled when `LLM_DEBUG_LOG` is on).
+#[derive(Default)]
+pub struct LlmLog {
+    exchanges: Mutex<VecDeque<LlmExchange>>,
+}
+
+impl LlmLog {
+    pub fn record(&self, system_prompt: &str, messages: &[Message], response: &str) {
+        let Ok(mut exchanges) = self.exchanges.lock() else {
+            return;
+        };
+        if exchanges.len() == LLM_LOG_CAPACITY {
+            exchanges.pop_front();
+        }
+        exchanges.push_back(LlmExchange {
+            at: Utc::now().naive_utc(),
+            system_prompt: system_prompt.to_string(),
+            messages: messages.to_vec(),
+            response: response.to_string(),
+        });
+    }
+
+    pub fn recent(&self) -> Vec<LlmExchange> {
+        self.exchanges
+            .lock()
+            .map(|e| e.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
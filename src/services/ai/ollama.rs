@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use anyhow::Context;
 use async_trait::async_trait;
 use serde_json::json;
@@ -7,22 +9,31 @@ use super::{LlmProvider, Message};
 pub struct OllamaProvider {
     url: String,
     model: String,
+    temperature: f32,
+    timeout: Duration,
     client: reqwest::Client,
 }
 
 impl OllamaProvider {
-    pub fn new(url: String, model: String) -> Self {
-        Self {
+    pub fn new(
+        url: String,
+        model: String,
+        temperature: f32,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
             url,
             model,
-            client: reqwest::Client::new(),
-        }
+            temperature,
+            timeout,
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("failed to build HTTP client")?,
+        })
     }
-}
 
-#[async_trait]
-impl LlmProvider for OllamaProvider {
-    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+    fn request_body(&self, system_prompt: &str, messages: &[Message]) -> serde_json::Value {
         let mut ollama_messages = vec![json!({
             "role": "system",
             "content": system_prompt,
@@ -35,11 +46,19 @@ impl LlmProvider for OllamaProvider {
             }));
         }
 
-        let body = json!({
+        json!({
             "model": self.model,
             "messages": ollama_messages,
             "stream": false,
-        });
+            "options": { "temperature": self.temperature },
+        })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+        let body = self.request_body(system_prompt, messages);
 
         let resp = self
             .client
@@ -47,7 +66,13 @@ impl LlmProvider for OllamaProvider {
             .json(&body)
             .send()
             .await
-            .context("failed to call Ollama API")?;
+            .map_err(|e| {
+                if e.is_timeout() {
+                    anyhow::anyhow!("Ollama API timed out after {}s", self.timeout.as_secs())
+                } else {
+                    anyhow::Error::new(e).context("failed to call Ollama API")
+                }
+            })?;
 
         let data: serde_json::Value = resp
             .json()
@@ -60,3 +85,49 @@ impl LlmProvider for OllamaProvider {
             .ok_or_else(|| anyhow::anyhow!("missing content in Ollama response"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_body_uses_configured_model_and_temperature() {
+        let provider = OllamaProvider::new(
+            "http://localhost:11434".to_string(),
+            "qwen2.5".to_string(),
+            0.2,
+            Duration::from_secs(15),
+        )
+        .unwrap();
+        let messages = [Message {
+            role: "user".to_string(),
+            content: "hi".to_string(),
+        }];
+        let body = provider.request_body("system", &messages);
+        assert_eq!(body["model"], "qwen2.5");
+        assert_eq!(body["options"]["temperature"].as_f64(), Some(0.2f32 as f64));
+        assert_eq!(body["messages"][0]["content"], "system");
+        assert_eq!(body["messages"][1]["content"], "hi");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_errors_within_timeout() {
+        // 10.255.255.1 is non-routable, so the connection hangs until the timeout
+        let provider = OllamaProvider::new(
+            "http://10.255.255.1:11434".to_string(),
+            "llama3.2".to_string(),
+            0.2,
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let started = std::time::Instant::now();
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            provider.chat("system", &[]),
+        )
+        .await
+        .expect("chat should give up on its own");
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,31 @@
+use crate::models::ConversationMessage;
+use crate::services::ai::{LlmProvider, Message};
+
+const SUMMARY_PROMPT: &str = "Summarize this SMS conversation between a customer and a business's booking assistant in at most three sentences. Keep the customer's name, appointments booked, moved or cancelled, and any stated preferences. Reply with the summary only.";
+
+/// Fold `messages` (and any `previous` summary) into a short summary that can
+/// stand in for them in later prompts.
+pub async fn summarize(
+    llm: &dyn LlmProvider,
+    previous: Option<&str>,
+    messages: &[ConversationMessage],
+) -> anyhow::Result<String> {
+    let system = match previous.filter(|p| !p.trim().is_empty()) {
+        Some(previous) => format!("{SUMMARY_PROMPT}\n\nSummary of the conversation before these messages: {previous}"),
+        None => SUMMARY_PROMPT.to_string(),
+    };
+    let messages: Vec<Message> = messages
+        .iter()
+        .map(|m| Message {
+            role: m.role.clone(),
+            content: m.content.clone(),
+        })
+        .collect();
+
+    let summary = llm.chat(&system, &messages).await?;
+    let summary = summary.trim();
+    if summary.is_empty() {
+        anyhow::bail!("LLM returned an empty summary");
+    }
+    Ok(summary.to_string())
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::db::queries;
+use crate::logging::mask_phone;
+use crate::services::inbox::record_inbox_event;
+use crate::services::messaging::segments::segment_count;
+use crate::state::AppState;
+
+#[derive(Debug, Default, Serialize)]
+pub struct BroadcastSummary {
+    pub queued: usize,
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped_blocked: usize,
+    pub skipped_opted_out: usize,
+}
+
+/// Text `message` to each recipient in batches of `BROADCAST_BATCH_SIZE`,
+/// pausing `BROADCAST_BATCH_DELAY_MS` between batches. Blocked and opted-out
+/// numbers and the owner's own phone are skipped.
+pub async fn send_broadcast(
+    state: &Arc<AppState>,
+    recipients: Vec<String>,
+    message: &str,
+) -> BroadcastSummary {
+    let mut summary = BroadcastSummary::default();
+    let queue: Vec<String> = {
+        let db = state.db.lock().unwrap();
+        let owner = queries::owner_phone(&db, &state.config.owner_phone)
+            .unwrap_or_else(|_| state.config.owner_phone.clone());
+        recipients
+            .into_iter()
+            .filter(|phone| *phone != owner)
+            .filter(|phone| {
+                let blocked = queries::is_blocked(&db, phone).unwrap_or(false);
+                if blocked {
+                    summary.skipped_blocked += 1;
+                }
+                !blocked
+            })
+            .filter(|phone| {
+                let opted_out = queries::is_opted_out(&db, phone).unwrap_or(false);
+                if opted_out {
+                    summary.skipped_opted_out += 1;
+                }
+                !opted_out
+            })
+            .collect()
+    };
+    summary.queued = queue.len();
+
+    let delay = Duration::from_millis(state.config.broadcast_batch_delay_ms);
+    for (i, batch) in queue.chunks(state.config.broadcast_batch_size).enumerate() {
+        if i > 0 {
+            tokio::time::sleep(delay).await;
+        }
+        for phone in batch {
+            match state.messaging.send_message(phone, message).await {
+                Ok(_) => {
+                    summary.sent += 1;
+                    record_inbox_event(state, phone, "owner_reply", message);
+                    let segments = segment_count(message);
+                    let db = state.db.lock().unwrap();
+                    let _ = queries::increment_monthly_sent(&db, segments);
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    tracing::error!(error = %e, phone = %mask_phone(phone), "failed to send broadcast");
+                }
+            }
+        }
+    }
+
+    tracing::info!(
+        queued = summary.queued,
+        sent = summary.sent,
+        failed = summary.failed,
+        "broadcast finished"
+    );
+    summary
+}
@@ -1,8 +1,89 @@
+use std::collections::HashMap;
+
 use chrono::Duration;
 
-use crate::models::Booking;
+use crate::models::{Booking, BookingStatus};
+
+/// Where the appointment is and who it's between, for calendar apps that show
+/// organizer and attendee details.
+pub struct EventContact<'a> {
+    pub business_name: &'a str,
+    /// The business's texting number, used as the organizer's `tel:` address.
+    pub business_phone: &'a str,
+    pub location: Option<&'a str>,
+}
+
+/// Escape a TEXT value per RFC 5545 (backslash, semicolon, comma, newline).
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// ORGANIZER, ATTENDEE and LOCATION lines; each is left out when its value is
+/// unknown rather than written empty.
+fn contact_lines(booking: &Booking, contact: &EventContact) -> String {
+    // CN is a quoted parameter value, which may not itself contain quotes
+    let cn = |name: &str| name.replace('"', "");
+    let mut lines = String::new();
+    if !contact.business_phone.is_empty() {
+        lines.push_str(&format!(
+            "ORGANIZER;CN=\"{}\":tel:{}\r\n",
+            cn(contact.business_name),
+            contact.business_phone
+        ));
+    }
+    if let Some(name) = booking.customer_name.as_deref().filter(|n| !n.trim().is_empty()) {
+        lines.push_str(&format!(
+            "ATTENDEE;CN=\"{}\":tel:{}\r\n",
+            cn(name.trim()),
+            booking.customer_phone
+        ));
+    }
+    if let Some(location) = contact.location.filter(|l| !l.is_empty()) {
+        lines.push_str(&format!("LOCATION:{}\r\n", escape_text(location)));
+    }
+    lines
+}
+
+fn ics_status(status: &BookingStatus) -> &'static str {
+    match status {
+        BookingStatus::Pending => "TENTATIVE",
+        BookingStatus::Confirmed => "CONFIRMED",
+        BookingStatus::Cancelled | BookingStatus::NoShow => "CANCELLED",
+    }
+}
+
+/// A display reminder `minutes` before the event, or nothing when 0.
+fn valarm(minutes: u32, summary: &str) -> String {
+    if minutes == 0 {
+        return String::new();
+    }
+    let trigger = if minutes.is_multiple_of(60) {
+        format!("-PT{}H", minutes / 60)
+    } else {
+        format!("-PT{minutes}M")
+    };
+    format!(
+        "BEGIN:VALARM\r\n\
+         TRIGGER:{trigger}\r\n\
+         ACTION:DISPLAY\r\n\
+         DESCRIPTION:Reminder: {summary}\r\n\
+         END:VALARM\r\n"
+    )
+}
 
-pub fn generate_ics(booking: &Booking, business_name: &str) -> String {
+/// `sequence` counts the booking's changes since creation; bumping it is what
+/// makes calendar clients apply an update such as a cancellation.
+pub fn generate_ics(
+    booking: &Booking,
+    contact: &EventContact,
+    alarm_minutes: u32,
+    sequence: u32,
+) -> String {
+    let business_name = contact.business_name;
     let dtstart = booking.date_time.format("%Y%m%dT%H%M%S").to_string();
     let dtend = (booking.date_time + Duration::minutes(booking.duration_minutes as i64))
         .format("%Y%m%dT%H%M%S")
@@ -18,6 +99,9 @@ pub fn generate_ics(booking: &Booking, business_name: &str) -> String {
         .notes
         .as_deref()
         .unwrap_or("No additional notes");
+    let status = ics_status(&booking.status);
+    let details = contact_lines(booking, contact);
+    let alarm = valarm(alarm_minutes, &summary);
 
     format!(
         "BEGIN:VCALENDAR\r\n\
@@ -30,17 +114,28 @@ pub fn generate_ics(booking: &Booking, business_name: &str) -> String {
          DTEND:{dtend}\r\n\
          SUMMARY:{summary}\r\n\
          DESCRIPTION:{description}\r\n\
+         STATUS:{status}\r\n\
+         SEQUENCE:{sequence}\r\n\
+         {details}\
+         {alarm}\
          END:VEVENT\r\n\
          END:VCALENDAR\r\n"
     )
 }
 
-pub fn generate_ics_feed(bookings: &[Booking], business_name: &str) -> String {
-    let mut ics = String::from(
+/// `sequences` maps booking id to its change count; missing ids are 0.
+pub fn generate_ics_feed(
+    bookings: &[Booking],
+    contact: &EventContact,
+    alarm_minutes: u32,
+    sequences: &HashMap<String, u32>,
+) -> String {
+    let business_name = contact.business_name;
+    let mut ics = format!(
         "BEGIN:VCALENDAR\r\n\
          VERSION:2.0\r\n\
          PRODID:-//Phonebook//Booking Agent//EN\r\n\
-         X-WR-CALNAME:Bookings\r\n\
+         X-WR-CALNAME:{business_name}\r\n\
          METHOD:PUBLISH\r\n",
     );
 
@@ -61,6 +156,10 @@ pub fn generate_ics_feed(bookings: &[Booking], business_name: &str) -> String {
             .notes
             .as_deref()
             .unwrap_or("No additional notes");
+        let status = ics_status(&booking.status);
+        let sequence = sequences.get(&booking.id).copied().unwrap_or(0);
+        let details = contact_lines(booking, contact);
+        let alarm = valarm(alarm_minutes, &summary);
 
         ics.push_str(&format!(
             "BEGIN:VEVENT\r\n\
@@ -70,7 +169,10 @@ pub fn generate_ics_feed(bookings: &[Booking], business_name: &str) -> String {
              DTEND:{dtend}\r\n\
              SUMMARY:{summary}\r\n\
              DESCRIPTION:{description}\r\n\
-             STATUS:CONFIRMED\r\n\
+             STATUS:{status}\r\n\
+             SEQUENCE:{sequence}\r\n\
+             {details}\
+             {alarm}\
              END:VEVENT\r\n"
         ));
     }
@@ -83,7 +185,14 @@ pub fn generate_ics_feed(bookings: &[Booking], business_name: &str) -> String {
 mod tests {
     use super::*;
     use chrono::NaiveDateTime;
-    use crate::models::{Booking, BookingStatus};
+
+    fn contact(business_name: &str) -> EventContact<'_> {
+        EventContact {
+            business_name,
+            business_phone: "",
+            location: None,
+        }
+    }
 
     #[test]
     fn test_generate_ics() {
@@ -95,11 +204,12 @@ mod tests {
             duration_minutes: 60,
             status: BookingStatus::Confirmed,
             notes: Some("Haircut".to_string()),
+            service: None,
             created_at: NaiveDateTime::parse_from_str("2025-03-10 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
             updated_at: NaiveDateTime::parse_from_str("2025-03-10 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
         };
 
-        let ics = generate_ics(&booking, "Bob's Barbershop");
+        let ics = generate_ics(&booking, &contact("Bob's Barbershop"), 0, 0);
         assert!(ics.contains("BEGIN:VCALENDAR"));
         assert!(ics.contains("BEGIN:VEVENT"));
         assert!(ics.contains("DTSTART:20250315T140000"));
@@ -121,13 +231,127 @@ mod tests {
             duration_minutes: 30,
             status: BookingStatus::Confirmed,
             notes: None,
+            service: None,
             created_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
             updated_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
         };
 
-        let ics = generate_ics(&booking, "Test Biz");
+        let ics = generate_ics(&booking, &contact("Test Biz"), 0, 0);
         assert!(ics.contains("DTSTART:20250401T093000"));
         assert!(ics.contains("DTEND:20250401T100000"));
         assert!(ics.contains("DESCRIPTION:No additional notes"));
+        assert!(!ics.contains("BEGIN:VALARM"));
+    }
+
+    #[test]
+    fn test_generate_ics_with_alarm() {
+        let booking = Booking {
+            id: "test-789".to_string(),
+            customer_phone: "+1234567890".to_string(),
+            customer_name: None,
+            date_time: NaiveDateTime::parse_from_str("2025-04-01 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            duration_minutes: 30,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            updated_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+
+        let ics = generate_ics(&booking, &contact("Test Biz"), 60, 0);
+        assert!(ics.contains("BEGIN:VALARM\r\nTRIGGER:-PT1H\r\nACTION:DISPLAY\r\n"));
+        assert!(ics.contains("DESCRIPTION:Reminder: Appointment with Test Biz\r\nEND:VALARM\r\nEND:VEVENT"));
+
+        let feed = generate_ics_feed(&[booking], &contact("Test Biz"), 15, &HashMap::new());
+        assert!(feed.contains("TRIGGER:-PT15M"));
+    }
+
+    #[test]
+    fn test_generate_ics_organizer_attendee_location() {
+        let booking = Booking {
+            id: "test-321".to_string(),
+            customer_phone: "+1234567890".to_string(),
+            customer_name: Some("Alice".to_string()),
+            date_time: NaiveDateTime::parse_from_str("2025-04-01 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            duration_minutes: 30,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            updated_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+        let contact = EventContact {
+            business_name: "Bob's Barbershop",
+            business_phone: "+15551234567",
+            location: Some("12 Main St, Suite 4"),
+        };
+
+        let ics = generate_ics(&booking, &contact, 0, 0);
+        assert!(ics.contains("ORGANIZER;CN=\"Bob's Barbershop\":tel:+15551234567\r\n"));
+        assert!(ics.contains("ATTENDEE;CN=\"Alice\":tel:+1234567890\r\n"));
+        assert!(ics.contains("LOCATION:12 Main St\\, Suite 4\r\n"));
+    }
+
+    #[test]
+    fn test_generate_ics_omits_unknown_attendee() {
+        let booking = Booking {
+            id: "test-654".to_string(),
+            customer_phone: "+1234567890".to_string(),
+            customer_name: None,
+            date_time: NaiveDateTime::parse_from_str("2025-04-01 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            duration_minutes: 30,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            updated_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+
+        let ics = generate_ics(&booking, &contact("Test Biz"), 0, 0);
+        assert!(!ics.contains("ATTENDEE"));
+        assert!(!ics.contains("ORGANIZER"));
+        assert!(!ics.contains("LOCATION"));
+    }
+
+    #[test]
+    fn test_generate_ics_status_confirmed() {
+        let booking = Booking {
+            id: "test-111".to_string(),
+            customer_phone: "+1234567890".to_string(),
+            customer_name: None,
+            date_time: NaiveDateTime::parse_from_str("2025-04-01 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            duration_minutes: 30,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            updated_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+
+        let ics = generate_ics(&booking, &contact("Test Biz"), 0, 0);
+        assert!(ics.contains("STATUS:CONFIRMED\r\nSEQUENCE:0\r\n"));
+    }
+
+    #[test]
+    fn test_generate_ics_status_cancelled() {
+        let booking = Booking {
+            id: "test-222".to_string(),
+            customer_phone: "+1234567890".to_string(),
+            customer_name: None,
+            date_time: NaiveDateTime::parse_from_str("2025-04-01 09:30:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            duration_minutes: 30,
+            status: BookingStatus::Cancelled,
+            notes: None,
+            service: None,
+            created_at: NaiveDateTime::parse_from_str("2025-03-25 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            updated_at: NaiveDateTime::parse_from_str("2025-03-26 08:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+        };
+
+        let ics = generate_ics(&booking, &contact("Test Biz"), 0, 1);
+        assert!(ics.contains("STATUS:CANCELLED\r\nSEQUENCE:1\r\n"));
+
+        let sequences = HashMap::from([("test-222".to_string(), 1)]);
+        let feed = generate_ics_feed(&[booking], &contact("Test Biz"), 0, &sequences);
+        assert!(feed.contains("STATUS:CANCELLED\r\nSEQUENCE:1\r\n"));
     }
 }
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use rusqlite::Connection;
+
+use crate::config::AppConfig;
+use crate::db::{pool, queries};
+use crate::services::{conversation, reminders};
+use crate::state::AppState;
+
+/// How often idle conversations are checked.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Nudge customers who stalled before confirming, send appointment reminders
+/// and drop stale failed-auth counts.
+pub fn spawn_sweeper(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let sent = conversation::reprompt_stale_confirmations(&state).await;
+            if sent > 0 {
+                tracing::info!(sent, "re-prompted stalled confirmations");
+            }
+            let reminded = reminders::send_due_reminders(&state).await;
+            if reminded > 0 {
+                tracing::info!(reminded, "sent appointment reminders");
+            }
+            state.auth_limiter.prune();
+        }
+    });
+}
+
+/// Rows removed by one [`run_cleanup`] pass.
+#[derive(Debug, Default, PartialEq)]
+pub struct CleanupReport {
+    pub conversations: usize,
+    pub rate_limit_windows: usize,
+    pub inbox_events: usize,
+}
+
+/// Delete conversations past their expiry and grace window, rate-limit
+/// windows too old to count, and inbox events older than
+/// `INBOX_RETENTION_DAYS` (0 keeps them forever).
+pub fn run_cleanup(conn: &Connection, config: &AppConfig) -> anyhow::Result<CleanupReport> {
+    let inbox_events = if config.inbox_retention_days > 0 {
+        let cutoff = Utc::now().naive_utc() - chrono::Duration::days(config.inbox_retention_days);
+        queries::prune_inbox_events(conn, cutoff)?
+    } else {
+        0
+    };
+    Ok(CleanupReport {
+        conversations: queries::expire_old_conversations(conn, config.conversation_grace_minutes)?,
+        rate_limit_windows: queries::cleanup_old_windows(conn)?,
+        inbox_events,
+    })
+}
+
+/// Run [`run_cleanup`] every `CLEANUP_INTERVAL_MINUTES`.
+pub fn spawn_cleanup(state: Arc<AppState>) {
+    let period = Duration::from_secs(state.config.cleanup_interval_minutes * 60);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            let result = pool::with_db(&state.db, {
+                let state = Arc::clone(&state);
+                move |conn| run_cleanup(conn, &state.config)
+            })
+            .await;
+            match result {
+                Ok(report) if report == CleanupReport::default() => {}
+                Ok(report) => tracing::info!(
+                    conversations = report.conversations,
+                    rate_limit_windows = report.rate_limit_windows,
+                    inbox_events = report.inbox_events,
+                    "cleaned up expired rows"
+                ),
+                Err(e) => tracing::error!(error = %e, "cleanup failed"),
+            }
+        }
+    });
+}
@@ -1,28 +1,117 @@
 use std::sync::Arc;
 
-use chrono::{Duration, Utc};
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::Connection;
 
-use crate::db::queries;
+use crate::db::{pool, queries};
+use crate::logging::mask_phone;
 use crate::models::{
-    AiPreferences, Availability, Booking, BookingStatus, Conversation, ConversationMessage,
-    ConversationState, Intent, PendingBooking,
+    AiPreferences, Availability, Booking, BookingActor, BookingLink, BookingStatus, Conversation,
+    ConversationMessage, ConversationState, DurationBounds, ExtractedIntent, Intent,
+    PendingBooking, RejectionMessages, Service,
 };
-use crate::services::ai::intent::extract_intent;
+use crate::services::ai::intent::{extract_intent, History};
+use crate::services::ai::summary::summarize;
+use crate::services::dates::{format_date_in, format_time, format_when, format_when_in, resolve_date};
+use crate::services::i18n;
 use crate::services::inbox::record_inbox_event;
-use crate::services::scheduling::validate_booking_time;
+use crate::services::messaging::segments::segment_count;
+use crate::services::notify::{notify_owner, owner_phone, OwnerEvent};
+use crate::services::quiet_hours;
+use crate::services::reminders::{self, ReminderReply};
+use crate::services::signing;
+use crate::services::scheduling::{
+    check_daily_limit, create_booking_if_free, free_slots, parse_time_window, pick_slot, validate_booking_time,
+    validate_reschedule_time, SchedulingError,
+};
+use crate::services::waitlist::offer_freed_slot;
 use crate::state::{AppState, DevNotification, DevNotificationKind};
 
+/// Most open slots offered for a vague request like "Thursday afternoon".
+const MAX_PROPOSED_SLOTS: usize = 3;
+
+/// What handling one customer message did, or in a dry run would have done.
+#[derive(Debug, Clone)]
+pub struct MessageOutcome {
+    pub reply: String,
+    pub intent: Intent,
+    pub previous_state: ConversationState,
+    pub state: ConversationState,
+    /// The booking still being worked out after this message, if any
+    pub pending_booking: Option<PendingBooking>,
+}
+
+/// Settings fixed for the length of one message's handling.
+struct Turn {
+    lang: String,
+    rejections: RejectionMessages,
+    durations: DurationBounds,
+    /// Prepended to the reply; only set on a brand-new conversation
+    welcome: String,
+    /// The business allows one booking per customer per day
+    one_booking_per_day: bool,
+    /// Template for the booking details added to every confirmation
+    confirmation: String,
+    business: String,
+    /// Show times on a 24-hour clock
+    use_24h: bool,
+    intent: Intent,
+    previous_state: ConversationState,
+    /// Work out the reply without writing to the database or texting anyone
+    dry_run: bool,
+}
+
 pub async fn process_message(
     state: &Arc<AppState>,
     from_phone: &str,
     message: &str,
 ) -> anyhow::Result<String> {
+    Ok(process_message_detailed(state, from_phone, message).await?.reply)
+}
+
+/// Like `process_message`, but also reports the detected intent and the
+/// conversation state it left behind.
+pub async fn process_message_detailed(
+    state: &Arc<AppState>,
+    from_phone: &str,
+    message: &str,
+) -> anyhow::Result<MessageOutcome> {
+    handle_message(state, from_phone, message, false).await
+}
+
+/// Work out the reply to a customer message without creating or cancelling
+/// bookings, saving the conversation, or texting anyone. For trying out AI
+/// preferences against the real state machine.
+pub async fn process_message_dry_run(
+    state: &Arc<AppState>,
+    from_phone: &str,
+    message: &str,
+) -> anyhow::Result<MessageOutcome> {
+    handle_message(state, from_phone, message, true).await
+}
+
+async fn handle_message(
+    state: &Arc<AppState>,
+    from_phone: &str,
+    message: &str,
+    dry_run: bool,
+) -> anyhow::Result<MessageOutcome> {
+    // Two rapid SMS from one number must not interleave and clobber each other's state
+    let _phone_guard = lock_phone(state, from_phone).await;
+
     // Load or create conversation
     let mut conv = {
         let db = state.db.lock().unwrap();
-        queries::get_conversation(&db, from_phone)?
+        queries::get_conversation_with_grace(
+            &db,
+            from_phone,
+            state.config.conversation_grace_minutes,
+        )?
     }
     .unwrap_or_else(|| new_conversation(from_phone));
+    // The agent is answering again, so a later pause may auto-reply once more
+    conv.paused_autoreply_sent = false;
+    conv.reprompt_sent = false;
 
     // Load user settings
     let user = {
@@ -39,6 +128,54 @@ pub async fn process_message(
         .as_ref()
         .and_then(|u| u.ai_preferences.as_deref())
         .and_then(|s| AiPreferences::from_json(s).ok());
+    let rejections = ai_preferences
+        .as_ref()
+        .map(|p| p.rejection_messages.clone())
+        .unwrap_or_default();
+    let durations = availability
+        .as_ref()
+        .map(Availability::duration_bounds)
+        .unwrap_or_default();
+    let services = user
+        .as_ref()
+        .and_then(|u| u.services.as_deref())
+        .and_then(|s| Service::list_from_json(s).ok())
+        .unwrap_or_default();
+    let offer_waitlist = ai_preferences
+        .as_ref()
+        .is_some_and(|p| p.capabilities.can_offer_waitlist);
+    let contact_notes = if ai_preferences
+        .as_ref()
+        .is_some_and(|p| p.returning_customers.remember_preferences)
+    {
+        let db = state.db.lock().unwrap();
+        queries::get_contact_notes(&db, from_phone).ok().flatten()
+    } else {
+        None
+    };
+    let lang = user
+        .as_ref()
+        .map(|u| u.language.clone())
+        .unwrap_or_else(|| i18n::DEFAULT_LANGUAGE.to_string());
+    let one_booking_per_day = user
+        .as_ref()
+        .is_some_and(|u| u.one_booking_per_customer_per_day);
+    let confirmation = ai_preferences
+        .as_ref()
+        .map(|p| p.confirmation_template.trim())
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| i18n::t(&lang, "booking_details", &[]));
+    let business = user
+        .as_ref()
+        .map(|u| u.business_name.clone())
+        .unwrap_or_default();
+    let use_24h = user.as_ref().is_some_and(|u| u.use_24h_time);
+    let welcome = if conv.messages.is_empty() {
+        welcome_text(ai_preferences.as_ref(), &lang)
+    } else {
+        String::new()
+    };
 
     // Append user message
     conv.messages.push(ConversationMessage {
@@ -46,20 +183,50 @@ pub async fn process_message(
         content: message.to_string(),
     });
 
-    // Forward customer message to owner via dev notification queue
-    if let Ok(mut notifications) = state.dev_notifications.lock() {
-        notifications.push(DevNotification {
+    if !dry_run {
+        // Forward customer message to owner via dev notification queue
+        state.dev_notifications.push(DevNotification {
             phone: Some(from_phone.to_string()),
             kind: DevNotificationKind::CustomerMessage,
             content: message.to_string(),
         });
+        record_inbox_event(state, from_phone, "customer_message", message);
+    }
+
+    // "C"/"X" answering a recent reminder acts on that booking, no LLM needed
+    if let Some(answer) = ReminderReply::parse(message) {
+        let reminded = {
+            let db = state.db.lock().unwrap();
+            let since = Utc::now().naive_utc() - reminders::REPLY_WINDOW;
+            queries::get_reminded_booking(&db, from_phone, &since)?
+        };
+        if let Some(booking) = reminded {
+            let turn = Turn {
+                lang,
+                rejections,
+                durations,
+                welcome,
+                one_booking_per_day,
+                confirmation,
+                business,
+                use_24h,
+                intent: match answer {
+                    ReminderReply::Confirm => Intent::Confirm,
+                    ReminderReply::Cancel => Intent::Cancel,
+                },
+                previous_state: conv.state.clone(),
+                dry_run,
+            };
+            let reply = answer_reminder(state, &booking, answer, from_phone, &turn).await?;
+            return finish_conversation(state, &mut conv, &reply, &turn).await;
+        }
     }
-    record_inbox_event(state, from_phone, "customer_message", message);
 
     // Build business context
     let mut business_context = format!(
         "Business phone: {}. Owner phone: {}.",
-        state.config.twilio_phone_number, state.config.owner_phone,
+        state.config.twilio_phone_number,
+        owner_phone(state).await,
     );
     if let Some(ref avail) = availability {
         let hours = avail.to_human_readable();
@@ -67,27 +234,240 @@ pub async fn process_message(
             business_context.push_str(&format!(" Business hours: {hours}."));
         }
     }
+    if !services.is_empty() {
+        let offered = services
+            .iter()
+            .map(Service::describe)
+            .collect::<Vec<_>>()
+            .join(", ");
+        business_context.push_str(&format!(" Services offered: {offered}."));
+    }
+    if let Some(ref notes) = contact_notes {
+        if !notes.notes.is_empty() {
+            business_context.push_str(&format!(" Owner's notes on this customer: {}.", notes.notes));
+        }
+        if !notes.tags.is_empty() {
+            business_context.push_str(&format!(" Customer tags: {}.", notes.tags));
+        }
+    }
+    if let Some(ref prefs) = ai_preferences {
+        let matched = prefs.matched_prices(message);
+        if !matched.is_empty() {
+            let prices = matched
+                .iter()
+                .map(|p| format!("{} is {}", p.service, p.price))
+                .collect::<Vec<_>>()
+                .join("; ");
+            business_context.push_str(&format!(" Price asked about: {prices}."));
+        }
+    }
+
+    // While the LLM keeps failing, answer with a holding reply instead of calling it
+    if !dry_run && !state.llm_breaker.allow() {
+        let turn = Turn {
+            lang,
+            rejections,
+            durations,
+            welcome,
+            one_booking_per_day,
+            confirmation,
+            business,
+            use_24h,
+            intent: Intent::Unknown,
+            previous_state: conv.state.clone(),
+            dry_run,
+        };
+        let reply = i18n::t(&turn.lang, "llm_unavailable", &[]);
+        return finish_conversation(state, &mut conv, &reply, &turn).await;
+    }
 
-    // Extract intent via LLM
-    let extracted = extract_intent(
-        state.llm.as_ref(),
-        &conv.messages,
-        message,
-        &business_context,
-        ai_preferences.as_ref(),
-    )
-    .await?;
+    // Extract intent via LLM, waiting for a free slot if the cap is reached
+    let extracted = {
+        let _permit = state.llm_permits.acquire().await?;
+        // The latest message was just appended; pass only the prior turns as history
+        let prior = &conv.messages[..conv.messages.len() - 1];
+        let history = History {
+            messages: prior,
+            max: state.config.max_history_messages,
+            summary: conv.summary.as_deref(),
+        };
+        extract_intent(
+            state.llm.as_ref(),
+            history,
+            message,
+            &business_context,
+            ai_preferences.as_ref(),
+            state.config.llm_debug_log.then_some(&state.llm_log),
+        )
+        .await
+    };
+    let mut extracted = match extracted {
+        Ok(extracted) => {
+            if !dry_run {
+                state.llm_breaker.record_success();
+            }
+            extracted
+        }
+        Err(e) => {
+            if !dry_run && state.llm_breaker.record_failure() {
+                let alert = format!(
+                    "The AI model keeps failing ({e}). Customers get a holding reply for the next {}s.",
+                    state.config.llm_breaker_cooldown_secs
+                );
+                notify_owner(state, OwnerEvent::System, &alert, None).await;
+            }
+            return Err(e);
+        }
+    };
+
+    // Pin year-less dates ("June 15") to a concrete year
+    if let Some(ref raw) = extracted.requested_date {
+        let today = Utc::now().date_naive();
+        if let Some(date) = resolve_date(raw, today, state.config.yearless_date_policy) {
+            extracted.requested_date = Some(date.format("%Y-%m-%d").to_string());
+        }
+    }
+
+    // Keep the LLM's appointment length within the owner's bounds
+    if let Some(minutes) = extracted.duration_minutes {
+        let clamped = durations.clamp(Some(minutes));
+        if clamped != minutes {
+            tracing::warn!(phone = %mask_phone(from_phone), requested = minutes, clamped, "appointment length out of bounds, clamped");
+            extracted.duration_minutes = Some(clamped);
+        }
+    }
+
+    // A named service fixes the appointment length, whatever the LLM guessed
+    let service = Service::find_in(&services, message).map(|svc| {
+        extracted.duration_minutes = Some(svc.duration_minutes);
+        svc.name.clone()
+    });
 
     tracing::info!(
-        phone = from_phone,
+        phone = %mask_phone(from_phone),
         intent = ?extracted.intent,
         state = conv.state.as_str(),
+        dry_run,
         "processing message"
     );
+    let turn = Turn {
+        lang,
+        rejections,
+        durations,
+        welcome,
+        one_booking_per_day,
+        confirmation,
+        business,
+        use_24h,
+        intent: extracted.intent.clone(),
+        previous_state: conv.state.clone(),
+        dry_run,
+    };
+
+    // A reply to offered slots ("the 2pm one") books the chosen slot. Only a
+    // booking or confirming reply counts, so a question that happens to
+    // mention a time or number never books anything
+    let picking = matches!(extracted.intent, Intent::Book | Intent::Confirm);
+    let picked_slot = match (&conv.state, &conv.pending_booking) {
+        (ConversationState::Confirming, Some(pending))
+            if picking && !pending.proposed_slots.is_empty() =>
+        {
+            let slots: Vec<NaiveDateTime> = pending
+                .proposed_slots
+                .iter()
+                .filter_map(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").ok())
+                .collect();
+            pick_slot(&slots, message, extracted.requested_time.as_deref())
+        }
+        _ => None,
+    };
+    if let Some(slot) = picked_slot {
+        if let Some(ref mut pending) = conv.pending_booking {
+            pending.date_time = Some(slot.format("%Y-%m-%d %H:%M").to_string());
+            pending.proposed_slots.clear();
+        }
+        let reply = book_pending(
+            state,
+            &mut conv,
+            from_phone,
+            availability.as_ref(),
+            &turn,
+            "",
+        )
+        .await?;
+        return finish_conversation(state, &mut conv, &reply, &turn).await;
+    }
+
+    // "Thursday afternoon": a date with a time window but no exact time
+    let vague_request = match (&extracted.requested_date, &extracted.requested_time) {
+        (Some(date), None) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .zip(extracted.time_window.as_deref().and_then(parse_time_window)),
+        _ => None,
+    };
 
     // State machine transition
     let reply = match (&conv.state, &extracted.intent) {
         // New booking request
+        (_, Intent::Book) if let Some((date, window)) = vague_request => {
+            let duration = turn.durations.clamp(extracted.duration_minutes);
+            let slots = {
+                let db = state.db.lock().unwrap();
+                free_slots(
+                    &db,
+                    availability.as_ref(),
+                    date,
+                    window,
+                    duration,
+                    Utc::now().naive_utc(),
+                    MAX_PROPOSED_SLOTS,
+                )
+            };
+
+            conv.pending_booking = Some(PendingBooking {
+                customer_name: extracted.customer_name,
+                date_time: None,
+                duration_minutes: extracted.duration_minutes,
+                notes: extracted.notes,
+                proposed_slots: slots
+                    .iter()
+                    .map(|s| s.format("%Y-%m-%d %H:%M").to_string())
+                    .collect(),
+                booking_id: None,
+                service: service.clone(),
+            });
+
+            if slots.is_empty() {
+                conv.state = ConversationState::CollectingInfo;
+                let date = format_date_in(date, &turn.lang);
+                i18n::t(&turn.lang, "no_openings", &[("date", &date)])
+            } else {
+                conv.state = ConversationState::Confirming;
+                let date = format_date_in(date, &turn.lang);
+                let slots = describe_slots(&slots, &turn.lang, turn.use_24h);
+                i18n::t(&turn.lang, "openings", &[("date", &date), ("slots", &slots)])
+            }
+        }
+
+        // "Yes, 2pm works" or just "2pm" while confirming: restating the
+        // proposed time confirms it, even when the LLM reads it as a new request
+        (ConversationState::Confirming, Intent::Book)
+            if conv.pending_booking.as_ref().is_some_and(|pending| {
+                pending.date_time.is_some()
+                    && with_requested_time(pending, &extracted) == pending.date_time
+            }) =>
+        {
+            book_pending(
+                state,
+                &mut conv,
+                from_phone,
+                availability.as_ref(),
+                &turn,
+                "",
+            )
+            .await?
+        }
+
         (_, Intent::Book) => {
             let has_enough_info = extracted.customer_name.is_some()
                 && extracted.requested_date.is_some()
@@ -103,19 +483,32 @@ pub async fn process_message(
                     ),
                     duration_minutes: extracted.duration_minutes,
                     notes: extracted.notes,
+                    proposed_slots: vec![],
+                    booking_id: None,
+                    service: service.clone(),
                 };
 
                 // Validate proposed time
                 if let Some(ref dt_str) = pending.date_time {
-                    if let Some(validation_err) = try_validate_time(
+                    if let Some(err) = try_validate_time(
                         state,
                         dt_str,
-                        pending.duration_minutes.unwrap_or(60),
+                        turn.durations.clamp(pending.duration_minutes),
                         availability.as_ref(),
+                        None,
+                        turn.daily_limit(from_phone),
                     ) {
+                        let validation_err = rejection_reply(
+                            state,
+                            from_phone,
+                            &err,
+                            &pending,
+                            offer_waitlist,
+                            &turn,
+                        );
                         conv.pending_booking = Some(pending);
                         conv.state = ConversationState::CollectingInfo;
-                        return finish_conversation(state, &mut conv, &validation_err).await;
+                        return finish_conversation(state, &mut conv, &validation_err, &turn).await;
                     }
                 }
 
@@ -131,14 +524,36 @@ pub async fn process_message(
                     ),
                     duration_minutes: extracted.duration_minutes,
                     notes: extracted.notes,
+                    proposed_slots: vec![],
+                    booking_id: None,
+                    service: service.clone(),
                 });
                 conv.state = ConversationState::CollectingInfo;
             }
             extracted.message_to_customer.clone()
         }
 
-        // Collecting info — LLM continues asking questions until it has enough
-        (ConversationState::CollectingInfo, _) => {
+        // Customer wants a person — go quiet on this thread until the owner steps in,
+        // even mid-booking or mid-reschedule
+        (_, Intent::Handoff) => {
+            conv.state = ConversationState::Idle;
+            conv.pending_booking = None;
+            if !turn.dry_run {
+                {
+                    let db = state.db.lock().unwrap();
+                    queries::request_human(&db, from_phone)?;
+                }
+                let alert = format!(
+                    "ACTION NEEDED: {from_phone} asked to talk to a person: \"{message}\". The assistant won't reply to them until you answer from the inbox."
+                );
+                notify_owner(state, OwnerEvent::System, &alert, Some(from_phone)).await;
+            }
+            i18n::t(&turn.lang, "handoff", &[])
+        }
+
+        // Collecting info (or a new time for a reschedule) — LLM continues
+        // asking questions until it has enough
+        (ConversationState::CollectingInfo | ConversationState::Rescheduling, _) => {
             // Update pending booking with any new info
             if let Some(ref mut pending) = conv.pending_booking {
                 if extracted.customer_name.is_some() {
@@ -156,16 +571,19 @@ pub async fn process_message(
                 if extracted.duration_minutes.is_some() {
                     pending.duration_minutes = extracted.duration_minutes;
                 }
+                if service.is_some() {
+                    pending.service = service.clone();
+                }
                 if extracted.notes.is_some() {
                     pending.notes = extracted.notes.clone();
                 }
             }
 
-            // Check if we now have enough info to confirm
+            // Check if we now have enough info to confirm (a reschedule keeps the old name)
             let has_enough = conv
                 .pending_booking
                 .as_ref()
-                .map(|p| p.customer_name.is_some() && p.date_time.is_some())
+                .map(|p| (p.customer_name.is_some() || p.booking_id.is_some()) && p.date_time.is_some())
                 .unwrap_or(false);
 
             if has_enough
@@ -176,10 +594,31 @@ pub async fn process_message(
                 // Validate before transitioning to Confirming
                 let should_confirm =
                     if let Some(ref dt_str) = conv.pending_booking.as_ref().and_then(|p| p.date_time.clone()) {
-                        let dur = conv.pending_booking.as_ref().and_then(|p| p.duration_minutes).unwrap_or(60);
-                        if let Some(validation_err) = try_validate_time(state, dt_str, dur, availability.as_ref()) {
-                            conv.state = ConversationState::CollectingInfo;
-                            return finish_conversation(state, &mut conv, &validation_err).await;
+                        let dur = turn.durations.clamp(conv.pending_booking.as_ref().and_then(|p| p.duration_minutes));
+                        let moving = conv.pending_booking.as_ref().and_then(|p| p.booking_id.clone());
+                        if let Some(err) =
+                            try_validate_time(
+                                state,
+                                dt_str,
+                                dur,
+                                availability.as_ref(),
+                                moving.as_deref(),
+                                turn.daily_limit(from_phone),
+                            )
+                        {
+                            let validation_err = match conv.pending_booking {
+                                Some(ref pending) => rejection_reply(
+                                    state,
+                                    from_phone,
+                                    &err,
+                                    pending,
+                                    offer_waitlist,
+                                    &turn,
+                                ),
+                                None => err.render(&turn.rejections, &turn.lang, turn.use_24h),
+                            };
+                            // Stay in the current collecting state
+                            return finish_conversation(state, &mut conv, &validation_err, &turn).await;
                         }
                         true
                     } else {
@@ -196,97 +635,120 @@ pub async fn process_message(
 
         // Customer confirms a proposed booking
         (ConversationState::Confirming, Intent::Confirm) => {
-            if let Some(ref pending) = conv.pending_booking {
-                // Final validation before creating booking
-                if let Some(ref dt_str) = pending.date_time {
-                    let dur = pending.duration_minutes.unwrap_or(60);
-                    if let Some(validation_err) = try_validate_time(state, dt_str, dur, availability.as_ref()) {
-                        conv.state = ConversationState::CollectingInfo;
-                        return finish_conversation(state, &mut conv, &validation_err).await;
+            book_pending(
+                state,
+                &mut conv,
+                from_phone,
+                availability.as_ref(),
+                &turn,
+                &extracted.message_to_customer,
+            )
+            .await?
+        }
+
+        // Customer declines a proposed time, possibly offering another
+        // ("no, make it 3pm instead"), which is re-proposed in its place
+        (ConversationState::Confirming, Intent::Decline) => {
+            let counter = conv
+                .pending_booking
+                .as_ref()
+                .and_then(|pending| with_requested_time(pending, &extracted));
+            match (counter, conv.pending_booking.as_mut()) {
+                (Some(dt_str), Some(pending)) => {
+                    pending.date_time = Some(dt_str.clone());
+                    pending.proposed_slots.clear();
+                    let duration = turn.durations.clamp(pending.duration_minutes);
+                    let moving = pending.booking_id.clone();
+                    match try_validate_time(
+                        state,
+                        &dt_str,
+                        duration,
+                        availability.as_ref(),
+                        moving.as_deref(),
+                        turn.daily_limit(from_phone),
+                    ) {
+                        Some(err) => {
+                            let reply = rejection_reply(
+                                state,
+                                from_phone,
+                                &err,
+                                pending,
+                                offer_waitlist,
+                                &turn,
+                            );
+                            conv.state = collecting_state(&conv);
+                            reply
+                        }
+                        None => {
+                            let when = NaiveDateTime::parse_from_str(&dt_str, "%Y-%m-%d %H:%M")
+                                .map(|dt| format_when_in(dt, &turn.lang, turn.use_24h))
+                                .unwrap_or(dt_str);
+                            i18n::t(&turn.lang, "counter_proposal", &[("when", &when)])
+                        }
                     }
                 }
+                _ => {
+                    conv.state = collecting_state(&conv);
+                    extracted.message_to_customer.clone()
+                }
+            }
+        }
 
-                let booking = create_booking_from_pending(from_phone, pending);
-                let ics_link = format!(
-                    "/calendar/{}.ics",
-                    booking.id
-                );
-                let reply = format!(
-                    "{}\n\nAdd to calendar: {}",
-                    extracted.message_to_customer,
-                    ics_link,
-                );
+        // Customer confirms cancelling the booking we asked about
+        (ConversationState::Cancelling, Intent::Confirm) => {
+            let booking_id = conv.pending_booking.as_ref().and_then(|p| p.booking_id.clone());
+            conv.state = ConversationState::Idle;
+            conv.pending_booking = None;
 
-                // Save booking to DB
-                {
+            let booking = match booking_id {
+                Some(id) => {
                     let db = state.db.lock().unwrap();
-                    queries::create_booking(&db, &booking)?;
-                    let _ = queries::increment_monthly_bookings(&db);
+                    queries::get_booking_by_id(&db, &id)?
+                        .filter(|b| b.status.holds_slot())
                 }
+                None => None,
+            };
 
-                // Notify owner
-                let owner_msg = format!(
-                    "New booking: {} for {} at {}",
-                    pending.customer_name.as_deref().unwrap_or("Unknown"),
-                    pending
-                        .date_time
-                        .as_deref()
-                        .unwrap_or("TBD"),
-                    from_phone,
-                );
-                notify_owner(state, &owner_msg, Some(from_phone)).await;
-
-                // Reset conversation
-                conv.state = ConversationState::Idle;
-                conv.pending_booking = None;
-
-                reply
-            } else {
-                conv.state = ConversationState::Idle;
-                "I'm sorry, something went wrong. Could you start over?".to_string()
+            match booking {
+                Some(booking) => cancel_for_customer(state, &booking, from_phone, &turn).await?,
+                None => i18n::t(&turn.lang, "no_bookings_to_cancel", &[]),
             }
         }
 
-        // Customer declines a proposed time
-        (ConversationState::Confirming, Intent::Decline) => {
-            conv.state = ConversationState::CollectingInfo;
-            extracted.message_to_customer.clone()
+        // Customer changed their mind about cancelling
+        (ConversationState::Cancelling, Intent::Decline) => {
+            conv.state = ConversationState::Idle;
+            conv.pending_booking = None;
+            i18n::t(&turn.lang, "cancel_declined", &[])
         }
 
-        // Cancel request
+        // Cancel request — confirm which booking before cancelling it
         (_, Intent::Cancel) => {
-            let owner_notification = {
+            let next_booking = {
                 let db = state.db.lock().unwrap();
-                let bookings = queries::get_bookings_for_phone(&db, from_phone)?;
-                if let Some(next_booking) = bookings.into_iter().next() {
-                    queries::update_booking_status(
-                        &db,
-                        &next_booking.id,
-                        &BookingStatus::Cancelled,
-                    )?;
-                    let _ = queries::increment_monthly_cancelled(&db);
-                    Some(format!(
-                        "Cancelled: {} for {} ({}) at {}",
-                        next_booking.customer_name.as_deref().unwrap_or("Unknown"),
-                        next_booking.date_time.format("%Y-%m-%d %H:%M"),
-                        from_phone,
-                        next_booking.id,
-                    ))
-                } else {
-                    None
-                }
+                queries::get_bookings_for_phone(&db, from_phone)?.into_iter().next()
             };
 
-            if let Some(msg) = &owner_notification {
-                notify_owner(state, msg, Some(from_phone)).await;
-            }
-
-            conv.state = ConversationState::Idle;
-            conv.pending_booking = None;
-            if owner_notification.is_some() {
-                extracted.message_to_customer.clone()
-            } else {
-                "I don't see any upcoming bookings to cancel. Would you like to book an appointment instead?".to_string()
+            match next_booking {
+                Some(booking) => {
+                    conv.state = ConversationState::Cancelling;
+                    conv.pending_booking = Some(PendingBooking {
+                        customer_name: booking.customer_name,
+                        date_time: Some(booking.date_time.format("%Y-%m-%d %H:%M").to_string()),
+                        duration_minutes: Some(booking.duration_minutes),
+                        notes: booking.notes,
+                        proposed_slots: vec![],
+                        booking_id: Some(booking.id),
+                        service: booking.service,
+                    });
+                    let when = format_when_in(booking.date_time, &turn.lang, turn.use_24h);
+                    i18n::t(&turn.lang, "cancel_confirm", &[("when", &when)])
+                }
+                None => {
+                    conv.state = ConversationState::Idle;
+                    conv.pending_booking = None;
+                    i18n::t(&turn.lang, "no_bookings_to_cancel", &[])
+                }
             }
         }
 
@@ -298,17 +760,7 @@ pub async fn process_message(
             };
 
             if let Some(next_booking) = bookings.into_iter().next() {
-                // Cancel old booking for rescheduling
-                {
-                    let db = state.db.lock().unwrap();
-                    queries::update_booking_status(
-                        &db,
-                        &next_booking.id,
-                        &BookingStatus::Cancelled,
-                    )?;
-                    let _ = queries::increment_monthly_rescheduled(&db);
-                }
-
+                // The old booking stays until the new time is confirmed
                 // Start new booking flow with existing info
                 conv.pending_booking = Some(PendingBooking {
                     customer_name: next_booking.customer_name.or(extracted.customer_name),
@@ -320,6 +772,9 @@ pub async fn process_message(
                         .duration_minutes
                         .or(Some(next_booking.duration_minutes)),
                     notes: extracted.notes.or(next_booking.notes),
+                    proposed_slots: vec![],
+                    booking_id: Some(next_booking.id.clone()),
+                    service: service.or(next_booking.service),
                 });
 
                 let has_time = extracted.requested_date.is_some()
@@ -327,15 +782,24 @@ pub async fn process_message(
 
                 if has_time {
                     if let Some(ref dt_str) = conv.pending_booking.as_ref().and_then(|p| p.date_time.clone()) {
-                        let dur = conv.pending_booking.as_ref().and_then(|p| p.duration_minutes).unwrap_or(60);
-                        if let Some(validation_err) = try_validate_time(state, dt_str, dur, availability.as_ref()) {
-                            conv.state = ConversationState::CollectingInfo;
-                            return finish_conversation(state, &mut conv, &validation_err).await;
+                        let dur = turn.durations.clamp(conv.pending_booking.as_ref().and_then(|p| p.duration_minutes));
+                        if let Some(err) = try_validate_time(
+                            state,
+                            dt_str,
+                            dur,
+                            availability.as_ref(),
+                            Some(&next_booking.id),
+                            turn.daily_limit(from_phone),
+                        ) {
+                            conv.state = ConversationState::Rescheduling;
+                            let validation_err =
+                                err.render(&turn.rejections, &turn.lang, turn.use_24h);
+                            return finish_conversation(state, &mut conv, &validation_err, &turn).await;
                         }
                     }
                     conv.state = ConversationState::Confirming;
                 } else {
-                    conv.state = ConversationState::CollectingInfo;
+                    conv.state = ConversationState::Rescheduling;
                 }
             } else {
                 conv.state = ConversationState::Idle;
@@ -355,49 +819,227 @@ pub async fn process_message(
         }
     };
 
-    // Append assistant reply
+    finish_conversation(state, &mut conv, &reply, &turn).await
+}
+
+pub fn inject_owner_reply(state: &Arc<AppState>, to_phone: &str, message: &str) -> anyhow::Result<()> {
+    let db = state.db.lock().unwrap();
+    let mut conv = queries::get_conversation(&db, to_phone, false)?
+        .unwrap_or_else(|| new_conversation(to_phone));
     conv.messages.push(ConversationMessage {
         role: "assistant".to_string(),
-        content: reply.clone(),
+        content: message.to_string(),
     });
-
-    // Forward AI reply to owner via dev notification queue
-    if let Ok(mut notifications) = state.dev_notifications.lock() {
-        notifications.push(DevNotification {
-            phone: Some(from_phone.to_string()),
-            kind: DevNotificationKind::AiReply,
-            content: reply.clone(),
-        });
-    }
-    record_inbox_event(state, from_phone, "ai_reply", &reply);
-
-    // Update timestamps
     let now = Utc::now().naive_utc();
     conv.last_activity = now;
     conv.expires_at = now + Duration::minutes(30);
+    queries::save_conversation(&db, &conv)?;
+    Ok(())
+}
+
+/// While the agent is paused, tell a customer once per conversation that the
+/// business will reply soon. Does nothing unless the owner set `paused_autoreply`.
+pub async fn send_paused_autoreply(state: &Arc<AppState>, from_phone: &str) -> anyhow::Result<()> {
+    let autoreply = {
+        let db = state.db.lock().unwrap();
+        queries::get_user(&db, "default")?
+            .and_then(|u| u.ai_preferences)
+            .and_then(|s| AiPreferences::from_json(&s).ok())
+            .map(|p| p.paused_autoreply)
+            .unwrap_or_default()
+    };
+    let autoreply = autoreply.trim();
+    if autoreply.is_empty() {
+        return Ok(());
+    }
 
-    // Save conversation
+    let _phone_guard = lock_phone(state, from_phone).await;
     {
         let db = state.db.lock().unwrap();
+        let mut conv = queries::get_conversation(&db, from_phone, false)?
+            .unwrap_or_else(|| new_conversation(from_phone));
+        if conv.paused_autoreply_sent {
+            return Ok(());
+        }
+        conv.paused_autoreply_sent = true;
+        let now = Utc::now().naive_utc();
+        conv.last_activity = now;
+        conv.expires_at = now + Duration::minutes(30);
         queries::save_conversation(&db, &conv)?;
     }
 
-    Ok(reply)
+    state.messaging.send_message(from_phone, autoreply).await?;
+    record_inbox_event(state, from_phone, "ai_reply", autoreply);
+    let segments = segment_count(autoreply);
+    let db = state.db.lock().unwrap();
+    let _ = queries::increment_monthly_sent(&db, segments);
+    Ok(())
 }
 
-pub fn inject_owner_reply(state: &Arc<AppState>, to_phone: &str, message: &str) -> anyhow::Result<()> {
+/// Tell a customer who just got auto-blocked how else to reach the business,
+/// if the owner wrote a message for it.
+pub async fn send_blocked_autoreply(state: &Arc<AppState>, from_phone: &str) {
+    let autoreply = {
+        let db = state.db.lock().unwrap();
+        queries::get_user(&db, "default")
+            .ok()
+            .flatten()
+            .and_then(|u| u.ai_preferences)
+            .and_then(|s| AiPreferences::from_json(&s).ok())
+            .map(|p| p.blocked_autoreply)
+            .unwrap_or_default()
+    };
+    let autoreply = autoreply.trim();
+    if autoreply.is_empty() {
+        return;
+    }
+    if let Err(e) = quiet_hours::send_or_queue(state, from_phone, autoreply).await {
+        tracing::error!(error = %e, "failed to send auto-block notice");
+        return;
+    }
+    record_inbox_event(state, from_phone, "ai_reply", autoreply);
+}
+
+/// Nudge customers who went quiet before confirming a booking, once per
+/// confirmation. The conversation keeps its expiry, so it still lapses if they
+/// never answer. Returns how many were sent.
+pub async fn reprompt_stale_confirmations(state: &Arc<AppState>) -> usize {
+    let minutes = state.config.confirm_reprompt_minutes;
+    if minutes == 0 {
+        return 0;
+    }
+    let idle_since = Utc::now().naive_utc() - Duration::minutes(minutes);
+    let (phones, lang) = {
+        let db = state.db.lock().unwrap();
+        let phones = match queries::get_stale_confirming(&db, &idle_since) {
+            Ok(phones) => phones,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to load stale confirmations");
+                return 0;
+            }
+        };
+        (phones, reply_language(&db))
+    };
+    let reply = i18n::t(&lang, "confirm_reprompt", &[]);
+
+    let mut sent = 0;
+    for phone in phones {
+        let _phone_guard = lock_phone(state, &phone).await;
+        {
+            let db = state.db.lock().unwrap();
+            // The customer may have answered since the query
+            let Ok(Some(mut conv)) = queries::get_conversation(&db, &phone, false) else {
+                continue;
+            };
+            if conv.reprompt_sent
+                || conv.state != ConversationState::Confirming
+                || conv.last_activity > idle_since
+                || queries::is_opted_out(&db, &phone).unwrap_or(false)
+            {
+                continue;
+            }
+            conv.reprompt_sent = true;
+            conv.messages.push(ConversationMessage {
+                role: "assistant".to_string(),
+                content: reply.clone(),
+            });
+            if let Err(e) = queries::save_conversation(&db, &conv) {
+                tracing::error!(error = %e, phone = %mask_phone(&phone), "failed to save re-prompt");
+                continue;
+            }
+        }
+        if let Err(e) = quiet_hours::send_or_queue(state, &phone, &reply).await {
+            tracing::error!(error = %e, phone = %mask_phone(&phone), "failed to send re-prompt");
+            continue;
+        }
+        record_inbox_event(state, &phone, "ai_reply", &reply);
+        sent += 1;
+    }
+    sent
+}
+
+/// Note an MMS attachment in the customer's inbox thread, one link per line.
+pub fn record_media(state: &Arc<AppState>, from_phone: &str, media_urls: &[String]) {
+    let content = format!("Sent media:\n{}", media_urls.join("\n"));
+    record_inbox_event(state, from_phone, "customer_message", &content);
+}
+
+/// Reply to a media-only message, which the engine can't act on.
+pub async fn ask_for_text(state: &Arc<AppState>, from_phone: &str) {
+    let lang = {
+        let db = state.db.lock().unwrap();
+        reply_language(&db)
+    };
+    let reply = i18n::t(&lang, "media_needs_text", &[]);
+    if let Err(e) = state.messaging.send_message(from_phone, &reply).await {
+        tracing::error!(error = %e, "failed to ask for a text description");
+        return;
+    }
+    record_inbox_event(state, from_phone, "ai_reply", &reply);
+    let segments = segment_count(&reply);
     let db = state.db.lock().unwrap();
-    let mut conv = queries::get_conversation(&db, to_phone)?
-        .unwrap_or_else(|| new_conversation(to_phone));
-    conv.messages.push(ConversationMessage {
-        role: "assistant".to_string(),
-        content: message.to_string(),
-    });
-    let now = Utc::now().naive_utc();
-    conv.last_activity = now;
-    conv.expires_at = now + Duration::minutes(30);
-    queries::save_conversation(&db, &conv)?;
-    Ok(())
+    let _ = queries::increment_monthly_sent(&db, segments);
+}
+
+/// Turn away a message over `MAX_INBOUND_BODY` without storing it or
+/// spending an LLM call on it.
+pub async fn reject_long_message(state: &Arc<AppState>, from_phone: &str, length: usize) {
+    let lang = {
+        let db = state.db.lock().unwrap();
+        reply_language(&db)
+    };
+    let max = state.config.max_inbound_body;
+    record_inbox_event(
+        state,
+        from_phone,
+        "system",
+        &format!("Ignored a {length}-character message (limit {max})"),
+    );
+    let reply = i18n::t(&lang, "message_too_long", &[("max", &max.to_string())]);
+    if let Err(e) = quiet_hours::send_or_queue(state, from_phone, &reply).await {
+        tracing::error!(error = %e, "failed to reject long message");
+        return;
+    }
+    record_inbox_event(state, from_phone, "ai_reply", &reply);
+}
+
+impl Turn {
+    /// The phone to hold to one booking per day, if the business limits it.
+    fn daily_limit<'a>(&self, phone: &'a str) -> Option<&'a str> {
+        self.one_booking_per_day.then_some(phone)
+    }
+}
+
+/// Held for the length of one turn on a phone's conversation. Dropping it
+/// also drops the phone's map entry unless another turn is waiting on it.
+struct PhoneGuard<'a> {
+    state: &'a AppState,
+    phone: String,
+    guard: Option<tokio::sync::OwnedMutexGuard<()>>,
+}
+
+impl Drop for PhoneGuard<'_> {
+    fn drop(&mut self) {
+        self.guard.take();
+        // `entry()` clones under the same shard lock, so a waiter that just
+        // grabbed the mutex always shows up in the count
+        self.state
+            .conversation_locks
+            .remove_if(&self.phone, |_, lock| Arc::strong_count(lock) == 1);
+    }
+}
+
+async fn lock_phone<'a>(state: &'a AppState, phone: &str) -> PhoneGuard<'a> {
+    let lock = state
+        .conversation_locks
+        .entry(phone.to_string())
+        .or_default()
+        .clone();
+    PhoneGuard {
+        state,
+        phone: phone.to_string(),
+        guard: Some(lock.lock_owned().await),
+    }
 }
 
 fn new_conversation(phone: &str) -> Conversation {
@@ -407,11 +1049,30 @@ fn new_conversation(phone: &str) -> Conversation {
         messages: vec![],
         state: ConversationState::Idle,
         pending_booking: None,
+        paused_autoreply_sent: false,
+        reprompt_sent: false,
+        summary: None,
         last_activity: now,
         expires_at: now + Duration::minutes(30),
     }
 }
 
+/// The pending time with whatever date or time the customer's latest message
+/// names swapped in. `None` if it names neither.
+fn with_requested_time(pending: &PendingBooking, extracted: &ExtractedIntent) -> Option<String> {
+    if extracted.requested_date.is_none() && extracted.requested_time.is_none() {
+        return None;
+    }
+    let (old_date, old_time) = pending
+        .date_time
+        .as_deref()
+        .and_then(|dt| dt.split_once(' '))
+        .unzip();
+    let date = extracted.requested_date.as_deref().or(old_date)?;
+    let time = extracted.requested_time.as_deref().or(old_time)?;
+    Some(format!("{date} {time}"))
+}
+
 fn make_datetime_string(date: Option<&str>, time: Option<&str>) -> Option<String> {
     match (date, time) {
         (Some(d), Some(t)) => Some(format!("{d} {t}")),
@@ -421,7 +1082,11 @@ fn make_datetime_string(date: Option<&str>, time: Option<&str>) -> Option<String
     }
 }
 
-fn create_booking_from_pending(phone: &str, pending: &PendingBooking) -> Booking {
+fn create_booking_from_pending(
+    phone: &str,
+    pending: &PendingBooking,
+    durations: &DurationBounds,
+) -> Booking {
     let now = Utc::now().naive_utc();
     let date_time = pending
         .date_time
@@ -438,85 +1103,475 @@ fn create_booking_from_pending(phone: &str, pending: &PendingBooking) -> Booking
         customer_phone: phone.to_string(),
         customer_name: pending.customer_name.clone(),
         date_time,
-        duration_minutes: pending.duration_minutes.unwrap_or(60),
+        duration_minutes: durations.clamp(pending.duration_minutes),
         status: BookingStatus::Confirmed,
         notes: pending.notes.clone(),
+        service: pending.service.clone(),
         created_at: now,
         updated_at: now,
     }
 }
 
+/// Create the booking for a confirmed pending request and reset the conversation.
+/// Returns the reply for the customer (a validation error if the time is no longer free).
+async fn book_pending(
+    state: &Arc<AppState>,
+    conv: &mut Conversation,
+    from_phone: &str,
+    availability: Option<&Availability>,
+    turn: &Turn,
+    message_to_customer: &str,
+) -> anyhow::Result<String> {
+    let (rejections, lang) = (&turn.rejections, turn.lang.as_str());
+    let Some(pending) = conv.pending_booking.clone() else {
+        conv.state = ConversationState::Idle;
+        return Ok(i18n::t(lang, "something_went_wrong", &[]));
+    };
+
+    // Slots were offered but none picked yet
+    if pending.date_time.is_none() && !pending.proposed_slots.is_empty() {
+        let slots: Vec<NaiveDateTime> = pending
+            .proposed_slots
+            .iter()
+            .filter_map(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").ok())
+            .collect();
+        let slots = describe_slots(&slots, lang, turn.use_24h);
+        return Ok(i18n::t(lang, "which_slot", &[("slots", &slots)]));
+    }
+
+    // The LLM's wording may not match what gets stored, so the confirmation
+    // always restates the booking itself
+    let booking = create_booking_from_pending(from_phone, &pending, &turn.durations);
+    let details = reminders::render(
+        &turn.confirmation,
+        &booking,
+        &turn.business,
+        &turn.lang,
+        turn.use_24h,
+    );
+    let confirmation = if message_to_customer.is_empty() {
+        details
+    } else {
+        format!("{message_to_customer}\n{details}")
+    };
+
+    // Say whether the slot is still free, but book nothing
+    if turn.dry_run {
+        let rejected = pending.date_time.as_deref().and_then(|dt| {
+            let db = state.db.lock().unwrap();
+            check_time(
+                &db,
+                dt,
+                turn.durations.clamp(pending.duration_minutes),
+                availability,
+                pending.booking_id.as_deref(),
+                turn.daily_limit(from_phone),
+            )
+        });
+        if let Some(err) = rejected {
+            conv.state = collecting_state(conv);
+            return Ok(err.render(rejections, lang, turn.use_24h));
+        }
+        conv.state = ConversationState::Idle;
+        conv.pending_booking = None;
+        return Ok(confirmation);
+    }
+
+    // Re-check the slot and insert in one transaction so racing confirmations
+    // can't double-book. A reschedule retires the old booking in the same step.
+    let outcome = {
+        let booking = booking.clone();
+        let replaces = pending.booking_id.clone();
+        let availability = availability.cloned();
+        let one_per_day = turn.one_booking_per_day;
+        pool::with_db(&state.db, move |conn| {
+            create_booking_if_free(
+                conn,
+                &booking,
+                replaces.as_deref(),
+                availability.as_ref(),
+                BookingActor::Customer,
+                one_per_day,
+            )
+        })
+        .await?
+    };
+    if let Err(e) = outcome {
+        conv.state = collecting_state(conv);
+        return Ok(e.render(rejections, lang, turn.use_24h));
+    }
+
+    // Issue a link that lets the customer move it later. Links are only
+    // texted when there's a public URL to point them at.
+    let public_url = state.config.public_url.trim_end_matches('/');
+    let reschedule_token = {
+        let (booking_id, date_time) = (booking.id.clone(), booking.date_time);
+        let rescheduled = pending.booking_id.is_some();
+        let issue_link = !public_url.is_empty();
+        pool::with_db(&state.db, move |conn| {
+            if rescheduled {
+                let _ = queries::increment_monthly_rescheduled(conn);
+            }
+            let _ = queries::increment_monthly_bookings(conn);
+            issue_link
+                .then(|| {
+                    queries::create_booking_link(
+                        conn,
+                        &booking_id,
+                        BookingLink::RESCHEDULE,
+                        &date_time,
+                    )
+                })
+                .transpose()
+        })
+        .await?
+    };
+    let reply = match reschedule_token {
+        Some(token) => {
+            let cancel = signing::cancel_path(&state.config.admin_token, &booking.id)?;
+            let links = i18n::t(
+                lang,
+                "booking_links",
+                &[
+                    ("calendar", &format!("{public_url}/calendar/{}.ics", booking.id)),
+                    ("reschedule", &format!("{public_url}/reschedule/{token}")),
+                    ("cancel", &format!("{public_url}{cancel}")),
+                ],
+            );
+            format!("{confirmation}\n\n{links}")
+        }
+        None => confirmation,
+    };
+
+    // Notify owner
+    let owner_msg = format!(
+        "{}: {} for {} at {}",
+        if pending.booking_id.is_some() { "Rescheduled" } else { "New booking" },
+        pending.customer_name.as_deref().unwrap_or("Unknown"),
+        format_when(booking.date_time, turn.use_24h),
+        from_phone,
+    );
+    notify_owner(state, OwnerEvent::Booking, &owner_msg, Some(from_phone)).await;
+
+    // Reset conversation
+    conv.state = ConversationState::Idle;
+    conv.pending_booking = None;
+
+    Ok(reply)
+}
+
+/// Where to go back to when a proposed time falls through: a reschedule keeps
+/// collecting a new time for the same booking.
+fn collecting_state(conv: &Conversation) -> ConversationState {
+    let rescheduling = conv
+        .pending_booking
+        .as_ref()
+        .is_some_and(|p| p.booking_id.is_some());
+    if rescheduling {
+        ConversationState::Rescheduling
+    } else {
+        ConversationState::CollectingInfo
+    }
+}
+
+/// "12:00 PM, 2:00 PM, or 3:00 PM" ("12:00, 14:00, or 15:00" on a 24-hour clock)
+fn describe_slots(slots: &[NaiveDateTime], lang: &str, use_24h: bool) -> String {
+    let times: Vec<String> = slots.iter().map(|s| format_time(*s, use_24h)).collect();
+    match times.as_slice() {
+        [] => String::new(),
+        [only] => only.clone(),
+        [a, b] => i18n::t(lang, "slots_pair", &[("first", a), ("second", b)]),
+        [rest @ .., last] => {
+            i18n::t(lang, "slots_last", &[("rest", &rest.join(", ")), ("last", last)])
+        }
+    }
+}
+
 fn try_validate_time(
     state: &Arc<AppState>,
     dt_str: &str,
     duration_minutes: i32,
     availability: Option<&Availability>,
-) -> Option<String> {
+    moving_booking: Option<&str>,
+    daily_limit: Option<&str>,
+) -> Option<SchedulingError> {
+    let db = state.db.lock().unwrap();
+    check_time(&db, dt_str, duration_minutes, availability, moving_booking, daily_limit)
+}
+
+/// Why the scheduler would reject `dt_str`, if it would. Unparseable times
+/// pass through so the booking step reports them instead. `daily_limit` is
+/// the customer to hold to one booking per day, when the business asks for it.
+fn check_time(
+    conn: &Connection,
+    dt_str: &str,
+    duration_minutes: i32,
+    availability: Option<&Availability>,
+    moving_booking: Option<&str>,
+    daily_limit: Option<&str>,
+) -> Option<SchedulingError> {
     let dt = chrono::NaiveDateTime::parse_from_str(dt_str, "%Y-%m-%d %H:%M")
         .or_else(|_| chrono::NaiveDateTime::parse_from_str(dt_str, "%Y-%m-%d %H:%M:%S"))
         .ok()?;
 
-    let db = state.db.lock().unwrap();
-    match validate_booking_time(&db, &dt, duration_minutes, availability) {
-        Ok(()) => None,
-        Err(e) => Some(e.to_string()),
+    let result = match moving_booking {
+        Some(id) => validate_reschedule_time(conn, id, &dt, duration_minutes, availability),
+        None => validate_booking_time(conn, &dt, duration_minutes, availability),
+    };
+    let result = result.and_then(|()| match daily_limit {
+        Some(phone) => check_daily_limit(conn, phone, &dt.date(), moving_booking),
+        None => Ok(()),
+    });
+    result.err()
+}
+
+/// Customer-facing reply for a time the scheduler rejected. When the business
+/// offers a waitlist, a new booking that lost to a conflict joins it instead.
+fn rejection_reply(
+    state: &Arc<AppState>,
+    phone: &str,
+    err: &SchedulingError,
+    pending: &PendingBooking,
+    offer_waitlist: bool,
+    turn: &Turn,
+) -> String {
+    let lang = turn.lang.as_str();
+    let waitlistable = offer_waitlist
+        && pending.booking_id.is_none()
+        && matches!(err, SchedulingError::Conflict);
+    let wanted = pending.date_time.as_deref().and_then(|dt| {
+        NaiveDateTime::parse_from_str(dt, "%Y-%m-%d %H:%M")
+            .or_else(|_| NaiveDateTime::parse_from_str(dt, "%Y-%m-%d %H:%M:%S"))
+            .ok()
+    });
+    if let Some(dt) = wanted.filter(|_| waitlistable) {
+        if turn.dry_run {
+            return i18n::t(lang, "waitlisted", &[]);
+        }
+        let duration = turn.durations.clamp(pending.duration_minutes);
+        let db = state.db.lock().unwrap();
+        match queries::add_to_waitlist(&db, phone, &dt, duration) {
+            Ok(()) => return i18n::t(lang, "waitlisted", &[]),
+            Err(e) => tracing::error!(error = %e, "failed to add to waitlist"),
+        }
     }
+    err.render(&turn.rejections, lang, turn.use_24h)
 }
 
+/// Cancel `booking` at the customer's request, tell the owner and offer the
+/// freed slot to the waitlist. Returns the reply.
+async fn cancel_for_customer(
+    state: &Arc<AppState>,
+    booking: &Booking,
+    from_phone: &str,
+    turn: &Turn,
+) -> anyhow::Result<String> {
+    if !turn.dry_run {
+        {
+            let db = state.db.lock().unwrap();
+            queries::update_booking_status(
+                &db,
+                &booking.id,
+                &BookingStatus::Cancelled,
+                BookingActor::Customer,
+            )?;
+            let _ = queries::increment_monthly_cancelled(&db);
+        }
+        let owner_msg = format!(
+            "Cancelled: {} for {} ({}) at {}",
+            booking.customer_name.as_deref().unwrap_or("Unknown"),
+            format_when(booking.date_time, turn.use_24h),
+            from_phone,
+            booking.id,
+        );
+        notify_owner(state, OwnerEvent::Cancel, &owner_msg, Some(from_phone)).await;
+        offer_freed_slot(state, booking).await;
+    }
+    let when = format_when_in(booking.date_time, &turn.lang, turn.use_24h);
+    Ok(i18n::t(&turn.lang, "cancelled", &[("when", &when)]))
+}
+
+/// Act on a `C`/`X` reply to `booking`'s reminder.
+async fn answer_reminder(
+    state: &Arc<AppState>,
+    booking: &Booking,
+    answer: ReminderReply,
+    from_phone: &str,
+    turn: &Turn,
+) -> anyhow::Result<String> {
+    match answer {
+        ReminderReply::Confirm => {
+            if booking.status == BookingStatus::Pending && !turn.dry_run {
+                let db = state.db.lock().unwrap();
+                queries::update_booking_status(
+                    &db,
+                    &booking.id,
+                    &BookingStatus::Confirmed,
+                    BookingActor::Customer,
+                )?;
+            }
+            let when = format_when_in(booking.date_time, &turn.lang, turn.use_24h);
+            Ok(i18n::t(&turn.lang, "reminder_confirmed", &[("when", &when)]))
+        }
+        ReminderReply::Cancel => cancel_for_customer(state, booking, from_phone, turn).await,
+    }
+}
+
+/// Run a customer's message through the engine and text back the reply,
+/// or a generic apology if processing fails.
+pub async fn reply_to_customer(state: &Arc<AppState>, from_phone: &str, message: &str) {
+    // A debounced batch can fire after the sender texted STOP
+    if pool::is_opted_out(&state.db, from_phone).await.unwrap_or(false) {
+        tracing::info!(from = %mask_phone(from_phone), "opted out, not replying");
+        return;
+    }
+    // The owner took this thread over; keep the message for the inbox only
+    if !pool::is_ai_enabled(&state.db, from_phone).await.unwrap_or(true) {
+        tracing::info!(from = %mask_phone(from_phone), "AI off for this thread, not replying");
+        record_inbox_event(state, from_phone, "customer_message", message);
+        return;
+    }
+    if pool::needs_human(&state.db, from_phone).await.unwrap_or(false) {
+        tracing::info!(from = %mask_phone(from_phone), "waiting for the owner, not replying");
+        record_inbox_event(state, from_phone, "customer_message", message);
+        return;
+    }
+    match process_message(state, from_phone, message).await {
+        Ok(reply) => {
+            if let Err(e) = quiet_hours::send_or_queue(state, from_phone, &reply).await {
+                tracing::error!(error = %e, "failed to send reply");
+            }
+        }
+        Err(e) => {
+            tracing::error!(error = %e, from = %mask_phone(from_phone), "conversation processing failed");
+            let lang = {
+                let db = state.db.lock().unwrap();
+                reply_language(&db)
+            };
+            let fallback = i18n::t(&lang, "something_went_wrong", &[]);
+            let _ = quiet_hours::send_or_queue(state, from_phone, &fallback).await;
+        }
+    }
+}
+
+/// The language customer replies are written in, from the owner's settings.
+pub fn reply_language(conn: &Connection) -> String {
+    queries::get_user(conn, "default")
+        .ok()
+        .flatten()
+        .map(|u| u.language)
+        .unwrap_or_else(|| i18n::DEFAULT_LANGUAGE.to_string())
+}
+
+/// The owner's first-touch greeting, followed by an automated-assistant notice
+/// unless they turned AI disclosure off. Empty when no greeting is set.
+fn welcome_text(prefs: Option<&AiPreferences>, lang: &str) -> String {
+    let Some(prefs) = prefs.filter(|p| !p.welcome_message.trim().is_empty()) else {
+        return String::new();
+    };
+    let welcome = prefs.welcome_message.trim();
+    if prefs.identity.disclose_ai {
+        format!("{welcome} {}", i18n::t(lang, "ai_disclosure", &[]))
+    } else {
+        welcome.to_string()
+    }
+}
+
+/// Once the stored history passes `SUMMARIZE_AFTER` messages, fold all but the
+/// most recent into the conversation summary. The summary then rides along in
+/// every prompt, and the LLM is only asked again after another batch builds up.
+async fn summarize_older_messages(state: &Arc<AppState>, conv: &mut Conversation) {
+    let threshold = state.config.summarize_after;
+    if threshold == 0 || conv.messages.len() <= threshold || state.llm_breaker.is_open() {
+        return;
+    }
+    let older = conv.messages.len() - state.config.max_history_messages.min(threshold);
+    let summary = {
+        let Ok(_permit) = state.llm_permits.acquire().await else {
+            return;
+        };
+        summarize(state.llm.as_ref(), conv.summary.as_deref(), &conv.messages[..older]).await
+    };
+    match summary {
+        Ok(summary) => {
+            conv.summary = Some(summary);
+            conv.messages.drain(..older);
+        }
+        Err(e) => {
+            tracing::warn!(error = %e, phone = %mask_phone(&conv.phone), "failed to summarize conversation, keeping full history");
+        }
+    }
+}
+
+/// Record and save the assistant's reply. A non-empty welcome is prepended,
+/// which only happens on a brand-new conversation's first reply. A dry run
+/// records and saves nothing.
 async fn finish_conversation(
     state: &Arc<AppState>,
     conv: &mut Conversation,
     reply: &str,
-) -> anyhow::Result<String> {
+    turn: &Turn,
+) -> anyhow::Result<MessageOutcome> {
+    let reply = if turn.welcome.is_empty() {
+        reply.to_string()
+    } else {
+        format!("{}\n\n{reply}", turn.welcome)
+    };
     conv.messages.push(ConversationMessage {
         role: "assistant".to_string(),
-        content: reply.to_string(),
+        content: reply.clone(),
     });
-    // Forward AI reply to owner via dev notification queue
-    if let Ok(mut notifications) = state.dev_notifications.lock() {
-        notifications.push(DevNotification {
-            phone: Some(conv.phone.clone()),
-            kind: DevNotificationKind::AiReply,
-            content: reply.to_string(),
-        });
-    }
-    record_inbox_event(state, &conv.phone, "ai_reply", reply);
     let now = Utc::now().naive_utc();
     conv.last_activity = now;
     conv.expires_at = now + Duration::minutes(30);
-    {
+    if !turn.dry_run {
+        summarize_older_messages(state, conv).await;
+        // Forward AI reply to owner via dev notification queue
+        state.dev_notifications.push(DevNotification {
+            phone: Some(conv.phone.clone()),
+            kind: DevNotificationKind::AiReply,
+            content: reply.clone(),
+        });
+        record_inbox_event(state, &conv.phone, "ai_reply", &reply);
         let db = state.db.lock().unwrap();
         queries::save_conversation(&db, conv)?;
     }
-    Ok(reply.to_string())
+    Ok(MessageOutcome {
+        reply,
+        intent: turn.intent.clone(),
+        previous_state: turn.previous_state.clone(),
+        state: conv.state.clone(),
+        pending_booking: conv.pending_booking.clone(),
+    })
 }
 
-async fn notify_owner(state: &Arc<AppState>, message: &str, phone: Option<&str>) {
-    // Always push to dev notification queue
-    if let Ok(mut notifications) = state.dev_notifications.lock() {
-        notifications.push(DevNotification {
-            phone: phone.map(|p| p.to_string()),
-            kind: DevNotificationKind::System,
-            content: message.to_string(),
-        });
-    }
-    if let Some(p) = phone {
-        record_inbox_event(state, p, "system", message);
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
 
-    if state.config.owner_phone.is_empty() {
-        tracing::warn!("owner_phone not configured, skipping notification");
-        return;
-    }
+    #[test]
+    fn test_check_time_reports_conflict_variant() {
+        let conn = db::init_db(":memory:").unwrap();
+        let now = Utc::now().naive_utc();
+        let booking = Booking {
+            id: "existing-1".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: None,
+            date_time: NaiveDateTime::parse_from_str("2025-06-16 10:00", "%Y-%m-%d %H:%M").unwrap(),
+            duration_minutes: 60,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
 
-    if let Err(e) = state
-        .messaging
-        .send_message(&state.config.owner_phone, message)
-        .await
-    {
-        tracing::error!(error = %e, "failed to notify owner");
-    } else {
-        let db = state.db.lock().unwrap();
-        let _ = queries::increment_monthly_sent(&db);
+        let err = check_time(&conn, "2025-06-16 10:30", 60, None, None, None);
+        assert!(matches!(err, Some(SchedulingError::Conflict)));
+        assert!(check_time(&conn, "2025-06-16 11:00", 60, None, None, None).is_none());
+        // Moving the booking itself doesn't conflict with its old slot
+        assert!(check_time(&conn, "2025-06-16 10:30", 60, None, Some("existing-1"), None).is_none());
     }
 }
@@ -0,0 +1,375 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// What to do when a customer gives a date without a year ("June 15").
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum YearlessDatePolicy {
+    /// Use the next occurrence: a date that has already passed this year
+    /// resolves to the same day next year. Today counts as upcoming.
+    RollForward,
+    /// Always use the current year, even if the date is in the past.
+    CurrentYear,
+}
+
+impl YearlessDatePolicy {
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "current_year" => YearlessDatePolicy::CurrentYear,
+            _ => YearlessDatePolicy::RollForward,
+        }
+    }
+}
+
+const YEARLESS_FORMATS: [&str; 6] = ["%m-%d", "%m/%d", "%B %d", "%b %d", "%d %B", "%d %b"];
+
+const FULL_DATE_FORMATS: [&str; 8] = [
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%m/%d/%Y",
+    "%B %d %Y",
+    "%b %d %Y",
+    "%d %B %Y",
+    "%d %b %Y",
+    "%A %B %d %Y",
+];
+
+const TIME_FORMATS: [&str; 4] = ["%H:%M", "%H:%M:%S", "%I:%M%p", "%I:%M:%S%p"];
+
+/// Resolve a date string to a calendar date. Dates with a year are taken
+/// as-is; year-less dates are resolved against `today` according to `policy`.
+pub fn resolve_date(raw: &str, today: NaiveDate, policy: YearlessDatePolicy) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    if let Some(date) = parse_full_date(raw) {
+        return Some(date);
+    }
+
+    let cleaned = strip_ordinal(raw);
+    let years = match policy {
+        // Look a few years ahead so Feb 29 lands on the next leap year
+        YearlessDatePolicy::RollForward => today.year()..=today.year() + 8,
+        YearlessDatePolicy::CurrentYear => today.year()..=today.year(),
+    };
+    for year in years {
+        let candidate = YEARLESS_FORMATS.iter().find_map(|fmt| {
+            NaiveDate::parse_from_str(&format!("{year} {cleaned}"), &format!("%Y {fmt}")).ok()
+        });
+        match candidate {
+            Some(date) if policy == YearlessDatePolicy::CurrentYear || date >= today => {
+                return Some(date);
+            }
+            _ => continue,
+        }
+    }
+    None
+}
+
+/// A date that names its year, in the forms an LLM tends to write it:
+/// "2025-06-15", an ISO timestamp, "June 15, 2025", "15th June 2025",
+/// "06/15/2025" (US order). `None` for anything else, including year-less dates.
+pub fn parse_full_date(raw: &str) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    let date_part = raw.split_once('T').map_or(raw, |(date, _)| date);
+    if let Ok(date) = NaiveDate::parse_from_str(date_part, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let cleaned = strip_ordinal(&raw.replace(',', " "));
+    FULL_DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(&cleaned, fmt).ok())
+}
+
+/// A time of day as `HH:MM`, from "14:00", "14:00:00", "2:00 PM", "2pm" or
+/// "2 p.m.". `None` if it isn't one of those.
+pub fn normalize_time(raw: &str) -> Option<String> {
+    let mut compact: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '.')
+        .collect::<String>()
+        .to_ascii_uppercase();
+    // chrono needs the minutes: "2PM" → "2:00PM"
+    if !compact.contains(':') && (compact.ends_with("AM") || compact.ends_with("PM")) {
+        compact.insert_str(compact.len() - 2, ":00");
+    }
+    TIME_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(&compact, fmt).ok())
+        .map(|t| t.format("%H:%M").to_string())
+}
+
+/// Weekday names from Monday, and month names from January, for the
+/// non-English reply languages. chrono only knows English ones.
+const ES_WEEKDAYS: [&str; 7] = [
+    "lunes",
+    "martes",
+    "miércoles",
+    "jueves",
+    "viernes",
+    "sábado",
+    "domingo",
+];
+const ES_MONTHS: [&str; 12] = [
+    "enero",
+    "febrero",
+    "marzo",
+    "abril",
+    "mayo",
+    "junio",
+    "julio",
+    "agosto",
+    "septiembre",
+    "octubre",
+    "noviembre",
+    "diciembre",
+];
+const FR_WEEKDAYS: [&str; 7] = [
+    "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+];
+const FR_MONTHS: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+
+/// A booking time as the owner reads it: "Monday, June 16 at 2:00 PM", or
+/// "Monday, June 16 at 14:00" on a 24-hour clock. Booking times are already
+/// the business's local time, so there's nothing to convert.
+pub fn format_when(dt: NaiveDateTime, use_24h: bool) -> String {
+    format_when_in(dt, "en", use_24h)
+}
+
+/// `format_when` in the customer's reply language: "lunes 16 de junio a las
+/// 14:00", "lundi 16 juin à 14:00". Other languages get English.
+pub fn format_when_in(dt: NaiveDateTime, lang: &str, use_24h: bool) -> String {
+    let date = format_date_in(dt.date(), lang);
+    let time = format_time(dt, use_24h);
+    match lang {
+        "es" => {
+            // "a la 1:00", but "a las 2:00"
+            let one = if use_24h {
+                dt.hour() == 1
+            } else {
+                dt.hour() % 12 == 1
+            };
+            let article = if one { "la" } else { "las" };
+            format!("{date} a {article} {time}")
+        }
+        "fr" => format!("{date} à {time}"),
+        _ => format!("{date} at {time}"),
+    }
+}
+
+/// A day in the customer's reply language: "Monday, June 16", "lunes 16 de
+/// junio", "lundi 16 juin". Other languages get English.
+pub fn format_date_in(date: NaiveDate, lang: &str) -> String {
+    let weekday = date.weekday().num_days_from_monday() as usize;
+    let month = date.month0() as usize;
+    match lang {
+        "es" => format!(
+            "{} {} de {}",
+            ES_WEEKDAYS[weekday],
+            date.day(),
+            ES_MONTHS[month]
+        ),
+        "fr" if date.day() == 1 => format!("{} 1er {}", FR_WEEKDAYS[weekday], FR_MONTHS[month]),
+        "fr" => format!(
+            "{} {} {}",
+            FR_WEEKDAYS[weekday],
+            date.day(),
+            FR_MONTHS[month]
+        ),
+        _ => date.format("%A, %B %-d").to_string(),
+    }
+}
+
+/// Just the clock time: "2:00 PM", or "14:00" on a 24-hour clock.
+pub fn format_time(dt: NaiveDateTime, use_24h: bool) -> String {
+    dt.format(if use_24h { "%H:%M" } else { "%-I:%M %p" })
+        .to_string()
+}
+
+/// "June 15th" → "June 15"
+fn strip_ordinal(s: &str) -> String {
+    s.split_whitespace()
+        .map(|word| {
+            let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+            if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                digits
+            } else {
+                word
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_format_when_around_midnight_and_noon() {
+        assert_eq!(
+            format_when(at("2025-06-16 00:00"), false),
+            "Monday, June 16 at 12:00 AM"
+        );
+        assert_eq!(
+            format_when(at("2025-06-16 12:00"), false),
+            "Monday, June 16 at 12:00 PM"
+        );
+        assert_eq!(
+            format_when(at("2025-06-16 14:05"), false),
+            "Monday, June 16 at 2:05 PM"
+        );
+        assert_eq!(
+            format_when(at("2025-06-16 00:00"), true),
+            "Monday, June 16 at 00:00"
+        );
+        assert_eq!(
+            format_when(at("2025-06-16 09:30"), true),
+            "Monday, June 16 at 09:30"
+        );
+    }
+
+    #[test]
+    fn test_format_when_in_spanish_and_french() {
+        assert_eq!(
+            format_when_in(at("2025-06-16 14:00"), "es", true),
+            "lunes 16 de junio a las 14:00"
+        );
+        assert_eq!(
+            format_when_in(at("2025-06-16 13:30"), "es", false),
+            "lunes 16 de junio a la 1:30 PM"
+        );
+        assert_eq!(
+            format_when_in(at("2025-08-17 09:00"), "fr", true),
+            "dimanche 17 août à 09:00"
+        );
+        assert_eq!(
+            format_date_in(date("2025-02-01"), "fr"),
+            "samedi 1er février"
+        );
+        assert_eq!(
+            format_when_in(at("2025-06-16 14:00"), "de", false),
+            format_when(at("2025-06-16 14:00"), false)
+        );
+    }
+
+    #[test]
+    fn test_format_when_weekday_names() {
+        let week: Vec<String> = (15..=21)
+            .map(|day| format_when(at(&format!("2025-06-{day} 10:00")), false))
+            .collect();
+        assert_eq!(
+            week.iter()
+                .map(|s| s.split(',').next().unwrap())
+                .collect::<Vec<_>>(),
+            [
+                "Sunday",
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_time() {
+        assert_eq!(normalize_time("2:00 PM").as_deref(), Some("14:00"));
+        assert_eq!(normalize_time("2pm").as_deref(), Some("14:00"));
+        assert_eq!(normalize_time("9:30 a.m.").as_deref(), Some("09:30"));
+        assert_eq!(normalize_time("12:00 AM").as_deref(), Some("00:00"));
+        assert_eq!(normalize_time("12 pm").as_deref(), Some("12:00"));
+        assert_eq!(normalize_time("14:00:00").as_deref(), Some("14:00"));
+        assert_eq!(normalize_time("9:05").as_deref(), Some("09:05"));
+        assert_eq!(normalize_time("afternoon"), None);
+        assert_eq!(normalize_time("25:00"), None);
+    }
+
+    #[test]
+    fn test_parse_full_date() {
+        for raw in [
+            "June 15, 2025",
+            "June 15 2025",
+            "Jun 15th, 2025",
+            "15 June 2025",
+            "Sunday, June 15, 2025",
+            "06/15/2025",
+            "2025/06/15",
+            "2025-06-15T14:00:00",
+        ] {
+            assert_eq!(parse_full_date(raw), Some(date("2025-06-15")), "{raw}");
+        }
+        assert_eq!(parse_full_date("June 15"), None);
+        assert_eq!(parse_full_date("06-15"), None);
+    }
+
+    #[test]
+    fn test_full_date_taken_as_is() {
+        let today = date("2025-07-10");
+        let resolved = resolve_date("2025-06-15", today, YearlessDatePolicy::RollForward);
+        assert_eq!(resolved, Some(date("2025-06-15")));
+    }
+
+    #[test]
+    fn test_past_yearless_date_rolls_forward() {
+        let today = date("2025-07-10");
+        assert_eq!(
+            resolve_date("June 15", today, YearlessDatePolicy::RollForward),
+            Some(date("2026-06-15"))
+        );
+        assert_eq!(
+            resolve_date("06-15", today, YearlessDatePolicy::RollForward),
+            Some(date("2026-06-15"))
+        );
+    }
+
+    #[test]
+    fn test_later_yearless_date_stays_in_current_year() {
+        let today = date("2025-07-10");
+        assert_eq!(
+            resolve_date("Aug 3rd", today, YearlessDatePolicy::RollForward),
+            Some(date("2025-08-03"))
+        );
+        assert_eq!(
+            resolve_date("July 10", today, YearlessDatePolicy::RollForward),
+            Some(date("2025-07-10"))
+        );
+    }
+
+    #[test]
+    fn test_current_year_policy_keeps_past_date() {
+        let today = date("2025-07-10");
+        assert_eq!(
+            resolve_date("June 15", today, YearlessDatePolicy::CurrentYear),
+            Some(date("2025-06-15"))
+        );
+    }
+
+    #[test]
+    fn test_unparseable_date() {
+        let today = date("2025-07-10");
+        assert_eq!(
+            resolve_date("next tuesday", today, YearlessDatePolicy::RollForward),
+            None
+        );
+    }
+}
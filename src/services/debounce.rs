@@ -0,0 +1,41 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::logging::mask_phone;
+use crate::services::conversation;
+use crate::state::AppState;
+
+/// Texts from one phone waiting for the sender to pause.
+#[derive(Default)]
+pub struct PendingMessages {
+    parts: Vec<String>,
+    /// Bumped on every new text; only the timer for the latest one fires.
+    generation: u64,
+}
+
+/// Buffer `body` and process everything buffered for `from` as one message
+/// once no new text has arrived for `DEBOUNCE_MS`. Each new text resets the timer.
+pub fn submit(state: &Arc<AppState>, from: &str, body: &str) {
+    let generation = {
+        let mut pending = state.debounce_buffers.entry(from.to_string()).or_default();
+        pending.parts.push(body.to_string());
+        pending.generation += 1;
+        pending.generation
+    };
+
+    let state = Arc::clone(state);
+    let from = from.to_string();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(state.config.debounce_ms)).await;
+        let Some((_, pending)) = state
+            .debounce_buffers
+            .remove_if(&from, |_, pending| pending.generation == generation)
+        else {
+            // A newer text restarted the timer
+            return;
+        };
+        let combined = pending.parts.join("\n");
+        tracing::info!(from = %mask_phone(&from), parts = pending.parts.len(), "processing debounced messages");
+        conversation::reply_to_customer(&state, &from, &combined).await;
+    });
+}
@@ -0,0 +1,397 @@
+pub const DEFAULT_LANGUAGE: &str = "en";
+pub const SUPPORTED_LANGUAGES: &[&str] = &["en", "es", "fr"];
+
+/// Fixed customer-facing replies the conversation engine writes itself.
+/// LLM-generated replies are localized by the prompt instead.
+const EN: &[(&str, &str)] = &[
+    ("ai_disclosure", "You're texting an automated assistant."),
+    ("booking_details", "Your appointment: {time} ({duration} minutes)."),
+    (
+        "booking_links",
+        "Add to calendar: {calendar}\nNeed a different time? {reschedule}\nCan't make it? {cancel}",
+    ),
+    (
+        "cancel_confirm",
+        "Just to confirm: cancel your appointment on {when}? Reply YES to cancel.",
+    ),
+    ("cancel_declined", "No problem, your appointment is still on."),
+    ("cancelled", "Your appointment on {when} has been cancelled."),
+    (
+        "closed",
+        "Sorry, we're closed through {until}. Could you pick a later date?",
+    ),
+    (
+        "closed_with_reason",
+        "Sorry, we're closed through {until} ({reason}). Could you pick a later date?",
+    ),
+    (
+        "confirm_reprompt",
+        "Are you still there? Your appointment isn't booked yet. Just reply here to confirm it.",
+    ),
+    (
+        "conflict",
+        "Sorry, that time slot is already booked. Could you pick a different time?",
+    ),
+    (
+        "counter_proposal",
+        "No problem. How about {when} instead? Reply YES to confirm.",
+    ),
+    (
+        "handoff",
+        "Of course. I've let the team know and a person will text you back as soon as they can.",
+    ),
+    (
+        "llm_unavailable",
+        "Thanks for your message! We're having a technical problem and will get back to you shortly.",
+    ),
+    (
+        "media_needs_text",
+        "I can't open photos or attachments. Could you text me what you'd like to book?",
+    ),
+    (
+        "message_too_long",
+        "Sorry, that message is too long for me to read. Please keep it short (under {max} characters).",
+    ),
+    (
+        "no_bookings_to_cancel",
+        "I don't see any upcoming bookings to cancel. Would you like to book an appointment instead?",
+    ),
+    (
+        "no_openings",
+        "Sorry, I don't have any openings then on {date}. Would another day or time work?",
+    ),
+    (
+        "off_grid",
+        "Sorry, appointments start every {block_size} minutes. Could you pick another time?",
+    ),
+    (
+        "off_grid_offer",
+        "Sorry, appointments start every {block_size} minutes. Would {slots} work instead?",
+    ),
+    (
+        "one_per_day",
+        "You already have an appointment on {date}, and we can only book one per day. Would you like a different date?",
+    ),
+    ("openings", "I have these openings on {date}: {slots}. Which works best?"),
+    ("opted_in", "You're resubscribed and will get our replies again. Reply STOP to unsubscribe."),
+    (
+        "opted_out",
+        "You're unsubscribed and won't receive any more messages from us. Reply START to resubscribe.",
+    ),
+    (
+        "outside_hours",
+        "That time is outside our business hours. We're available: {hours}",
+    ),
+    (
+        "reminder",
+        "Reminder: you have an appointment on {time}. Reply C to confirm or X to cancel.",
+    ),
+    ("reminder_confirmed", "Thanks, you're confirmed for {when}. See you then!"),
+    ("slots_last", "{rest}, or {last}"),
+    ("slots_pair", "{first} or {second}"),
+    (
+        "something_went_wrong",
+        "I'm sorry, something went wrong. Could you start over?",
+    ),
+    (
+        "too_soon",
+        "Sorry, that's too soon for us to book. The earliest we can do is {earliest}.",
+    ),
+    (
+        "waitlist_opening",
+        "Good news: {when} just opened up. Reply if you'd like to book it.",
+    ),
+    (
+        "waitlisted",
+        "Sorry, that time slot is already booked. I've put you on the waitlist and will text you if it opens up. Would another time work in the meantime?",
+    ),
+    ("which_slot", "Which time works best: {slots}?"),
+];
+
+const ES: &[(&str, &str)] = &[
+    ("ai_disclosure", "Está escribiendo a un asistente automático."),
+    ("booking_details", "Su cita: {time} ({duration} minutos)."),
+    (
+        "booking_links",
+        "Añadir al calendario: {calendar}\n¿Necesita otro horario? {reschedule}\n¿No puede asistir? {cancel}",
+    ),
+    (
+        "cancel_confirm",
+        "Para confirmar: ¿cancelar su cita del {when}? Responda SÍ para cancelar.",
+    ),
+    ("cancel_declined", "No hay problema, su cita sigue en pie."),
+    ("cancelled", "Su cita del {when} ha sido cancelada."),
+    (
+        "closed",
+        "Lo siento, estamos cerrados hasta el {until}. ¿Podría elegir una fecha posterior?",
+    ),
+    (
+        "closed_with_reason",
+        "Lo siento, estamos cerrados hasta el {until} ({reason}). ¿Podría elegir una fecha posterior?",
+    ),
+    (
+        "confirm_reprompt",
+        "¿Sigue ahí? Su cita todavía no está reservada. Responda aquí para confirmarla.",
+    ),
+    (
+        "conflict",
+        "Lo siento, ese horario ya está reservado. ¿Podría elegir otra hora?",
+    ),
+    (
+        "counter_proposal",
+        "No hay problema. ¿Qué tal el {when}? Responda SÍ para confirmar.",
+    ),
+    (
+        "handoff",
+        "Por supuesto. Ya avisé al equipo y una persona le escribirá lo antes posible.",
+    ),
+    (
+        "llm_unavailable",
+        "¡Gracias por su mensaje! Tenemos un problema técnico y le responderemos en breve.",
+    ),
+    (
+        "media_needs_text",
+        "No puedo abrir fotos ni archivos adjuntos. ¿Podría escribirme qué le gustaría reservar?",
+    ),
+    (
+        "message_too_long",
+        "Lo siento, ese mensaje es demasiado largo para mí. Por favor, sea breve (menos de {max} caracteres).",
+    ),
+    (
+        "no_bookings_to_cancel",
+        "No veo ninguna cita próxima para cancelar. ¿Le gustaría reservar una cita?",
+    ),
+    (
+        "no_openings",
+        "Lo siento, no tengo horarios disponibles el {date} en ese momento. ¿Le sirve otro día u hora?",
+    ),
+    (
+        "off_grid",
+        "Lo siento, las citas empiezan cada {block_size} minutos. ¿Podría elegir otra hora?",
+    ),
+    (
+        "off_grid_offer",
+        "Lo siento, las citas empiezan cada {block_size} minutos. ¿Le vendría bien {slots}?",
+    ),
+    (
+        "one_per_day",
+        "Ya tiene una cita el {date} y solo podemos reservar una por día. ¿Le gustaría otra fecha?",
+    ),
+    (
+        "openings",
+        "Tengo estos horarios disponibles el {date}: {slots}. ¿Cuál le viene mejor?",
+    ),
+    (
+        "opted_in",
+        "Se ha vuelto a suscribir y recibirá nuestras respuestas de nuevo. Responda STOP para darse de baja.",
+    ),
+    (
+        "opted_out",
+        "Se ha dado de baja y no recibirá más mensajes nuestros. Responda START para volver a suscribirse.",
+    ),
+    (
+        "outside_hours",
+        "Ese horario está fuera de nuestro horario de atención. Estamos disponibles: {hours}",
+    ),
+    (
+        "reminder",
+        "Recordatorio: tiene una cita el {time}. Responda C para confirmar o X para cancelar.",
+    ),
+    ("reminder_confirmed", "Gracias, su cita del {when} está confirmada. ¡Hasta entonces!"),
+    ("slots_last", "{rest} o {last}"),
+    ("slots_pair", "{first} o {second}"),
+    (
+        "something_went_wrong",
+        "Lo siento, algo salió mal. ¿Podría empezar de nuevo?",
+    ),
+    (
+        "too_soon",
+        "Lo siento, es demasiado pronto para reservar. Lo más pronto posible es {earliest}.",
+    ),
+    (
+        "waitlist_opening",
+        "Buenas noticias: se liberó el horario del {when}. Responda si desea reservarlo.",
+    ),
+    (
+        "waitlisted",
+        "Lo siento, ese horario ya está reservado. Lo anoté en la lista de espera y le avisaré por mensaje si se libera. Mientras tanto, ¿le sirve otra hora?",
+    ),
+    ("which_slot", "¿Qué hora le viene mejor: {slots}?"),
+];
+
+const FR: &[(&str, &str)] = &[
+    ("ai_disclosure", "Vous écrivez à un assistant automatisé."),
+    ("booking_details", "Votre rendez-vous : {time} ({duration} minutes)."),
+    (
+        "booking_links",
+        "Ajouter au calendrier : {calendar}\nBesoin d'un autre horaire ? {reschedule}\nVous ne pouvez pas venir ? {cancel}",
+    ),
+    (
+        "cancel_confirm",
+        "Pour confirmer : annuler votre rendez-vous du {when} ? Répondez OUI pour annuler.",
+    ),
+    ("cancel_declined", "Pas de problème, votre rendez-vous est maintenu."),
+    ("cancelled", "Votre rendez-vous du {when} a été annulé."),
+    (
+        "closed",
+        "Désolé, nous sommes fermés jusqu'au {until}. Pourriez-vous choisir une date ultérieure ?",
+    ),
+    (
+        "closed_with_reason",
+        "Désolé, nous sommes fermés jusqu'au {until} ({reason}). Pourriez-vous choisir une date ultérieure ?",
+    ),
+    (
+        "confirm_reprompt",
+        "Vous êtes toujours là ? Votre rendez-vous n'est pas encore réservé. Répondez ici pour le confirmer.",
+    ),
+    (
+        "conflict",
+        "Désolé, ce créneau est déjà réservé. Pourriez-vous choisir un autre horaire ?",
+    ),
+    (
+        "counter_proposal",
+        "Pas de problème. Que diriez-vous du {when} ? Répondez OUI pour confirmer.",
+    ),
+    (
+        "handoff",
+        "Bien sûr. J'ai prévenu l'équipe et une personne vous répondra par SMS dès que possible.",
+    ),
+    (
+        "llm_unavailable",
+        "Merci pour votre message ! Nous rencontrons un problème technique et vous répondrons rapidement.",
+    ),
+    (
+        "media_needs_text",
+        "Je ne peux pas ouvrir les photos ni les pièces jointes. Pourriez-vous m'écrire ce que vous souhaitez réserver ?",
+    ),
+    (
+        "message_too_long",
+        "Désolé, ce message est trop long pour moi. Merci de faire court (moins de {max} caractères).",
+    ),
+    (
+        "no_bookings_to_cancel",
+        "Je ne vois aucun rendez-vous à venir à annuler. Souhaitez-vous plutôt prendre rendez-vous ?",
+    ),
+    (
+        "no_openings",
+        "Désolé, je n'ai aucune disponibilité à ce moment-là le {date}. Un autre jour ou horaire vous conviendrait-il ?",
+    ),
+    (
+        "off_grid",
+        "Désolé, les rendez-vous commencent toutes les {block_size} minutes. Pourriez-vous choisir un autre horaire ?",
+    ),
+    (
+        "off_grid_offer",
+        "Désolé, les rendez-vous commencent toutes les {block_size} minutes. {slots} vous conviendrait-il ?",
+    ),
+    (
+        "one_per_day",
+        "Vous avez déjà un rendez-vous le {date} et nous ne pouvons en réserver qu'un par jour. Souhaitez-vous une autre date ?",
+    ),
+    (
+        "openings",
+        "J'ai ces disponibilités le {date} : {slots}. Lequel vous convient le mieux ?",
+    ),
+    (
+        "opted_in",
+        "Vous êtes de nouveau abonné et recevrez nos réponses. Répondez STOP pour vous désabonner.",
+    ),
+    (
+        "opted_out",
+        "Vous êtes désabonné et ne recevrez plus de messages de notre part. Répondez START pour vous réabonner.",
+    ),
+    (
+        "outside_hours",
+        "Ce créneau est en dehors de nos heures d'ouverture. Nous sommes disponibles : {hours}",
+    ),
+    (
+        "reminder",
+        "Rappel : vous avez un rendez-vous le {time}. Répondez C pour confirmer ou X pour annuler.",
+    ),
+    ("reminder_confirmed", "Merci, votre rendez-vous du {when} est confirmé. À bientôt !"),
+    ("slots_last", "{rest} ou {last}"),
+    ("slots_pair", "{first} ou {second}"),
+    (
+        "something_went_wrong",
+        "Désolé, un problème est survenu. Pourriez-vous recommencer ?",
+    ),
+    (
+        "too_soon",
+        "Désolé, c'est trop tôt pour réserver. Le plus tôt possible est {earliest}.",
+    ),
+    (
+        "waitlist_opening",
+        "Bonne nouvelle : le créneau du {when} vient de se libérer. Répondez si vous souhaitez le réserver.",
+    ),
+    (
+        "waitlisted",
+        "Désolé, ce créneau est déjà réservé. Je vous ai inscrit sur la liste d'attente et vous préviendrai par SMS s'il se libère. Un autre horaire vous conviendrait-il en attendant ?",
+    ),
+    ("which_slot", "Quel horaire vous convient le mieux : {slots} ?"),
+];
+
+pub fn is_supported(lang: &str) -> bool {
+    SUPPORTED_LANGUAGES.contains(&lang)
+}
+
+fn catalog(lang: &str) -> &'static [(&'static str, &'static str)] {
+    match lang {
+        "es" => ES,
+        "fr" => FR,
+        _ => EN,
+    }
+}
+
+fn lookup(catalog: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    catalog.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+/// Look up `key` in the `lang` catalog and fill its `{name}` placeholders from
+/// `args`. Unknown languages and missing keys fall back to English; a key
+/// missing from English too is returned as-is so the gap is visible.
+pub fn t(lang: &str, key: &str, args: &[(&str, &str)]) -> String {
+    let template = lookup(catalog(lang), key)
+        .or_else(|| lookup(EN, key))
+        .unwrap_or(key);
+    args.iter().fold(template.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{name}}}"), value)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_t_fills_placeholders() {
+        assert_eq!(
+            t("en", "cancelled", &[("when", "Monday")]),
+            "Your appointment on Monday has been cancelled."
+        );
+        assert_eq!(
+            t("fr", "slots_pair", &[("first", "9:00"), ("second", "10:00")]),
+            "9:00 ou 10:00"
+        );
+    }
+
+    #[test]
+    fn test_t_falls_back_to_english() {
+        assert_eq!(
+            t("de", "cancel_declined", &[]),
+            "No problem, your appointment is still on."
+        );
+        assert_eq!(t("es", "no_such_key", &[]), "no_such_key");
+    }
+
+    #[test]
+    fn test_every_language_covers_english_keys() {
+        for lang in SUPPORTED_LANGUAGES {
+            for (key, _) in EN {
+                assert!(
+                    lookup(catalog(lang), key).is_some(),
+                    "{lang} is missing {key}"
+                );
+            }
+        }
+    }
+}
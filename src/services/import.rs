@@ -0,0 +1,260 @@
+//! Bulk-loading bookings from CSV, for businesses moving over from another
+//! tool. Columns are matched by header name and use the same names as the
+//! `/api/admin/bookings` listing; only `customer_phone` and `date_time` are
+//! required.
+
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::db::queries;
+use crate::models::{Availability, Booking, BookingActor, BookingStatus};
+use crate::services::scheduling::validate_booking_time;
+
+const REQUIRED_COLUMNS: &[&str] = &["customer_phone", "date_time"];
+
+/// Outcome of one CSV row. `row` is the 1-based data row (the header is row 0).
+#[derive(Debug, Serialize)]
+pub struct ImportRow {
+    pub row: usize,
+    pub id: Option<String>,
+    /// "imported", "skipped" (duplicate id) or "error".
+    pub status: &'static str,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+/// Why the file as a whole couldn't be imported.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("CSV is empty")]
+    Empty,
+    #[error("CSV header is missing the `{0}` column")]
+    MissingColumn(&'static str),
+    #[error(transparent)]
+    Database(#[from] anyhow::Error),
+}
+
+/// Insert every well-formed row of `csv` as a booking, all in one
+/// transaction. Bad rows and duplicate ids are reported and skipped rather
+/// than aborting the batch. With `validate`, bookings holding a slot must pass
+/// `validate_booking_time` against the owner's availability and the rows
+/// imported before them. Cancelled and no-show rows free their slot, so they
+/// are never checked and never block a later row.
+pub fn import_bookings(
+    conn: &Connection,
+    csv: &str,
+    validate: bool,
+) -> Result<ImportSummary, ImportError> {
+    let mut records = parse_csv(csv).into_iter();
+    let header: Vec<String> = records
+        .next()
+        .ok_or(ImportError::Empty)?
+        .into_iter()
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect();
+    if let Some(missing) = REQUIRED_COLUMNS
+        .iter()
+        .find(|c| !header.iter().any(|h| h == *c))
+    {
+        return Err(ImportError::MissingColumn(missing));
+    }
+
+    let availability = queries::get_user(conn, "default")?
+        .and_then(|u| u.availability)
+        .and_then(|s| Availability::from_json(&s).ok());
+    let default_duration = availability
+        .as_ref()
+        .map(Availability::duration_bounds)
+        .unwrap_or_default()
+        .default;
+
+    let tx = conn.unchecked_transaction().map_err(anyhow::Error::from)?;
+    let mut summary = ImportSummary::default();
+    for (i, fields) in records.enumerate() {
+        if fields.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        let row = i + 1;
+        let result = booking_from_row(&header, &fields, default_duration).and_then(|booking| {
+            if queries::get_booking_by_id(&tx, &booking.id)?.is_some() {
+                return Ok((booking.id, false));
+            }
+            // Cancelled and no-show rows are history, not a claim on the slot
+            if validate && booking.status.holds_slot() {
+                validate_booking_time(
+                    &tx,
+                    &booking.date_time,
+                    booking.duration_minutes,
+                    availability.as_ref(),
+                )
+                .map_err(|e| anyhow::anyhow!("{e}"))?;
+            }
+            queries::create_booking(&tx, &booking, BookingActor::Admin)?;
+            Ok((booking.id, true))
+        });
+        summary.rows.push(match result {
+            Ok((id, true)) => {
+                summary.imported += 1;
+                ImportRow {
+                    row,
+                    id: Some(id),
+                    status: "imported",
+                    error: None,
+                }
+            }
+            Ok((id, false)) => {
+                summary.skipped += 1;
+                ImportRow {
+                    row,
+                    id: Some(id),
+                    status: "skipped",
+                    error: Some("a booking with this id already exists".to_string()),
+                }
+            }
+            Err(e) => {
+                summary.failed += 1;
+                ImportRow {
+                    row,
+                    id: field(&header, &fields, "id").map(str::to_string),
+                    status: "error",
+                    error: Some(e.to_string()),
+                }
+            }
+        });
+    }
+    tx.commit().map_err(anyhow::Error::from)?;
+
+    Ok(summary)
+}
+
+fn field<'a>(header: &[String], fields: &'a [String], name: &str) -> Option<&'a str> {
+    header
+        .iter()
+        .position(|h| h == name)
+        .and_then(|i| fields.get(i))
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+}
+
+fn parse_timestamp(column: &str, value: &str) -> anyhow::Result<NaiveDateTime> {
+    [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(value, fmt).ok())
+    .ok_or_else(|| anyhow::anyhow!("invalid {column} '{value}' (expected YYYY-MM-DD HH:MM)"))
+}
+
+fn booking_from_row(
+    header: &[String],
+    fields: &[String],
+    default_duration: i32,
+) -> anyhow::Result<Booking> {
+    let get = |name| field(header, fields, name);
+    let customer_phone =
+        get("customer_phone").ok_or_else(|| anyhow::anyhow!("missing customer_phone"))?;
+    let date_time = get("date_time").ok_or_else(|| anyhow::anyhow!("missing date_time"))?;
+    let date_time = parse_timestamp("date_time", date_time)?;
+    let duration_minutes = match get("duration_minutes") {
+        Some(d) => d
+            .parse::<i32>()
+            .ok()
+            .filter(|d| *d > 0)
+            .ok_or_else(|| anyhow::anyhow!("invalid duration_minutes '{d}'"))?,
+        None => default_duration,
+    };
+    let status = match get("status").map(str::to_ascii_lowercase).as_deref() {
+        None => BookingStatus::Confirmed,
+        Some(s @ ("pending" | "confirmed" | "cancelled" | "no_show")) => BookingStatus::parse(s),
+        Some(s) => anyhow::bail!("invalid status '{s}'"),
+    };
+    let now = Utc::now().naive_utc();
+    let created_at = get("created_at")
+        .map(|v| parse_timestamp("created_at", v))
+        .transpose()?
+        .unwrap_or(now);
+    let updated_at = get("updated_at")
+        .map(|v| parse_timestamp("updated_at", v))
+        .transpose()?
+        .unwrap_or(created_at);
+
+    Ok(Booking {
+        id: get("id")
+            .map(str::to_string)
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        customer_phone: customer_phone.to_string(),
+        customer_name: get("customer_name").map(str::to_string),
+        date_time,
+        duration_minutes,
+        status,
+        notes: get("notes").map(str::to_string),
+        service: get("service").map(str::to_string),
+        created_at,
+        updated_at,
+    })
+}
+
+/// Split RFC 4180 CSV into records: quoted fields may contain commas,
+/// newlines and doubled quotes; both `\n` and `\r\n` end a record.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_handles_quotes_and_crlf() {
+        let records =
+            parse_csv("id,notes\r\nbk-1,\"Bring \"\"the\"\" file, please\nthanks\"\r\nbk-2,\n");
+        assert_eq!(
+            records,
+            vec![
+                vec!["id".to_string(), "notes".to_string()],
+                vec![
+                    "bk-1".to_string(),
+                    "Bring \"the\" file, please\nthanks".to_string()
+                ],
+                vec!["bk-2".to_string(), String::new()],
+            ]
+        );
+    }
+}
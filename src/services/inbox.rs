@@ -1,17 +1,20 @@
 use std::sync::Arc;
 
 use crate::db::queries;
-use crate::models::InboxEvent;
+use crate::models::{InboxEvent, InboxUpdate, ThreadSummary};
 use crate::state::AppState;
 
 pub fn record_inbox_event(state: &Arc<AppState>, phone: &str, kind: &str, content: &str) {
-    let event_id = {
+    let inserted = {
         let db = state.db.lock().unwrap();
-        queries::insert_inbox_event(&db, phone, kind, content)
+        queries::insert_inbox_event(&db, phone, kind, content).and_then(|id| {
+            let unread_count = queries::get_thread_unread_count(&db, phone)?;
+            Ok((id, unread_count))
+        })
     };
 
-    match event_id {
-        Ok(id) => {
+    match inserted {
+        Ok((id, unread_count)) => {
             let event = InboxEvent {
                 id,
                 phone: phone.to_string(),
@@ -23,10 +26,18 @@ pub fn record_inbox_event(state: &Arc<AppState>, phone: &str, kind: &str, conten
                     .to_string(),
             };
             // Broadcast to SSE subscribers; ignore if no receivers
-            let _ = state.inbox_tx.send(event);
+            let _ = state.inbox_tx.send(InboxUpdate::Event(event));
+            broadcast_thread_summary(state, phone, unread_count);
         }
         Err(e) => {
             tracing::error!(error = %e, "failed to record inbox event");
         }
     }
 }
+
+pub fn broadcast_thread_summary(state: &Arc<AppState>, phone: &str, unread_count: i64) {
+    let _ = state.inbox_tx.send(InboxUpdate::Summary(ThreadSummary {
+        phone: phone.to_string(),
+        unread_count,
+    }));
+}
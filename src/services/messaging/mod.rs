@@ -1,3 +1,4 @@
+pub mod segments;
 pub mod twilio;
 
 use async_trait::async_trait;
@@ -0,0 +1,161 @@
+//! SMS length rules: Twilio rejects bodies over 1600 characters, and carriers
+//! bill per segment — 160 GSM-7 characters, or only 70 once any character
+//! falls outside the GSM alphabet and the message is sent as UCS-2.
+
+/// Longest body Twilio accepts in a single API call.
+pub const MAX_MESSAGE_CHARS: usize = 1600;
+
+const GSM_SINGLE: usize = 160;
+const GSM_MULTIPART: usize = 153;
+const UCS2_SINGLE: usize = 70;
+const UCS2_MULTIPART: usize = 67;
+
+const GSM_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !\"#¤%&'()*+,-./0123456789:;<=>?\
+¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà";
+/// Characters in the GSM extension table, which take two septets each.
+const GSM_EXTENDED: &str = "^{}\\[~]|€\u{000C}";
+
+/// Septets needed to encode `c` in GSM-7, or `None` if it isn't representable.
+fn gsm_septets(c: char) -> Option<usize> {
+    if GSM_BASIC.contains(c) {
+        Some(1)
+    } else if GSM_EXTENDED.contains(c) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Whether the whole text can be sent with the GSM-7 alphabet.
+pub fn is_gsm(text: &str) -> bool {
+    text.chars().all(|c| gsm_septets(c).is_some())
+}
+
+/// Segments a single message body occupies on the carrier side.
+pub fn segments_for(text: &str) -> usize {
+    let (units, single, multipart) = if is_gsm(text) {
+        let septets: usize = text.chars().filter_map(gsm_septets).sum();
+        (septets, GSM_SINGLE, GSM_MULTIPART)
+    } else {
+        let code_units: usize = text.chars().map(char::len_utf16).sum();
+        (code_units, UCS2_SINGLE, UCS2_MULTIPART)
+    };
+
+    if units <= single {
+        1
+    } else {
+        units.div_ceil(multipart)
+    }
+}
+
+/// Split a body into messages of at most `MAX_MESSAGE_CHARS`, breaking on
+/// whitespace where possible. Short bodies come back as a single message.
+pub fn split_message(body: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut rest = body.trim();
+
+    while rest.chars().count() > MAX_MESSAGE_CHARS {
+        let cut = rest
+            .char_indices()
+            .nth(MAX_MESSAGE_CHARS)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        // Prefer the last whitespace before the cut; hard-split one giant word.
+        // `cut` is a char boundary, so this slice is safe for multi-byte text
+        let split_at = rest[..cut]
+            .rfind(char::is_whitespace)
+            .filter(|&i| i > 0)
+            .unwrap_or(cut);
+        parts.push(rest[..split_at].trim_end().to_string());
+        rest = rest[split_at..].trim_start();
+    }
+
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+/// Total billable segments for sending `body`, after splitting.
+pub fn segment_count(body: &str) -> usize {
+    split_message(body).iter().map(|part| segments_for(part)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_gsm_message_is_one_segment() {
+        let text = "Your appointment is confirmed for Monday at 2pm.";
+        assert!(is_gsm(text));
+        assert_eq!(split_message(text), vec![text.to_string()]);
+        assert_eq!(segment_count(text), 1);
+    }
+
+    #[test]
+    fn test_long_message_splits_on_word_boundaries() {
+        let text = "booking ".repeat(500); // 4000 chars
+        let parts = split_message(&text);
+
+        assert_eq!(parts.len(), 3);
+        for part in &parts {
+            assert!(part.chars().count() <= MAX_MESSAGE_CHARS);
+            assert!(!part.starts_with(' ') && !part.ends_with(' '));
+            assert!(part.split(' ').all(|w| w == "booking"));
+        }
+        assert_eq!(parts.join(" "), text.trim());
+        // 1599 septets → 11 segments at 153 per concatenated segment
+        assert_eq!(segments_for(&parts[0]), parts[0].len().div_ceil(GSM_MULTIPART));
+    }
+
+    #[test]
+    fn test_unbroken_text_is_hard_split() {
+        let text = "x".repeat(MAX_MESSAGE_CHARS + 10);
+        let parts = split_message(&text);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].len(), MAX_MESSAGE_CHARS);
+        assert_eq!(parts[1].len(), 10);
+    }
+
+    #[test]
+    fn test_multibyte_char_at_cut_point() {
+        let text = "é".repeat(MAX_MESSAGE_CHARS + 10);
+        let parts = split_message(&text);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].chars().count(), MAX_MESSAGE_CHARS);
+        assert_eq!(parts[1].chars().count(), 10);
+        assert_eq!(
+            segment_count(&text),
+            segments_for(&parts[0]) + segments_for(&parts[1])
+        );
+
+        let spaced = format!("{} {}", "a".repeat(MAX_MESSAGE_CHARS - 1), "ü".repeat(20));
+        let parts = split_message(&spaced);
+        assert_eq!(
+            parts,
+            vec!["a".repeat(MAX_MESSAGE_CHARS - 1), "ü".repeat(20)]
+        );
+    }
+
+    #[test]
+    fn test_unicode_shrinks_segment_limit() {
+        let plain = "a".repeat(100);
+        assert_eq!(segments_for(&plain), 1);
+
+        // One emoji forces UCS-2: 70 per segment, 67 when concatenated
+        let with_emoji = format!("{} 👍", "a".repeat(97));
+        assert!(!is_gsm(&with_emoji));
+        assert_eq!(segments_for(&with_emoji), 2);
+        assert_eq!(segments_for("Café ok ✓"), 1);
+        assert_eq!(segments_for(&"ü".repeat(160)), 1); // ü is in the GSM alphabet
+    }
+
+    #[test]
+    fn test_extended_gsm_chars_count_double() {
+        let text = "€".repeat(80);
+        assert!(is_gsm(&text));
+        assert_eq!(segments_for(&text), 1);
+        assert_eq!(segments_for(&"€".repeat(81)), 2);
+    }
+}
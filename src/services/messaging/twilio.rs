@@ -1,23 +1,39 @@
+use std::time::Duration;
+
 use anyhow::Context;
 use async_trait::async_trait;
 
+use super::segments::split_message;
 use super::MessagingProvider;
 
 pub struct TwilioSmsProvider {
     account_sid: String,
     auth_token: String,
     from_number: String,
+    status_callback: Option<String>,
+    timeout: Duration,
     client: reqwest::Client,
 }
 
 impl TwilioSmsProvider {
-    pub fn new(account_sid: String, auth_token: String, from_number: String) -> Self {
-        Self {
+    pub fn new(
+        account_sid: String,
+        auth_token: String,
+        from_number: String,
+        status_callback: Option<String>,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
             account_sid,
             auth_token,
             from_number,
-            client: reqwest::Client::new(),
-        }
+            status_callback,
+            timeout,
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .context("failed to build HTTP client")?,
+        })
     }
 }
 
@@ -29,15 +45,29 @@ impl MessagingProvider for TwilioSmsProvider {
             self.account_sid
         );
 
-        self.client
-            .post(&url)
-            .basic_auth(&self.account_sid, Some(&self.auth_token))
-            .form(&[("To", to), ("From", &self.from_number), ("Body", body)])
-            .send()
-            .await
-            .context("failed to send Twilio SMS")?
-            .error_for_status()
-            .context("Twilio API returned error")?;
+        // Long bodies go out as several sequential messages
+        for part in split_message(body) {
+            let mut form = vec![("To", to), ("From", &self.from_number), ("Body", &part)];
+            if let Some(callback) = &self.status_callback {
+                form.push(("StatusCallback", callback));
+            }
+
+            self.client
+                .post(&url)
+                .basic_auth(&self.account_sid, Some(&self.auth_token))
+                .form(&form)
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        anyhow::anyhow!("Twilio API timed out after {}s", self.timeout.as_secs())
+                    } else {
+                        anyhow::Error::new(e).context("failed to send Twilio SMS")
+                    }
+                })?
+                .error_for_status()
+                .context("Twilio API returned error")?;
+        }
 
         Ok(())
     }
@@ -1,6 +1,19 @@
 pub mod ai;
+pub mod broadcast;
 pub mod calendar;
+pub mod cleanup;
 pub mod conversation;
+pub mod dates;
+pub mod debounce;
+pub mod i18n;
+pub mod import;
 pub mod inbox;
 pub mod messaging;
+pub mod notify;
+pub mod phone;
+pub mod quiet_hours;
+pub mod reminders;
 pub mod scheduling;
+pub mod signing;
+pub mod spam;
+pub mod waitlist;
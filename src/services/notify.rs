@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use crate::db::{pool, queries};
+use crate::services::inbox::record_inbox_event;
+use crate::services::messaging::segments::segment_count;
+use crate::state::{AppState, DevNotification, DevNotificationKind};
+
+/// What an owner alert is about, so the owner can mute the noisy ones.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OwnerEvent {
+    Booking,
+    Cancel,
+    Block,
+    Pause,
+    /// Operational problems; always sent.
+    System,
+}
+
+/// Whether the owner's settings allow texting them about `event`. Without a
+/// saved user everything is on.
+fn sms_enabled(state: &AppState, event: OwnerEvent) -> bool {
+    let user = {
+        let db = state.db.lock().unwrap();
+        queries::get_user(&db, "default").ok().flatten()
+    };
+    let Some(user) = user else {
+        return true;
+    };
+    match event {
+        OwnerEvent::Booking => user.notify_on_booking,
+        OwnerEvent::Cancel => user.notify_on_cancel,
+        OwnerEvent::Block => user.notify_on_block,
+        OwnerEvent::Pause => user.notify_on_pause,
+        OwnerEvent::System => true,
+    }
+}
+
+/// The owner's phone: the one saved in settings, else `OWNER_PHONE`.
+pub async fn owner_phone(state: &AppState) -> String {
+    let fallback = state.config.owner_phone.clone();
+    pool::with_db(&state.db, move |conn| queries::owner_phone(conn, &fallback))
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!(error = %e, "failed to load owner phone");
+            state.config.owner_phone.clone()
+        })
+}
+
+/// Alert the owner by SMS, also recording the alert in the dev queue and inbox.
+/// The SMS is skipped when the owner turned off alerts for `event`.
+/// If the primary owner phone can't be reached, retries once on the backup phone.
+pub async fn notify_owner(
+    state: &Arc<AppState>,
+    event: OwnerEvent,
+    message: &str,
+    phone: Option<&str>,
+) {
+    state.dev_notifications.push(DevNotification {
+        phone: phone.map(|p| p.to_string()),
+        kind: DevNotificationKind::System,
+        content: message.to_string(),
+    });
+    if let Some(p) = phone {
+        record_inbox_event(state, p, "system", message);
+    }
+
+    let owner = owner_phone(state).await;
+    if owner.is_empty() {
+        tracing::warn!("owner_phone not configured, skipping notification");
+        return;
+    }
+    if !sms_enabled(state, event) {
+        tracing::debug!(?event, "owner muted this alert, skipping SMS");
+        return;
+    }
+
+    let mut result = state
+        .messaging
+        .send_message(&owner, message)
+        .await;
+
+    if let Err(ref e) = result {
+        if state.config.backup_owner_phone.is_empty() {
+            tracing::error!(error = %e, "failed to notify owner");
+            return;
+        }
+        tracing::warn!(error = %e, "failed to notify owner, failing over to backup phone");
+        result = state
+            .messaging
+            .send_message(&state.config.backup_owner_phone, message)
+            .await;
+        if let Err(ref e) = result {
+            tracing::error!(error = %e, "failed to notify owner on backup phone");
+        }
+    }
+
+    if result.is_ok() {
+        let segments = segment_count(message);
+        let db = state.db.lock().unwrap();
+        let _ = queries::increment_monthly_sent(&db, segments);
+    }
+}
@@ -0,0 +1,121 @@
+//! Turning numbers typed by the owner into the E.164 form Twilio sends us,
+//! so `#block 555-111-2222` matches the `From` of the customer's texts.
+
+/// Why a typed number couldn't be turned into E.164.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum PhoneError {
+    #[error("no phone number given")]
+    Empty,
+    #[error("'{0}' is not a phone number")]
+    Invalid(String),
+    #[error("'{0}' has no country code; start it with + or set DEFAULT_COUNTRY_CODE")]
+    NoCountryCode(String),
+    #[error("'{input}' is the wrong length for a {country_code} number")]
+    WrongLength { input: String, country_code: String },
+}
+
+/// Normalize `input` to `+<digits>`. Spaces, dashes, dots and parentheses
+/// are ignored and a leading `00` counts as `+`. A number without either is
+/// taken as local to `default_country_code` (e.g. `+1`; empty = none), with a
+/// single trunk `0` dropped; under `+1` it must be 10 digits, or 11 starting
+/// with the `1`.
+pub fn normalize_phone(input: &str, default_country_code: &str) -> Result<String, PhoneError> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err(PhoneError::Empty);
+    }
+    let compact: String = trimmed
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    let invalid = || PhoneError::Invalid(trimmed.to_string());
+
+    let (international, digits) = match compact.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => match compact.strip_prefix("00") {
+            Some(rest) => (true, rest),
+            None => (false, compact.as_str()),
+        },
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let full = if international {
+        digits.to_string()
+    } else {
+        let cc = default_country_code.trim_start_matches('+');
+        if cc.is_empty() {
+            return Err(PhoneError::NoCountryCode(trimmed.to_string()));
+        }
+        if cc == "1" {
+            match digits.len() {
+                10 => format!("1{digits}"),
+                11 if digits.starts_with('1') => digits.to_string(),
+                _ => {
+                    return Err(PhoneError::WrongLength {
+                        input: trimmed.to_string(),
+                        country_code: default_country_code.to_string(),
+                    })
+                }
+            }
+        } else {
+            format!("{cc}{}", digits.strip_prefix('0').unwrap_or(digits))
+        }
+    };
+
+    // E.164 allows at most 15 digits; anything under 8 is a short code
+    if !(8..=15).contains(&full.len()) {
+        return Err(invalid());
+    }
+    Ok(format!("+{full}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(normalize_phone("5551112222", "+1").unwrap(), "+15551112222");
+        assert_eq!(
+            normalize_phone("(555) 111-2222", "+1").unwrap(),
+            "+15551112222"
+        );
+        assert_eq!(
+            normalize_phone("1 555 111 2222", "+1").unwrap(),
+            "+15551112222"
+        );
+        assert_eq!(normalize_phone("+15551112222", "").unwrap(), "+15551112222");
+        assert_eq!(
+            normalize_phone("0044 7911 123456", "+1").unwrap(),
+            "+447911123456"
+        );
+        assert_eq!(
+            normalize_phone("07911 123456", "+44").unwrap(),
+            "+447911123456"
+        );
+
+        assert_eq!(normalize_phone("  ", "+1"), Err(PhoneError::Empty));
+        assert!(matches!(
+            normalize_phone("call me", "+1"),
+            Err(PhoneError::Invalid(_))
+        ));
+        assert!(matches!(
+            normalize_phone("+1555abc2222", "+1"),
+            Err(PhoneError::Invalid(_))
+        ));
+        assert!(matches!(
+            normalize_phone("+123", "+1"),
+            Err(PhoneError::Invalid(_))
+        ));
+        assert!(matches!(
+            normalize_phone("5551112222", ""),
+            Err(PhoneError::NoCountryCode(_))
+        ));
+        assert!(matches!(
+            normalize_phone("555111222", "+1"),
+            Err(PhoneError::WrongLength { .. })
+        ));
+    }
+}
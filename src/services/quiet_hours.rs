@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::db::queries;
+use crate::logging::mask_phone;
+use crate::models::QuietHours;
+use crate::services::messaging::segments::segment_count;
+use crate::state::AppState;
+
+/// How often held replies are checked for delivery.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether the owner's quiet hours cover the current time in the business
+/// timezone. An unknown timezone is treated as UTC.
+pub fn in_effect(state: &AppState) -> bool {
+    let user = {
+        let db = state.db.lock().unwrap();
+        queries::get_user(&db, "default").ok().flatten()
+    };
+    let Some(user) = user else {
+        return false;
+    };
+    let Some(quiet) = user
+        .quiet_hours
+        .as_deref()
+        .and_then(|q| QuietHours::from_json(q).ok())
+    else {
+        return false;
+    };
+    let tz: chrono_tz::Tz = user.timezone.parse().unwrap_or(chrono_tz::UTC);
+    quiet.contains(Utc::now().with_timezone(&tz).time())
+}
+
+/// Text `body` to a customer now, or hold it until quiet hours end.
+pub async fn send_or_queue(state: &Arc<AppState>, phone: &str, body: &str) -> anyhow::Result<()> {
+    if in_effect(state) {
+        tracing::info!(to = %mask_phone(phone), "quiet hours, holding reply");
+        let db = state.db.lock().unwrap();
+        return queries::queue_reply(&db, phone, body);
+    }
+    state.messaging.send_message(phone, body).await?;
+    let segments = segment_count(body);
+    let db = state.db.lock().unwrap();
+    let _ = queries::increment_monthly_sent(&db, segments);
+    Ok(())
+}
+
+/// Send the replies held during quiet hours, once they're over. Customers who
+/// opted out in the meantime are skipped. Returns how many were sent.
+pub async fn flush(state: &Arc<AppState>) -> usize {
+    if in_effect(state) {
+        return 0;
+    }
+    let queued = {
+        let db = state.db.lock().unwrap();
+        match queries::get_queued_replies(&db) {
+            Ok(queued) => queued,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to load queued replies");
+                return 0;
+            }
+        }
+    };
+
+    let mut sent = 0;
+    for reply in queued {
+        let opted_out = {
+            let db = state.db.lock().unwrap();
+            queries::is_opted_out(&db, &reply.phone).unwrap_or(false)
+        };
+        if !opted_out {
+            if let Err(e) = state.messaging.send_message(&reply.phone, &reply.body).await {
+                // Left queued for the next pass
+                tracing::error!(error = %e, to = %mask_phone(&reply.phone), "failed to send queued reply");
+                continue;
+            }
+            sent += 1;
+        }
+        let segments = segment_count(&reply.body);
+        let db = state.db.lock().unwrap();
+        if !opted_out {
+            let _ = queries::increment_monthly_sent(&db, segments);
+        }
+        let _ = queries::remove_queued_reply(&db, reply.id);
+    }
+    sent
+}
+
+/// Deliver held replies in the background as quiet hours end.
+pub fn spawn_flusher(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let sent = flush(&state).await;
+            if sent > 0 {
+                tracing::info!(sent, "delivered replies held during quiet hours");
+            }
+        }
+    });
+}
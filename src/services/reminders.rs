@@ -0,0 +1,234 @@
+//! Appointment reminders texted ahead of a booking, and the one-letter
+//! replies to them: `C` confirms the reminded booking and `X` cancels it.
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+
+use crate::db::queries;
+use crate::logging::mask_phone;
+use crate::models::{Booking, Service, User};
+use crate::services::dates::format_when_in;
+use crate::services::i18n;
+use crate::services::inbox::record_inbox_event;
+use crate::services::quiet_hours;
+use crate::state::AppState;
+
+/// How long after a reminder a bare `C`/`X` still answers it.
+pub const REPLY_WINDOW: Duration = Duration::hours(24);
+
+/// A customer's answer to a reminder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReminderReply {
+    Confirm,
+    Cancel,
+}
+
+impl ReminderReply {
+    /// `C` or `X` on its own, in either case and ignoring punctuation.
+    pub fn parse(message: &str) -> Option<Self> {
+        let word = message
+            .trim()
+            .trim_matches(|c: char| c.is_ascii_punctuation());
+        match word.to_ascii_uppercase().as_str() {
+            "C" => Some(Self::Confirm),
+            "X" => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// Fill `{name}`, `{time}`, `{duration}`, `{business}` and `{service}` in a
+/// reminder or confirmation template, with `{time}` written in `lang`.
+pub fn render(
+    template: &str,
+    booking: &Booking,
+    business: &str,
+    lang: &str,
+    use_24h: bool,
+) -> String {
+    let time = format_when_in(booking.date_time, lang, use_24h);
+    template
+        .replace("{name}", booking.customer_name.as_deref().unwrap_or(""))
+        .replace("{time}", &time)
+        .replace("{duration}", &booking.duration_minutes.to_string())
+        .replace("{business}", business)
+        .replace("{service}", booking.service.as_deref().unwrap_or(""))
+}
+
+/// The reminder text for `booking`: its service's template, else the
+/// business's, else the localized default.
+pub fn reminder_text(user: &User, services: &[Service], booking: &Booking) -> String {
+    let service_template = booking
+        .service
+        .as_deref()
+        .and_then(|name| services.iter().find(|s| s.name.eq_ignore_ascii_case(name)))
+        .and_then(|s| s.reminder_template.clone());
+    let template = service_template
+        .or_else(|| user.reminder_template.clone())
+        .unwrap_or_else(|| i18n::t(&user.language, "reminder", &[]));
+    render(
+        &template,
+        booking,
+        &user.business_name,
+        &user.language,
+        user.use_24h_time,
+    )
+}
+
+/// Text every customer whose booking starts within the owner's
+/// `reminder_hours_before` and hasn't been reminded. Each booking is marked
+/// before sending so a slow send can't remind twice. Returns how many went out.
+pub async fn send_due_reminders(state: &Arc<AppState>) -> usize {
+    let due = {
+        let db = state.db.lock().unwrap();
+        let Some(user) = queries::get_user(&db, "default")
+            .ok()
+            .flatten()
+            .filter(|u| u.reminder_hours_before > 0)
+        else {
+            return 0;
+        };
+        let services = user
+            .services
+            .as_deref()
+            .and_then(|s| Service::list_from_json(s).ok())
+            .unwrap_or_default();
+        let now = Utc::now().naive_utc();
+        let until = now + Duration::hours(user.reminder_hours_before);
+        let bookings = match queries::get_bookings_due_for_reminder(&db, &now, &until) {
+            Ok(bookings) => bookings,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to load bookings due for a reminder");
+                return 0;
+            }
+        };
+
+        let mut due = vec![];
+        for booking in bookings {
+            if let Err(e) = queries::mark_reminder_sent(&db, &booking.id) {
+                tracing::error!(error = %e, booking = %booking.id, "failed to mark reminder sent");
+                continue;
+            }
+            let phone = &booking.customer_phone;
+            if queries::is_opted_out(&db, phone).unwrap_or(false)
+                || queries::is_blocked(&db, phone).unwrap_or(false)
+            {
+                continue;
+            }
+            due.push((
+                booking.customer_phone.clone(),
+                reminder_text(&user, &services, &booking),
+            ));
+        }
+        due
+    };
+
+    let mut sent = 0;
+    for (phone, text) in due {
+        if let Err(e) = quiet_hours::send_or_queue(state, &phone, &text).await {
+            tracing::error!(error = %e, phone = %mask_phone(&phone), "failed to send reminder");
+            continue;
+        }
+        record_inbox_event(state, &phone, "ai_reply", &text);
+        sent += 1;
+    }
+    sent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BookingStatus;
+
+    fn booking(service: Option<&str>) -> Booking {
+        let now = Utc::now().naive_utc();
+        Booking {
+            id: "bk-1".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: Some("Dana".to_string()),
+            date_time: chrono::NaiveDate::from_ymd_opt(2025, 6, 3)
+                .unwrap()
+                .and_hms_opt(14, 30, 0)
+                .unwrap(),
+            duration_minutes: 60,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: service.map(str::to_string),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_render_fills_placeholders() {
+        assert_eq!(
+            render(
+                "Hi {name}, see you {time} at {business}. Reply C to confirm, X to cancel.",
+                &booking(None),
+                "Test Biz",
+                "en",
+                false
+            ),
+            "Hi Dana, see you Tuesday, June 3 at 2:30 PM at Test Biz. Reply C to confirm, X to cancel."
+        );
+    }
+
+    #[test]
+    fn test_reminder_text_prefers_service_then_business_template() {
+        let services = Service::list_from_json(
+            r#"[{"name":"Color","duration_minutes":90,"reminder_template":"{service} on {time}: no washing beforehand!"},
+                {"name":"Haircut","duration_minutes":30}]"#,
+        )
+        .unwrap();
+        let mut user = User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: String::new(),
+            owner_phone: String::new(),
+            twilio_account_sid: String::new(),
+            twilio_auth_token: String::new(),
+            twilio_phone_number: String::new(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: None,
+            language: "fr".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 24,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+
+        // The owner's templates are filled in the reply language too
+        assert_eq!(
+            reminder_text(&user, &services, &booking(Some("Color"))),
+            "Color on mardi 3 juin à 2:30 PM: no washing beforehand!"
+        );
+        assert!(reminder_text(&user, &services, &booking(Some("Haircut")))
+            .starts_with("Rappel : vous avez un rendez-vous le mardi 3 juin à 2:30 PM."));
+
+        user.reminder_template = Some("{business}: {name}, {time}".to_string());
+        user.use_24h_time = true;
+        assert_eq!(
+            reminder_text(&user, &services, &booking(Some("Haircut"))),
+            "Test Biz: Dana, mardi 3 juin à 14:30"
+        );
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(ReminderReply::parse(" c "), Some(ReminderReply::Confirm));
+        assert_eq!(ReminderReply::parse("X!"), Some(ReminderReply::Cancel));
+        assert_eq!(ReminderReply::parse("cancel my haircut"), None);
+        assert_eq!(ReminderReply::parse("Cx"), None);
+    }
+}
@@ -1,32 +1,111 @@
-use chrono::{Duration, NaiveDateTime};
-use rusqlite::Connection;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use rusqlite::{Connection, Transaction, TransactionBehavior};
 
 use crate::db::queries;
-use crate::models::Availability;
+use crate::models::availability::DEFAULT_BLOCK_SIZE;
+use crate::models::{Availability, Booking, BookingActor, RejectionMessages};
+use crate::services::dates::{format_date_in, format_time, format_when_in};
+use crate::services::i18n;
 
 #[derive(Debug)]
 pub enum SchedulingError {
     OutsideBusinessHours { hours: String },
+    Closed {
+        until: String,
+        reason: Option<String>,
+    },
     Conflict,
+    /// The customer already has a booking that day and the business allows one.
+    AlreadyBookedThatDay { date: NaiveDate },
+    /// Starts before the day's `min_notice` has passed; `earliest` is the
+    /// first bookable moment.
+    TooSoon { earliest: NaiveDateTime },
+    /// Starts between the day's `block_size` boundaries; `nearest` holds the
+    /// bookable grid times either side (possibly none).
+    OffGrid { block_size: u32, nearest: Vec<NaiveDateTime> },
 }
 
 impl std::fmt::Display for SchedulingError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(i18n::DEFAULT_LANGUAGE, false))
+    }
+}
+
+impl SchedulingError {
+    /// The built-in customer-facing wording in `lang`, with times on a
+    /// 24-hour clock when `use_24h` is set.
+    pub fn localized(&self, lang: &str, use_24h: bool) -> String {
         match self {
             SchedulingError::OutsideBusinessHours { hours } => {
-                write!(
-                    f,
-                    "That time is outside our business hours. We're available: {hours}"
-                )
+                i18n::t(lang, "outside_hours", &[("hours", hours)])
+            }
+            SchedulingError::Closed { until, reason } => match reason {
+                Some(reason) => i18n::t(
+                    lang,
+                    "closed_with_reason",
+                    &[("until", until), ("reason", reason)],
+                ),
+                None => i18n::t(lang, "closed", &[("until", until)]),
+            },
+            SchedulingError::Conflict => i18n::t(lang, "conflict", &[]),
+            SchedulingError::AlreadyBookedThatDay { date } => {
+                i18n::t(lang, "one_per_day", &[("date", &format_date_in(*date, lang))])
             }
-            SchedulingError::Conflict => {
-                write!(
-                    f,
-                    "Sorry, that time slot is already booked. Could you pick a different time?"
-                )
+            SchedulingError::TooSoon { earliest } => i18n::t(
+                lang,
+                "too_soon",
+                &[("earliest", &format_when_in(*earliest, lang, use_24h))],
+            ),
+            SchedulingError::OffGrid { block_size, nearest } => {
+                let block_size = block_size.to_string();
+                let nearest: Vec<String> =
+                    nearest.iter().map(|t| format_time(*t, use_24h)).collect();
+                match nearest.as_slice() {
+                    [] => i18n::t(lang, "off_grid", &[("block_size", &block_size)]),
+                    [only] => i18n::t(
+                        lang,
+                        "off_grid_offer",
+                        &[("block_size", &block_size), ("slots", only)],
+                    ),
+                    [first, second, ..] => {
+                        let slots =
+                            i18n::t(lang, "slots_pair", &[("first", first), ("second", second)]);
+                        i18n::t(
+                            lang,
+                            "off_grid_offer",
+                            &[("block_size", &block_size), ("slots", &slots)],
+                        )
+                    }
+                }
             }
         }
     }
+
+    /// Customer-facing reply, using the owner's template for this kind of
+    /// rejection when one is set.
+    pub fn render(&self, templates: &RejectionMessages, lang: &str, use_24h: bool) -> String {
+        let template = match self {
+            SchedulingError::OutsideBusinessHours { .. } => &templates.outside_hours,
+            SchedulingError::Closed { .. } => &templates.closed,
+            SchedulingError::Conflict => &templates.conflict,
+            SchedulingError::AlreadyBookedThatDay { .. }
+            | SchedulingError::TooSoon { .. }
+            | SchedulingError::OffGrid { .. } => return self.localized(lang, use_24h),
+        };
+        if template.trim().is_empty() {
+            return self.localized(lang, use_24h);
+        }
+        match self {
+            SchedulingError::OutsideBusinessHours { hours } => template.replace("{hours}", hours),
+            SchedulingError::Closed { until, reason } => template
+                .replace("{until}", until)
+                .replace("{reason}", reason.as_deref().unwrap_or("")),
+            SchedulingError::Conflict
+            | SchedulingError::AlreadyBookedThatDay { .. }
+            | SchedulingError::TooSoon { .. }
+            | SchedulingError::OffGrid { .. } => template.clone(),
+        }
+    }
 }
 
 pub fn validate_booking_time(
@@ -34,9 +113,95 @@ pub fn validate_booking_time(
     dt: &NaiveDateTime,
     duration_minutes: i32,
     availability: Option<&Availability>,
+) -> Result<(), SchedulingError> {
+    check_booking_time(conn, dt, duration_minutes, availability, None)
+}
+
+/// Like `validate_booking_time`, but the booking being moved doesn't conflict with itself.
+pub fn validate_reschedule_time(
+    conn: &Connection,
+    booking_id: &str,
+    dt: &NaiveDateTime,
+    duration_minutes: i32,
+    availability: Option<&Availability>,
+) -> Result<(), SchedulingError> {
+    check_booking_time(conn, dt, duration_minutes, availability, Some(booking_id))
+}
+
+/// Reject a second booking for `phone` on `date`, for businesses that allow
+/// one per customer per day. `ignore_booking` is the booking being moved.
+pub fn check_daily_limit(
+    conn: &Connection,
+    phone: &str,
+    date: &NaiveDate,
+    ignore_booking: Option<&str>,
+) -> Result<(), SchedulingError> {
+    let already = queries::get_bookings_for_phone_on_date(conn, phone, date)
+        .map(|bookings| bookings.iter().any(|b| Some(b.id.as_str()) != ignore_booking))
+        .unwrap_or(false);
+    if already {
+        return Err(SchedulingError::AlreadyBookedThatDay { date: *date });
+    }
+    Ok(())
+}
+
+/// Validate and insert `booking` atomically. The conflict re-check and the
+/// insert share one `BEGIN IMMEDIATE` transaction, so two confirmations racing
+/// for the same slot can't both succeed. `replaces` is the booking being
+/// rescheduled; it's marked as such in the same transaction. With
+/// `one_per_day`, the customer's other bookings that day are re-checked too.
+pub fn create_booking_if_free(
+    conn: &Connection,
+    booking: &Booking,
+    replaces: Option<&str>,
+    availability: Option<&Availability>,
+    actor: BookingActor,
+    one_per_day: bool,
+) -> anyhow::Result<Result<(), SchedulingError>> {
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+
+    let check = check_booking_time(
+        &tx,
+        &booking.date_time,
+        booking.duration_minutes,
+        availability,
+        replaces,
+    )
+    .and_then(|()| {
+        if !one_per_day {
+            return Ok(());
+        }
+        check_daily_limit(&tx, &booking.customer_phone, &booking.date_time.date(), replaces)
+    });
+    if let Err(e) = check {
+        // Dropping the transaction rolls it back
+        return Ok(Err(e));
+    }
+
+    if let Some(old_id) = replaces {
+        queries::mark_booking_rescheduled(&tx, old_id, actor)?;
+    }
+    queries::create_booking(&tx, booking, actor)?;
+    tx.commit()?;
+    Ok(Ok(()))
+}
+
+fn check_booking_time(
+    conn: &Connection,
+    dt: &NaiveDateTime,
+    duration_minutes: i32,
+    availability: Option<&Availability>,
+    ignore_booking: Option<&str>,
 ) -> Result<(), SchedulingError> {
     // Check availability if configured
     if let Some(avail) = availability {
+        if let Some(closure) = avail.closure_for(&dt.date()) {
+            return Err(SchedulingError::Closed {
+                until: closure.end.clone(),
+                reason: closure.reason.clone(),
+            });
+        }
+        check_notice(avail, dt, Utc::now().naive_utc())?;
         if !avail.effective_slots().is_empty() {
             if !avail.is_available(dt) {
                 return Err(SchedulingError::OutsideBusinessHours {
@@ -49,34 +214,225 @@ pub fn validate_booking_time(
                 });
             }
         }
+        if let Some((earlier, later)) = avail.off_grid(dt) {
+            // Both are on the grid, so checking them can't recurse further
+            let nearest = [earlier, later]
+                .into_iter()
+                .filter(|t| {
+                    check_booking_time(conn, t, duration_minutes, availability, ignore_booking)
+                        .is_ok()
+                })
+                .collect();
+            return Err(SchedulingError::OffGrid {
+                block_size: avail.block_size_for(&dt.date()).unwrap_or_default(),
+                nearest,
+            });
+        }
     }
 
     // Check for conflicts with existing bookings
+    match find_conflict_ignoring(conn, dt, duration_minutes, ignore_booking) {
+        Ok(None) => Ok(()),
+        Ok(Some(_)) | Err(_) => Err(SchedulingError::Conflict),
+    }
+}
+
+/// Reject a start sooner than the day's `min_notice` after `now`.
+fn check_notice(
+    availability: &Availability,
+    dt: &NaiveDateTime,
+    now: NaiveDateTime,
+) -> Result<(), SchedulingError> {
+    let notice = availability.min_notice_for(&dt.date());
+    let earliest = now + Duration::minutes(notice as i64);
+    if notice > 0 && *dt < earliest {
+        return Err(SchedulingError::TooSoon {
+            earliest,
+        });
+    }
+    Ok(())
+}
+
+/// The first non-cancelled booking overlapping `[dt, dt + duration)`, if any.
+pub fn find_conflict(
+    conn: &Connection,
+    dt: &NaiveDateTime,
+    duration_minutes: i32,
+) -> anyhow::Result<Option<Booking>> {
+    find_conflict_ignoring(conn, dt, duration_minutes, None)
+}
+
+fn find_conflict_ignoring(
+    conn: &Connection,
+    dt: &NaiveDateTime,
+    duration_minutes: i32,
+    ignore_booking: Option<&str>,
+) -> anyhow::Result<Option<Booking>> {
     let day_start = dt.date().and_hms_opt(0, 0, 0).unwrap_or(*dt);
     let day_end = dt.date().and_hms_opt(23, 59, 59).unwrap_or(*dt);
 
-    let bookings = queries::get_bookings_in_range(conn, &day_start, &day_end)
-        .map_err(|_| SchedulingError::Conflict)?;
+    let bookings = queries::get_bookings_in_range(conn, &day_start, &day_end)?;
 
     let proposed_end = *dt + Duration::minutes(duration_minutes as i64);
 
-    for booking in &bookings {
+    Ok(bookings.into_iter().find(|booking| {
+        if Some(booking.id.as_str()) == ignore_booking {
+            return false;
+        }
         let booking_end =
             booking.date_time + Duration::minutes(booking.duration_minutes as i64);
         // Overlap: booking starts before proposed ends AND booking ends after proposed starts
-        if booking.date_time < proposed_end && booking_end > *dt {
-            return Err(SchedulingError::Conflict);
+        booking.date_time < proposed_end && booking_end > *dt
+    }))
+}
+
+/// Resolve a vague time of day ("afternoon") or an explicit `HH:MM-HH:MM`
+/// range into a start/end window.
+pub fn parse_time_window(window: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let hm = |h, m| NaiveTime::from_hms_opt(h, m, 0);
+    match window.trim().to_lowercase().as_str() {
+        "morning" => Some((hm(9, 0)?, hm(12, 0)?)),
+        "afternoon" => Some((hm(12, 0)?, hm(17, 0)?)),
+        "evening" => Some((hm(17, 0)?, hm(21, 0)?)),
+        "any" | "anytime" | "all day" => Some((hm(0, 0)?, hm(23, 59)?)),
+        other => {
+            let (start, end) = other.split_once('-')?;
+            let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+            let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+            (start < end).then_some((start, end))
         }
     }
+}
 
-    Ok(())
+/// Up to `limit` bookable start times on `date` within `window`, each passing
+/// `validate_booking_time` and none overlapping another. Candidates are tried
+/// every `block_size` minutes for that day (30 when unset), on its grid;
+/// anything at or before `after` is skipped.
+pub fn free_slots(
+    conn: &Connection,
+    availability: Option<&Availability>,
+    date: NaiveDate,
+    window: (NaiveTime, NaiveTime),
+    duration_minutes: i32,
+    after: NaiveDateTime,
+    limit: usize,
+) -> Vec<NaiveDateTime> {
+    let (window_start, window_end) = window;
+    let window_end = date.and_time(window_end);
+    let duration = Duration::minutes(duration_minutes as i64);
+    let step = availability
+        .and_then(|a| a.block_size_for(&date))
+        .unwrap_or(DEFAULT_BLOCK_SIZE);
+
+    let mut slots = vec![];
+    let mut candidate = date.and_time(window_start);
+    while slots.len() < limit && candidate + duration <= window_end {
+        // Keep to the block grid after a window start or booking that's off it
+        if let Some((_, next)) = availability.and_then(|a| a.off_grid(&candidate)) {
+            candidate = next;
+            continue;
+        }
+        if candidate > after
+            && validate_booking_time(conn, &candidate, duration_minutes, availability).is_ok()
+        {
+            slots.push(candidate);
+            candidate += duration;
+        } else {
+            candidate += Duration::minutes(step as i64);
+        }
+    }
+    slots
+}
+
+/// Which of the offered `slots` the customer picked, from an explicit time
+/// ("the 2pm one", "14:30") or position ("the first one"). A bare number
+/// ("2 kids") is never read as a time. `None` if unclear.
+pub fn pick_slot(
+    slots: &[NaiveDateTime],
+    message: &str,
+    requested_time: Option<&str>,
+) -> Option<NaiveDateTime> {
+    if let Some(time) = requested_time.and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok()) {
+        if let Some(slot) = slots.iter().find(|s| s.time() == time) {
+            return Some(*slot);
+        }
+    }
+
+    let text = message
+        .to_lowercase()
+        .replace(" pm", "pm")
+        .replace(" am", "am")
+        .replace("p.m.", "pm")
+        .replace("a.m.", "am");
+    let tokens: Vec<&str> = text
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == ':'))
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let mut matches: Vec<NaiveDateTime> = vec![];
+    for token in &tokens {
+        let position = match *token {
+            "first" | "1st" => Some(0),
+            "second" | "2nd" => Some(1),
+            "third" | "3rd" => Some(2),
+            "last" => slots.len().checked_sub(1),
+            _ => None,
+        };
+        if let Some(slot) = position.and_then(|i| slots.get(i)) {
+            matches.push(*slot);
+            continue;
+        }
+
+        let Some((hour, minute, meridiem)) = parse_time_token(token) else {
+            continue;
+        };
+        for slot in slots {
+            let hour_matches = match meridiem {
+                Some(pm) => slot.hour() == hour % 12 + if pm { 12 } else { 0 },
+                None if hour > 12 => slot.hour() == hour,
+                None => slot.hour() % 12 == hour % 12,
+            };
+            if hour_matches && slot.minute() == minute {
+                matches.push(*slot);
+            }
+        }
+    }
+
+    matches.dedup();
+    match matches.as_slice() {
+        [only] => Some(*only),
+        _ => None,
+    }
+}
+
+/// Parse "2pm", "2:30pm" or "14:00" into (hour, minute, Some(is_pm) when a
+/// meridiem was given). A bare number isn't a time.
+fn parse_time_token(token: &str) -> Option<(u32, u32, Option<bool>)> {
+    let (clock, meridiem) = if let Some(t) = token.strip_suffix("pm") {
+        (t, Some(true))
+    } else if let Some(t) = token.strip_suffix("am") {
+        (t, Some(false))
+    } else {
+        (token, None)
+    };
+
+    let (hour, minute) = match (clock.split_once(':'), meridiem) {
+        (Some((h, m)), _) => (h.parse().ok()?, m.parse().ok()?),
+        (None, Some(_)) => (clock.parse().ok()?, 0),
+        (None, None) => return None,
+    };
+    let max_hour = if meridiem.is_some() { 12 } else { 23 };
+    if !(0..=max_hour).contains(&hour) || minute > 59 {
+        return None;
+    }
+    Some((hour, minute, meridiem))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db;
-    use crate::models::{Booking, BookingStatus};
+    use crate::models::BookingStatus;
 
     fn setup_db() -> Connection {
         db::init_db(":memory:").unwrap()
@@ -134,10 +490,11 @@ mod tests {
             duration_minutes: 60,
             status: BookingStatus::Confirmed,
             notes: None,
+            service: None,
             created_at: now,
             updated_at: now,
         };
-        queries::create_booking(&conn, &booking).unwrap();
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
 
         // Proposing 10:30 overlaps with 10:00-11:00
         let result = validate_booking_time(&conn, &dt("2025-06-16 10:30"), 60, None);
@@ -158,10 +515,11 @@ mod tests {
             duration_minutes: 60,
             status: BookingStatus::Confirmed,
             notes: None,
+            service: None,
             created_at: now,
             updated_at: now,
         };
-        queries::create_booking(&conn, &booking).unwrap();
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
 
         // 11:00 starts exactly when previous ends — no overlap
         let result = validate_booking_time(&conn, &dt("2025-06-16 11:00"), 60, None);
@@ -186,4 +544,299 @@ mod tests {
         let result = validate_booking_time(&conn, &dt("2025-06-15 20:00"), 60, Some(&avail));
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_closed_date_range_rejected() {
+        let conn = setup_db();
+        let avail = make_avail(
+            r#"{"slots":[{"day":"mon","start":"09:00","end":"17:00"}],"closures":[{"start":"2025-06-14","end":"2025-06-16","reason":"Vacation"}]}"#,
+        );
+        // 2025-06-16 is a Monday, the last day of the closure
+        let result = validate_booking_time(&conn, &dt("2025-06-16 10:00"), 60, Some(&avail));
+        let err = result.unwrap_err();
+        assert!(matches!(err, SchedulingError::Closed { .. }));
+        assert!(err.to_string().contains("Vacation"));
+        assert!(err.to_string().contains("2025-06-16"));
+    }
+
+    #[test]
+    fn test_reschedule_ignores_own_slot() {
+        let conn = setup_db();
+        let now = chrono::Utc::now().naive_utc();
+
+        let booking = Booking {
+            id: "existing-3".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: Some("Alice".to_string()),
+            date_time: dt("2025-06-16 10:00"),
+            duration_minutes: 60,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
+
+        // Sliding 30 minutes later overlaps only the booking being moved
+        let result = validate_reschedule_time(&conn, &booking.id, &dt("2025-06-16 10:30"), 60, None);
+        assert!(result.is_ok());
+        let result = validate_booking_time(&conn, &dt("2025-06-16 10:30"), 60, None);
+        assert!(matches!(result.unwrap_err(), SchedulingError::Conflict));
+    }
+
+    #[test]
+    fn test_parse_time_window() {
+        let t = |s| NaiveTime::parse_from_str(s, "%H:%M").unwrap();
+        assert_eq!(parse_time_window("Afternoon"), Some((t("12:00"), t("17:00"))));
+        assert_eq!(parse_time_window("14:00-16:30"), Some((t("14:00"), t("16:30"))));
+        assert_eq!(parse_time_window("16:00-14:00"), None);
+        assert_eq!(parse_time_window("soonish"), None);
+    }
+
+    #[test]
+    fn test_free_slots_skips_conflicts_and_breaks() {
+        let conn = setup_db();
+        let now = chrono::Utc::now().naive_utc();
+        let booking = Booking {
+            id: "existing-4".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: None,
+            date_time: dt("2025-06-19 14:00"),
+            duration_minutes: 60,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
+        let avail = make_avail(
+            r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","breaks":[{"start":"12:00","end":"13:00"}]}"#,
+        );
+
+        let date = NaiveDate::from_ymd_opt(2025, 6, 19).unwrap();
+        let window = parse_time_window("afternoon").unwrap();
+        let slots = free_slots(&conn, Some(&avail), date, window, 60, dt("2025-06-01 00:00"), 3);
+        assert_eq!(
+            slots,
+            vec![dt("2025-06-19 13:00"), dt("2025-06-19 15:00"), dt("2025-06-19 16:00")]
+        );
+
+        // Nothing in the past is offered
+        let slots = free_slots(&conn, Some(&avail), date, window, 60, dt("2025-06-19 15:30"), 3);
+        assert_eq!(slots, vec![dt("2025-06-19 16:00")]);
+    }
+
+    #[test]
+    fn test_free_slots_steps_by_weekday_block_size() {
+        let conn = setup_db();
+        let avail = make_avail(
+            r#"{"block_size":60,"slots":[
+                {"day":"mon","start":"09:00","end":"17:00"},
+                {"day":"sat","start":"09:00","end":"13:00","block_size":15}]}"#,
+        );
+        let window = parse_time_window("10:00-12:00").unwrap();
+
+        // 2025-06-21 is a Saturday: 15-minute steps
+        let sat = NaiveDate::from_ymd_opt(2025, 6, 21).unwrap();
+        let slots = free_slots(&conn, Some(&avail), sat, window, 30, dt("2025-06-21 10:05"), 1);
+        assert_eq!(slots, vec![dt("2025-06-21 10:15")]);
+
+        // Weekdays keep the top-level hour
+        let mon = NaiveDate::from_ymd_opt(2025, 6, 16).unwrap();
+        let slots = free_slots(&conn, Some(&avail), mon, window, 30, dt("2025-06-16 10:05"), 1);
+        assert_eq!(slots, vec![dt("2025-06-16 11:00")]);
+    }
+
+    #[test]
+    fn test_min_notice_uses_weekday_override() {
+        let avail = make_avail(
+            r#"{"min_notice":60,"slots":[
+                {"day":"fri","start":"09:00","end":"17:00"},
+                {"day":"sat","start":"09:00","end":"13:00","min_notice":1440}]}"#,
+        );
+        // Friday noon
+        let now = dt("2025-06-20 12:00");
+
+        assert!(matches!(
+            check_notice(&avail, &dt("2025-06-20 12:30"), now),
+            Err(SchedulingError::TooSoon { .. })
+        ));
+        assert!(check_notice(&avail, &dt("2025-06-20 13:00"), now).is_ok());
+        let err = check_notice(&avail, &dt("2025-06-21 10:00"), now).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Sorry, that's too soon for us to book. The earliest we can do is Saturday, June 21 at 12:00 PM."
+        );
+        assert!(err.localized("en", true).ends_with("Saturday, June 21 at 12:00."));
+        assert!(check_notice(&avail, &dt("2025-06-21 12:00"), now).is_ok());
+    }
+
+    #[test]
+    fn test_off_grid_time_rejected_with_nearest_grid_times() {
+        let conn = setup_db();
+        let avail = make_avail(
+            r#"{"block_size":30,"slots":[{"day":"mon","start":"09:15","end":"17:00"}]}"#,
+        );
+        // The grid runs from the slot start: 09:15, 09:45, 10:15, ...
+        assert!(validate_booking_time(&conn, &dt("2025-06-16 10:45"), 30, Some(&avail)).is_ok());
+
+        let err = validate_booking_time(&conn, &dt("2025-06-16 10:52"), 30, Some(&avail)).unwrap_err();
+        assert!(matches!(
+            &err,
+            SchedulingError::OffGrid { block_size: 30, nearest }
+                if nearest == &[dt("2025-06-16 10:45"), dt("2025-06-16 11:15")]
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Sorry, appointments start every 30 minutes. Would 10:45 AM or 11:15 AM work instead?"
+        );
+        assert_eq!(
+            err.localized("en", true),
+            "Sorry, appointments start every 30 minutes. Would 10:45 or 11:15 work instead?"
+        );
+
+        // A taken neighbour isn't offered
+        let now = chrono::Utc::now().naive_utc();
+        let booking = Booking {
+            id: "existing-5".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: None,
+            date_time: dt("2025-06-16 11:15"),
+            duration_minutes: 30,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
+        let err = validate_booking_time(&conn, &dt("2025-06-16 10:52"), 30, Some(&avail)).unwrap_err();
+        assert!(matches!(
+            err,
+            SchedulingError::OffGrid { nearest, .. } if nearest == [dt("2025-06-16 10:45")]
+        ));
+
+        // Offers stay on the grid too, even after a 45-minute slot
+        let mon = NaiveDate::from_ymd_opt(2025, 6, 16).unwrap();
+        let window = parse_time_window("12:00-14:30").unwrap();
+        let slots = free_slots(&conn, Some(&avail), mon, window, 45, dt("2025-06-01 00:00"), 2);
+        assert_eq!(slots, vec![dt("2025-06-16 12:15"), dt("2025-06-16 13:15")]);
+
+        // Without a block size any start time is fine
+        let avail = make_avail(r#"{"slots":[{"day":"mon","start":"09:15","end":"17:00"}]}"#);
+        assert!(validate_booking_time(&conn, &dt("2025-06-16 10:22"), 30, Some(&avail)).is_ok());
+    }
+
+    #[test]
+    fn test_pick_slot() {
+        let slots = [dt("2025-06-19 12:00"), dt("2025-06-19 14:00"), dt("2025-06-19 15:30")];
+        assert_eq!(pick_slot(&slots, "the 2pm one", None), Some(slots[1]));
+        assert_eq!(pick_slot(&slots, "3:30 PM please", None), Some(slots[2]));
+        assert_eq!(pick_slot(&slots, "I'll take the first", None), Some(slots[0]));
+        assert_eq!(pick_slot(&slots, "ok", Some("12:00")), Some(slots[0]));
+        assert_eq!(pick_slot(&slots, "14:00 works", None), Some(slots[1]));
+        assert_eq!(pick_slot(&slots, "5pm?", None), None);
+        assert_eq!(pick_slot(&slots, "12pm or 2pm", None), None);
+        assert_eq!(pick_slot(&slots, "Can I bring 2 kids?", None), None);
+    }
+
+    #[test]
+    fn test_create_booking_if_free_rejects_overlap() {
+        let conn = setup_db();
+        let now = chrono::Utc::now().naive_utc();
+        let booking = |id: &str, at: &str| Booking {
+            id: id.to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: None,
+            date_time: dt(at),
+            duration_minutes: 60,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let first = booking("first", "2025-06-16 10:00");
+        let result = create_booking_if_free(&conn, &first, None, None, BookingActor::Customer, false);
+        assert!(result.unwrap().is_ok());
+
+        let second = booking("second", "2025-06-16 10:30");
+        let result = create_booking_if_free(&conn, &second, None, None, BookingActor::Customer, false);
+        assert!(matches!(result.unwrap(), Err(SchedulingError::Conflict)));
+        assert!(queries::get_booking_by_id(&conn, "second").unwrap().is_none());
+
+        // Moving "first" onto an overlapping time is fine and retires the original
+        let moved = booking("moved", "2025-06-16 10:30");
+        let result =
+            create_booking_if_free(&conn, &moved, Some("first"), None, BookingActor::Customer, false);
+        assert!(result.unwrap().is_ok());
+        let first = queries::get_booking_by_id(&conn, "first").unwrap().unwrap();
+        assert_eq!(first.status, BookingStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_daily_limit_counts_only_that_customer_and_day() {
+        let conn = setup_db();
+        let now = chrono::Utc::now().naive_utc();
+        let booking = Booking {
+            id: "morning".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: None,
+            date_time: dt("2025-06-16 10:00"),
+            duration_minutes: 60,
+            status: BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        queries::create_booking(&conn, &booking, BookingActor::Customer).unwrap();
+
+        let day = dt("2025-06-16 15:00").date();
+        let err = check_daily_limit(&conn, "+15551110000", &day, None);
+        assert!(matches!(err, Err(SchedulingError::AlreadyBookedThatDay { .. })));
+        assert!(check_daily_limit(&conn, "+15552220000", &day, None).is_ok());
+        assert!(check_daily_limit(&conn, "+15551110000", &dt("2025-06-17 10:00").date(), None).is_ok());
+        // Moving the day's only booking doesn't count against itself
+        assert!(check_daily_limit(&conn, "+15551110000", &day, Some("morning")).is_ok());
+    }
+
+    #[test]
+    fn test_render_uses_owner_template() {
+        let err = SchedulingError::OutsideBusinessHours {
+            hours: "Mon 09:00-17:00".to_string(),
+        };
+        let templates = RejectionMessages {
+            outside_hours: "We're only open {hours}, sorry!".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(err.render(&templates, "en", false), "We're only open Mon 09:00-17:00, sorry!");
+
+        let closed = SchedulingError::Closed {
+            until: "June 20".to_string(),
+            reason: Some("vacation".to_string()),
+        };
+        let templates = RejectionMessages {
+            closed: "Away until {until} ({reason}).".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(closed.render(&templates, "en", false), "Away until June 20 (vacation).");
+    }
+
+    #[test]
+    fn test_render_falls_back_to_default_wording() {
+        let templates = RejectionMessages {
+            outside_hours: "Custom {hours}".to_string(),
+            ..Default::default()
+        };
+        let err = SchedulingError::Conflict;
+        assert_eq!(err.render(&templates, "en", false), err.to_string());
+        assert_eq!(
+            err.render(&RejectionMessages::default(), "fr", false),
+            "Désolé, ce créneau est déjà réservé. Pourriez-vous choisir un autre horaire ?"
+        );
+    }
 }
@@ -0,0 +1,72 @@
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+/// Token proving a link for `value` was issued by this server, so customer
+/// links can't be guessed or pointed at someone else's booking.
+pub fn sign(secret: &str, value: &str) -> anyhow::Result<String> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes())?;
+    mac.update(value.as_bytes());
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+}
+
+/// Whether `token` is `sign(secret, value)`, compared in constant time.
+pub fn verify(secret: &str, value: &str, token: &str) -> bool {
+    let Ok(expected) = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(token) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(value.as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Compare a presented secret with the expected one without bailing at the
+/// first differing byte, so response timing doesn't reveal how much matched.
+pub fn constant_time_eq(given: &str, expected: &str) -> bool {
+    let (a, b) = (given.as_bytes(), expected.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Self-service cancel link for a booking, texted with the confirmation.
+pub fn cancel_path(secret: &str, booking_id: &str) -> anyhow::Result<String> {
+    Ok(format!(
+        "/booking/{booking_id}/cancel?token={}",
+        sign(secret, booking_id)?
+    ))
+}
+
+/// Link listing a customer's upcoming bookings. Phones are E.164, so `+` is
+/// the only character that needs escaping in the query string.
+pub fn lookup_path(secret: &str, phone: &str) -> anyhow::Result<String> {
+    Ok(format!(
+        "/booking/lookup?phone={}&token={}",
+        phone.replace('+', "%2B"),
+        sign(secret, phone)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let token = sign("secret", "booking-1").unwrap();
+        assert!(verify("secret", "booking-1", &token));
+        assert!(!verify("secret", "booking-2", &token));
+        assert!(!verify("other", "booking-1", &token));
+        assert!(!verify("secret", "booking-1", "not a token"));
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("test-token", "test-token"));
+        assert!(constant_time_eq("", ""));
+        assert!(!constant_time_eq("test-tokem", "test-token"));
+        assert!(!constant_time_eq("test", "test-token"));
+        assert!(!constant_time_eq("test-token-2", "test-token"));
+        assert!(!constant_time_eq("", "test-token"));
+    }
+}
@@ -0,0 +1,110 @@
+//! The owner's spam keywords, checked against customer texts before they
+//! reach the conversation engine.
+
+/// Words real customers book with. A keyword containing one would turn them
+/// away, so settings refuse it.
+const BOOKING_WORDS: &[&str] = &[
+    "appointment",
+    "appt",
+    "available",
+    "availability",
+    "book",
+    "booking",
+    "cancel",
+    "confirm",
+    "reschedule",
+    "today",
+    "tomorrow",
+];
+
+/// Keywords shorter than this match too much ordinary text.
+const MIN_KEYWORD_LEN: usize = 3;
+
+/// Why a spam keyword can't be saved.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum SpamKeywordError {
+    #[error("spam keyword '{0}' is too short; use at least 3 characters")]
+    TooShort(String),
+    #[error("spam keyword '{keyword}' contains '{word}', which customers book with")]
+    BookingWord { keyword: String, word: &'static str },
+}
+
+/// Split a stored or submitted list on commas and newlines, dropping blanks.
+pub fn parse_keywords(list: &str) -> Vec<&str> {
+    list.split([',', '\n'])
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+pub fn check_keyword(keyword: &str) -> Result<(), SpamKeywordError> {
+    if keyword.chars().count() < MIN_KEYWORD_LEN {
+        return Err(SpamKeywordError::TooShort(keyword.to_string()));
+    }
+    let lower = keyword.to_lowercase();
+    match BOOKING_WORDS.iter().find(|w| contains_phrase(&lower, w)) {
+        Some(word) => Err(SpamKeywordError::BookingWord {
+            keyword: keyword.to_string(),
+            word,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The first of `keywords` found in `message`, ignoring case. A keyword only
+/// matches whole words, so "cash" doesn't catch "cashew".
+pub fn matched_keyword<'a>(keywords: &'a str, message: &str) -> Option<&'a str> {
+    let message = message.to_lowercase();
+    parse_keywords(keywords)
+        .into_iter()
+        .find(|k| contains_phrase(&message, &k.to_lowercase()))
+}
+
+/// Whether `phrase` occurs in `text` with no letter or digit directly on
+/// either side.
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    let is_word = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    text.match_indices(phrase).any(|(i, m)| {
+        !is_word(text[..i].chars().next_back()) && !is_word(text[i + m.len()..].chars().next())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matched_keyword_is_case_insensitive_and_whole_word() {
+        let keywords = "crypto, free money\nWIN BIG";
+        assert_eq!(
+            matched_keyword(keywords, "Get FREE MONEY now!!!"),
+            Some("free money")
+        );
+        assert_eq!(
+            matched_keyword(keywords, "win big: crypto."),
+            Some("crypto")
+        );
+        assert_eq!(matched_keyword(keywords, "Cryptography class at 3?"), None);
+        assert_eq!(
+            matched_keyword(keywords, "Can I book a haircut tomorrow at 2?"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_keyword_refuses_booking_words() {
+        assert_eq!(check_keyword("crypto"), Ok(()));
+        assert_eq!(check_keyword("bookie"), Ok(()));
+        assert_eq!(
+            check_keyword("ok"),
+            Err(SpamKeywordError::TooShort("ok".to_string()))
+        );
+        assert_eq!(
+            check_keyword("Book now"),
+            Err(SpamKeywordError::BookingWord {
+                keyword: "Book now".to_string(),
+                word: "book",
+            })
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use crate::db::queries;
+use crate::logging::mask_phone;
+use crate::models::{Availability, Booking};
+use crate::services::dates::format_when_in;
+use crate::services::i18n;
+use crate::services::inbox::record_inbox_event;
+use crate::services::quiet_hours;
+use crate::services::scheduling::validate_booking_time;
+use crate::state::AppState;
+
+/// Text the longest-waiting customer whose requested time overlapped `freed`
+/// and is now bookable, holding the text through quiet hours. Their entry is
+/// removed so nobody is told twice, and put back if the text can't go out.
+pub async fn offer_freed_slot(state: &Arc<AppState>, freed: &Booking) {
+    let (entry, lang, use_24h) = {
+        let db = state.db.lock().unwrap();
+        let user = queries::get_user(&db, "default").ok().flatten();
+        let availability = user
+            .as_ref()
+            .and_then(|u| u.availability.as_deref())
+            .and_then(|s| Availability::from_json(s).ok());
+        let use_24h = user.as_ref().is_some_and(|u| u.use_24h_time);
+        let lang = user
+            .map(|u| u.language)
+            .unwrap_or_else(|| i18n::DEFAULT_LANGUAGE.to_string());
+
+        let waiting = match queries::get_waitlist_overlapping(
+            &db,
+            &freed.date_time,
+            freed.duration_minutes,
+        ) {
+            Ok(waiting) => waiting,
+            Err(e) => {
+                tracing::error!(error = %e, "failed to read waitlist");
+                return;
+            }
+        };
+        // Another booking may still cover part of a waiting customer's time
+        let entry = waiting.into_iter().find(|e| {
+            !queries::is_opted_out(&db, &e.phone).unwrap_or(false)
+                && validate_booking_time(&db, &e.date_time, e.duration_minutes, availability.as_ref())
+                    .is_ok()
+        });
+        if let Some(ref e) = entry {
+            let _ = queries::remove_waitlist_entry(&db, e.id);
+        }
+        (entry, lang, use_24h)
+    };
+    let Some(entry) = entry else {
+        return;
+    };
+
+    let when = format_when_in(entry.date_time, &lang, use_24h);
+    let message = i18n::t(&lang, "waitlist_opening", &[("when", &when)]);
+    match quiet_hours::send_or_queue(state, &entry.phone, &message).await {
+        Ok(()) => {
+            tracing::info!(phone = %mask_phone(&entry.phone), "notified waitlisted customer");
+            record_inbox_event(state, &entry.phone, "ai_reply", &message);
+        }
+        Err(e) => {
+            tracing::error!(error = %e, phone = %mask_phone(&entry.phone), "failed to notify waitlisted customer");
+            let db = state.db.lock().unwrap();
+            let _ = queries::restore_waitlist_entry(&db, &entry);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Number of requests whose handlers are still running.
+#[derive(Clone, Default)]
+pub struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Decrements the counter even if the handler future is dropped mid-way.
+struct InFlightGuard(InFlight);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        (self.0).0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Middleware counting requests in flight, so shutdown can report what it's waiting on.
+pub async fn track_in_flight(State(in_flight): State<InFlight>, req: Request, next: Next) -> Response {
+    in_flight.0.fetch_add(1, Ordering::SeqCst);
+    let _guard = InFlightGuard(in_flight);
+    next.run(req).await
+}
+
+/// Resolves on SIGINT (Ctrl-C) or SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{middleware, Router};
+    use tokio::sync::Notify;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_in_flight_counts_running_handlers() {
+        let in_flight = InFlight::default();
+        let started = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+
+        let app = Router::new()
+            .route(
+                "/slow",
+                get({
+                    let (started, release) = (Arc::clone(&started), Arc::clone(&release));
+                    move || async move {
+                        started.notify_one();
+                        release.notified().await;
+                        "done"
+                    }
+                }),
+            )
+            .layer(middleware::from_fn_with_state(in_flight.clone(), track_in_flight));
+
+        let request = tokio::spawn(
+            app.oneshot(axum::http::Request::get("/slow").body(Body::empty()).unwrap()),
+        );
+        started.notified().await;
+        assert_eq!(in_flight.count(), 1);
+
+        release.notify_one();
+        request.await.unwrap().unwrap();
+        assert_eq!(in_flight.count(), 0);
+    }
+}
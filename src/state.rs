@@ -1,13 +1,18 @@
+use std::collections::VecDeque;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex};
 
+use dashmap::DashMap;
 use rusqlite::Connection;
 use serde::Serialize;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Semaphore};
 
+use crate::auth_limit::AuthLimiter;
 use crate::config::AppConfig;
-use crate::models::InboxEvent;
-use crate::services::ai::LlmProvider;
+use crate::models::InboxUpdate;
+use crate::services::ai::breaker::CircuitBreaker;
+use crate::services::ai::{LlmLog, LlmProvider};
+use crate::services::debounce::PendingMessages;
 use crate::services::messaging::MessagingProvider;
 
 #[derive(Clone, Serialize)]
@@ -25,12 +30,102 @@ pub enum DevNotificationKind {
     System,
 }
 
+/// Notifications for the dev chat UI. Nothing drains it in production, so
+/// only the most recent `capacity` entries are kept; 0 records nothing.
+pub struct DevNotificationQueue {
+    capacity: usize,
+    entries: Mutex<VecDeque<DevNotification>>,
+}
+
+impl DevNotificationQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn push(&self, notification: DevNotification) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(notification);
+    }
+
+    pub fn peek(&self) -> Vec<DevNotification> {
+        self.entries
+            .lock()
+            .map(|e| e.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn drain(&self) -> Vec<DevNotification> {
+        self.entries
+            .lock()
+            .map(|mut e| e.drain(..).collect())
+            .unwrap_or_default()
+    }
+}
+
 pub struct AppState {
     pub db: Arc<Mutex<Connection>>,
     pub config: AppConfig,
     pub llm: Box<dyn LlmProvider>,
+    /// Caps simultaneous LLM calls; extra requests queue for a permit.
+    pub llm_permits: Semaphore,
+    /// Skips the LLM after repeated failures (`LLM_BREAKER_THRESHOLD`).
+    pub llm_breaker: CircuitBreaker,
+    /// Recent prompts and raw responses, recorded only when `LLM_DEBUG_LOG` is on.
+    pub llm_log: LlmLog,
     pub messaging: Box<dyn MessagingProvider>,
+    /// One lock per phone number so messages from the same sender are
+    /// processed in order; different numbers still run in parallel.
+    pub conversation_locks: DashMap<String, Arc<tokio::sync::Mutex<()>>>,
+    /// Rapid texts waiting to be processed together (only when `DEBOUNCE_MS` > 0).
+    pub debounce_buffers: DashMap<String, PendingMessages>,
     pub paused: AtomicBool,
-    pub dev_notifications: Mutex<Vec<DevNotification>>,
-    pub inbox_tx: broadcast::Sender<InboxEvent>,
+    /// Capped by `DEV_NOTIFICATION_BUFFER`.
+    pub dev_notifications: DevNotificationQueue,
+    pub inbox_tx: broadcast::Sender<InboxUpdate>,
+    /// Failed token checks per client, for locking out brute-force attempts.
+    pub auth_limiter: AuthLimiter,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(n: usize) -> DevNotification {
+        DevNotification {
+            phone: None,
+            kind: DevNotificationKind::System,
+            content: n.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dev_notification_queue_keeps_most_recent() {
+        let queue = DevNotificationQueue::new(100);
+        for n in 0..200 {
+            queue.push(notification(n));
+        }
+        let kept = queue.drain();
+        assert_eq!(kept.len(), 100);
+        assert_eq!(kept[0].content, "100");
+        assert_eq!(kept[99].content, "199");
+        assert!(queue.drain().is_empty());
+    }
+
+    #[test]
+    fn test_dev_notification_queue_disabled_at_zero() {
+        let queue = DevNotificationQueue::new(0);
+        queue.push(notification(1));
+        assert!(queue.drain().is_empty());
+    }
 }
@@ -4,18 +4,23 @@ use std::sync::{Arc, Mutex};
 use async_trait::async_trait;
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
-use axum::routing::{get, post};
+use axum::routing::{delete, get, post};
 use axum::Router;
+use dashmap::DashMap;
 use tower::ServiceExt;
 
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, Semaphore};
+use tokio_stream::StreamExt;
 
+use phonebook::auth_limit::AuthLimiter;
 use phonebook::config::AppConfig;
 use phonebook::db;
 use phonebook::handlers;
-use phonebook::services::ai::{LlmProvider, Message};
+use phonebook::models::BookingActor;
+use phonebook::services::ai::breaker::CircuitBreaker;
+use phonebook::services::ai::{LlmLog, LlmProvider, Message};
 use phonebook::services::messaging::MessagingProvider;
-use phonebook::state::AppState;
+use phonebook::state::{AppState, DevNotificationQueue};
 
 // ── Mock Providers ──
 
@@ -39,6 +44,99 @@ impl LlmProvider for MockLlm {
     }
 }
 
+/// Wraps `MockLlm` and records every system prompt it receives.
+#[derive(Default)]
+struct RecordingLlm {
+    prompts: Arc<Mutex<Vec<String>>>,
+    histories: Arc<Mutex<Vec<Vec<Message>>>>,
+}
+
+#[async_trait]
+impl LlmProvider for RecordingLlm {
+    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+        self.prompts.lock().unwrap().push(system_prompt.to_string());
+        self.histories.lock().unwrap().push(messages.to_vec());
+        MockLlm.chat(system_prompt, messages).await
+    }
+}
+
+/// Replays canned LLM responses in order, one per `chat` call.
+struct ScriptedLlm {
+    responses: Mutex<std::collections::VecDeque<String>>,
+}
+
+impl ScriptedLlm {
+    fn new(responses: &[&str]) -> Self {
+        Self {
+            responses: Mutex::new(responses.iter().map(|r| r.to_string()).collect()),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ScriptedLlm {
+    async fn chat(&self, _system_prompt: &str, _messages: &[Message]) -> anyhow::Result<String> {
+        self.responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| anyhow::anyhow!("no scripted response left"))
+    }
+}
+
+/// Tracks how many `chat` calls are in flight at once.
+struct ConcurrencyProbeLlm {
+    in_flight: Arc<std::sync::atomic::AtomicUsize>,
+    max_seen: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait]
+impl LlmProvider for ConcurrencyProbeLlm {
+    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+        use std::sync::atomic::Ordering;
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_seen.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        MockLlm.chat(system_prompt, messages).await
+    }
+}
+
+/// Signals each `chat` call as it starts, then waits until the test releases
+/// it, so a turn can be held open deterministically.
+struct GatedLlm {
+    entered: tokio::sync::mpsc::UnboundedSender<()>,
+    release: Arc<tokio::sync::Semaphore>,
+    in_flight: Arc<std::sync::atomic::AtomicUsize>,
+    max_seen: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait]
+impl LlmProvider for GatedLlm {
+    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+        use std::sync::atomic::Ordering;
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_seen.fetch_max(now, Ordering::SeqCst);
+        let _ = self.entered.send(());
+        self.release.acquire().await?.forget();
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        MockLlm.chat(system_prompt, messages).await
+    }
+}
+
+/// Always fails, like an LLM that's down, and counts the attempts.
+struct FailingLlm {
+    calls: Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait]
+impl LlmProvider for FailingLlm {
+    async fn chat(&self, _system_prompt: &str, _messages: &[Message]) -> anyhow::Result<String> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        anyhow::bail!("connection refused")
+    }
+}
+
 struct MockMessaging {
     sent: Arc<Mutex<Vec<(String, String)>>>,
 }
@@ -62,91 +160,253 @@ impl MessagingProvider for MockMessaging {
     }
 }
 
+/// Records successful sends and fails every send to `unreachable`.
+struct FlakyMessaging {
+    unreachable: String,
+    sent: SentMessages,
+}
+
+#[async_trait]
+impl MessagingProvider for FlakyMessaging {
+    async fn send_message(&self, to: &str, body: &str) -> anyhow::Result<()> {
+        if to == self.unreachable {
+            anyhow::bail!("carrier rejected message to {to}");
+        }
+        self.sent
+            .lock()
+            .unwrap()
+            .push((to.to_string(), body.to_string()));
+        Ok(())
+    }
+}
+
 // ── Helpers ──
 
 fn test_config() -> AppConfig {
     AppConfig {
         port: 3000,
         database_url: ":memory:".to_string(),
+        db_busy_timeout_ms: 5000,
         admin_token: "test-token".to_string(),
+        json_webhook_token: "".to_string(),
         ollama_url: "http://localhost:11434".to_string(),
+        ollama_model: "llama3.2".to_string(),
         twilio_account_sid: "".to_string(),
         twilio_auth_token: "".to_string(), // empty = skip signature validation
         twilio_phone_number: "+15551234567".to_string(),
         owner_phone: "+15559999999".to_string(),
+        backup_owner_phone: "".to_string(),
+        public_url: "".to_string(),
         llm_provider: "ollama".to_string(),
         groq_api_key: "".to_string(),
         groq_model: "llama-3.3-70b-versatile".to_string(),
+        llm_temperature: 0.2,
+        yearless_date_policy: phonebook::services::dates::YearlessDatePolicy::RollForward,
+        llm_max_concurrency: 8,
+        max_history_messages: 20,
+        metrics_token: "".to_string(),
+        shutdown_timeout_secs: 30,
+        auto_block_minutes: 1440,
+        debounce_ms: 0,
+        broadcast_batch_size: 10,
+        broadcast_batch_delay_ms: 0,
+        sse_keepalive_secs: 30,
+        ics_alarm_minutes: 0,
+        llm_debug_log: false,
+        http_timeout_secs: 15,
+        llm_breaker_threshold: 5,
+        llm_breaker_cooldown_secs: 60,
+        dev_notification_buffer: 100,
+        max_inbound_body: 1600,
+        summarize_after: 0,
+        confirm_reprompt_minutes: 10,
+        conversation_grace_minutes: 5,
+        cleanup_interval_minutes: 10,
+        inbox_retention_days: 365,
+        log_format: "pretty".to_string(),
+        log_message_bodies: true,
+        default_country_code: "+1".to_string(),
+        admin_command_secret: String::new(),
+        auth_max_failures: 10,
+        auth_lockout_secs: 900,
+        trusted_proxies: vec![],
     }
 }
 
-fn test_state() -> Arc<AppState> {
-    let config = test_config();
+fn build_state(
+    config: AppConfig,
+    llm: Box<dyn LlmProvider>,
+    messaging: Box<dyn MessagingProvider>,
+) -> Arc<AppState> {
     let conn = db::init_db(":memory:").unwrap();
     let (inbox_tx, _) = broadcast::channel(64);
     Arc::new(AppState {
         db: Arc::new(Mutex::new(conn)),
+        llm_permits: Semaphore::new(config.llm_max_concurrency),
+        llm_breaker: CircuitBreaker::new(
+            config.llm_breaker_threshold,
+            std::time::Duration::from_secs(config.llm_breaker_cooldown_secs),
+        ),
+        llm_log: LlmLog::default(),
+        dev_notifications: DevNotificationQueue::new(config.dev_notification_buffer),
+        auth_limiter: AuthLimiter::new(
+            config.auth_max_failures,
+            std::time::Duration::from_secs(config.auth_lockout_secs),
+            config.trusted_proxies.clone(),
+        ),
         config,
-        llm: Box::new(MockLlm),
-        messaging: Box::new(MockMessaging::new()),
+        llm,
+        messaging,
+        conversation_locks: DashMap::new(),
+        debounce_buffers: DashMap::new(),
         paused: AtomicBool::new(false),
-        dev_notifications: Mutex::new(Vec::new()),
         inbox_tx,
     })
 }
 
-fn test_state_with_sent() -> (Arc<AppState>, Arc<Mutex<Vec<(String, String)>>>) {
-    let config = test_config();
-    let conn = db::init_db(":memory:").unwrap();
+fn test_state() -> Arc<AppState> {
+    build_state(
+        test_config(),
+        Box::new(MockLlm),
+        Box::new(MockMessaging::new()),
+    )
+}
+
+type SentMessages = Arc<Mutex<Vec<(String, String)>>>;
+
+fn test_state_with_sent() -> (Arc<AppState>, SentMessages) {
     let sent = Arc::new(Mutex::new(vec![]));
     let messaging = MockMessaging {
         sent: Arc::clone(&sent),
     };
-    let (inbox_tx, _) = broadcast::channel(64);
-    let state = Arc::new(AppState {
-        db: Arc::new(Mutex::new(conn)),
-        config,
-        llm: Box::new(MockLlm),
-        messaging: Box::new(messaging),
-        paused: AtomicBool::new(false),
-        dev_notifications: Mutex::new(Vec::new()),
-        inbox_tx,
-    });
+    let state = build_state(test_config(), Box::new(MockLlm), Box::new(messaging));
     (state, sent)
 }
 
+fn test_state_with_llm(llm: Box<dyn LlmProvider>) -> Arc<AppState> {
+    build_state(test_config(), llm, Box::new(MockMessaging::new()))
+}
+
 /// Build a POST to /webhook/sms from the owner phone number.
 fn owner_sms_request(body: &str) -> Request<Body> {
-    let encoded = body
-        .replace('%', "%25")
-        .replace('#', "%23")
-        .replace('+', "%2B")
-        .replace(' ', "+");
+    sms_request("+15559999999", body)
+}
+
+/// Build a POST to /webhook/sms from `from`.
+fn sms_request(from: &str, body: &str) -> Request<Body> {
+    let encode = |s: &str| {
+        s.replace('%', "%25")
+            .replace('#', "%23")
+            .replace('+', "%2B")
+            .replace('&', "%26")
+            .replace(' ', "+")
+    };
     Request::builder()
         .method("POST")
         .uri("/webhook/sms")
         .header("Content-Type", "application/x-www-form-urlencoded")
         .body(Body::from(format!(
-            "From=%2B15559999999&To=%2B15551234567&Body={encoded}&MessageSid=SM_admin"
+            "From={}&To=%2B15551234567&Body={}&MessageSid=SM_test",
+            encode(from),
+            encode(body)
         )))
         .unwrap()
 }
 
+/// Insert a confirmed one-hour booking for +15551110000.
+fn seed_customer_booking(state: &Arc<AppState>, id: &str, date_time: chrono::NaiveDateTime) {
+    let db = state.db.lock().unwrap();
+    let now = chrono::Utc::now().naive_utc();
+    let booking = phonebook::models::Booking {
+        id: id.to_string(),
+        customer_phone: "+15551110000".to_string(),
+        customer_name: Some("Alice".to_string()),
+        date_time,
+        duration_minutes: 60,
+        status: phonebook::models::BookingStatus::Confirmed,
+        notes: None,
+        service: None,
+        created_at: now,
+        updated_at: now,
+    };
+    phonebook::db::queries::create_booking(&db, &booking, BookingActor::Customer).unwrap();
+}
+
+fn stored_conversation(state: &Arc<AppState>, phone: &str) -> phonebook::models::Conversation {
+    let db = state.db.lock().unwrap();
+    phonebook::db::queries::get_conversation(&db, phone, false)
+        .unwrap()
+        .expect("conversation should be stored")
+}
+
 fn test_app(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/health", get(handlers::health::health))
+        .route("/metrics", get(handlers::metrics::metrics))
         .route("/webhook/sms", post(handlers::webhook::sms_webhook))
+        .route("/webhook/sms/json", post(handlers::webhook::sms_webhook_json))
+        .route("/webhook/status", post(handlers::webhook::status_webhook))
+        .route("/reschedule/:token", get(handlers::reschedule::reschedule_page))
+        .route("/reschedule/:token", post(handlers::reschedule::submit_reschedule))
+        .route("/booking/lookup", get(handlers::booking::lookup))
+        .route(
+            "/booking/:id/cancel",
+            get(handlers::booking::cancel_page).post(handlers::booking::submit_cancel),
+        )
         .route("/app", get(handlers::admin::app_page))
         .route("/admin", get(handlers::admin::redirect_to_app))
         .route("/api/admin/status", get(handlers::admin::get_status))
+        .route("/api/admin/activity", get(handlers::admin::get_activity))
         .route("/api/admin/bookings", get(handlers::admin::get_bookings))
+        .route(
+            "/api/admin/bookings/import",
+            post(handlers::admin::import_bookings_csv),
+        )
         .route(
             "/api/admin/bookings/:id/cancel",
             post(handlers::admin::cancel_booking),
         )
+        .route(
+            "/api/admin/bookings/:id/no-show",
+            post(handlers::admin::mark_no_show),
+        )
+        .route(
+            "/api/admin/bookings/:id/history",
+            get(handlers::admin::get_booking_history),
+        )
+        .route("/api/admin/contacts", get(handlers::admin::get_contacts))
+        .route(
+            "/api/admin/contacts/merge",
+            post(handlers::admin::merge_contacts),
+        )
+        .route(
+            "/api/admin/contacts/:phone/notes",
+            get(handlers::admin::get_contact_notes).put(handlers::admin::put_contact_notes),
+        )
+        .route(
+            "/api/admin/contacts/:phone/export",
+            get(handlers::admin::export_contact),
+        )
+        .route(
+            "/api/admin/contacts/:phone",
+            delete(handlers::admin::purge_contact),
+        )
+        .route(
+            "/api/admin/conversation/:phone",
+            get(handlers::admin::get_conversation),
+        )
+        .route(
+            "/api/admin/conversation/:phone/reset",
+            post(handlers::admin::reset_conversation),
+        )
         .route("/api/admin/blocked", get(handlers::admin::get_blocked))
         .route("/api/admin/block", post(handlers::admin::block_number))
         .route("/api/admin/unblock", post(handlers::admin::unblock_number))
+        .route("/api/admin/allow", post(handlers::admin::allow_number))
+        .route(
+            "/api/admin/allow/:phone",
+            delete(handlers::admin::unallow_number),
+        )
         .route("/api/admin/pause", post(handlers::admin::pause_agent))
         .route("/api/admin/resume", post(handlers::admin::resume_agent))
         .route("/api/admin/settings", get(handlers::admin::get_settings))
@@ -154,11 +414,50 @@ fn test_app(state: Arc<AppState>) -> Router {
             "/api/admin/settings",
             post(handlers::admin::update_settings),
         )
+        .route("/api/admin/backup", get(handlers::admin::backup_database))
+        .route("/api/admin/broadcast", post(handlers::admin::broadcast))
+        .route("/api/admin/pricing", get(handlers::admin::get_pricing))
+        .route(
+            "/api/admin/availability/override",
+            get(handlers::admin::list_overrides),
+        )
+        .route(
+            "/api/admin/availability/override",
+            post(handlers::admin::upsert_override),
+        )
+        .route(
+            "/api/admin/availability/override/:date",
+            delete(handlers::admin::delete_override),
+        )
+        .route(
+            "/api/admin/availability/check",
+            get(handlers::admin::check_availability),
+        )
+        .route("/calendar/feed.ics", get(handlers::calendar::calendar_feed))
+        .route("/api/dev/message", post(handlers::dev::send_message))
+        .route("/api/dev/llm-log", get(handlers::dev::llm_log))
+        .route("/api/dev/notifications", get(handlers::dev::notifications))
         .route(
             "/calendar/:booking_id",
             get(handlers::calendar::download_ics),
         )
-        .with_state(state)
+        .route("/api/inbox/thread/:phone", get(handlers::inbox::get_thread))
+        .route("/api/inbox/events", get(handlers::inbox::events_stream))
+        .route(
+            "/api/inbox/thread/:phone/ai",
+            post(handlers::inbox::set_thread_ai),
+        )
+        .route(
+            "/api/inbox/thread/:phone/resume",
+            post(handlers::inbox::resume_thread),
+        )
+        .route("/api/inbox/reply", post(handlers::inbox::send_reply))
+        .with_state(state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            state,
+            phonebook::auth_limit::limit_failed_auth,
+        ))
+        .layer(axum::middleware::from_fn(phonebook::access_log::log_requests))
 }
 
 // ── Admin API Tests ──
@@ -200,6 +499,80 @@ async fn test_admin_wrong_token() {
     assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[tokio::test]
+async fn test_repeated_wrong_tokens_are_rate_limited() {
+    let mut config = test_config();
+    config.auth_max_failures = 3;
+    let state = build_state(config, Box::new(MockLlm), Box::new(MockMessaging::new()));
+
+    let status_from = |ip: [u8; 4], token: &str| {
+        Request::builder()
+            .uri("/api/admin/status")
+            .header("Authorization", format!("Bearer {token}"))
+            .extension(axum::extract::ConnectInfo(std::net::SocketAddr::from((ip, 40000))))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let attacker = [203, 0, 113, 5];
+    let owner = [198, 51, 100, 7];
+
+    let mut statuses = Vec::new();
+    for _ in 0..6 {
+        let res = test_app(state.clone())
+            .oneshot(status_from(attacker, "guess"))
+            .await
+            .unwrap();
+        statuses.push(res.status());
+    }
+    assert_eq!(&statuses[..3], &[StatusCode::UNAUTHORIZED; 3]);
+    assert_eq!(&statuses[3..], &[StatusCode::TOO_MANY_REQUESTS; 3]);
+
+    // The lockout covers the token query-param endpoints too
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/calendar/feed.ics?token=guess")
+                .extension(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+                    attacker, 40000,
+                ))))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // ...and the dev endpoints that take the admin token
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/dev/llm-log")
+                .header("Authorization", "Bearer guess")
+                .extension(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+                    attacker, 40000,
+                ))))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Other clients are unaffected, and a correct token clears their count
+    for token in ["typo", "typo", "test-token", "typo", "typo", "test-token"] {
+        let res = test_app(state.clone())
+            .oneshot(status_from(owner, token))
+            .await
+            .unwrap();
+        let expected = if token == "test-token" {
+            StatusCode::OK
+        } else {
+            StatusCode::UNAUTHORIZED
+        };
+        assert_eq!(res.status(), expected);
+    }
+}
+
 #[tokio::test]
 async fn test_admin_status() {
     let state = test_state();
@@ -227,6 +600,60 @@ async fn test_admin_status() {
     assert_eq!(json["upcoming_bookings_count"], 0);
 }
 
+#[tokio::test]
+async fn test_admin_activity() {
+    let state = test_state();
+    {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::increment_monthly_received(&db).unwrap();
+        phonebook::db::queries::increment_monthly_received(&db).unwrap();
+        phonebook::db::queries::increment_monthly_bookings(&db).unwrap();
+    }
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/activity")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    // Default is six months, oldest first, current month last
+    assert_eq!(json.len(), 6);
+    let current = json.last().unwrap();
+    assert_eq!(current["month"], chrono::Utc::now().format("%Y-%m").to_string());
+    assert_eq!(current["messages_received"], 2);
+    assert_eq!(current["bookings_created"], 1);
+    assert_eq!(current["messages_sent"], 0);
+    assert_eq!(json[0]["messages_received"], 0);
+
+    // Out-of-range values are clamped
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/activity?months=100")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.len(), 24);
+}
+
 #[tokio::test]
 async fn test_admin_pause_resume() {
     let state = test_state();
@@ -389,7 +816,7 @@ async fn test_admin_settings() {
                 .header("Authorization", "Bearer test-token")
                 .header("Content-Type", "application/json")
                 .body(Body::from(
-                    r#"{"business_name":"Test Biz","owner_name":"Alice","timezone":"America/New_York","availability":"Mon-Fri 9-5"}"#,
+                    r#"{"business_name":"Test Biz","owner_name":"Alice","timezone":"America/New_York","availability":"{\"slots\":[{\"day\":\"mon\",\"start\":\"09:00\",\"end\":\"17:00\"}]}"}"#,
                 ))
                 .unwrap(),
         )
@@ -416,399 +843,5531 @@ async fn test_admin_settings() {
     assert_eq!(json["business_name"], "Test Biz");
     assert_eq!(json["owner_name"], "Alice");
     assert_eq!(json["timezone"], "America/New_York");
-    assert_eq!(json["availability"], "Mon-Fri 9-5");
+    assert_eq!(
+        json["availability"],
+        r#"{"slots":[{"day":"mon","start":"09:00","end":"17:00"}]}"#
+    );
 }
 
-// ── Webhook Tests ──
-
 #[tokio::test]
-async fn test_webhook_processes_message() {
+async fn test_settings_update_ai_preferences_and_owner_phone() {
     let state = test_state();
-    let app = test_app(state);
+    let settings = |body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/settings")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
 
-    let res = app
+    let prefs = serde_json::json!({ "tone": "casual", "agent_name": "Sam" }).to_string();
+    let res = test_app(state.clone())
+        .oneshot(settings(serde_json::json!({
+            "ai_preferences": prefs,
+            "owner_phone": " +15557654321 ",
+        })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = test_app(state.clone())
+        .oneshot(settings(serde_json::json!({ "ai_preferences": "{not json" })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json["error"].as_str().unwrap().starts_with("invalid ai_preferences JSON: "));
+
+    // The rejected update left the earlier preferences in place
+    let res = test_app(state)
         .oneshot(
             Request::builder()
-                .method("POST")
-                .uri("/webhook/sms")
-                .header("Content-Type", "application/x-www-form-urlencoded")
-                .body(Body::from(
-                    "From=%2B15551110000&To=%2B15551234567&Body=hello&MessageSid=SM123",
-                ))
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-
-    assert_eq!(res.status(), StatusCode::OK);
     let body = axum::body::to_bytes(res.into_body(), usize::MAX)
         .await
         .unwrap();
-    let text = String::from_utf8(body.to_vec()).unwrap();
-    assert!(text.contains("<Response>"));
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["ai_preferences"], prefs);
+    assert_eq!(json["owner_phone"], "+15557654321");
 }
 
 #[tokio::test]
-async fn test_webhook_blocked_number_ignored() {
+async fn test_settings_rejects_malformed_availability() {
     let state = test_state();
+    let settings = |availability: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/settings")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::json!({ "availability": availability }).to_string()))
+            .unwrap()
+    };
 
-    // Block a number first
+    for bad in ["Mon-Fri 9-5", r#"{"slots":[{"day":"funday","start":"09:00","end":"17:00"}]}"#] {
+        let res = test_app(state.clone()).oneshot(settings(bad)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{bad} should be rejected");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["error"].as_str().unwrap().starts_with("invalid availability JSON: "));
+    }
     {
         let db = state.db.lock().unwrap();
-        phonebook::db::queries::block_number(&db, "+15551110000", Some("test"), false).unwrap();
+        let user = phonebook::db::queries::get_user(&db, "default").unwrap();
+        assert!(user.and_then(|u| u.availability).is_none());
     }
 
-    let app = test_app(state);
-    let res = app
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/webhook/sms")
-                .header("Content-Type", "application/x-www-form-urlencoded")
-                .body(Body::from(
-                    "From=%2B15551110000&To=%2B15551234567&Body=hello&MessageSid=SM123",
-                ))
-                .unwrap(),
-        )
+    let res = test_app(state.clone())
+        .oneshot(settings(r#"{"slots":[{"day":"tue","start":"10:00","end":"14:00"}]}"#))
         .await
         .unwrap();
-
     assert_eq!(res.status(), StatusCode::OK);
+    let tuesday = chrono::NaiveDate::from_ymd_opt(2025, 6, 17)
+        .unwrap()
+        .and_hms_opt(11, 0, 0)
+        .unwrap();
+    assert!(stored_availability(&state).is_available(&tuesday));
 }
 
 #[tokio::test]
-async fn test_webhook_paused_agent_silent() {
-    let state = test_state();
-    state
-        .paused
-        .store(true, std::sync::atomic::Ordering::SeqCst);
+async fn test_system_prompt_override_reaches_provider() {
+    let prompts = Arc::new(Mutex::new(vec![]));
+    let state = test_state_with_llm(Box::new(RecordingLlm {
+        prompts: Arc::clone(&prompts),
+        ..Default::default()
+    }));
 
-    let app = test_app(state);
-    let res = app
+    let prefs = serde_json::json!({
+        "system_prompt_override": "Only ever book 30-minute slots. Dates are always DD/MM."
+    })
+    .to_string();
+    let res = test_app(state.clone())
         .oneshot(
             Request::builder()
                 .method("POST")
-                .uri("/webhook/sms")
-                .header("Content-Type", "application/x-www-form-urlencoded")
-                .body(Body::from(
-                    "From=%2B15551110000&To=%2B15551234567&Body=hello&MessageSid=SM123",
-                ))
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "ai_preferences": prefs }).to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
-
     assert_eq!(res.status(), StatusCode::OK);
-}
 
-// ── Rate Limiting Tests ──
+    phonebook::services::conversation::process_message(&state, "+15551110000", "hello")
+        .await
+        .unwrap();
+
+    let prompts = prompts.lock().unwrap();
+    let system = &prompts[0];
+    assert!(system.contains("Only ever book 30-minute slots. Dates are always DD/MM."));
+    // The response schema stays; the default guidance is replaced
+    assert!(system.contains("Return ONLY valid JSON"));
+    assert!(!system.contains("Intent rules:"));
+}
+
+#[tokio::test]
+async fn test_named_service_sets_pending_duration() {
+    let prompts = Arc::new(Mutex::new(vec![]));
+    let state = test_state_with_llm(Box::new(RecordingLlm {
+        prompts: Arc::clone(&prompts),
+        ..Default::default()
+    }));
+
+    let settings = |services: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/settings")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::json!({ "services": services }).to_string()))
+            .unwrap()
+    };
+    let res = test_app(state.clone())
+        .oneshot(settings(r#"[{"name":"Color","duration_minutes":0}]"#))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test_app(state.clone())
+        .oneshot(settings(
+            r#"[{"name":"Haircut","duration_minutes":30,"price":"$35"},{"name":"Color","duration_minutes":90,"price":"$80"}]"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The mock LLM guesses 60 minutes; the configured service wins
+    phonebook::services::conversation::process_message(
+        &state,
+        "+15551110000",
+        "can I book a color?",
+    )
+    .await
+    .unwrap();
+
+    let conv = stored_conversation(&state, "+15551110000");
+    let pending = conv.pending_booking.expect("pending booking");
+    assert_eq!(pending.duration_minutes, Some(90));
+    assert_eq!(pending.service.as_deref(), Some("Color"));
+    assert!(prompts.lock().unwrap()[0].contains("Services offered: Haircut (30 min, $35), Color (90 min, $80)."));
+}
+
+#[tokio::test]
+async fn test_broadcast_reaches_selected_audience() {
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let mut config = test_config();
+    config.broadcast_batch_size = 2;
+    let state = build_state(
+        config,
+        Box::new(MockLlm),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+
+    // Alice has an upcoming booking; Bob and Cara only texted; Dan is blocked
+    seed_customer_booking(
+        &state,
+        "bk-upcoming",
+        chrono::Utc::now().naive_utc() + chrono::Duration::days(3),
+    );
+    {
+        let db = state.db.lock().unwrap();
+        for phone in ["+15552220001", "+15552220002", "+15552220003", "+15559999999"] {
+            phonebook::db::queries::insert_inbox_event(&db, phone, "customer_message", "hi").unwrap();
+        }
+        phonebook::db::queries::block_number(&db, "+15552220003", Some("spam"), false).unwrap();
+    }
+
+    let broadcast = |body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/broadcast")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let recipients = |sent: &SentMessages, message: &str| -> Vec<String> {
+        let mut to: Vec<String> = sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, body)| body == message)
+            .map(|(to, _)| to.clone())
+            .collect();
+        to.sort();
+        to
+    };
+
+    let res = test_app(state.clone())
+        .oneshot(broadcast(serde_json::json!({ "message": "Hi", "audience": "everyone" })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = test_app(state.clone())
+        .oneshot(broadcast(
+            serde_json::json!({ "message": "Closed Friday", "audience": "upcoming" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(recipients(&sent, "Closed Friday"), vec!["+15551110000"]);
+
+    let res = test_app(state.clone())
+        .oneshot(broadcast(serde_json::json!({ "message": "20% off" })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["queued"], 3);
+    assert_eq!(summary["sent"], 3);
+    assert_eq!(summary["failed"], 0);
+    assert_eq!(summary["skipped_blocked"], 1);
+    // Blocked numbers and the owner never receive it
+    assert_eq!(
+        recipients(&sent, "20% off"),
+        vec!["+15551110000", "+15552220001", "+15552220002"]
+    );
+
+    let res = test_app(state.clone())
+        .oneshot(broadcast(
+            serde_json::json!({ "message": "New hours", "audience": "recent", "days": 7 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(recipients(&sent, "New hours"), vec!["+15552220001", "+15552220002"]);
+
+    // Each send lands in the recipient's inbox thread
+    let db = state.db.lock().unwrap();
+    let events = phonebook::db::queries::get_thread_events(&db, "+15552220001", 50).unwrap();
+    assert!(events.iter().any(|e| e.kind == "owner_reply" && e.content == "New hours"));
+}
+
+fn import_request(uri: &str, csv: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri(uri)
+        .header("Authorization", "Bearer test-token")
+        .header("content-type", "text/csv")
+        .body(Body::from(csv.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_import_bookings_csv() {
+    let state = test_state();
+    seed_customer_booking(
+        &state,
+        "bk-existing",
+        chrono::NaiveDate::from_ymd_opt(2030, 1, 7)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap(),
+    );
+
+    let csv = "id,customer_phone,customer_name,date_time,duration_minutes,status,notes,service\n\
+               bk-a,+15552220001,Bob,2030-01-07 11:00:00,30,confirmed,\"Side door, ring twice\",Haircut\n\
+               bk-b,+15552220002,Cara,2030-01-07 14:00,,pending,,\n\
+               bk-existing,+15551110000,Alice,2030-01-07 09:00:00,60,confirmed,,\n";
+    let res = test_app(state.clone())
+        .oneshot(import_request("/api/admin/bookings/import", csv))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["imported"], 2);
+    assert_eq!(summary["skipped"], 1);
+    assert_eq!(summary["failed"], 0);
+    assert_eq!(summary["rows"][2]["status"], "skipped");
+
+    {
+        let db = state.db.lock().unwrap();
+        let a = phonebook::db::queries::get_booking_by_id(&db, "bk-a")
+            .unwrap()
+            .unwrap();
+        assert_eq!(a.notes.as_deref(), Some("Side door, ring twice"));
+        assert_eq!(a.service.as_deref(), Some("Haircut"));
+        let b = phonebook::db::queries::get_booking_by_id(&db, "bk-b")
+            .unwrap()
+            .unwrap();
+        assert_eq!(b.status, phonebook::models::BookingStatus::Pending);
+        assert_eq!(b.duration_minutes, 60);
+    }
+
+    // An overlapping booking is refused unless validation is switched off
+    let csv = "customer_phone,date_time\n+15552220003,2030-01-07 11:15\n";
+    let res = test_app(state.clone())
+        .oneshot(import_request("/api/admin/bookings/import", csv))
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["failed"], 1);
+
+    let res = test_app(state.clone())
+        .oneshot(import_request("/api/admin/bookings/import?validate=false", csv))
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["imported"], 1);
+
+    // Cancelled and no-show rows aren't checked against the taken 11:00 slot,
+    // and don't take the 16:00 one from the confirmed row after them
+    let csv = "id,customer_phone,date_time,status\n\
+               bk-gone,+15552220004,2030-01-07 11:00,cancelled\n\
+               bk-absent,+15552220005,2030-01-07 11:00,no_show\n\
+               bk-absent2,+15552220006,2030-01-07 16:00,no_show\n\
+               bk-later,+15552220007,2030-01-07 16:00,confirmed\n";
+    let res = test_app(state.clone())
+        .oneshot(import_request("/api/admin/bookings/import", csv))
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["imported"], 4, "{summary}");
+    assert_eq!(summary["failed"], 0);
+    let db = state.db.lock().unwrap();
+    let absent = phonebook::db::queries::get_booking_by_id(&db, "bk-absent")
+        .unwrap()
+        .unwrap();
+    assert_eq!(absent.status, phonebook::models::BookingStatus::NoShow);
+}
+
+#[tokio::test]
+async fn test_import_bookings_reports_malformed_row() {
+    let state = test_state();
+
+    let csv = "id,customer_phone,date_time,status\n\
+               bk-1,+15552220001,2030-02-04 10:00,confirmed\n\
+               bk-2,+15552220002,next tuesday,confirmed\n\
+               bk-3,+15552220003,2030-02-04 12:00,confirmed\n";
+    let res = test_app(state.clone())
+        .oneshot(import_request("/api/admin/bookings/import", csv))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["imported"], 2);
+    assert_eq!(summary["failed"], 1);
+    assert_eq!(summary["rows"][1]["row"], 2);
+    assert_eq!(summary["rows"][1]["id"], "bk-2");
+    assert_eq!(summary["rows"][1]["status"], "error");
+    assert!(summary["rows"][1]["error"]
+        .as_str()
+        .unwrap()
+        .contains("invalid date_time"));
+
+    {
+        let db = state.db.lock().unwrap();
+        assert!(phonebook::db::queries::get_booking_by_id(&db, "bk-3")
+            .unwrap()
+            .is_some());
+        assert!(phonebook::db::queries::get_booking_by_id(&db, "bk-2")
+            .unwrap()
+            .is_none());
+    }
+
+    // A file without the required columns is rejected outright
+    let res = test_app(state)
+        .oneshot(import_request("/api/admin/bookings/import", "id,name\nx,y\n"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_database_backup_download() {
+    let state = test_state();
+    seed_customer_booking(
+        &state,
+        "bk-backup",
+        chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+    );
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/backup")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/backup")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "application/octet-stream"
+    );
+    let length: usize = res.headers()["content-length"].to_str().unwrap().parse().unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.starts_with(b"SQLite format 3\0"));
+    assert_eq!(body.len(), length);
+}
+
+#[tokio::test]
+async fn test_contact_export_and_purge() {
+    let state = test_state();
+
+    // A customer with a booking and a live conversation/inbox thread
+    seed_customer_booking(
+        &state,
+        "bk-privacy",
+        chrono::Utc::now().naive_utc() + chrono::Duration::days(3),
+    );
+    let res = test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello there"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/contacts/%2B15551110000/export")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let export: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(export["phone"], "+15551110000");
+    assert_eq!(export["bookings"][0]["customer_name"], "Alice");
+    assert!(!export["inbox_events"].as_array().unwrap().is_empty());
+    assert_eq!(export["conversation"]["messages"][0]["content"], "hello there");
+
+    // Purging requires auth
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/admin/contacts/%2B15551110000")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/admin/contacts/%2B15551110000?anonymize_bookings=true")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let purge: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(purge["conversations_deleted"], 1);
+    assert_eq!(purge["bookings_anonymized"], 1);
+
+    let db = state.db.lock().unwrap();
+    assert!(phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+        .unwrap()
+        .is_none());
+    assert!(phonebook::db::queries::get_thread_events(&db, "+15551110000", 100)
+        .unwrap()
+        .is_empty());
+    let booking = phonebook::db::queries::get_booking_by_id(&db, "bk-privacy")
+        .unwrap()
+        .unwrap();
+    assert_eq!(booking.customer_name, None);
+    assert_eq!(booking.status, phonebook::models::BookingStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn test_merge_contacts_moves_history_to_primary() {
+    let state = test_state();
+    let now = chrono::Utc::now().naive_utc();
+    seed_customer_booking(&state, "bk-primary", now + chrono::Duration::days(3));
+    {
+        let db = state.db.lock().unwrap();
+        let secondary = "5551110000";
+        let booking = phonebook::models::Booking {
+            id: "bk-secondary".to_string(),
+            customer_phone: secondary.to_string(),
+            customer_name: Some("Alice".to_string()),
+            date_time: now + chrono::Duration::days(5),
+            duration_minutes: 60,
+            status: phonebook::models::BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        phonebook::db::queries::create_booking(&db, &booking, BookingActor::Customer).unwrap();
+        for (phone, content) in [
+            ("+15551110000", "hi from the new format"),
+            (secondary, "hi from the old format"),
+        ] {
+            phonebook::db::queries::insert_inbox_event(&db, phone, "customer_message", content)
+                .unwrap();
+        }
+        let conv = phonebook::models::Conversation {
+            phone: secondary.to_string(),
+            messages: vec![],
+            state: phonebook::models::ConversationState::Idle,
+            pending_booking: None,
+            paused_autoreply_sent: false,
+            reprompt_sent: false,
+            summary: Some("Regular, likes mornings".to_string()),
+            last_activity: now,
+            expires_at: now + chrono::Duration::minutes(30),
+        };
+        phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+    }
+
+    let merge = |primary: &str, secondary: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/contacts/merge")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "primary": primary, "secondary": secondary }).to_string(),
+            ))
+            .unwrap()
+    };
+    let res = test_app(state.clone())
+        .oneshot(merge("+15551110000", "+15551110000"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = test_app(state.clone())
+        .oneshot(merge("+15551110000", "5551110000"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["merged"]["bookings_moved"], 1);
+    assert_eq!(json["merged"]["inbox_events_moved"], 1);
+    assert_eq!(json["merged"]["conversation_moved"], true);
+    assert_eq!(json["contact"]["phone"], "+15551110000");
+    assert_eq!(json["contact"]["total_bookings"], 2);
+
+    let db = state.db.lock().unwrap();
+    let export = phonebook::db::queries::export_contact(&db, "+15551110000").unwrap();
+    assert_eq!(export.bookings.len(), 2);
+    assert_eq!(export.inbox_events.len(), 2);
+    assert_eq!(
+        export.conversation.unwrap().summary.as_deref(),
+        Some("Regular, likes mornings")
+    );
+    let leftover = phonebook::db::queries::export_contact(&db, "5551110000").unwrap();
+    assert!(leftover.bookings.is_empty());
+    assert!(leftover.inbox_events.is_empty());
+    assert!(leftover.conversation.is_none());
+}
+
+#[tokio::test]
+async fn test_contact_notes_round_trip_and_reach_llm() {
+    let prompts = Arc::new(Mutex::new(vec![]));
+    let state = test_state_with_llm(Box::new(RecordingLlm {
+        prompts: Arc::clone(&prompts),
+        ..Default::default()
+    }));
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: Some(
+                r#"{"returning_customers":{"remember_preferences":true}}"#.to_string(),
+            ),
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+    let res = test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello there"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("PUT")
+                .uri("/api/admin/contacts/%2B15551110000/notes")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"notes": "Prefers Dana", "tags": "vip , mornings,"})
+                        .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let saved: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(saved["tags"], "vip,mornings");
+
+    // Notes and tags show up in the contacts list
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/contacts")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let contacts: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    let alice = contacts
+        .iter()
+        .find(|c| c["phone"] == "+15551110000")
+        .unwrap();
+    assert_eq!(alice["notes"], "Prefers Dana");
+    assert_eq!(alice["tags"], "vip,mornings");
+
+    // With remember_preferences on, the LLM sees them too
+    let res = test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hi again"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let prompts = prompts.lock().unwrap();
+    let last = prompts.last().unwrap();
+    assert!(last.contains("Prefers Dana"));
+    assert!(last.contains("vip,mornings"));
+}
+
+#[tokio::test]
+async fn test_structured_pricing_in_context_and_api() {
+    let prompts = Arc::new(Mutex::new(vec![]));
+    let state = test_state_with_llm(Box::new(RecordingLlm {
+        prompts: Arc::clone(&prompts),
+        ..Default::default()
+    }));
+
+    let prefs = r#"{"boundaries":{"pricing":[{"service":"Haircut","price":"$35"},{"service":"Color","price":"$80"}]}}"#;
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "ai_preferences": prefs }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Pricing is queryable for the dashboard
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/pricing")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.len(), 2);
+    assert_eq!(json[1]["service"], "Color");
+    assert_eq!(json[1]["price"], "$80");
+
+    // The asked-about price lands in the business context
+    phonebook::services::conversation::process_message(
+        &state,
+        "+15550004444",
+        "how much for a color?",
+    )
+    .await
+    .unwrap();
+    let prompts = prompts.lock().unwrap();
+    let system = prompts.last().unwrap();
+    assert!(system.contains("Haircut: $35, Color: $80"));
+    assert!(
+        system.contains("Price asked about: Color is $80."),
+        "expected matched price in context, got: {system}"
+    );
+}
+
+fn stored_availability(state: &Arc<AppState>) -> phonebook::models::Availability {
+    let db = state.db.lock().unwrap();
+    let user = phonebook::db::queries::get_user(&db, "default")
+        .unwrap()
+        .unwrap();
+    phonebook::models::Availability::from_json(user.availability.as_deref().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_availability_override_endpoints() {
+    let state = test_state();
+    // 2025-06-16 is a Monday
+    let monday =
+        chrono::NaiveDateTime::parse_from_str("2025-06-16 10:00", "%Y-%m-%d %H:%M").unwrap();
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"availability":"{\"slots\":[],\"day_from\":\"mon\",\"day_to\":\"fri\",\"time_from\":\"09:00\",\"time_to\":\"17:00\"}"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(stored_availability(&state).is_available(&monday));
+
+    // Add an override closing the day
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/availability/override")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"date":"2025-06-16","available":false}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(!stored_availability(&state).is_available(&monday));
+
+    // List overrides
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/availability/override")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.len(), 1);
+    assert_eq!(json[0]["date"], "2025-06-16");
+    assert_eq!(json[0]["available"], false);
+
+    // Invalid date is rejected
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/availability/override")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"date":"16/06/2025","available":false}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Delete the override — the day opens again
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/admin/availability/override/2025-06-16")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let avail = stored_availability(&state);
+    assert!(avail.overrides.is_empty());
+    assert!(avail.is_available(&monday));
+}
+
+// ── Inbox ──
+
+#[tokio::test]
+async fn test_thread_events_page_backward_with_before_id() {
+    let state = test_state();
+    {
+        let db = state.db.lock().unwrap();
+        for i in 0..30 {
+            phonebook::db::queries::insert_inbox_event(
+                &db,
+                "+15551110000",
+                "customer_message",
+                &format!("msg {i}"),
+            )
+            .unwrap();
+        }
+    }
+
+    let mut pages: Vec<Vec<String>> = vec![];
+    let mut before_id = i64::MAX;
+    loop {
+        let res = test_app(state.clone())
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/api/inbox/thread/%2B15551110000?limit=10&before_id={before_id}"
+                    ))
+                    .header("Authorization", "Bearer test-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let events: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        if events.is_empty() {
+            break;
+        }
+        before_id = events[0]["id"].as_i64().unwrap();
+        pages.push(
+            events
+                .iter()
+                .map(|e| e["content"].as_str().unwrap().to_string())
+                .collect(),
+        );
+    }
+
+    assert_eq!(pages.len(), 3);
+    // Newest page first, each page in chronological order
+    assert_eq!(pages[0].first().unwrap(), "msg 20");
+    assert_eq!(pages[0].last().unwrap(), "msg 29");
+    assert_eq!(pages[2].first().unwrap(), "msg 0");
+    assert_eq!(pages[2].last().unwrap(), "msg 9");
+}
+
+#[tokio::test]
+async fn test_sse_sends_thread_summary_after_event() {
+    let state = test_state();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/inbox/events?token=test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut stream = res.into_body().into_data_stream();
+
+    phonebook::services::inbox::record_inbox_event(&state, "+15551110000", "customer_message", "hi");
+    phonebook::services::inbox::record_inbox_event(&state, "+15551110000", "customer_message", "hello?");
+
+    let mut received = String::new();
+    while received.matches("event: thread_summary").count() < 2 {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(2), stream.next())
+            .await
+            .expect("timed out waiting for SSE events")
+            .unwrap()
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    // The existing event still arrives, followed by the thread's new unread count
+    let event_at = received.find("event: inbox_event").unwrap();
+    let summary_at = received.find("event: thread_summary").unwrap();
+    assert!(event_at < summary_at);
+    assert!(received.contains(r#"data: {"phone":"+15551110000","unread_count":2}"#));
+}
+
+#[tokio::test]
+async fn test_sse_events_carry_ids_and_resume_from_last_event_id() {
+    let state = test_state();
+    phonebook::services::inbox::record_inbox_event(&state, "+15551110000", "customer_message", "seen");
+
+    // Last-Event-ID stands in for last_id, so the already-seen event isn't replayed
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/inbox/events?token=test-token")
+                .header("Last-Event-ID", "1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut stream = res.into_body().into_data_stream();
+    phonebook::services::inbox::record_inbox_event(&state, "+15551110000", "customer_message", "new");
+
+    let mut received = String::new();
+    while !received.contains("event: thread_summary") {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(2), stream.next())
+            .await
+            .expect("timed out waiting for SSE events")
+            .unwrap()
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+
+    assert!(received.contains("retry:3000\n"));
+    assert!(received.contains("id: 2\n"));
+    assert!(!received.contains("seen"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_sse_event_recorded_during_catchup_is_sent_once() {
+    let state = test_state();
+
+    // Hold the DB so the stream subscribes and then waits on its catch-up query
+    let request = {
+        let db = state.db.lock().unwrap();
+        let request = tokio::spawn(test_app(state.clone()).oneshot(
+            Request::builder()
+                .uri("/api/inbox/events?token=test-token&last_id=0")
+                .body(Body::empty())
+                .unwrap(),
+        ));
+        while state.inbox_tx.receiver_count() == 0 {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        // Recorded in the window: visible to the catch-up query and broadcast live
+        let id = phonebook::db::queries::insert_inbox_event(
+            &db,
+            "+15551110000",
+            "customer_message",
+            "in the gap",
+        )
+        .unwrap();
+        let _ = state.inbox_tx.send(phonebook::models::InboxUpdate::Event(
+            phonebook::models::InboxEvent {
+                id,
+                phone: "+15551110000".to_string(),
+                kind: "customer_message".to_string(),
+                content: "in the gap".to_string(),
+                is_read: false,
+                created_at: "2025-06-01 12:00:00".to_string(),
+            },
+        ));
+        request
+    };
+
+    let res = request.await.unwrap().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut stream = res.into_body().into_data_stream();
+    phonebook::services::inbox::record_inbox_event(&state, "+15551110000", "customer_message", "after");
+
+    let mut received = String::new();
+    while !received.contains("after") {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(2), stream.next())
+            .await
+            .expect("timed out waiting for SSE events")
+            .unwrap()
+            .unwrap();
+        received.push_str(&String::from_utf8_lossy(&chunk));
+    }
+    assert_eq!(received.matches("in the gap").count(), 1);
+}
+
+#[test]
+fn test_inbox_thread_unread_counts_match_per_thread_count() {
+    let state = test_state();
+    let db = state.db.lock().unwrap();
+    for i in 0..60 {
+        let phone = format!("+1555222{i:04}");
+        for j in 0..(i % 7 + 1) {
+            let kind = if j % 2 == 0 {
+                "customer_message"
+            } else {
+                "ai_reply"
+            };
+            phonebook::db::queries::insert_inbox_event(&db, &phone, kind, &format!("{i}-{j}"))
+                .unwrap();
+        }
+        if i % 3 == 0 {
+            phonebook::db::queries::mark_thread_read(&db, &phone).unwrap();
+        } else if i % 3 == 1 {
+            db.execute(
+                "UPDATE inbox_events SET is_read = 1
+                 WHERE id = (SELECT MIN(id) FROM inbox_events WHERE phone = ?1)",
+                [&phone],
+            )
+            .unwrap();
+        }
+    }
+
+    let mut threads = phonebook::db::queries::get_inbox_threads(&db).unwrap();
+    threads.sort_by(|a, b| a.phone.cmp(&b.phone));
+    assert_eq!(threads.len(), 60);
+
+    // The straightforward per-thread version the query replaced
+    let mut stmt = db
+        .prepare(
+            "SELECT e.phone, e.content, e.kind,
+                    (SELECT COUNT(*) FROM inbox_events e2 WHERE e2.phone = e.phone AND e2.is_read = 0)
+             FROM inbox_events e
+             WHERE e.id = (SELECT MAX(id) FROM inbox_events e3 WHERE e3.phone = e.phone)
+             ORDER BY e.phone",
+        )
+        .unwrap();
+    let expected: Vec<(String, String, String, i64)> = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let actual: Vec<(String, String, String, i64)> = threads
+        .into_iter()
+        .map(|t| (t.phone, t.last_message, t.last_kind, t.unread_count))
+        .collect();
+    assert_eq!(actual, expected);
+}
+
+// ── LLM History ──
+
+#[tokio::test]
+async fn test_llm_history_capped_to_recent_messages() {
+    let histories = Arc::new(Mutex::new(vec![]));
+    let state = test_state_with_llm(Box::new(RecordingLlm {
+        histories: Arc::clone(&histories),
+        ..Default::default()
+    }));
+
+    // A long-running thread with 50 stored messages
+    {
+        let db = state.db.lock().unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        let conv = phonebook::models::Conversation {
+            phone: "+15551110000".to_string(),
+            messages: (0..50)
+                .map(|i| phonebook::models::ConversationMessage {
+                    role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+                    content: format!("message {i}"),
+                })
+                .collect(),
+            state: phonebook::models::ConversationState::Idle,
+            pending_booking: None,
+            paused_autoreply_sent: false,
+            reprompt_sent: false,
+            summary: None,
+            last_activity: now,
+            expires_at: now + chrono::Duration::minutes(30),
+        };
+        phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+    }
+
+    let app = test_app(state.clone());
+    app.oneshot(
+        Request::builder()
+            .method("POST")
+            .uri("/webhook/sms")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(
+                "From=%2B15551110000&To=%2B15551234567&Body=hello+again&MessageSid=SM1",
+            ))
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let histories = histories.lock().unwrap();
+    let sent = &histories[0];
+    // Summary line + last 20 prior messages + the new message
+    assert_eq!(sent.len(), 22);
+    assert_eq!(sent[0].role, "system");
+    assert!(sent[0].content.contains("30 earlier messages"));
+    let prior: Vec<_> = sent[1..21].iter().map(|m| m.content.as_str()).collect();
+    let expected: Vec<_> = (30..50).map(|i| format!("message {i}")).collect();
+    assert_eq!(prior, expected);
+    assert_eq!(sent[21].content, "hello again");
+
+    // The full history is still persisted
+    let db = state.db.lock().unwrap();
+    let conv = phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(conv.messages.len(), 52);
+}
+
+/// Answers summary requests with a canned summary; everything else goes to
+/// `RecordingLlm`.
+struct SummarizingLlm {
+    inner: RecordingLlm,
+    summary_requests: Arc<Mutex<Vec<Vec<Message>>>>,
+}
+
+#[async_trait]
+impl LlmProvider for SummarizingLlm {
+    async fn chat(&self, system_prompt: &str, messages: &[Message]) -> anyhow::Result<String> {
+        if system_prompt.starts_with("Summarize") {
+            self.summary_requests.lock().unwrap().push(messages.to_vec());
+            return Ok("Dana prefers morning appointments.".to_string());
+        }
+        self.inner.chat(system_prompt, messages).await
+    }
+}
+
+#[tokio::test]
+async fn test_long_conversation_is_summarized_into_later_prompts() {
+    let histories = Arc::new(Mutex::new(vec![]));
+    let summary_requests = Arc::new(Mutex::new(vec![]));
+    let mut config = test_config();
+    config.summarize_after = 4;
+    config.max_history_messages = 2;
+    let state = build_state(
+        config,
+        Box::new(SummarizingLlm {
+            inner: RecordingLlm {
+                histories: Arc::clone(&histories),
+                ..Default::default()
+            },
+            summary_requests: Arc::clone(&summary_requests),
+        }),
+        Box::new(MockMessaging::new()),
+    );
+
+    for message in ["hi, I'm Dana", "mornings work best", "what are your hours?"] {
+        phonebook::services::conversation::process_message(&state, "+15551110000", message)
+            .await
+            .unwrap();
+    }
+
+    // Six messages passed the threshold: the oldest four were folded away
+    let requests = summary_requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].len(), 4);
+    assert_eq!(requests[0][0].content, "hi, I'm Dana");
+    let conv = stored_conversation(&state, "+15551110000");
+    assert_eq!(conv.summary.as_deref(), Some("Dana prefers morning appointments."));
+    assert_eq!(conv.messages.len(), 2);
+    assert_eq!(conv.messages[0].content, "what are your hours?");
+
+    phonebook::services::conversation::process_message(&state, "+15551110000", "book me in")
+        .await
+        .unwrap();
+    let histories = histories.lock().unwrap();
+    let latest = histories.last().unwrap();
+    assert_eq!(latest[0].role, "system");
+    assert!(latest[0].content.contains("Dana prefers morning appointments."));
+    assert_eq!(summary_requests.lock().unwrap().len(), 1, "no new summary until the next batch");
+}
+
+// ── LLM Concurrency ──
+
+#[tokio::test]
+async fn test_llm_concurrency_is_capped() {
+    let in_flight = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let max_seen = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let llm = ConcurrencyProbeLlm {
+        in_flight: Arc::clone(&in_flight),
+        max_seen: Arc::clone(&max_seen),
+    };
+    let mut config = test_config();
+    config.llm_max_concurrency = 3;
+    let state = build_state(config, Box::new(llm), Box::new(MockMessaging::new()));
+
+    let mut handles = vec![];
+    for i in 0..20 {
+        let state = state.clone();
+        handles.push(tokio::spawn(async move {
+            let phone = format!("+1555000{i:04}");
+            phonebook::services::conversation::process_message(&state, &phone, "hello")
+                .await
+                .unwrap();
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let max = max_seen.load(std::sync::atomic::Ordering::SeqCst);
+    assert!(max >= 1);
+    assert!(max <= 3, "saw {max} concurrent LLM calls, cap is 3");
+}
+
+#[tokio::test]
+async fn test_llm_breaker_opens_after_failures_and_holds_replies() {
+    let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let sent = Arc::new(Mutex::new(vec![]));
+    let mut config = test_config();
+    config.llm_breaker_threshold = 2;
+    let state = build_state(
+        config,
+        Box::new(FailingLlm {
+            calls: Arc::clone(&calls),
+        }),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+
+    for body in ["hello", "hello?", "anyone there?"] {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", body))
+            .await
+            .unwrap();
+    }
+
+    // The third message never reached the LLM
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert!(state.llm_breaker.is_open());
+
+    let sent = sent.lock().unwrap();
+    let replies: Vec<&str> = sent
+        .iter()
+        .filter(|(to, _)| to == "+15551110000")
+        .map(|(_, body)| body.as_str())
+        .collect();
+    assert_eq!(replies.len(), 3);
+    assert!(replies[0].contains("something went wrong"), "got: {}", replies[0]);
+    assert!(replies[2].contains("get back to you shortly"), "got: {}", replies[2]);
+    let alerts = sent
+        .iter()
+        .filter(|(to, body)| to == "+15559999999" && body.contains("AI model keeps failing"))
+        .count();
+    assert_eq!(alerts, 1);
+}
+
+#[tokio::test]
+async fn test_availability_check_explains_rejection() {
+    let state = test_state();
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: Some(
+                r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","breaks":[{"start":"12:00","end":"13:00"}]}"#
+                    .to_string(),
+            ),
+            timezone: "UTC".to_string(),
+            ai_preferences: None,
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+
+    let check = |query: &'static str| {
+        let app = test_app(state.clone());
+        async move {
+            let res = app
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/api/admin/availability/check?{query}"))
+                        .header("Authorization", "Bearer test-token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        }
+    };
+
+    // 2025-06-16 is a Monday; 11:30 + 60min runs into the 12:00 break
+    let json = check("datetime=2025-06-16T11:30&duration=60").await;
+    assert_eq!(json["available"], false);
+    assert_eq!(json["reason"], "break");
+    assert_eq!(json["break_overlap"]["start"], "12:00");
+    assert_eq!(json["matched_slot"]["day"], "mon");
+
+    let json = check("datetime=2025-06-16T18:00&duration=60").await;
+    assert_eq!(json["available"], false);
+    assert_eq!(json["reason"], "outside_hours");
+    assert_eq!(json["break_overlap"], serde_json::Value::Null);
+
+    let json = check("datetime=2025-06-16T10:00").await;
+    assert_eq!(json["available"], true);
+    assert_eq!(json["reason"], serde_json::Value::Null);
+}
+
+// ── Webhook Tests ──
+
+#[tokio::test]
+async fn test_json_webhook_replies_and_records() {
+    let (state, sent) = test_state_with_sent();
+    let post_json = |auth: &str, body: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/webhook/sms/json")
+            .header("Content-Type", "application/json")
+            .header("Authorization", auth)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let payload = r#"{"from":"+15551110000","to":"+15551234567","body":"hello","message_id":"gw-1"}"#;
+
+    let res = test_app(state.clone())
+        .oneshot(post_json("Bearer wrong", payload))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert!(sent.lock().unwrap().is_empty());
+
+    let res = test_app(state.clone())
+        .oneshot(post_json("Bearer test-token", payload))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert_eq!(
+        sent.lock().unwrap().as_slice(),
+        &[(
+            "+15551110000".to_string(),
+            "Hello! How can I help you today?".to_string()
+        )]
+    );
+    let db = state.db.lock().unwrap();
+    let kinds: Vec<String> = db
+        .prepare("SELECT kind FROM inbox_events WHERE phone = ?1 ORDER BY id")
+        .unwrap()
+        .query_map(["+15551110000"], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(kinds, vec!["customer_message", "ai_reply"]);
+}
+
+#[tokio::test]
+async fn test_webhook_processes_message() {
+    let state = test_state();
+    let app = test_app(state);
+
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(
+                    "From=%2B15551110000&To=%2B15551234567&Body=hello&MessageSid=SM123",
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains("<Response>"));
+}
+
+/// Twilio's X-Twilio-Signature: base64 HMAC-SHA1 of the URL plus every
+/// posted field, sorted by name.
+fn twilio_signature(token: &str, url: &str, fields: &[(&str, &str)]) -> String {
+    use base64::Engine;
+    use hmac::Mac;
+
+    let mut sorted = fields.to_vec();
+    sorted.sort_by(|a, b| a.0.cmp(b.0));
+    let mut data = url.to_string();
+    for (key, value) in sorted {
+        data.push_str(key);
+        data.push_str(value);
+    }
+    let mut mac = hmac::Hmac::<sha1::Sha1>::new_from_slice(token.as_bytes()).unwrap();
+    mac.update(data.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
+
+#[tokio::test]
+async fn test_webhook_signature_covers_all_posted_fields() {
+    let mut config = test_config();
+    config.twilio_auth_token = "test-auth-token".to_string();
+    let state = build_state(config, Box::new(MockLlm), Box::new(MockMessaging::new()));
+
+    // A real inbound payload carries far more than From/To/Body/MessageSid
+    let fields = [
+        ("ToCountry", "US"),
+        ("ToState", "CA"),
+        ("SmsMessageSid", "SM123"),
+        ("NumMedia", "0"),
+        ("ToCity", "SAN FRANCISCO"),
+        ("FromZip", "94105"),
+        ("SmsSid", "SM123"),
+        ("FromState", "CA"),
+        ("SmsStatus", "received"),
+        ("FromCity", "SAN FRANCISCO"),
+        ("Body", "hello there"),
+        ("FromCountry", "US"),
+        ("To", "+15551234567"),
+        ("NumSegments", "1"),
+        ("MessageSid", "SM123"),
+        ("AccountSid", "AC123"),
+        ("From", "+15551110000"),
+        ("ApiVersion", "2010-04-01"),
+    ];
+    let form = fields
+        .iter()
+        .map(|(k, v)| format!("{k}={}", v.replace('+', "%2B").replace(' ', "+")))
+        .collect::<Vec<_>>()
+        .join("&");
+    let url = "https://sms.example.com/webhook/sms";
+    let signed_request = |signature: String| {
+        Request::builder()
+            .method("POST")
+            .uri("/webhook/sms")
+            .header("Host", "sms.example.com")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("X-Twilio-Signature", signature)
+            .body(Body::from(form.clone()))
+            .unwrap()
+    };
+
+    let res = test_app(state.clone())
+        .oneshot(signed_request(twilio_signature("test-auth-token", url, &fields)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Signing only the four fields we read is not what Twilio does
+    let partial = [
+        ("From", "+15551110000"),
+        ("To", "+15551234567"),
+        ("Body", "hello there"),
+        ("MessageSid", "SM123"),
+    ];
+    let res = test_app(state)
+        .oneshot(signed_request(twilio_signature("test-auth-token", url, &partial)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_spoofed_owner_command_rejected_in_prod() {
+    let mut config = test_config();
+    config.twilio_auth_token = "test-auth-token".to_string();
+    let sent: SentMessages = Arc::new(Mutex::new(Vec::new()));
+    let state = build_state(
+        config,
+        Box::new(MockLlm),
+        Box::new(MockMessaging { sent: sent.clone() }),
+    );
+
+    // Posting as the owner without Twilio's signature, or with a forged one
+    let res = test_app(state.clone())
+        .oneshot(owner_sms_request("#pause"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let mut forged = owner_sms_request("#pause");
+    forged
+        .headers_mut()
+        .insert("X-Twilio-Signature", "bm90IGEgc2lnbmF0dXJl".parse().unwrap());
+    let res = test_app(state.clone()).oneshot(forged).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // The dev chat can't stand in for the owner either
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/dev/message")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({"from_phone": "+15559999999", "message": "#pause"})
+                        .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let reply: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(reply["reply"].as_str().unwrap().contains("refused"));
+
+    assert!(!state.paused.load(std::sync::atomic::Ordering::SeqCst));
+    assert!(sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_owner_command_requires_shared_secret() {
+    let mut config = test_config();
+    config.admin_command_secret = "s3cret".to_string();
+    let sent: SentMessages = Arc::new(Mutex::new(Vec::new()));
+    let state = build_state(
+        config,
+        Box::new(MockLlm),
+        Box::new(MockMessaging { sent: sent.clone() }),
+    );
+
+    for body in ["#pause", "#wrong pause"] {
+        test_app(state.clone())
+            .oneshot(owner_sms_request(body))
+            .await
+            .unwrap();
+    }
+    assert!(!state.paused.load(std::sync::atomic::Ordering::SeqCst));
+    assert!(sent.lock().unwrap().is_empty());
+
+    test_app(state.clone())
+        .oneshot(owner_sms_request("#s3cret pause"))
+        .await
+        .unwrap();
+    assert!(state.paused.load(std::sync::atomic::Ordering::SeqCst));
+    assert!(sent.lock().unwrap()[0].1.contains("Agent paused"));
+}
+
+#[tokio::test]
+async fn test_webhook_media_only_message_asks_for_text() {
+    let (state, sent) = test_state_with_sent();
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(
+                    "From=%2B15551110000&To=%2B15551234567&Body=&MessageSid=MM123\
+                     &NumMedia=1&MediaUrl0=https%3A%2F%2Fapi.twilio.com%2FMedia%2FME123\
+                     &MediaContentType0=image%2Fjpeg",
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let replies = sent.lock().unwrap().clone();
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].0, "+15551110000");
+    assert!(replies[0].1.contains("photos or attachments"));
+
+    let db = state.db.lock().unwrap();
+    let events = phonebook::db::queries::get_thread_events(&db, "+15551110000", 10).unwrap();
+    assert_eq!(events[0].kind, "customer_message");
+    assert!(events[0].content.contains("https://api.twilio.com/Media/ME123"));
+    assert_eq!(events[1].kind, "ai_reply");
+}
+
+#[tokio::test]
+async fn test_webhook_rejects_overlong_body() {
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let prompts = Arc::new(Mutex::new(vec![]));
+    let mut config = test_config();
+    config.max_inbound_body = 50;
+    let state = build_state(
+        config,
+        Box::new(RecordingLlm {
+            prompts: Arc::clone(&prompts),
+            ..Default::default()
+        }),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+
+    let res = test_app(state.clone())
+        .oneshot(sms_request("+15551110000", &"a".repeat(51)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert!(prompts.lock().unwrap().is_empty(), "the LLM is never called");
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert!(sent[0].1.contains("too long"), "got: {}", sent[0].1);
+    assert!(sent[0].1.contains("50"));
+    let db = state.db.lock().unwrap();
+    assert!(phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_webhook_ignores_whitespace_body() {
+    let (state, sent) = test_state_with_sent();
+
+    let res = test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "   \n\t "))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert!(sent.lock().unwrap().is_empty());
+    let db = state.db.lock().unwrap();
+    assert!(phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_webhook_blocked_number_ignored() {
+    let state = test_state();
+
+    // Block a number first
+    {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::block_number(&db, "+15551110000", Some("test"), false).unwrap();
+    }
+
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(
+                    "From=%2B15551110000&To=%2B15551234567&Body=hello&MessageSid=SM123",
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_webhook_paused_agent_silent() {
+    let state = test_state();
+    state
+        .paused
+        .store(true, std::sync::atomic::Ordering::SeqCst);
+
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(
+                    "From=%2B15551110000&To=%2B15551234567&Body=hello&MessageSid=SM123",
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_debounce_coalesces_rapid_messages() {
+    let histories = Arc::new(Mutex::new(vec![]));
+    let sent = Arc::new(Mutex::new(vec![]));
+    let mut config = test_config();
+    config.debounce_ms = 150;
+    let state = build_state(
+        config,
+        Box::new(RecordingLlm {
+            histories: Arc::clone(&histories),
+            ..Default::default()
+        }),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+
+    for body in ["hi", "I want to book", "tomorrow at 3"] {
+        let res = test_app(state.clone())
+            .oneshot(sms_request("+15551110000", body))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    // Nothing is processed while the customer is still typing
+    assert!(histories.lock().unwrap().is_empty());
+
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+
+    let histories = histories.lock().unwrap();
+    assert_eq!(histories.len(), 1, "expected a single LLM call");
+    assert_eq!(
+        histories[0].last().unwrap().content,
+        "hi\nI want to book\ntomorrow at 3"
+    );
+    assert_eq!(sent.lock().unwrap().len(), 1);
+    assert!(state.debounce_buffers.is_empty());
+}
+
+/// While SQLite is busy, a webhook waiting on the DB must not stall the
+/// runtime: on a single-threaded runtime other requests still get served.
+#[tokio::test(flavor = "current_thread")]
+async fn test_webhook_db_wait_does_not_block_runtime() {
+    let state = test_state();
+
+    // Simulate a slow disk write holding the connection
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let holder = {
+        let state = state.clone();
+        std::thread::spawn(move || {
+            let _db = state.db.lock().unwrap();
+            locked_tx.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        })
+    };
+    locked_rx.recv().unwrap();
+
+    let started = std::time::Instant::now();
+    let webhook = tokio::spawn(
+        test_app(state.clone()).oneshot(sms_request("+15551110000", "hello")),
+    );
+    tokio::task::yield_now().await;
+
+    let res = test_app(state.clone())
+        .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(
+        started.elapsed() < std::time::Duration::from_millis(250),
+        "health check waited {:?} behind the DB lock",
+        started.elapsed()
+    );
+
+    let res = webhook.await.unwrap().unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    holder.join().unwrap();
+}
+
+/// Rapid messages from one number are processed one at a time, in arrival
+/// order, so neither turn's state overwrites the other's.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_same_phone_messages_processed_in_order() {
+    let (entered_tx, mut entered) = tokio::sync::mpsc::unbounded_channel();
+    let release = Arc::new(tokio::sync::Semaphore::new(0));
+    let max_seen = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let state = test_state_with_llm(Box::new(GatedLlm {
+        entered: entered_tx,
+        release: Arc::clone(&release),
+        in_flight: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+        max_seen: Arc::clone(&max_seen),
+    }));
+
+    // Hold the first turn inside the LLM call
+    let first = tokio::spawn(
+        test_app(state.clone()).oneshot(sms_request("+15551110000", "first message")),
+    );
+    entered.recv().await.unwrap();
+
+    // Wait until the second turn is queued on the same phone's lock
+    let second = tokio::spawn(
+        test_app(state.clone()).oneshot(sms_request("+15551110000", "second message")),
+    );
+    while state
+        .conversation_locks
+        .get("+15551110000")
+        .map(|lock| Arc::strong_count(&lock))
+        != Some(3)
+    {
+        tokio::task::yield_now().await;
+    }
+    assert!(entered.try_recv().is_err(), "second turn ran alongside the first");
+
+    release.add_permits(2);
+    assert_eq!(first.await.unwrap().unwrap().status(), StatusCode::OK);
+    assert_eq!(second.await.unwrap().unwrap().status(), StatusCode::OK);
+
+    let conv = stored_conversation(&state, "+15551110000");
+    let from_customer: Vec<_> = conv
+        .messages
+        .iter()
+        .filter(|m| m.role == "user")
+        .map(|m| m.content.as_str())
+        .collect();
+    assert_eq!(from_customer, vec!["first message", "second message"]);
+    assert_eq!(conv.messages.len(), 4);
+    assert_eq!(max_seen.load(std::sync::atomic::Ordering::SeqCst), 1);
+    // Nothing is waiting on the phone any more, so its lock is dropped
+    assert!(state.conversation_locks.is_empty());
+}
+
+/// Two customers confirming the same slot at once: the re-check inside the
+/// booking transaction lets exactly one of them through.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_confirmations_book_slot_once() {
+    let state = test_state();
+    let slot = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let phones = ["+15551110001", "+15551110002"];
+
+    {
+        let db = state.db.lock().unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        for (i, phone) in phones.iter().enumerate() {
+            let conv = phonebook::models::Conversation {
+                phone: phone.to_string(),
+                messages: vec![],
+                state: phonebook::models::ConversationState::Confirming,
+                pending_booking: Some(phonebook::models::PendingBooking {
+                    customer_name: Some(format!("Customer {i}")),
+                    date_time: Some(slot.format("%Y-%m-%d %H:%M").to_string()),
+                    duration_minutes: Some(60),
+                    notes: None,
+                    proposed_slots: vec![],
+                    booking_id: None,
+                    service: None,
+                }),
+                paused_autoreply_sent: false,
+                reprompt_sent: false,
+                summary: None,
+                last_activity: now,
+                expires_at: now + chrono::Duration::minutes(30),
+            };
+            phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+        }
+    }
+
+    let confirmations: Vec<_> = phones
+        .iter()
+        .map(|phone| tokio::spawn(test_app(state.clone()).oneshot(sms_request(phone, "yes"))))
+        .collect();
+    for confirmation in confirmations {
+        let res = confirmation.await.unwrap().unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let db = state.db.lock().unwrap();
+    let booked: i64 = db
+        .query_row(
+            "SELECT COUNT(*) FROM bookings WHERE date_time = ?1 AND status = 'confirmed'",
+            [slot.format("%Y-%m-%d %H:%M:%S").to_string()],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(booked, 1);
+}
+
+#[tokio::test]
+async fn test_late_confirmation_within_grace_still_books() {
+    let state = test_state();
+    let slot = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let now = chrono::Utc::now().naive_utc();
+
+    // Expired 1 minute ago (inside the 5-minute grace) and 10 minutes ago (outside)
+    {
+        let db = state.db.lock().unwrap();
+        for (phone, expired_ago, hour) in [("+15551110001", 1, 10), ("+15551110002", 10, 14)] {
+            let conv = phonebook::models::Conversation {
+                phone: phone.to_string(),
+                messages: vec![],
+                state: phonebook::models::ConversationState::Confirming,
+                pending_booking: Some(phonebook::models::PendingBooking {
+                    customer_name: Some("Dana".to_string()),
+                    date_time: Some(format!("{} {hour}:00", slot.date())),
+                    duration_minutes: Some(60),
+                    notes: None,
+                    proposed_slots: vec![],
+                    booking_id: None,
+                    service: None,
+                }),
+                paused_autoreply_sent: false,
+                reprompt_sent: false,
+                summary: None,
+                last_activity: now - chrono::Duration::minutes(30 + expired_ago),
+                expires_at: now - chrono::Duration::minutes(expired_ago),
+            };
+            phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+        }
+
+        // The sweep leaves the one still in its grace window
+        assert_eq!(phonebook::db::queries::expire_old_conversations(&db, 5).unwrap(), 1);
+        assert!(phonebook::db::queries::get_conversation(&db, "+15551110001", false)
+            .unwrap()
+            .is_none());
+        assert!(
+            phonebook::db::queries::get_conversation_with_grace(&db, "+15551110001", 5)
+                .unwrap()
+                .unwrap()
+                .pending_booking
+                .is_some()
+        );
+    }
+
+    for phone in ["+15551110001", "+15551110002"] {
+        test_app(state.clone())
+            .oneshot(sms_request(phone, "yes"))
+            .await
+            .unwrap();
+    }
+
+    let db = state.db.lock().unwrap();
+    let bookings = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110001").unwrap();
+    assert_eq!(bookings.len(), 1);
+    assert_eq!(bookings[0].date_time, slot);
+    assert!(phonebook::db::queries::get_bookings_for_phone(&db, "+15551110002")
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_cleanup_deletes_expired_conversations() {
+    let state = test_state();
+    let now = chrono::Utc::now().naive_utc();
+    let db = state.db.lock().unwrap();
+    for (phone, expires_at) in [
+        ("+15551110003", now - chrono::Duration::hours(1)),
+        ("+15551110004", now + chrono::Duration::minutes(20)),
+    ] {
+        let conv = phonebook::models::Conversation {
+            phone: phone.to_string(),
+            messages: vec![],
+            state: phonebook::models::ConversationState::Idle,
+            pending_booking: None,
+            paused_autoreply_sent: false,
+            reprompt_sent: false,
+            summary: None,
+            last_activity: expires_at - chrono::Duration::minutes(30),
+            expires_at,
+        };
+        phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+    }
+    db.execute(
+        "INSERT INTO rate_limits (phone_number, window_start) VALUES ('+15551110003', '2020-01-01 00:00:00')",
+        [],
+    )
+    .unwrap();
+
+    let report = phonebook::services::cleanup::run_cleanup(&db, &state.config).unwrap();
+    assert_eq!(report.conversations, 1);
+    assert_eq!(report.rate_limit_windows, 1);
+    assert!(
+        phonebook::db::queries::get_conversation_with_grace(&db, "+15551110003", 60)
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        phonebook::db::queries::get_conversation(&db, "+15551110004", false)
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn test_cleanup_prunes_old_inbox_events_but_keeps_latest_per_thread() {
+    let state = test_state();
+    let db = state.db.lock().unwrap();
+    let insert = |phone: &str, content: &str, days_ago: i64| {
+        let id = phonebook::db::queries::insert_inbox_event(&db, phone, "customer_message", content)
+            .unwrap();
+        db.execute(
+            "UPDATE inbox_events SET created_at = datetime('now', ?1) WHERE id = ?2",
+            rusqlite::params![format!("-{days_ago} days"), id],
+        )
+        .unwrap();
+    };
+    // Active thread: two old events, one recent
+    insert("+15551110005", "old 1", 500);
+    insert("+15551110005", "old 2", 400);
+    insert("+15551110005", "recent", 3);
+    // Dormant thread: everything is old, the newest must survive
+    insert("+15551110006", "ancient", 800);
+    insert("+15551110006", "last word", 600);
+
+    let report = phonebook::services::cleanup::run_cleanup(&db, &state.config).unwrap();
+    assert_eq!(report.inbox_events, 3);
+
+    let contents = |phone| {
+        phonebook::db::queries::get_thread_events(&db, phone, i64::MAX)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.content)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(contents("+15551110005"), vec!["recent"]);
+    assert_eq!(contents("+15551110006"), vec!["last word"]);
+    let threads = phonebook::db::queries::get_inbox_threads(&db).unwrap();
+    assert_eq!(threads.len(), 2);
+}
+
+#[tokio::test]
+async fn test_stop_opts_out_until_start() {
+    let (state, sent) = test_state_with_sent();
+    let texts_to = |phone: &str| -> Vec<String> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter(|(to, _)| to == phone)
+            .map(|(_, body)| body.clone())
+            .collect()
+    };
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "Stop"))
+        .await
+        .unwrap();
+    let replies = texts_to("+15551110000");
+    assert_eq!(replies.len(), 1);
+    assert!(replies[0].contains("unsubscribed"), "got: {}", replies[0]);
+
+    // No agent reply, no owner reply, no broadcast while opted out
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello"))
+        .await
+        .unwrap();
+    assert_eq!(texts_to("+15551110000").len(), 1);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/inbox/reply")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "phone": "+15551110000", "message": "Hi!" }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/broadcast")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "message": "Sale!" }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(summary["skipped_opted_out"], 1);
+    assert_eq!(texts_to("+15551110000").len(), 1);
+
+    // START re-enables replies
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "START"))
+        .await
+        .unwrap();
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello"))
+        .await
+        .unwrap();
+    let replies = texts_to("+15551110000");
+    assert_eq!(replies.len(), 3);
+    assert!(replies[1].contains("resubscribed"), "got: {}", replies[1]);
+    assert_eq!(replies[2], "Hello! How can I help you today?");
+}
+
+#[tokio::test]
+async fn test_paused_autoreply_sent_once_per_conversation() {
+    let (state, sent) = test_state_with_sent();
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: Some(
+                r#"{"paused_autoreply":"Thanks! We'll get back to you soon."}"#.to_string(),
+            ),
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+    state.paused.store(true, std::sync::atomic::Ordering::SeqCst);
+
+    for body in ["hello?", "anyone there?"] {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", body))
+            .await
+            .unwrap();
+    }
+
+    let replies: Vec<String> = sent
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(to, _)| to == "+15551110000")
+        .map(|(_, body)| body.clone())
+        .collect();
+    assert_eq!(replies, vec!["Thanks! We'll get back to you soon.".to_string()]);
+    assert!(stored_conversation(&state, "+15551110000").paused_autoreply_sent);
+}
+
+#[tokio::test]
+async fn test_welcome_message_only_on_first_reply() {
+    let (state, sent) = test_state_with_sent();
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: Some(
+                r#"{"welcome_message":"Thanks for texting Test Biz!"}"#.to_string(),
+            ),
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+
+    for body in ["hello", "are you open saturday?"] {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", body))
+            .await
+            .unwrap();
+    }
+
+    let replies: Vec<String> = sent
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(to, _)| to == "+15551110000")
+        .map(|(_, body)| body.clone())
+        .collect();
+    assert_eq!(replies.len(), 2);
+    assert!(
+        replies[0].starts_with(
+            "Thanks for texting Test Biz! You're texting an automated assistant.\n\n"
+        ),
+        "got: {}",
+        replies[0]
+    );
+    assert!(!replies[1].contains("Thanks for texting"), "got: {}", replies[1]);
+}
+
+#[tokio::test]
+async fn test_welcome_message_skips_disclosure_when_disabled() {
+    let (state, sent) = test_state_with_sent();
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: Some(
+                r#"{"welcome_message":"Hi from Test Biz!","identity":{"disclose_ai":false}}"#
+                    .to_string(),
+            ),
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello"))
+        .await
+        .unwrap();
+
+    let sent = sent.lock().unwrap();
+    let (_, reply) = sent.iter().find(|(to, _)| to == "+15551110000").unwrap();
+    assert!(reply.starts_with("Hi from Test Biz!\n\n"), "got: {reply}");
+    assert!(!reply.contains("automated assistant"), "got: {reply}");
+}
+
+#[tokio::test]
+async fn test_dry_run_proposes_booking_without_persisting() {
+    use phonebook::models::{ConversationState, Intent};
+    use phonebook::services::conversation;
+
+    let state = test_state();
+    let count = |sql: &str| -> i64 {
+        let db = state.db.lock().unwrap();
+        db.query_row(sql, [], |row| row.get(0)).unwrap()
+    };
+
+    let outcome = conversation::process_message_dry_run(&state, "+15551110000", "book an appointment")
+        .await
+        .unwrap();
+    assert_eq!(outcome.intent, Intent::Book);
+    assert_eq!(outcome.previous_state, ConversationState::Idle);
+    assert_eq!(outcome.state, ConversationState::Confirming);
+    let pending = outcome.pending_booking.expect("pending booking");
+    assert_eq!(pending.date_time.as_deref(), Some("2025-06-15 14:00"));
+    assert!(outcome.reply.contains("June 15"), "got: {}", outcome.reply);
+    {
+        let db = state.db.lock().unwrap();
+        assert!(phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+            .unwrap()
+            .is_none());
+    }
+    assert_eq!(count("SELECT COUNT(*) FROM inbox_events"), 0);
+
+    // Confirming a real proposal in a dry run books nothing
+    conversation::process_message(&state, "+15551110000", "book an appointment")
+        .await
+        .unwrap();
+    let outcome = conversation::process_message_dry_run(&state, "+15551110000", "yes")
+        .await
+        .unwrap();
+    assert_eq!(outcome.intent, Intent::Confirm);
+    assert_eq!(outcome.state, ConversationState::Idle);
+    assert_eq!(count("SELECT COUNT(*) FROM bookings"), 0);
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Confirming
+    );
+}
+
+#[tokio::test]
+async fn test_dev_message_reports_intent_and_pending_booking() {
+    let state = test_state();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/dev/message")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    r#"{"from_phone":"+15551110000","message":"book an appointment"}"#,
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["success"], true);
+    assert_eq!(json["intent"], "book");
+    assert_eq!(json["previous_state"], "idle");
+    assert_eq!(json["state"], "confirming");
+    assert_eq!(json["pending_booking"]["date_time"], "2025-06-15 14:00");
+    // Not a dry run, so the conversation moved on
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        phonebook::models::ConversationState::Confirming
+    );
+}
+
+#[tokio::test]
+async fn test_llm_log_returns_recent_exchanges_when_enabled() {
+    let llm_log = |state: &Arc<AppState>| {
+        test_app(state.clone()).oneshot(
+            Request::builder()
+                .uri("/api/dev/llm-log")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    // Off by default
+    let state = test_state();
+    let res = llm_log(&state).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let mut config = test_config();
+    config.llm_debug_log = true;
+    let state = build_state(config, Box::new(MockLlm), Box::new(MockMessaging::new()));
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello"))
+        .await
+        .unwrap();
+
+    let res = llm_log(&state).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let exchanges = json.as_array().unwrap();
+    assert_eq!(exchanges.len(), 1);
+    assert!(exchanges[0]["system_prompt"]
+        .as_str()
+        .unwrap()
+        .contains("Business context:"));
+    assert_eq!(exchanges[0]["messages"][0]["content"], "hello");
+    assert!(exchanges[0]["response"]
+        .as_str()
+        .unwrap()
+        .contains("How can I help you today?"));
+
+    let res = test_app(state.clone())
+        .oneshot(Request::builder().uri("/api/dev/llm-log").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_dev_notifications_endpoint_peeks_and_drains() {
+    let state = test_state();
+    phonebook::services::conversation::process_message(&state, "+15551110000", "hello")
+        .await
+        .unwrap();
+
+    let notifications = |uri: &str| {
+        test_app(state.clone()).oneshot(
+            Request::builder()
+                .uri(uri)
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+    let read = |res: axum::response::Response| async move {
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice::<Vec<serde_json::Value>>(&body).unwrap()
+    };
+
+    // Peeking leaves the queue in place
+    let peeked = read(notifications("/api/dev/notifications?drain=false").await.unwrap()).await;
+    assert_eq!(peeked.len(), 2);
+    assert_eq!(peeked[0]["kind"], "customer_message");
+    assert_eq!(peeked[0]["content"], "hello");
+    assert_eq!(peeked[1]["kind"], "ai_reply");
+    assert_eq!(peeked[1]["phone"], "+15551110000");
+
+    let drained = read(notifications("/api/dev/notifications").await.unwrap()).await;
+    assert_eq!(drained, peeked);
+    let empty = read(notifications("/api/dev/notifications").await.unwrap()).await;
+    assert!(empty.is_empty());
+
+    let res = test_app(state)
+        .oneshot(Request::builder().uri("/api/dev/notifications").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+// ── Rate Limiting Tests ──
+
+#[tokio::test]
+async fn test_rate_limit_auto_blocks() {
+    let state = test_state();
+
+    // Send 16 messages from the same number (exceeds 15/hr limit)
+    for i in 0..16 {
+        let app = test_app(state.clone());
+        let res = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/webhook/sms")
+                    .header("Content-Type", "application/x-www-form-urlencoded")
+                    .body(Body::from(format!(
+                        "From=%2B15551110000&To=%2B15551234567&Body=msg{}&MessageSid=SM{}",
+                        i, i
+                    )))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    // Verify the number is now blocked
+    let db = state.db.lock().unwrap();
+    assert!(phonebook::db::queries::is_blocked(&db, "+15551110000").unwrap());
+}
+
+#[tokio::test]
+async fn test_allowlisted_number_never_auto_blocked() {
+    let state = test_state();
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/allow")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"phone":"+15551110000","note":"VIP"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    for i in 0..20 {
+        let res = test_app(state.clone())
+            .oneshot(sms_request("+15551110000", &format!("msg{i}")))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+    {
+        let db = state.db.lock().unwrap();
+        assert!(!phonebook::db::queries::is_blocked(&db, "+15551110000").unwrap());
+    }
+
+    // The owner is implicitly allowlisted
+    for i in 0..20 {
+        test_app(state.clone())
+            .oneshot(owner_sms_request(&format!("hi {i}")))
+            .await
+            .unwrap();
+    }
+    {
+        let db = state.db.lock().unwrap();
+        assert!(!phonebook::db::queries::is_blocked(&db, "+15559999999").unwrap());
+    }
+
+    // Removing the exemption restores the limit
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("DELETE")
+                .uri("/api/admin/allow/%2B15551110000")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "one more"))
+        .await
+        .unwrap();
+    let db = state.db.lock().unwrap();
+    assert!(phonebook::db::queries::is_blocked(&db, "+15551110000").unwrap());
+}
+
+#[tokio::test]
+async fn test_unblock_and_unallow_accept_formatted_numbers() {
+    let state = test_state();
+    let admin_request = |method: &str, uri: &str, body: &str| {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    // Block and unblock with the same locally formatted number
+    let res = test_app(state.clone())
+        .oneshot(admin_request(
+            "POST",
+            "/api/admin/block",
+            r#"{"phone":"555-111-2222"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test_app(state.clone())
+        .oneshot(admin_request(
+            "POST",
+            "/api/admin/unblock",
+            r#"{"phone":"555-111-2222"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    {
+        let db = state.db.lock().unwrap();
+        assert!(!phonebook::db::queries::is_blocked(&db, "+15551112222").unwrap());
+    }
+
+    // Allow and unallow the same way
+    let res = test_app(state.clone())
+        .oneshot(admin_request(
+            "POST",
+            "/api/admin/allow",
+            r#"{"phone":"(555) 111-2222"}"#,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test_app(state.clone())
+        .oneshot(admin_request("DELETE", "/api/admin/allow/555-111-2222", ""))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let db = state.db.lock().unwrap();
+    assert!(!phonebook::db::queries::is_allowlisted(&db, "+15551112222").unwrap());
+}
+
+#[tokio::test]
+async fn test_auto_block_expires_but_manual_block_does_not() {
+    let (state, sent) = test_state_with_sent();
+    let now = chrono::Utc::now().naive_utc();
+    {
+        let db = state.db.lock().unwrap();
+        // Tripped the limit yesterday; the block has since lapsed
+        phonebook::db::queries::auto_block_number(
+            &db,
+            "+15551110001",
+            Some("auto-blocked: rate limit exceeded"),
+            &(now - chrono::Duration::minutes(1)),
+        )
+        .unwrap();
+        phonebook::db::queries::auto_block_number(
+            &db,
+            "+15551110002",
+            Some("auto-blocked: rate limit exceeded"),
+            &(now + chrono::Duration::hours(1)),
+        )
+        .unwrap();
+        phonebook::db::queries::block_number(&db, "+15551110003", Some("spam"), false).unwrap();
+    }
+
+    for phone in ["+15551110001", "+15551110002", "+15551110003"] {
+        let res = test_app(state.clone())
+            .oneshot(sms_request(phone, "hello"))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let replied: Vec<String> = sent.lock().unwrap().iter().map(|(to, _)| to.clone()).collect();
+    assert_eq!(replied, vec!["+15551110001".to_string()]);
+
+    let db = state.db.lock().unwrap();
+    assert!(!phonebook::db::queries::is_blocked(&db, "+15551110001").unwrap());
+    assert!(phonebook::db::queries::is_blocked(&db, "+15551110002").unwrap());
+    assert!(phonebook::db::queries::is_blocked(&db, "+15551110003").unwrap());
+    // An auto-block never overrides an existing manual block
+    phonebook::db::queries::auto_block_number(&db, "+15551110003", None, &now).unwrap();
+    assert!(phonebook::db::queries::is_blocked(&db, "+15551110003").unwrap());
+}
+
+#[tokio::test]
+async fn test_rate_limit_auto_block_has_expiry() {
+    let state = test_state();
+    for i in 0..16 {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", &format!("msg{i}")))
+            .await
+            .unwrap();
+    }
+
+    let db = state.db.lock().unwrap();
+    let blocked = phonebook::db::queries::list_blocked(&db).unwrap();
+    assert_eq!(blocked.len(), 1);
+    assert!(blocked[0].is_auto);
+    let until = chrono::NaiveDateTime::parse_from_str(
+        blocked[0].blocked_until.as_deref().unwrap(),
+        "%Y-%m-%d %H:%M:%S",
+    )
+    .unwrap();
+    let remaining = until - chrono::Utc::now().naive_utc();
+    assert!(remaining > chrono::Duration::minutes(1430) && remaining <= chrono::Duration::minutes(1440));
+}
+
+#[tokio::test]
+async fn test_auto_block_sends_explanation_once() {
+    let (state, sent) = test_state_with_sent();
+    let notice = "We've paused replies to this number. Please call us at 555-0100.";
+    let prefs = serde_json::json!({ "blocked_autoreply": notice }).to_string();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "ai_preferences": prefs }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let notices = |sent: &SentMessages| {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter(|(to, body)| to == "+15551110000" && body == notice)
+            .count()
+    };
+    // The 16th message trips the limit and gets the notice; later ones get nothing
+    for i in 0..16 {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", &format!("msg{i}")))
+            .await
+            .unwrap();
+    }
+    assert_eq!(notices(&sent), 1);
+    let before = sent.lock().unwrap().len();
+    for i in 16..19 {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", &format!("msg{i}")))
+            .await
+            .unwrap();
+    }
+    assert_eq!(notices(&sent), 1);
+    assert_eq!(sent.lock().unwrap().len(), before);
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/blocked")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        json[0]["reason"],
+        "auto-blocked: rate limit exceeded (16 msgs/hour, limit 15)"
+    );
+    assert!(json[0]["blocked_at"].is_string());
+}
+
+// ── Spam Keywords ──
+
+#[tokio::test]
+async fn test_spam_keywords_drop_or_block_but_let_bookings_through() {
+    let (state, sent) = test_state_with_sent();
+    let settings = |body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/settings")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let res = test_app(state.clone())
+        .oneshot(settings(
+            serde_json::json!({ "spam_keywords": "crypto, Book now" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test_app(state.clone())
+        .oneshot(settings(
+            serde_json::json!({ "spam_keywords": "crypto,\nfree money" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let sent_to = |phone: &str| {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter(|(to, _)| to == phone)
+            .count()
+    };
+    test_app(state.clone())
+        .oneshot(sms_request("+15552220001", "FREE MONEY waiting for you"))
+        .await
+        .unwrap();
+    assert_eq!(sent_to("+15552220001"), 0);
+    {
+        let db = state.db.lock().unwrap();
+        assert!(!phonebook::db::queries::is_blocked(&db, "+15552220001").unwrap());
+        let export = phonebook::db::queries::export_contact(&db, "+15552220001").unwrap();
+        assert_eq!(export.inbox_events.len(), 1);
+        assert_eq!(
+            export.inbox_events[0].content,
+            "Ignored a message containing spam keyword \"free money\""
+        );
+    }
+
+    // Ordinary booking messages never hit a keyword
+    test_app(state.clone())
+        .oneshot(sms_request(
+            "+15552220002",
+            "Can I book a haircut tomorrow at 2pm?",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(sent_to("+15552220002"), 1);
+
+    let res = test_app(state.clone())
+        .oneshot(settings(serde_json::json!({ "block_spam": true })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    test_app(state.clone())
+        .oneshot(sms_request("+15552220001", "Cheap crypto deals"))
+        .await
+        .unwrap();
+    assert_eq!(sent_to("+15552220001"), 0);
+    assert_eq!(sent_to("+15559999999"), 1);
+    let db = state.db.lock().unwrap();
+    assert!(phonebook::db::queries::is_blocked(&db, "+15552220001").unwrap());
+    let blocked = phonebook::db::queries::list_blocked(&db).unwrap();
+    assert_eq!(
+        blocked[0].reason.as_deref(),
+        Some("spam keyword \"crypto\"")
+    );
+}
+
+// ── Calendar .ics Tests ──
+
+#[tokio::test]
+async fn test_calendar_not_found() {
+    let state = test_state();
+    let app = test_app(state);
+
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/calendar/nonexistent.ics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_calendar_download() {
+    let state = test_state();
+
+    // Create a booking
+    {
+        let db = state.db.lock().unwrap();
+        let booking = phonebook::models::Booking {
+            id: "test-booking-1".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: Some("Alice".to_string()),
+            date_time: chrono::NaiveDateTime::parse_from_str(
+                "2025-06-15 14:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+            duration_minutes: 60,
+            status: phonebook::models::BookingStatus::Confirmed,
+            notes: Some("Haircut".to_string()),
+            service: None,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+        };
+        phonebook::db::queries::create_booking(&db, &booking, BookingActor::Admin).unwrap();
+    }
+
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/calendar/test-booking-1.ics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/calendar; charset=utf-8"
+    );
+
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains("BEGIN:VCALENDAR"));
+    assert!(text.contains("BEGIN:VEVENT"));
+    assert!(text.contains("DTSTART:20250615T140000"));
+    assert!(text.contains("DESCRIPTION:Haircut"));
+}
+
+#[tokio::test]
+async fn test_calendar_feed_filters_by_range() {
+    let state = test_state();
+    let at = |s: &str| chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: None,
+            timezone: "UTC".to_string(),
+            ai_preferences: None,
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+    seed_customer_booking(&state, "bk-early", at("2030-01-10 10:00"));
+    seed_customer_booking(&state, "bk-inside", at("2030-01-15 10:00"));
+    seed_customer_booking(&state, "bk-late", at("2030-01-20 10:00"));
+
+    let res = test_app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/calendar/feed.ics?token=test-token&from=2030-01-14&to=2030-01-15&status=confirmed")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains("UID:bk-inside@phonebook"));
+    assert!(!text.contains("bk-early"));
+    assert!(!text.contains("bk-late"));
+    assert!(text.contains("X-WR-CALNAME:Test Biz\r\n"));
+}
+
+#[tokio::test]
+async fn test_calendar_feed_marks_cancelled_bookings() {
+    let state = test_state();
+    let at = chrono::NaiveDateTime::parse_from_str("2030-02-01 10:00", "%Y-%m-%d %H:%M").unwrap();
+    seed_customer_booking(&state, "bk-cancelled", at);
+    {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::update_booking_status(
+            &db,
+            "bk-cancelled",
+            &phonebook::models::BookingStatus::Cancelled,
+            BookingActor::Admin,
+        )
+        .unwrap();
+    }
+
+    let res = test_app(state)
+        .oneshot(
+            Request::builder()
+                .uri("/calendar/feed.ics?token=test-token&from=2030-02-01&to=2030-02-01")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    assert!(text.contains("UID:bk-cancelled@phonebook"));
+    assert!(text.contains("STATUS:CANCELLED\r\nSEQUENCE:1\r\n"));
+}
+
+// ── Reschedule Links ──
+
+/// Insert a confirmed booking at `date_time` and issue a reschedule link for it.
+fn seed_booking_with_link(state: &Arc<AppState>, id: &str, date_time: chrono::NaiveDateTime) -> String {
+    seed_customer_booking(state, id, date_time);
+    let db = state.db.lock().unwrap();
+    phonebook::db::queries::create_booking_link(&db, id, "reschedule", &date_time).unwrap()
+}
+
+#[tokio::test]
+async fn test_reschedule_link_moves_booking() {
+    let state = test_state();
+    let start = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let token = seed_booking_with_link(&state, "resched-1", start);
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/reschedule/{token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains(&format!("action=\"/reschedule/{token}\"")));
+
+    let new_date = (start + chrono::Duration::days(1)).format("%Y-%m-%d");
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!("/reschedule/{token}"))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!("date={new_date}&time=15%3A00")))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    {
+        let db = state.db.lock().unwrap();
+        let old = phonebook::db::queries::get_booking_by_id(&db, "resched-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(old.status, phonebook::models::BookingStatus::Cancelled);
+        let upcoming = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000").unwrap();
+        assert_eq!(upcoming.len(), 1);
+        assert_eq!(
+            upcoming[0].date_time.format("%Y-%m-%d %H:%M").to_string(),
+            format!("{new_date} 15:00")
+        );
+    }
+
+    // The old link dies with the moved booking
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/reschedule/{token}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_reschedule_link_enforces_daily_limit_and_escapes_errors() {
+    let state = test_state();
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: None,
+            timezone: "America/New_York".to_string(),
+            ai_preferences: Some(
+                r#"{"rejection_messages":{"conflict":"That <b>slot</b> is taken"}}"#.to_string(),
+            ),
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: true,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+    let start = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let token = seed_booking_with_link(&state, "resched-3", start);
+    // The same customer already has a booking the next day
+    let other_day = start + chrono::Duration::days(1);
+    seed_customer_booking(&state, "resched-other", other_day);
+
+    let submit = |time: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(format!("/reschedule/{token}"))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!(
+                "date={}&time={time}",
+                other_day.format("%Y-%m-%d")
+            )))
+            .unwrap()
+    };
+
+    // Overlapping the other booking renders the owner's message, escaped
+    let res = test_app(state.clone()).oneshot(submit("10%3A30")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains("That &lt;b&gt;slot&lt;/b&gt; is taken"), "{html}");
+
+    // A free slot that day still breaks the one-booking-per-day rule
+    let res = test_app(state.clone()).oneshot(submit("15%3A00")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let db = state.db.lock().unwrap();
+    let old = phonebook::db::queries::get_booking_by_id(&db, "resched-3")
+        .unwrap()
+        .unwrap();
+    assert_eq!(old.status, phonebook::models::BookingStatus::Confirmed);
+    let upcoming = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000").unwrap();
+    assert_eq!(upcoming.len(), 2);
+}
+
+#[tokio::test]
+async fn test_reschedule_link_expires_at_appointment_time() {
+    let state = test_state();
+    let past = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+    let token = seed_booking_with_link(&state, "resched-2", past);
+
+    for method in ["GET", "POST"] {
+        let app = test_app(state.clone());
+        let res = app
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(format!("/reschedule/{token}"))
+                    .header("Content-Type", "application/x-www-form-urlencoded")
+                    .body(Body::from("date=2099-01-01&time=10%3A00"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::GONE, "{method} should be rejected");
+    }
+
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/reschedule/not-a-real-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_booking_links_use_public_url() {
+    let day = (chrono::Utc::now() + chrono::Duration::days(3)).date_naive();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Alice","requested_date":"{}","requested_time":"15:00","duration_minutes":60,"notes":null,"message_to_customer":"Shall I book that?"}}"#,
+        day.format("%Y-%m-%d")
+    );
+    let confirm = r#"{"intent":"confirm","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Done!"}"#;
+
+    // Without a public URL there's nothing to link to
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[&book, confirm])));
+    phonebook::services::conversation::process_message(&state, "+15551110000", "book 3pm")
+        .await
+        .unwrap();
+    let reply = phonebook::services::conversation::process_message(&state, "+15551110000", "yes")
+        .await
+        .unwrap();
+    assert!(reply.contains("Done!"), "reply: {reply}");
+    assert!(!reply.contains("/reschedule/"), "reply: {reply}");
+
+    let mut config = test_config();
+    config.public_url = "https://book.example.com/".to_string();
+    let state = build_state(
+        config,
+        Box::new(ScriptedLlm::new(&[&book, confirm])),
+        Box::new(MockMessaging::new()),
+    );
+    phonebook::services::conversation::process_message(&state, "+15551110000", "book 3pm")
+        .await
+        .unwrap();
+    let reply = phonebook::services::conversation::process_message(&state, "+15551110000", "yes")
+        .await
+        .unwrap();
+    assert!(reply.contains("https://book.example.com/calendar/"), "reply: {reply}");
+    assert!(reply.contains("https://book.example.com/reschedule/"), "reply: {reply}");
+    assert!(reply.contains("https://book.example.com/booking/"), "reply: {reply}");
+}
+
+#[tokio::test]
+async fn test_signed_cancel_link_cancels_booking() {
+    let state = test_state();
+    let start = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    seed_customer_booking(&state, "cancel-link-1", start);
+    let uri = phonebook::services::signing::cancel_path("test-token", "cancel-link-1").unwrap();
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains(&format!("action=\"{uri}\"")));
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(&uri)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    {
+        let db = state.db.lock().unwrap();
+        let booking = phonebook::db::queries::get_booking_by_id(&db, "cancel-link-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(booking.status, phonebook::models::BookingStatus::Cancelled);
+    }
+
+    // A second use finds nothing left to cancel
+    let app = test_app(state);
+    let res = app
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::GONE);
+}
+
+#[tokio::test]
+async fn test_cancel_link_rejects_bad_token() {
+    let state = test_state();
+    let start = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    seed_customer_booking(&state, "cancel-link-2", start);
+    // A token for another booking must not open this one
+    let token = phonebook::services::signing::sign("test-token", "cancel-link-1").unwrap();
+
+    for method in ["GET", "POST"] {
+        let app = test_app(state.clone());
+        let res = app
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(format!("/booking/cancel-link-2/cancel?token={token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::FORBIDDEN, "{method} should be rejected");
+    }
+
+    let db = state.db.lock().unwrap();
+    let booking = phonebook::db::queries::get_booking_by_id(&db, "cancel-link-2")
+        .unwrap()
+        .unwrap();
+    assert_eq!(booking.status, phonebook::models::BookingStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn test_booking_lookup_lists_upcoming_bookings() {
+    let state = test_state();
+    let start = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    seed_customer_booking(&state, "lookup-1", start);
+    seed_customer_booking(&state, "lookup-past", start - chrono::Duration::days(30));
+    let uri = phonebook::services::signing::lookup_path("test-token", "+15551110000").unwrap();
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8(body.to_vec()).unwrap();
+    assert!(html.contains(&start.format("%A, %B %-d at %-I:%M %p").to_string()));
+
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri(&uri)
+                .header("Accept", "application/json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let bookings = json["bookings"].as_array().unwrap();
+    assert_eq!(bookings.len(), 1, "past bookings are left out");
+    assert_eq!(bookings[0]["id"], "lookup-1");
+}
+
+#[tokio::test]
+async fn test_booking_lookup_rejects_tampered_token() {
+    let state = test_state();
+    let start = (chrono::Utc::now() + chrono::Duration::days(7))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    seed_customer_booking(&state, "lookup-2", start);
+    // A valid token for one phone can't be replayed against another
+    let token = phonebook::services::signing::sign("test-token", "+15559990000").unwrap();
+
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri(format!("/booking/lookup?phone=%2B15551110000&token={token}"))
+                .header("Accept", "application/json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(json.get("bookings").is_none());
+}
+
+// ── Booking CRUD via Admin API ──
+
+#[tokio::test]
+async fn test_admin_bookings_and_cancel() {
+    let state = test_state();
+
+    // Create a booking directly in DB
+    {
+        let db = state.db.lock().unwrap();
+        let booking = phonebook::models::Booking {
+            id: "bk-1".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: Some("Bob".to_string()),
+            date_time: chrono::NaiveDateTime::parse_from_str(
+                "2025-07-01 10:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+            duration_minutes: 30,
+            status: phonebook::models::BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+        };
+        phonebook::db::queries::create_booking(&db, &booking, BookingActor::Admin).unwrap();
+    }
+
+    // List bookings
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/bookings")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.len(), 1);
+    assert_eq!(json[0]["customer_name"], "Bob");
+    assert_eq!(json[0]["status"], "confirmed");
+
+    // Cancel booking
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/bookings/bk-1/cancel")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Verify cancelled
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/bookings?status=cancelled")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.len(), 1);
+    assert_eq!(json[0]["status"], "cancelled");
+}
+
+#[tokio::test]
+async fn test_admin_marks_past_booking_as_no_show() {
+    let state = test_state();
+    let now = chrono::Utc::now().naive_utc();
+    let past = now - chrono::Duration::hours(2);
+    seed_customer_booking(&state, "bk-missed", past);
+    seed_customer_booking(&state, "bk-upcoming", now + chrono::Duration::days(2));
+    {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::insert_inbox_event(&db, "+15551110000", "customer_message", "hi")
+            .unwrap();
+    }
+
+    let no_show = |id: &str| {
+        Request::builder()
+            .method("POST")
+            .uri(format!("/api/admin/bookings/{id}/no-show"))
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let res = test_app(state.clone())
+        .oneshot(no_show("bk-upcoming"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test_app(state.clone())
+        .oneshot(no_show("bk-nope"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = test_app(state.clone())
+        .oneshot(no_show("bk-missed"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test_app(state.clone())
+        .oneshot(no_show("bk-missed"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let db = state.db.lock().unwrap();
+    let booking = phonebook::db::queries::get_booking_by_id(&db, "bk-missed")
+        .unwrap()
+        .unwrap();
+    assert_eq!(booking.status, phonebook::models::BookingStatus::NoShow);
+    // The slot no longer counts as taken
+    let taken = phonebook::db::queries::get_bookings_in_range(
+        &db,
+        &(past - chrono::Duration::hours(1)),
+        &(past + chrono::Duration::hours(1)),
+    )
+    .unwrap();
+    assert!(taken.is_empty());
+    let contact = phonebook::db::queries::get_contact(&db, "+15551110000")
+        .unwrap()
+        .unwrap();
+    assert_eq!(contact.total_bookings, 2);
+    assert_eq!(contact.no_shows, 1);
+}
+
+#[tokio::test]
+async fn test_booking_history_records_create_and_cancel() {
+    let state = test_state();
+
+    {
+        let db = state.db.lock().unwrap();
+        let booking = phonebook::models::Booking {
+            id: "bk-hist".to_string(),
+            customer_phone: "+15551110000".to_string(),
+            customer_name: Some("Bob".to_string()),
+            date_time: chrono::NaiveDateTime::parse_from_str(
+                "2025-07-01 10:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+            duration_minutes: 30,
+            status: phonebook::models::BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+        };
+        phonebook::db::queries::create_booking(&db, &booking, BookingActor::Customer).unwrap();
+    }
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/bookings/bk-hist/cancel")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/bookings/bk-hist/history")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json.len(), 2);
+    assert_eq!(json[0]["event"], "create");
+    assert_eq!(json[0]["actor"], "customer");
+    assert_eq!(json[0]["old_status"], serde_json::Value::Null);
+    assert_eq!(json[0]["new_status"], "confirmed");
+    assert_eq!(json[1]["event"], "cancel");
+    assert_eq!(json[1]["actor"], "admin");
+    assert_eq!(json[1]["old_status"], "confirmed");
+    assert_eq!(json[1]["new_status"], "cancelled");
+
+    // Unknown booking → 404
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/admin/bookings/missing/history")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+// ── Multi-slot Proposals ──
+
+#[tokio::test]
+async fn test_vague_request_offers_open_slots_and_books_pick() {
+    use chrono::Datelike;
+
+    // Next Thursday, at least a day out
+    let today = chrono::Utc::now().date_naive();
+    let mut thursday = today + chrono::Duration::days(1);
+    while thursday.weekday() != chrono::Weekday::Thu {
+        thursday += chrono::Duration::days(1);
+    }
+    let date = thursday.format("%Y-%m-%d").to_string();
+
+    let vague = format!(
+        r#"{{"intent":"book","customer_name":"Sam","requested_date":"{date}","requested_time":null,"time_window":"afternoon","duration_minutes":60,"notes":null,"message_to_customer":"Let me check Thursday afternoon."}}"#
+    );
+    let pick = r#"{"intent":"confirm","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Great!"}"#;
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[&vague, pick])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+
+    // 12:00-13:00 is already taken, so the first open slots are 13:00, 14:00, 15:00
+    {
+        let db = state.db.lock().unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        let booking = phonebook::models::Booking {
+            id: "taken".to_string(),
+            customer_phone: "+15552220000".to_string(),
+            customer_name: Some("Other".to_string()),
+            date_time: thursday.and_hms_opt(12, 0, 0).unwrap(),
+            duration_minutes: 60,
+            status: phonebook::models::BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: now,
+            updated_at: now,
+        };
+        phonebook::db::queries::create_booking(&db, &booking, BookingActor::Customer).unwrap();
+    }
+
+    for body in ["anytime+Thursday+afternoon%3F+-Sam", "the+2pm+one"] {
+        let app = test_app(state.clone());
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "From=%2B15551110000&To=%2B15551234567&Body={body}&MessageSid=SM1"
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let replies: Vec<String> = sent
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(to, _)| to == "+15551110000")
+        .map(|(_, body)| body.clone())
+        .collect();
+    assert_eq!(replies.len(), 2);
+    assert!(replies[0].contains("1:00 PM, 2:00 PM, or 3:00 PM"), "offer: {}", replies[0]);
+    assert!(!replies[0].contains("12:00 PM"));
+    assert!(replies[1].contains("2:00 PM"), "confirmation: {}", replies[1]);
+
+    let db = state.db.lock().unwrap();
+    let bookings = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000").unwrap();
+    assert_eq!(bookings.len(), 1);
+    assert_eq!(bookings[0].date_time, thursday.and_hms_opt(14, 0, 0).unwrap());
+    assert_eq!(bookings[0].customer_name.as_deref(), Some("Sam"));
+}
+
+#[tokio::test]
+async fn test_slot_offer_uses_24h_clock_when_set() {
+    use chrono::Datelike;
+
+    let today = chrono::Utc::now().date_naive();
+    let mut thursday = today + chrono::Duration::days(1);
+    while thursday.weekday() != chrono::Weekday::Thu {
+        thursday += chrono::Duration::days(1);
+    }
+    let date = thursday.format("%Y-%m-%d").to_string();
+
+    let vague = format!(
+        r#"{{"intent":"book","customer_name":"Sam","requested_date":"{date}","requested_time":null,"time_window":"afternoon","duration_minutes":60,"notes":null,"message_to_customer":"Let me check Thursday afternoon."}}"#
+    );
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[&vague])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"use_24h_time":true}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "anytime Thursday afternoon? -Sam"))
+        .await
+        .unwrap();
+
+    let replies = texts_to(&sent, "+15551110000");
+    assert_eq!(replies.len(), 1);
+    assert!(replies[0].contains("12:00, 13:00, or 14:00"), "offer: {}", replies[0]);
+    assert!(!replies[0].contains("PM"));
+}
+
+#[tokio::test]
+async fn test_question_with_number_does_not_pick_offered_slot() {
+    use chrono::Datelike;
+
+    let today = chrono::Utc::now().date_naive();
+    let mut thursday = today + chrono::Duration::days(1);
+    while thursday.weekday() != chrono::Weekday::Thu {
+        thursday += chrono::Duration::days(1);
+    }
+    let date = thursday.format("%Y-%m-%d").to_string();
+
+    let vague = format!(
+        r#"{{"intent":"book","customer_name":"Sam","requested_date":"{date}","requested_time":null,"time_window":"afternoon","duration_minutes":60,"notes":null,"message_to_customer":"Let me check Thursday afternoon."}}"#
+    );
+    let question = r#"{"intent":"general_question","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Of course, kids are welcome."}"#;
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[&vague, question])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+
+    for body in ["anytime+Thursday+afternoon%3F+-Sam", "Can+I+bring+2+kids%3F"] {
+        let app = test_app(state.clone());
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "From=%2B15551110000&To=%2B15551234567&Body={body}&MessageSid=SM1"
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let db = state.db.lock().unwrap();
+    let bookings = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000").unwrap();
+    assert!(bookings.is_empty(), "booked: {:?}", bookings);
+    let conv = phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+        .unwrap()
+        .unwrap();
+    assert_eq!(conv.state, phonebook::models::ConversationState::Confirming);
+}
+
+// ── Waitlist ──
+
+#[tokio::test]
+async fn test_conflict_joins_waitlist_when_enabled() {
+    let slot = (chrono::Utc::now() + chrono::Duration::days(2))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Bea","requested_date":"{}","requested_time":"10:00","duration_minutes":60,"notes":null,"message_to_customer":"Booking you in."}}"#,
+        slot.format("%Y-%m-%d")
+    );
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[&book, &book])));
+    seed_customer_booking(&state, "taken", slot);
+
+    // Without the capability, a conflict is only rejected
+    let reply = phonebook::services::conversation::process_message(&state, "+15550002222", "10am please")
+        .await
+        .unwrap();
+    assert!(reply.contains("already booked"), "got: {reply}");
+    assert!(!reply.contains("waitlist"));
+
+    let prefs = serde_json::json!({ "capabilities": { "can_offer_waitlist": true } }).to_string();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "ai_preferences": prefs }).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let reply = phonebook::services::conversation::process_message(&state, "+15550002222", "10am please")
+        .await
+        .unwrap();
+    assert!(reply.contains("put you on the waitlist"), "got: {reply}");
+
+    let db = state.db.lock().unwrap();
+    let waiting = phonebook::db::queries::get_waitlist_overlapping(&db, &slot, 60).unwrap();
+    assert_eq!(waiting.len(), 1);
+    assert_eq!(waiting[0].phone, "+15550002222");
+    assert_eq!(waiting[0].date_time, slot);
+}
+
+#[tokio::test]
+async fn test_cancel_notifies_first_waitlisted_customer() {
+    let slot = (chrono::Utc::now() + chrono::Duration::days(2))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let cancel = r#"{"intent":"cancel","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Cancel it?"}"#;
+    let confirm = r#"{"intent":"confirm","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Done."}"#;
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[cancel, confirm])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
+    seed_customer_booking(&state, "taken", slot);
+    {
+        let db = state.db.lock().unwrap();
+        for phone in ["+15550002222", "+15550003333"] {
+            phonebook::db::queries::add_to_waitlist(&db, phone, &slot, 60).unwrap();
+        }
+        let later = slot + chrono::Duration::hours(3);
+        phonebook::db::queries::add_to_waitlist(&db, "+15550004444", &later, 60).unwrap();
+    }
+    let notified = |sent: &SentMessages| -> Vec<String> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, body)| body.contains("just opened up"))
+            .map(|(to, _)| to.clone())
+            .collect()
+    };
+
+    // Customer cancels: only the longest-waiting overlapping entry hears about it
+    for message in ["cancel my appointment", "yes"] {
+        phonebook::services::conversation::process_message(&state, "+15551110000", message)
+            .await
+            .unwrap();
+    }
+    assert_eq!(notified(&sent), vec!["+15550002222".to_string()]);
+
+    // The slot is taken again, then cancelled by the owner: next in line is told
+    seed_customer_booking(&state, "retaken", slot);
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/bookings/retaken/cancel")
+                .header("Authorization", "Bearer test-token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        notified(&sent),
+        vec!["+15550002222".to_string(), "+15550003333".to_string()]
+    );
+
+    let db = state.db.lock().unwrap();
+    let later = slot + chrono::Duration::hours(3);
+    let waiting = phonebook::db::queries::get_waitlist_overlapping(&db, &later, 60).unwrap();
+    assert_eq!(waiting.len(), 1);
+    assert_eq!(waiting[0].phone, "+15550004444");
+}
+
+#[tokio::test]
+async fn test_waitlist_opening_survives_failed_send_and_respects_quiet_hours() {
+    let slot = (chrono::Utc::now() + chrono::Duration::days(2))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(MockLlm),
+        Box::new(FlakyMessaging {
+            unreachable: "+15550002222".to_string(),
+            sent: Arc::clone(&sent),
+        }),
+    );
+    seed_customer_booking(&state, "freed", slot);
+    let freed = {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::add_to_waitlist(&db, "+15550002222", &slot, 60).unwrap();
+        phonebook::db::queries::get_booking_by_id(&db, "freed")
+            .unwrap()
+            .unwrap()
+    };
+    let waiting = |state: &Arc<AppState>| {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::get_waitlist_overlapping(&db, &slot, 60).unwrap()
+    };
+
+    // A failed send keeps the customer's place in line
+    phonebook::services::waitlist::offer_freed_slot(&state, &freed).await;
+    assert_eq!(waiting(&state).len(), 1);
+
+    // During quiet hours the text is held instead of sent
+    let (state, sent) = test_state_with_sent();
+    {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::add_to_waitlist(&db, "+15550002222", &slot, 60).unwrap();
+    }
+    let now = chrono::Utc::now();
+    let quiet = serde_json::json!({
+        "start": (now - chrono::Duration::hours(1)).format("%H:%M").to_string(),
+        "end": (now + chrono::Duration::hours(1)).format("%H:%M").to_string(),
+    })
+    .to_string();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "quiet_hours": quiet }).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    phonebook::services::waitlist::offer_freed_slot(&state, &freed).await;
+    assert!(texts_to(&sent, "+15550002222").is_empty());
+    let db = state.db.lock().unwrap();
+    let queued = phonebook::db::queries::get_queued_replies(&db).unwrap();
+    assert_eq!(queued.len(), 1);
+    assert!(queued[0].body.contains("just opened up"));
+    drop(db);
+    assert!(waiting(&state).is_empty());
+}
+
+// ── Reminders ──
+
+#[tokio::test]
+async fn test_reminder_reply_confirms_or_cancels_booking() {
+    let (state, sent) = test_state_with_sent();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "business_name": "Test Biz",
+                        "reminder_hours_before": 24,
+                        "reminder_template": "Hi {name}, see you {time} at {business}. Reply C to confirm, X to cancel.",
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let now = chrono::Utc::now().naive_utc();
+    let start = now + chrono::Duration::hours(3);
+    {
+        let db = state.db.lock().unwrap();
+        for (id, phone, hours, status) in [
+            ("bk-remind-c", "+15551110007", 3, phonebook::models::BookingStatus::Pending),
+            ("bk-remind-x", "+15551110008", 5, phonebook::models::BookingStatus::Confirmed),
+            ("bk-later", "+15551110009", 48, phonebook::models::BookingStatus::Confirmed),
+        ] {
+            let booking = phonebook::models::Booking {
+                id: id.to_string(),
+                customer_phone: phone.to_string(),
+                customer_name: Some("Dana".to_string()),
+                date_time: start + chrono::Duration::hours(hours - 3),
+                duration_minutes: 60,
+                status,
+                notes: None,
+                service: None,
+                created_at: now,
+                updated_at: now,
+            };
+            phonebook::db::queries::create_booking(&db, &booking, BookingActor::Customer).unwrap();
+        }
+    }
+
+    // Only bookings inside the 24h window are reminded, and only once
+    assert_eq!(phonebook::services::reminders::send_due_reminders(&state).await, 2);
+    assert_eq!(phonebook::services::reminders::send_due_reminders(&state).await, 0);
+    let when = start.format("%A, %B %-d at %-I:%M %p");
+    assert!(sent.lock().unwrap().contains(&(
+        "+15551110007".to_string(),
+        format!("Hi Dana, see you {when} at Test Biz. Reply C to confirm, X to cancel."),
+    )));
+
+    for (phone, reply) in [("+15551110007", "c"), ("+15551110008", "X"), ("+15551110009", "X")] {
+        let res = test_app(state.clone())
+            .oneshot(sms_request(phone, reply))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let last_reply = |phone: &str| {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|(to, _)| to == phone)
+            .map(|(_, body)| body.clone())
+            .unwrap()
+    };
+    assert!(last_reply("+15551110007").contains("you're confirmed"));
+    assert!(last_reply("+15551110008").contains("has been cancelled"));
+
+    let db = state.db.lock().unwrap();
+    let status = |id| {
+        phonebook::db::queries::get_booking_by_id(&db, id)
+            .unwrap()
+            .unwrap()
+            .status
+    };
+    assert_eq!(status("bk-remind-c"), phonebook::models::BookingStatus::Confirmed);
+    assert_eq!(status("bk-remind-x"), phonebook::models::BookingStatus::Cancelled);
+    // No reminder went to this customer, so "X" goes through the normal engine
+    assert_eq!(status("bk-later"), phonebook::models::BookingStatus::Confirmed);
+}
+
+// ── Conversation States ──
+
+#[tokio::test]
+async fn test_reschedule_keeps_rescheduling_state_until_confirmed() {
+    use phonebook::models::{BookingStatus, ConversationState};
+
+    let start = (chrono::Utc::now() + chrono::Duration::days(3))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let new_date = (start + chrono::Duration::days(1)).format("%Y-%m-%d").to_string();
+    let reschedule = r#"{"intent":"reschedule","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Sure, what time works better?"}"#;
+    let question = r#"{"intent":"general_question","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"We're open 9 to 5."}"#;
+    let new_time = format!(
+        r#"{{"intent":"general_question","customer_name":null,"requested_date":"{new_date}","requested_time":"15:00","duration_minutes":null,"notes":null,"message_to_customer":"How about {new_date} at 3pm?"}}"#
+    );
+    let confirm = r#"{"intent":"confirm","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Done!"}"#;
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[
+        reschedule, question, &new_time, confirm,
+    ])));
+    seed_customer_booking(&state, "orig", start);
+
+    let send = |body: &'static str| {
+        let app = test_app(state.clone());
+        async move {
+            app.oneshot(sms_request("+15551110000", body)).await.unwrap();
+        }
+    };
+    let booking_status = |id: &str| {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::get_booking_by_id(&db, id)
+            .unwrap()
+            .unwrap()
+            .status
+    };
+
+    send("I need to move my appointment").await;
+    let conv = stored_conversation(&state, "+15551110000");
+    assert_eq!(conv.state, ConversationState::Rescheduling);
+    assert_eq!(
+        conv.pending_booking.unwrap().booking_id.as_deref(),
+        Some("orig")
+    );
+    // The original booking is kept until the new time is confirmed
+    assert_eq!(booking_status("orig"), BookingStatus::Confirmed);
+
+    // A side question mid-flow resumes in the rescheduling state
+    send("what are your hours?").await;
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Rescheduling
+    );
+
+    send("the next day at 3pm").await;
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Confirming
+    );
+
+    send("yes").await;
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Idle
+    );
+    assert_eq!(booking_status("orig"), BookingStatus::Cancelled);
+    let db = state.db.lock().unwrap();
+    let upcoming = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000").unwrap();
+    assert_eq!(upcoming.len(), 1);
+    assert_eq!(
+        upcoming[0].date_time.format("%Y-%m-%d %H:%M").to_string(),
+        format!("{new_date} 15:00")
+    );
+}
+
+#[tokio::test]
+async fn test_cancel_asks_for_confirmation() {
+    use phonebook::models::{BookingStatus, ConversationState};
+
+    let (state, sent) = test_state_with_sent();
+    let start = (chrono::Utc::now() + chrono::Duration::days(3))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    seed_customer_booking(&state, "to-cancel", start);
+
+    let app = test_app(state.clone());
+    app.oneshot(sms_request("+15551110000", "please cancel")).await.unwrap();
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Cancelling
+    );
+    {
+        let db = state.db.lock().unwrap();
+        let booking = phonebook::db::queries::get_booking_by_id(&db, "to-cancel")
+            .unwrap()
+            .unwrap();
+        assert_eq!(booking.status, BookingStatus::Confirmed);
+    }
+
+    let app = test_app(state.clone());
+    app.oneshot(sms_request("+15551110000", "yes")).await.unwrap();
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Idle
+    );
+    let db = state.db.lock().unwrap();
+    let booking = phonebook::db::queries::get_booking_by_id(&db, "to-cancel")
+        .unwrap()
+        .unwrap();
+    assert_eq!(booking.status, BookingStatus::Cancelled);
+
+    let replies: Vec<String> = sent
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(to, _)| to == "+15551110000")
+        .map(|(_, body)| body.clone())
+        .collect();
+    assert!(replies[0].contains("Reply YES to cancel"));
+    assert!(replies[1].contains("has been cancelled"));
+}
+
+#[tokio::test]
+async fn test_spanish_user_gets_spanish_no_bookings_reply() {
+    let state = test_state();
+
+    let settings = |body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/admin/settings")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let res = test_app(state.clone())
+        .oneshot(settings(serde_json::json!({ "language": "de" })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = test_app(state.clone())
+        .oneshot(settings(serde_json::json!({ "language": "es" })))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let reply = phonebook::services::conversation::process_message(
+        &state,
+        "+15551110000",
+        "please cancel",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        reply,
+        "No veo ninguna cita próxima para cancelar. ¿Le gustaría reservar una cita?"
+    );
+}
+
+#[tokio::test]
+async fn test_llm_duration_is_clamped_to_configured_bounds() {
+    let book = |minutes: i32| {
+        format!(
+            r#"{{"intent":"book","customer_name":"Test User","requested_date":"2025-06-16","requested_time":"10:00","duration_minutes":{minutes},"notes":null,"message_to_customer":"Monday at 10 AM?"}}"#
+        )
+    };
+    let (too_short, too_long) = (book(5), book(600));
+    let llm = ScriptedLlm::new(&[&too_short, &too_long]);
+    let state = test_state_with_llm(Box::new(llm));
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: Some(
+                r#"{"slots":[],"day_from":"mon","day_to":"fri","time_from":"09:00","time_to":"17:00","min_duration":30,"max_duration":120}"#
+                    .to_string(),
+            ),
+            timezone: "UTC".to_string(),
+            ai_preferences: None,
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+
+    for (phone, expected) in [("+15550003001", 30), ("+15550003002", 120)] {
+        phonebook::services::conversation::process_message(&state, phone, "book monday 10am")
+            .await
+            .unwrap();
+        let pending = stored_conversation(&state, phone).pending_booking.unwrap();
+        assert_eq!(pending.duration_minutes, Some(expected));
+    }
+}
+
+
+#[tokio::test]
+async fn test_stale_confirmation_is_reprompted_once() {
+    let (state, sent) = test_state_with_sent();
+    let now = chrono::Utc::now().naive_utc();
+    {
+        let db = state.db.lock().unwrap();
+        for (phone, idle) in [("+15551110001", 15), ("+15551110002", 2)] {
+            let last_activity = now - chrono::Duration::minutes(idle);
+            let conv = phonebook::models::Conversation {
+                phone: phone.to_string(),
+                messages: vec![],
+                state: phonebook::models::ConversationState::Confirming,
+                pending_booking: Some(phonebook::models::PendingBooking {
+                    customer_name: Some("Dana".to_string()),
+                    date_time: Some("2030-01-07 10:00".to_string()),
+                    duration_minutes: Some(60),
+                    notes: None,
+                    proposed_slots: vec![],
+                    booking_id: None,
+                    service: None,
+                }),
+                paused_autoreply_sent: false,
+                reprompt_sent: false,
+                summary: None,
+                last_activity,
+                expires_at: last_activity + chrono::Duration::minutes(30),
+            };
+            phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+        }
+    }
+
+    // Only the conversation idle past CONFIRM_REPROMPT_MINUTES is nudged, and only once
+    assert_eq!(phonebook::services::conversation::reprompt_stale_confirmations(&state).await, 1);
+    assert_eq!(phonebook::services::conversation::reprompt_stale_confirmations(&state).await, 0);
+
+    let sent = sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].0, "+15551110001");
+    assert!(sent[0].1.starts_with("Are you still there?"));
+    let conv = stored_conversation(&state, "+15551110001");
+    assert!(conv.reprompt_sent);
+    assert_eq!(conv.state, phonebook::models::ConversationState::Confirming);
+    // The nudge doesn't extend the conversation's life
+    assert!(conv.expires_at < now + chrono::Duration::minutes(16));
+}
+
+#[tokio::test]
+async fn test_admin_conversation_shows_stored_state_even_when_expired() {
+    let state = test_state();
+    let now = chrono::Utc::now().naive_utc();
+    {
+        let db = state.db.lock().unwrap();
+        let conv = phonebook::models::Conversation {
+            phone: "+15551110000".to_string(),
+            messages: vec![phonebook::models::ConversationMessage {
+                role: "user".to_string(),
+                content: "Book me Friday at 2pm".to_string(),
+            }],
+            state: phonebook::models::ConversationState::Confirming,
+            pending_booking: Some(phonebook::models::PendingBooking {
+                customer_name: Some("Alice".to_string()),
+                date_time: Some("2030-03-01 14:00".to_string()),
+                duration_minutes: Some(60),
+                notes: None,
+                proposed_slots: vec![],
+                booking_id: None,
+                service: None,
+            }),
+            paused_autoreply_sent: false,
+            reprompt_sent: false,
+            summary: None,
+            last_activity: now - chrono::Duration::hours(2),
+            expires_at: now - chrono::Duration::hours(1),
+        };
+        phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+        assert!(
+            phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    let get = |phone: &str| {
+        Request::builder()
+            .uri(format!("/api/admin/conversation/{phone}"))
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let res = test_app(state.clone())
+        .oneshot(get("%2B15551110000"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["state"], "confirming");
+    assert_eq!(json["pending_booking"]["date_time"], "2030-03-01 14:00");
+    assert_eq!(json["pending_booking"]["customer_name"], "Alice");
+    assert_eq!(json["messages"][0]["content"], "Book me Friday at 2pm");
+    assert!(json["expires_at"].is_string());
+
+    let res = test_app(state)
+        .oneshot(get("%2B15552220000"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_reset_conversation_starts_customer_fresh() {
+    let (state, sent) = test_state_with_sent();
+    let now = chrono::Utc::now().naive_utc();
+    {
+        let db = state.db.lock().unwrap();
+        let conv = phonebook::models::Conversation {
+            phone: "+15551110000".to_string(),
+            messages: vec![],
+            state: phonebook::models::ConversationState::Confirming,
+            pending_booking: Some(phonebook::models::PendingBooking {
+                customer_name: Some("Alice".to_string()),
+                date_time: Some("2030-03-01 14:00".to_string()),
+                duration_minutes: Some(60),
+                notes: None,
+                proposed_slots: vec![],
+                booking_id: None,
+                service: None,
+            }),
+            paused_autoreply_sent: false,
+            reprompt_sent: false,
+            summary: None,
+            last_activity: now,
+            expires_at: now + chrono::Duration::minutes(30),
+        };
+        phonebook::db::queries::save_conversation(&db, &conv).unwrap();
+    }
+
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/conversation/%2B15551110000/reset")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "message": "Sorry for the mix-up! Let's start over." })
+                        .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["reset"], true);
+    assert_eq!(json["message_sent"], true);
+    assert_eq!(
+        sent.lock().unwrap().last().unwrap(),
+        &(
+            "+15551110000".to_string(),
+            "Sorry for the mix-up! Let's start over.".to_string()
+        )
+    );
+
+    // "yes" no longer confirms the dropped booking
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "yes"))
+        .await
+        .unwrap();
+    let db = state.db.lock().unwrap();
+    assert!(
+        phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000")
+            .unwrap()
+            .is_empty()
+    );
+    let conv = phonebook::db::queries::get_conversation(&db, "+15551110000", false)
+        .unwrap()
+        .unwrap();
+    assert!(conv.pending_booking.is_none());
+    assert_ne!(conv.state, phonebook::models::ConversationState::Confirming);
+}
+
+#[tokio::test]
+async fn test_decline_with_new_time_reproposes_it() {
+    use phonebook::models::ConversationState;
+    use phonebook::services::conversation::process_message;
+
+    let day = (chrono::Utc::now() + chrono::Duration::days(3)).date_naive();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Alice","requested_date":"{}","requested_time":"10:00","duration_minutes":60,"notes":null,"message_to_customer":"Shall I book that?"}}"#,
+        day.format("%Y-%m-%d")
+    );
+    let decline = r#"{"intent":"decline","customer_name":null,"requested_date":null,"requested_time":"15:00","duration_minutes":null,"notes":null,"message_to_customer":"What time works better?"}"#;
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[&book, decline])));
+
+    let reply = process_message(&state, "+15551110000", "10am please")
+        .await
+        .unwrap();
+    assert_eq!(reply, "Shall I book that?");
+
+    // "No, make it 3pm instead" keeps the date and proposes the new time
+    let reply = process_message(&state, "+15551110000", "no, make it 3pm instead")
+        .await
+        .unwrap();
+    let when = day
+        .and_hms_opt(15, 0, 0)
+        .unwrap()
+        .format("%A, %B %-d at %-I:%M %p")
+        .to_string();
+    assert_eq!(
+        reply,
+        format!("No problem. How about {when} instead? Reply YES to confirm.")
+    );
+    let conv = stored_conversation(&state, "+15551110000");
+    assert_eq!(conv.state, ConversationState::Confirming);
+    assert_eq!(
+        conv.pending_booking.unwrap().date_time,
+        Some(format!("{} 15:00", day.format("%Y-%m-%d")))
+    );
+}
+
+#[tokio::test]
+async fn test_restating_pending_time_confirms_booking() {
+    use phonebook::models::ConversationState;
+    use phonebook::services::conversation::process_message;
+
+    let day = (chrono::Utc::now() + chrono::Duration::days(3)).date_naive();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Alice","requested_date":"{}","requested_time":"10:00","duration_minutes":60,"notes":null,"message_to_customer":"Shall I book that?"}}"#,
+        day.format("%Y-%m-%d")
+    );
+    let restate = r#"{"intent":"book","customer_name":null,"requested_date":null,"requested_time":"10:00","duration_minutes":null,"notes":null,"message_to_customer":"Shall I book 10am?"}"#;
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[&book, restate])));
+
+    process_message(&state, "+15551110000", "10am please")
+        .await
+        .unwrap();
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        ConversationState::Confirming
+    );
+
+    // "Yes, 10am works" read as another booking request still confirms
+    let reply = process_message(&state, "+15551110000", "yes, 10am works")
+        .await
+        .unwrap();
+    let when = day
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+        .format("%A, %B %-d at %-I:%M %p")
+        .to_string();
+    assert!(
+        reply.starts_with(&format!("Your appointment: {when} (60 minutes).")),
+        "reply: {reply}"
+    );
+
+    let db = state.db.lock().unwrap();
+    let bookings = phonebook::db::queries::get_bookings_for_phone(&db, "+15551110000").unwrap();
+    assert_eq!(bookings.len(), 1);
+    assert_eq!(bookings[0].date_time, day.and_hms_opt(10, 0, 0).unwrap());
+}
+
+#[tokio::test]
+async fn test_confirmation_restates_stored_booking() {
+    use phonebook::services::conversation::process_message;
+
+    let day = (chrono::Utc::now() + chrono::Duration::days(3)).date_naive();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Alice","requested_date":"{}","requested_time":"10:00","duration_minutes":90,"notes":null,"message_to_customer":"Shall I book that?"}}"#,
+        day.format("%Y-%m-%d")
+    );
+    let confirm = r#"{"intent":"confirm","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"All set, see you soon!"}"#;
+    let afternoon = book.replace("10:00", "13:00");
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[
+        &book, confirm, &afternoon, confirm,
+    ])));
+    let when = day
+        .and_hms_opt(10, 0, 0)
+        .unwrap()
+        .format("%A, %B %-d at %-I:%M %p")
+        .to_string();
+
+    // A vague LLM reply still gets the stored time and length spelled out
+    process_message(&state, "+15551110000", "10am please")
+        .await
+        .unwrap();
+    let reply = process_message(&state, "+15551110000", "yes")
+        .await
+        .unwrap();
+    assert!(
+        reply.starts_with(&format!(
+            "All set, see you soon!\nYour appointment: {when} (90 minutes)."
+        )),
+        "reply: {reply}"
+    );
+
+    // The owner can word it themselves
+    let prefs = serde_json::json!({
+        "confirmation_template": "{name}, you're down for {duration} min on {time} at {business}."
+    });
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({
+                        "business_name": "Test Biz",
+                        "ai_preferences": prefs.to_string(),
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    process_message(&state, "+15550002222", "1pm please")
+        .await
+        .unwrap();
+    let reply = process_message(&state, "+15550002222", "yes")
+        .await
+        .unwrap();
+    let when = day
+        .and_hms_opt(13, 0, 0)
+        .unwrap()
+        .format("%A, %B %-d at %-I:%M %p")
+        .to_string();
+    assert!(
+        reply.contains(&format!(
+            "Alice, you're down for 90 min on {when} at Test Biz."
+        )),
+        "reply: {reply}"
+    );
+}
+
+// ── Scheduling Validation Tests ──
+
+#[tokio::test]
+async fn test_booking_outside_business_hours_rejected() {
+    let state = test_state();
+
+    // Configure availability: Mon-Fri 09:00-17:00
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: Some(
+                r#"{"slots":[{"day":"mon","start":"09:00","end":"17:00"},{"day":"tue","start":"09:00","end":"17:00"},{"day":"wed","start":"09:00","end":"17:00"},{"day":"thu","start":"09:00","end":"17:00"},{"day":"fri","start":"09:00","end":"17:00"}]}"#
+                    .to_string(),
+            ),
+            timezone: "America/New_York".to_string(),
+            ai_preferences: None,
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+
+    // The MockLlm returns a booking for Sunday (2025-06-15 is a Sunday) at 14:00
+    // which is outside Mon-Fri availability
+    let reply = phonebook::services::conversation::process_message(
+        &state,
+        "+15550001111",
+        "I'd like to book an appointment",
+    )
+    .await
+    .unwrap();
+
+    // Should get a rejection about business hours
+    assert!(
+        reply.contains("outside our business hours") || reply.contains("available"),
+        "Expected business hours rejection, got: {reply}"
+    );
+}
+
+#[tokio::test]
+async fn test_custom_rejection_template_used_in_reply() {
+    let state = test_state();
+
+    // Weekdays only, with the owner's own wording for out-of-hours requests
+    {
+        let db = state.db.lock().unwrap();
+        let user = phonebook::models::User {
+            id: "default".to_string(),
+            business_name: "Test Biz".to_string(),
+            owner_name: "Alice".to_string(),
+            owner_phone: "+15559999999".to_string(),
+            twilio_account_sid: "".to_string(),
+            twilio_auth_token: "".to_string(),
+            twilio_phone_number: "+15551234567".to_string(),
+            availability: Some(
+                r#"{"slots":[{"day":"mon","start":"09:00","end":"17:00"},{"day":"fri","start":"09:00","end":"17:00"}]}"#
+                    .to_string(),
+            ),
+            timezone: "America/New_York".to_string(),
+            ai_preferences: Some(
+                r#"{"rejection_messages":{"outside_hours":"Oops, we're closed then! Our hours: {hours}"}}"#
+                    .to_string(),
+            ),
+            language: "en".to_string(),
+            services: None,
+            location: None,
+            notify_on_booking: true,
+            notify_on_cancel: true,
+            notify_on_block: true,
+            notify_on_pause: true,
+            quiet_hours: None,
+            one_booking_per_customer_per_day: false,
+            reminder_hours_before: 0,
+            reminder_template: None,
+            spam_keywords: None,
+            block_spam: false,
+            use_24h_time: false,
+        };
+        phonebook::db::queries::save_user(&db, &user).unwrap();
+    }
+
+    // MockLlm proposes Sunday 2025-06-15 at 14:00
+    let reply = phonebook::services::conversation::process_message(
+        &state,
+        "+15550001111",
+        "I'd like to book an appointment",
+    )
+    .await
+    .unwrap();
+
+    assert!(
+        reply.starts_with("Oops, we're closed then! Our hours: "),
+        "Expected custom rejection, got: {reply}"
+    );
+    assert!(!reply.contains("{hours}"));
+    assert!(!reply.contains("outside our business hours"));
+}
+
+#[tokio::test]
+async fn test_conflicting_booking_rejected() {
+    let state = test_state();
+
+    // Create an existing booking at the same time the MockLlm will propose (2025-06-15 14:00)
+    {
+        let db = state.db.lock().unwrap();
+        let booking = phonebook::models::Booking {
+            id: "conflict-1".to_string(),
+            customer_phone: "+15559990000".to_string(),
+            customer_name: Some("Existing".to_string()),
+            date_time: chrono::NaiveDateTime::parse_from_str(
+                "2025-06-15 14:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+            duration_minutes: 60,
+            status: phonebook::models::BookingStatus::Confirmed,
+            notes: None,
+            service: None,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+        };
+        phonebook::db::queries::create_booking(&db, &booking, BookingActor::Admin).unwrap();
+    }
+
+    let reply = phonebook::services::conversation::process_message(
+        &state,
+        "+15550002222",
+        "I'd like to book an appointment",
+    )
+    .await
+    .unwrap();
+
+    assert!(
+        reply.contains("already booked") || reply.contains("different time"),
+        "Expected conflict rejection, got: {reply}"
+    );
+}
 
 #[tokio::test]
-async fn test_rate_limit_auto_blocks() {
+async fn test_valid_booking_succeeds() {
     let state = test_state();
 
-    // Send 16 messages from the same number (exceeds 15/hr limit)
-    for i in 0..16 {
-        let app = test_app(state.clone());
-        let res = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/webhook/sms")
-                    .header("Content-Type", "application/x-www-form-urlencoded")
-                    .body(Body::from(format!(
-                        "From=%2B15551110000&To=%2B15551234567&Body=msg{}&MessageSid=SM{}",
-                        i, i
-                    )))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
-        assert_eq!(res.status(), StatusCode::OK);
-    }
+    // No availability restrictions, no conflicting bookings
+    // MockLlm proposes 2025-06-15 14:00
+    let reply = phonebook::services::conversation::process_message(
+        &state,
+        "+15550003333",
+        "I'd like to book an appointment",
+    )
+    .await
+    .unwrap();
 
-    // Verify the number is now blocked
-    let db = state.db.lock().unwrap();
-    assert!(phonebook::db::queries::is_blocked(&db, "+15551110000").unwrap());
+    // Should get the LLM's normal response (not a validation error)
+    assert!(
+        reply.contains("June 15") || reply.contains("2:00 PM") || reply.contains("book"),
+        "Expected booking proposal, got: {reply}"
+    );
 }
 
-// ── Calendar .ics Tests ──
+#[tokio::test]
+async fn test_one_booking_per_customer_per_day() {
+    let day = (chrono::Utc::now() + chrono::Duration::days(3)).date_naive();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Alice","requested_date":"{}","requested_time":"15:00","duration_minutes":60,"notes":null,"message_to_customer":"Shall I book that?"}}"#,
+        day.format("%Y-%m-%d")
+    );
+    let state = test_state_with_llm(Box::new(ScriptedLlm::new(&[&book, &book])));
+    seed_customer_booking(&state, "morning", day.and_hms_opt(10, 0, 0).unwrap());
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"one_booking_per_customer_per_day":true}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The customer with a morning booking can't take an afternoon slot too
+    let reply = phonebook::services::conversation::process_message(&state, "+15551110000", "3pm too")
+        .await
+        .unwrap();
+    assert!(reply.contains("only book one per day"), "reply: {reply}");
+    assert_ne!(
+        stored_conversation(&state, "+15551110000").state,
+        phonebook::models::ConversationState::Confirming
+    );
+
+    // Someone else can still book that afternoon
+    let reply = phonebook::services::conversation::process_message(&state, "+15550002222", "3pm please")
+        .await
+        .unwrap();
+    assert_eq!(reply, "Shall I book that?");
+    assert_eq!(
+        stored_conversation(&state, "+15550002222").state,
+        phonebook::models::ConversationState::Confirming
+    );
+}
+
+// ── Health Check ──
 
 #[tokio::test]
-async fn test_calendar_not_found() {
+async fn test_health() {
     let state = test_state();
     let app = test_app(state);
 
     let res = app
         .oneshot(
             Request::builder()
-                .uri("/calendar/nonexistent.ics")
+                .uri("/health")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
 
-    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+/// Collects formatted log output for assertions.
+#[derive(Clone, Default)]
+struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for CapturedLogs {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl CapturedLogs {
+    /// Parsed `access` events, one per request.
+    fn access_events(&self) -> Vec<serde_json::Value> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|event| event["target"] == "access")
+            .collect()
+    }
 }
 
 #[tokio::test]
-async fn test_calendar_download() {
+async fn test_access_log_records_status_as_json() {
+    let logs = CapturedLogs::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(move || writer.clone())
+        .finish();
+    // Current-thread test runtime, so the requests log on this thread
+    let _guard = tracing::subscriber::set_default(subscriber);
     let state = test_state();
 
-    // Create a booking
+    test_app(state.clone())
+        .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    test_app(state.clone())
+        .oneshot(sms_request("+15551234567", "hi"))
+        .await
+        .unwrap();
+
+    let events = logs.access_events();
+    assert_eq!(events.len(), 2, "events: {events:?}");
+    let health = &events[0]["fields"];
+    assert_eq!(health["method"], "GET");
+    assert_eq!(health["path"], "/health");
+    assert_eq!(health["status"], 200);
+    assert!(health["latency_ms"].is_u64());
+    assert!(health.get("from").is_none());
+
+    let webhook = &events[1]["fields"];
+    assert_eq!(webhook["path"], "/webhook/sms");
+    assert_eq!(webhook["from"], "+1555***4567");
+}
+
+// ── Metrics ──
+
+#[tokio::test]
+async fn test_metrics_prometheus_format() {
+    let state = test_state();
     {
         let db = state.db.lock().unwrap();
-        let booking = phonebook::models::Booking {
-            id: "test-booking-1".to_string(),
-            customer_phone: "+15551110000".to_string(),
-            customer_name: Some("Alice".to_string()),
-            date_time: chrono::NaiveDateTime::parse_from_str(
-                "2025-06-15 14:00:00",
-                "%Y-%m-%d %H:%M:%S",
-            )
-            .unwrap(),
-            duration_minutes: 60,
-            status: phonebook::models::BookingStatus::Confirmed,
-            notes: Some("Haircut".to_string()),
-            created_at: chrono::Utc::now().naive_utc(),
-            updated_at: chrono::Utc::now().naive_utc(),
-        };
-        phonebook::db::queries::create_booking(&db, &booking).unwrap();
+        phonebook::db::queries::increment_monthly_received(&db).unwrap();
+        phonebook::db::queries::block_number(&db, "+15551112222", None, false).unwrap();
     }
 
     let app = test_app(state);
     let res = app
         .oneshot(
             Request::builder()
-                .uri("/calendar/test-booking-1.ics")
+                .uri("/metrics")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+
+    assert!(text.contains("# TYPE phonebook_bookings_upcoming gauge"));
+    let upcoming = text
+        .lines()
+        .find(|l| l.starts_with("phonebook_bookings_upcoming "))
+        .expect("missing phonebook_bookings_upcoming sample");
+    let value = upcoming.split_whitespace().nth(1).unwrap();
+    assert!(value.parse::<f64>().is_ok(), "non-numeric value: {value}");
+    assert!(text.contains("phonebook_blocked_numbers 1"));
+    assert!(text.contains("phonebook_agent_paused 0"));
+    assert!(text
+        .lines()
+        .any(|l| l.starts_with("phonebook_messages_received{month=") && l.ends_with(" 1")));
+}
+
+#[tokio::test]
+async fn test_metrics_token_guard() {
+    let mut config = test_config();
+    config.metrics_token = "scrape-secret".to_string();
+    let state = build_state(config, Box::new(MockLlm), Box::new(MockMessaging::new()));
+
+    let app = test_app(state.clone());
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
 
+    let app = test_app(state);
+    let res = app
+        .oneshot(
+            Request::builder()
+                .uri("/metrics")
+                .header("Authorization", "Bearer scrape-secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
     assert_eq!(res.status(), StatusCode::OK);
-    assert_eq!(
-        res.headers().get("content-type").unwrap(),
-        "text/calendar; charset=utf-8"
+}
+
+// ── Quiet Hours ──
+
+#[tokio::test]
+async fn test_quiet_hours_hold_replies_until_they_end() {
+    let (state, sent) = test_state_with_sent();
+    let set_quiet_hours = |from_hours: i64, to_hours: i64| {
+        let now = chrono::Utc::now();
+        let quiet = serde_json::json!({
+            "start": (now + chrono::Duration::hours(from_hours)).format("%H:%M").to_string(),
+            "end": (now + chrono::Duration::hours(to_hours)).format("%H:%M").to_string(),
+        })
+        .to_string();
+        test_app(state.clone()).oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::json!({ "quiet_hours": quiet }).to_string()))
+                .unwrap(),
+        )
+    };
+    let queued = |state: &Arc<AppState>| {
+        let db = state.db.lock().unwrap();
+        phonebook::db::queries::get_queued_replies(&db).unwrap()
+    };
+
+    assert_eq!(set_quiet_hours(-1, 1).await.unwrap().status(), StatusCode::OK);
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello"))
+        .await
+        .unwrap();
+
+    assert!(sent.lock().unwrap().is_empty(), "nothing is texted during quiet hours");
+    let held = queued(&state);
+    assert_eq!(held.len(), 1);
+    assert_eq!(held[0].phone, "+15551110000");
+    assert_eq!(phonebook::services::quiet_hours::flush(&state).await, 0);
+
+    // The owner's admin commands are answered right away
+    test_app(state.clone())
+        .oneshot(sms_request("+15559999999", "#status"))
+        .await
+        .unwrap();
+    assert_eq!(sent.lock().unwrap().len(), 1);
+    assert_eq!(sent.lock().unwrap()[0].0, "+15559999999");
+
+    // Quiet hours over: the held reply goes out once
+    assert_eq!(set_quiet_hours(2, 3).await.unwrap().status(), StatusCode::OK);
+    assert_eq!(phonebook::services::quiet_hours::flush(&state).await, 1);
+    {
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1].0, "+15551110000");
+        assert!(sent[1].1.contains("How can I help you today?"));
+    }
+    assert!(queued(&state).is_empty());
+}
+
+// ── Owner Notifications ──
+
+#[tokio::test]
+async fn test_muted_booking_alert_skips_owner_sms_but_records_inbox() {
+    let slot = (chrono::Utc::now() + chrono::Duration::days(2))
+        .date_naive()
+        .and_hms_opt(10, 0, 0)
+        .unwrap();
+    let book = format!(
+        r#"{{"intent":"book","customer_name":"Bea","requested_date":"{}","requested_time":"10:00","duration_minutes":60,"notes":null,"message_to_customer":"Shall I book that?"}}"#,
+        slot.format("%Y-%m-%d")
+    );
+    let confirm = r#"{"intent":"confirm","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"You're all set."}"#;
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[&book, confirm])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
     );
 
-    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"notify_on_booking":false}"#))
+                .unwrap(),
+        )
         .await
         .unwrap();
-    let text = String::from_utf8(body.to_vec()).unwrap();
-    assert!(text.contains("BEGIN:VCALENDAR"));
-    assert!(text.contains("BEGIN:VEVENT"));
-    assert!(text.contains("DTSTART:20250615T140000"));
-    assert!(text.contains("DESCRIPTION:Haircut"));
+    assert_eq!(res.status(), StatusCode::OK);
+
+    for message in ["10am please", "yes"] {
+        phonebook::services::conversation::process_message(&state, "+15550002222", message)
+            .await
+            .unwrap();
+    }
+
+    let db = state.db.lock().unwrap();
+    let bookings = phonebook::db::queries::get_bookings_for_phone(&db, "+15550002222").unwrap();
+    assert_eq!(bookings.len(), 1);
+    let sent = sent.lock().unwrap();
+    assert!(
+        sent.iter().all(|(to, _)| to != "+15559999999"),
+        "owner should not be texted, sent: {sent:?}"
+    );
+    let events = phonebook::db::queries::get_thread_events(&db, "+15550002222", 50).unwrap();
+    assert!(events
+        .iter()
+        .any(|e| e.kind == "system" && e.content.starts_with("New booking: Bea")));
 }
 
-// ── Booking CRUD via Admin API ──
+#[tokio::test]
+async fn test_owner_alert_fails_over_to_backup_phone() {
+    let mut config = test_config();
+    config.backup_owner_phone = "+15558888888".to_string();
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let messaging = FlakyMessaging {
+        unreachable: config.owner_phone.clone(),
+        sent: Arc::clone(&sent),
+    };
+    let state = build_state(config, Box::new(MockLlm), Box::new(messaging));
+
+    // Exceed the per-customer limit so the owner gets an auto-block alert
+    for i in 0..16 {
+        let app = test_app(state.clone());
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/webhook/sms")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(format!(
+                    "From=%2B15551110000&To=%2B15551234567&Body=msg{i}&MessageSid=SM{i}"
+                )))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    }
+
+    let sent = sent.lock().unwrap();
+    assert!(
+        sent.iter()
+            .any(|(to, body)| to == "+15558888888" && body.contains("Auto-blocked")),
+        "backup phone should receive the alert, sent: {sent:?}"
+    );
+    assert!(sent.iter().all(|(to, _)| to != "+15559999999"));
+}
 
 #[tokio::test]
-async fn test_admin_bookings_and_cancel() {
+async fn test_failed_delivery_status_records_system_event() {
     let state = test_state();
 
-    // Create a booking directly in DB
-    {
-        let db = state.db.lock().unwrap();
-        let booking = phonebook::models::Booking {
-            id: "bk-1".to_string(),
-            customer_phone: "+15551110000".to_string(),
-            customer_name: Some("Bob".to_string()),
-            date_time: chrono::NaiveDateTime::parse_from_str(
-                "2025-07-01 10:00:00",
-                "%Y-%m-%d %H:%M:%S",
+    let post_status = |body: &'static str| {
+        let app = test_app(state.clone());
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/webhook/status")
+                    .header("Content-Type", "application/x-www-form-urlencoded")
+                    .body(Body::from(body))
+                    .unwrap(),
             )
-            .unwrap(),
-            duration_minutes: 30,
-            status: phonebook::models::BookingStatus::Confirmed,
-            notes: None,
-            created_at: chrono::Utc::now().naive_utc(),
-            updated_at: chrono::Utc::now().naive_utc(),
-        };
-        phonebook::db::queries::create_booking(&db, &booking).unwrap();
-    }
+            .await
+            .unwrap()
+        }
+    };
 
-    // List bookings
-    let app = test_app(state.clone());
+    let res = post_status("MessageSid=SM100&MessageStatus=sent&To=%2B15551110000&AccountSid=AC1").await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = post_status(
+        "MessageSid=SM100&MessageStatus=failed&ErrorCode=30003&To=%2B15551110000&AccountSid=AC1",
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    // Twilio may retry the callback; the failure should only be surfaced once
+    post_status("MessageSid=SM100&MessageStatus=failed&ErrorCode=30003&To=%2B15551110000").await;
+
+    let db = state.db.lock().unwrap();
+    let status = phonebook::db::queries::get_message_status(&db, "SM100").unwrap();
+    assert_eq!(status, Some(("failed".to_string(), Some("30003".to_string()))));
+
+    let events = phonebook::db::queries::get_thread_events(&db, "+15551110000", 50).unwrap();
+    let system: Vec<_> = events.iter().filter(|e| e.kind == "system").collect();
+    assert_eq!(system.len(), 1);
+    assert!(system[0].content.contains("failed"));
+    assert!(system[0].content.contains("30003"));
+}
+
+#[tokio::test]
+async fn test_status_callback_requires_sid_and_status() {
+    let state = test_state();
+    let app = test_app(state);
     let res = app
         .oneshot(
             Request::builder()
-                .uri("/api/admin/bookings")
-                .header("Authorization", "Bearer test-token")
-                .body(Body::empty())
+                .method("POST")
+                .uri("/webhook/status")
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from("MessageStatus=failed"))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
-    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+// ── Human Handoff ──
+
+const HANDOFF: &str = r#"{"intent":"handoff","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Let me get someone."}"#;
+const ANSWER: &str = r#"{"intent":"general_question","customer_name":null,"requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"We open at 9."}"#;
+
+fn handoff_state(sent: &SentMessages) -> Arc<AppState> {
+    // Only two LLM responses: a third call would fail the turn and show up as a reply
+    build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[HANDOFF, ANSWER])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(sent),
+        }),
+    )
+}
+
+fn texts_to(sent: &SentMessages, phone: &str) -> Vec<String> {
+    sent.lock()
+        .unwrap()
+        .iter()
+        .filter(|(to, _)| to == phone)
+        .map(|(_, body)| body.clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_handoff_silences_agent_until_owner_replies() {
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = handoff_state(&sent);
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "can I talk to a real person"))
         .await
         .unwrap();
-    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
-    assert_eq!(json.len(), 1);
-    assert_eq!(json[0]["customer_name"], "Bob");
-    assert_eq!(json[0]["status"], "confirmed");
+    let replies = texts_to(&sent, "+15551110000");
+    assert_eq!(replies.len(), 1);
+    assert!(replies[0].contains("a person will text you back"));
+    let owner = texts_to(&sent, "+15559999999");
+    assert!(
+        owner.iter().any(|m| m.starts_with("ACTION NEEDED")),
+        "owner alerts: {owner:?}"
+    );
 
-    // Cancel booking
-    let app = test_app(state.clone());
-    let res = app
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/api/admin/bookings/bk-1/cancel")
-                .header("Authorization", "Bearer test-token")
-                .body(Body::empty())
-                .unwrap(),
-        )
+    // The agent stays quiet, but the owner still sees the message in the inbox
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello?"))
         .await
         .unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(texts_to(&sent, "+15551110000").len(), 1);
+    {
+        let db = state.db.lock().unwrap();
+        let events = phonebook::db::queries::get_thread_events(&db, "+15551110000", 50).unwrap();
+        assert!(events
+            .iter()
+            .any(|e| e.kind == "customer_message" && e.content == "hello?"));
+    }
 
-    // Verify cancelled
-    let app = test_app(state);
-    let res = app
+    let res = test_app(state.clone())
         .oneshot(
             Request::builder()
-                .uri("/api/admin/bookings?status=cancelled")
+                .method("POST")
+                .uri("/api/inbox/reply")
                 .header("Authorization", "Bearer test-token")
-                .body(Body::empty())
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "phone": "+15551110000", "message": "Hi, it's Sam" })
+                        .to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
-    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+    assert_eq!(res.status(), StatusCode::OK);
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "when do you open?"))
         .await
         .unwrap();
-    let json: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
-    assert_eq!(json.len(), 1);
-    assert_eq!(json[0]["status"], "cancelled");
+    let replies = texts_to(&sent, "+15551110000");
+    assert_eq!(replies.len(), 3);
+    assert_eq!(replies[2], "We open at 9.");
 }
 
-// ── Scheduling Validation Tests ──
-
 #[tokio::test]
-async fn test_booking_outside_business_hours_rejected() {
-    let state = test_state();
+async fn test_handoff_while_collecting_info_silences_agent() {
+    let partial = r#"{"intent":"book","customer_name":"Sam","requested_date":null,"requested_time":null,"duration_minutes":null,"notes":null,"message_to_customer":"Sure, what day works?"}"#;
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(ScriptedLlm::new(&[partial, HANDOFF])),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
+    );
 
-    // Configure availability: Mon-Fri 09:00-17:00
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "I'd like to book, I'm Sam"))
+        .await
+        .unwrap();
+    assert_eq!(
+        stored_conversation(&state, "+15551110000").state,
+        phonebook::models::ConversationState::CollectingInfo
+    );
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "actually can I talk to a real person"))
+        .await
+        .unwrap();
+    let replies = texts_to(&sent, "+15551110000");
+    assert_eq!(replies.len(), 2);
+    assert!(replies[1].contains("a person will text you back"), "{replies:?}");
+    assert!(texts_to(&sent, "+15559999999")
+        .iter()
+        .any(|m| m.starts_with("ACTION NEEDED")));
     {
         let db = state.db.lock().unwrap();
-        let user = phonebook::models::User {
-            id: "default".to_string(),
-            business_name: "Test Biz".to_string(),
-            owner_name: "Alice".to_string(),
-            owner_phone: "+15559999999".to_string(),
-            twilio_account_sid: "".to_string(),
-            twilio_auth_token: "".to_string(),
-            twilio_phone_number: "+15551234567".to_string(),
-            availability: Some(
-                r#"{"slots":[{"day":"mon","start":"09:00","end":"17:00"},{"day":"tue","start":"09:00","end":"17:00"},{"day":"wed","start":"09:00","end":"17:00"},{"day":"thu","start":"09:00","end":"17:00"},{"day":"fri","start":"09:00","end":"17:00"}]}"#
-                    .to_string(),
-            ),
-            timezone: "America/New_York".to_string(),
-            ai_preferences: None,
-        };
-        phonebook::db::queries::save_user(&db, &user).unwrap();
+        assert!(phonebook::db::queries::needs_human(&db, "+15551110000").unwrap());
     }
 
-    // The MockLlm returns a booking for Sunday (2025-06-15 is a Sunday) at 14:00
-    // which is outside Mon-Fri availability
-    let reply = phonebook::services::conversation::process_message(
-        &state,
-        "+15550001111",
-        "I'd like to book an appointment",
-    )
-    .await
-    .unwrap();
-
-    // Should get a rejection about business hours
-    assert!(
-        reply.contains("outside our business hours") || reply.contains("available"),
-        "Expected business hours rejection, got: {reply}"
-    );
+    // No scripted response is left, so any reply here would be an error apology
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello?"))
+        .await
+        .unwrap();
+    assert_eq!(texts_to(&sent, "+15551110000").len(), 2);
 }
 
 #[tokio::test]
-async fn test_conflicting_booking_rejected() {
-    let state = test_state();
+async fn test_resume_endpoint_hands_thread_back_to_agent() {
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = handoff_state(&sent);
 
-    // Create an existing booking at the same time the MockLlm will propose (2025-06-15 14:00)
-    {
-        let db = state.db.lock().unwrap();
-        let booking = phonebook::models::Booking {
-            id: "conflict-1".to_string(),
-            customer_phone: "+15559990000".to_string(),
-            customer_name: Some("Existing".to_string()),
-            date_time: chrono::NaiveDateTime::parse_from_str(
-                "2025-06-15 14:00:00",
-                "%Y-%m-%d %H:%M:%S",
-            )
-            .unwrap(),
-            duration_minutes: 60,
-            status: phonebook::models::BookingStatus::Confirmed,
-            notes: None,
-            created_at: chrono::Utc::now().naive_utc(),
-            updated_at: chrono::Utc::now().naive_utc(),
-        };
-        phonebook::db::queries::create_booking(&db, &booking).unwrap();
-    }
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "I want to speak to the owner"))
+        .await
+        .unwrap();
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "anyone there?"))
+        .await
+        .unwrap();
+    assert_eq!(texts_to(&sent, "+15551110000").len(), 1);
 
-    let reply = phonebook::services::conversation::process_message(
-        &state,
-        "+15550002222",
-        "I'd like to book an appointment",
-    )
-    .await
-    .unwrap();
+    let resume = || {
+        Request::builder()
+            .method("POST")
+            .uri("/api/inbox/thread/+15551110000/resume")
+            .header("Authorization", "Bearer test-token")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let res = test_app(state.clone()).oneshot(resume()).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["resumed"], true);
 
-    assert!(
-        reply.contains("already booked") || reply.contains("different time"),
-        "Expected conflict rejection, got: {reply}"
+    // Resuming doesn't text the customer; their next message is answered
+    assert_eq!(texts_to(&sent, "+15551110000").len(), 1);
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "when do you open?"))
+        .await
+        .unwrap();
+    assert_eq!(
+        texts_to(&sent, "+15551110000").last().map(String::as_str),
+        Some("We open at 9.")
     );
+
+    let res = test_app(state.clone()).oneshot(resume()).await.unwrap();
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["resumed"], false);
 }
 
 #[tokio::test]
-async fn test_valid_booking_succeeds() {
-    let state = test_state();
-
-    // No availability restrictions, no conflicting bookings
-    // MockLlm proposes 2025-06-15 14:00
-    let reply = phonebook::services::conversation::process_message(
-        &state,
-        "+15550003333",
-        "I'd like to book an appointment",
-    )
-    .await
-    .unwrap();
-
-    // Should get the LLM's normal response (not a validation error)
-    assert!(
-        reply.contains("June 15") || reply.contains("2:00 PM") || reply.contains("book"),
-        "Expected booking proposal, got: {reply}"
+async fn test_thread_ai_off_records_without_replying() {
+    let sent: SentMessages = Arc::new(Mutex::new(vec![]));
+    let state = build_state(
+        test_config(),
+        Box::new(MockLlm),
+        Box::new(MockMessaging {
+            sent: Arc::clone(&sent),
+        }),
     );
-}
+    let set_ai = |enabled: bool| {
+        Request::builder()
+            .method("POST")
+            .uri("/api/inbox/thread/+15551110000/ai")
+            .header("Authorization", "Bearer test-token")
+            .header("Content-Type", "application/json")
+            .body(Body::from(serde_json::json!({ "enabled": enabled }).to_string()))
+            .unwrap()
+    };
 
-// ── Health Check ──
+    let res = test_app(state.clone()).oneshot(set_ai(false)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
 
-#[tokio::test]
-async fn test_health() {
-    let state = test_state();
-    let app = test_app(state);
+    // Repeated inbound messages neither get a reply nor switch the AI back on
+    for body in ["hi there", "still there?"] {
+        test_app(state.clone())
+            .oneshot(sms_request("+15551110000", body))
+            .await
+            .unwrap();
+    }
+    assert!(texts_to(&sent, "+15551110000").is_empty());
+    {
+        let db = state.db.lock().unwrap();
+        assert!(!phonebook::db::queries::is_ai_enabled(&db, "+15551110000").unwrap());
+        let events = phonebook::db::queries::get_thread_events(&db, "+15551110000", 50).unwrap();
+        assert!(events
+            .iter()
+            .any(|e| e.kind == "customer_message" && e.content == "still there?"));
+        assert!(!events.iter().any(|e| e.kind == "ai_reply"));
+        let threads = phonebook::db::queries::get_inbox_threads(&db).unwrap();
+        assert!(!threads[0].ai_enabled);
+    }
 
-    let res = app
+    // The owner can still answer by hand
+    let res = test_app(state.clone())
         .oneshot(
             Request::builder()
-                .uri("/health")
-                .body(Body::empty())
+                .method("POST")
+                .uri("/api/inbox/reply")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(
+                    serde_json::json!({ "phone": "+15551110000", "message": "On it" })
+                        .to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(texts_to(&sent, "+15551110000"), vec!["On it".to_string()]);
 
+    let res = test_app(state.clone()).oneshot(set_ai(true)).await.unwrap();
     assert_eq!(res.status(), StatusCode::OK);
+    test_app(state.clone())
+        .oneshot(sms_request("+15551110000", "hello again"))
+        .await
+        .unwrap();
+    assert_eq!(texts_to(&sent, "+15551110000").len(), 2);
 }
 
 // ── SMS Admin Command Tests ──
@@ -835,6 +6394,41 @@ async fn test_admin_sms_pause() {
     );
 }
 
+#[tokio::test]
+async fn test_owner_phone_saved_in_settings_replaces_config() {
+    let (state, sent) = test_state_with_sent();
+    let res = test_app(state.clone())
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/settings")
+                .header("Authorization", "Bearer test-token")
+                .header("Content-Type", "application/json")
+                .body(Body::from(r#"{"owner_phone":"+15557654321"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // The old OWNER_PHONE is just a customer now
+    test_app(state.clone())
+        .oneshot(owner_sms_request("#pause"))
+        .await
+        .unwrap();
+    assert!(!state.paused.load(std::sync::atomic::Ordering::SeqCst));
+
+    test_app(state.clone())
+        .oneshot(sms_request("+15557654321", "#pause"))
+        .await
+        .unwrap();
+    assert!(state.paused.load(std::sync::atomic::Ordering::SeqCst));
+    assert_eq!(
+        sent.lock().unwrap().last().map(|(to, _)| to.as_str()),
+        Some("+15557654321")
+    );
+}
+
 #[tokio::test]
 async fn test_admin_sms_resume() {
     let (state, sent) = test_state_with_sent();
@@ -913,6 +6507,49 @@ async fn test_admin_sms_block() {
     );
 }
 
+#[tokio::test]
+async fn test_admin_sms_block_normalizes_local_number() {
+    let (state, sent) = test_state_with_sent();
+
+    test_app(state.clone())
+        .oneshot(owner_sms_request("#block 555-111-2222"))
+        .await
+        .unwrap();
+    test_app(state.clone())
+        .oneshot(owner_sms_request("#block call me"))
+        .await
+        .unwrap();
+    {
+        let db = state.db.lock().unwrap();
+        assert!(phonebook::db::queries::is_blocked(&db, "+15551112222").unwrap());
+        assert_eq!(phonebook::db::queries::list_blocked(&db).unwrap().len(), 1);
+    }
+    {
+        let messages = sent.lock().unwrap();
+        assert_eq!(messages[0].1, "Blocked +15551112222");
+        assert!(
+            messages[1].1.contains("not a phone number"),
+            "got: {}",
+            messages[1].1
+        );
+    }
+
+    // The admin API rejects what it can't normalize
+    let res = test_app(state)
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/api/admin/block")
+                .header("Authorization", "Bearer test-token")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"phone":"12345"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn test_admin_sms_unblock() {
     let (state, sent) = test_state_with_sent();
@@ -948,6 +6585,34 @@ async fn test_admin_sms_unblock() {
     );
 }
 
+#[tokio::test]
+async fn test_admin_sms_allow_and_unallow() {
+    let (state, sent) = test_state_with_sent();
+
+    test_app(state.clone())
+        .oneshot(owner_sms_request("#allow +15551112222"))
+        .await
+        .unwrap();
+    {
+        let db = state.db.lock().unwrap();
+        assert!(phonebook::db::queries::is_allowlisted(&db, "+15551112222").unwrap());
+    }
+
+    test_app(state.clone())
+        .oneshot(owner_sms_request("#unallow +15551112222"))
+        .await
+        .unwrap();
+    {
+        let db = state.db.lock().unwrap();
+        assert!(!phonebook::db::queries::is_allowlisted(&db, "+15551112222").unwrap());
+    }
+
+    let messages = sent.lock().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].1.contains("Allowlisted"));
+    assert!(messages[1].1.contains("Removed"));
+}
+
 #[tokio::test]
 async fn test_admin_sms_block_no_arg() {
     let (state, sent) = test_state_with_sent();